@@ -2,9 +2,12 @@ fn main() {
     windows::build!(
         // Windows::Win32::TaskScheduler::{ITaskService, TaskScheduler, ITaskFolder, ITaskDefinition, IRegistrationInfo, IPrincipal, ITaskSettings, IIdleSettings, ITriggerCollection, ITrigger, ITimeTrigger, IActionCollection, IAction, IExecAction},
         Windows::Win32::TaskScheduler::*,
-        Windows::Win32::Automation::VARIANT,
+        Windows::Win32::Automation::{
+            SafeArrayCreateVector, SafeArrayPutElement, VariantClear, VARENUM, VARIANT,
+        },
         Windows::Win32::Com::{
-            CoCreateInstance, CoInitializeEx, COINIT, CoUninitialize,
+            CoCreateInstance, CoInitializeEx, COINIT, CoUninitialize, CoInitializeSecurity,
+            EOLE_AUTHENTICATION_CAPABILITIES, RPC_C_AUTHN_LEVEL, RPC_C_IMP_LEVEL,
         },
     );
 }
@@ -1,42 +1,326 @@
 #![allow(warnings)]
 
-use chrono::Duration;
-use core::default::Default;
-use iter::once;
-use std::{ffi::OsStr, path::PathBuf, ptr};
-use std::{iter, os::windows::ffi::OsStrExt};
-use task_scheduler::{execute, execute_task};
-// use task_scheduler::Task;
+use std::{collections::HashSet, env, fs, path::PathBuf, process};
+#[cfg(windows)]
+use task_scheduler::{Com, Plan, RegistrationMode, RegistrationOptions, TaskService};
+use task_scheduler::{Linter, Manifest, Severity, TaskError};
 
-fn to_win_str(string: &str) -> Vec<u16> {
-    OsStr::new(string).encode_wide().chain(once(0)).collect()
+const USAGE: &str = "usage: test_task <plan|apply|validate|prune|lint> <manifest.toml> [--folder <folder>] [--dry-run] [--recursive] [--json]
+
+commands:
+  plan      shows what apply would change, without changing anything
+  apply     creates or updates every task in the manifest
+  validate  checks the manifest and has Task Scheduler validate every task
+  prune     deletes the tasks in the manifest folders that aren't in the manifest,
+            it refuses to prune the root folder \\
+  lint      checks every task in the manifest for security and configuration problems,
+            and fails if there is a finding of medium severity or higher
+
+options:
+  --folder     the folder the manifest folders are applied under, \\ by default
+  --dry-run    with prune, only lists the tasks that would be deleted
+  --recursive  with prune, also prunes the folders under the manifest folders
+  --json       with lint, writes each finding as a line of JSON";
+
+/// HRESULTs for a task or folder that doesn't exist
+#[cfg(windows)]
+const NOT_FOUND: [u32; 2] = [0x8007_0002, 0x8007_0003];
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Command {
+    Plan,
+    Apply,
+    Validate,
+    Prune,
+    Lint,
+}
+
+#[derive(Debug)]
+struct Arguments {
+    command: Command,
+    manifest: PathBuf,
+    folder: String,
+    dry_run: bool,
+    recursive: bool,
+    json: bool,
+}
+
+fn parse_arguments(mut args: impl Iterator<Item = String>) -> Result<Arguments, String> {
+    let command = match args.next().as_deref() {
+        Some("plan") => Command::Plan,
+        Some("apply") => Command::Apply,
+        Some("validate") => Command::Validate,
+        Some("prune") => Command::Prune,
+        Some("lint") => Command::Lint,
+        Some(command) => return Err(format!("unknown command: {}", command)),
+        None => return Err("missing command".to_string()),
+    };
+
+    let mut manifest = None;
+    let mut folder = "\\".to_string();
+    let mut dry_run = false;
+    let mut recursive = false;
+    let mut json = false;
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--folder" => {
+                folder = args
+                    .next()
+                    .ok_or_else(|| "--folder needs a folder".to_string())?
+            }
+            "--dry-run" => dry_run = true,
+            "--recursive" => recursive = true,
+            "--json" => json = true,
+            _ if arg.starts_with("--") => return Err(format!("unknown option: {}", arg)),
+            _ if manifest.is_none() => manifest = Some(PathBuf::from(arg)),
+            _ => return Err(format!("unexpected argument: {}", arg)),
+        }
+    }
+
+    Ok(Arguments {
+        command,
+        manifest: manifest.ok_or_else(|| "missing manifest".to_string())?,
+        folder,
+        dry_run,
+        recursive,
+        json,
+    })
 }
+
 fn main() {
-    let task_path = PathBuf::from("C:\\Windows\\System32\\notepad.exe");
-    let task_name = "Open Notepad";
-    // let now = Utc::now();
-
-    // let daily_trigger = DailyTrigger::new("my daily trigger".to_string())
-    //     .with_start_time(Utc::now() + Duration::seconds(3))
-    //     .with_end_time(Utc::now() + Duration::minutes(1));
-
-    // let time_trigger = SpecificTimeTrigger::new(
-    //     "My Time Trigger".to_string(),
-    //     Utc::now() + Duration::seconds(1),
-    // );
-    // let other_time_trigger = SpecificTimeTrigger::new(
-    //     "My Other Time Trigger".to_string(),
-    //     Utc::now() + Duration::seconds(10),
-    // );
-
-    // let triggers = TaskTriggersBuilder::new();
-    // // let triggers = triggers.with_daily(daily_trigger).build();
-    // let triggers = triggers
-    //     .with_specific_time(time_trigger)
-    //     // .with_specific_time(other_time_trigger)
-    //     .build();
-    // let actions = Actions::new(task_path);
-
-    // execute_task(actions, task_name.to_owned(), triggers).unwrap();
-    execute(task_path, task_name);
+    let arguments = match parse_arguments(env::args().skip(1)) {
+        Ok(arguments) => arguments,
+        Err(error) => {
+            eprintln!("{}\n\n{}", error, USAGE);
+            process::exit(2);
+        }
+    };
+
+    let manifest = fs::read_to_string(&arguments.manifest)
+        .map_err(|error| TaskError::Error(format!("{}: {}", arguments.manifest.display(), error)))
+        .and_then(|toml| Manifest::from_toml(&toml).map_err(TaskError::from));
+    let result = manifest.and_then(|manifest| match arguments.command {
+        Command::Lint => lint(&arguments, &manifest),
+        _ => run(&arguments, &manifest),
+    });
+    if let Err(error) = result {
+        eprintln!("error: {}", error);
+        process::exit(1);
+    }
+}
+
+#[cfg(windows)]
+fn run(arguments: &Arguments, manifest: &Manifest) -> Result<(), TaskError> {
+    // keep com initialized for as long as the task service is used
+    let _com = Com::initialize()?;
+    let task_service = TaskService::new()?;
+    task_service.connect()?;
+
+    if arguments.command == Command::Prune {
+        return prune(&task_service, arguments, manifest);
+    }
+
+    for folder in manifest.folders() {
+        for manifest_task in folder.tasks() {
+            let path = folder.task_path(&arguments.folder, manifest_task);
+            let task = manifest_task.task();
+            match arguments.command {
+                Command::Plan => println!("{}: {}", path, task_service.plan(&path, task)?),
+                Command::Validate => {
+                    task_service.register(
+                        &path,
+                        task,
+                        RegistrationOptions::new(RegistrationMode::ValidateOnly),
+                    )?;
+                    println!("{}: valid", path);
+                }
+                Command::Apply => match task_service.plan(&path, task)? {
+                    Plan::NoChanges => println!("{}: no changes", path),
+                    plan => {
+                        task_service.register(&path, task, RegistrationOptions::default())?;
+                        match plan {
+                            Plan::Create => println!("{}: created", path),
+                            _ => println!("{}: updated\n{}", path, plan),
+                        }
+                    }
+                },
+                Command::Prune | Command::Lint => unreachable!(),
+            }
+        }
+    }
+    Ok(())
+}
+
+/// The folders that prune deletes tasks from: every manifest folder under the root folder,
+/// once. Pruning the root folder would delete every task on the computer, so it is refused.
+fn prune_folders(manifest: &Manifest, root: &str) -> Result<Vec<String>, TaskError> {
+    let mut folders: Vec<String> = Vec::new();
+    for folder in manifest.folders() {
+        let path = folder.path_under(root);
+        if path == "\\" {
+            return Err(TaskError::Error(
+                "prune won't delete the tasks in the root folder \\, use --folder to prune a folder under it"
+                    .to_string(),
+            ));
+        }
+        if !folders.iter().any(|seen| seen.eq_ignore_ascii_case(&path)) {
+            folders.push(path);
+        }
+    }
+    Ok(folders)
+}
+
+/// Deletes the registered tasks in the manifest folders that the manifest doesn't have,
+/// and in the folders under them with --recursive
+#[cfg(windows)]
+fn prune(
+    task_service: &TaskService,
+    arguments: &Arguments,
+    manifest: &Manifest,
+) -> Result<(), TaskError> {
+    // a manifest folder can be nested in another one, so tasks are compared by their full path
+    let desired: HashSet<String> = manifest
+        .folders()
+        .iter()
+        .flat_map(|folder| {
+            folder
+                .tasks()
+                .iter()
+                .map(move |task| folder.task_path(&arguments.folder, task).to_lowercase())
+        })
+        .collect();
+    let mut visited = HashSet::new();
+    let mut folders = prune_folders(manifest, &arguments.folder)?;
+    while let Some(folder_path) = folders.pop() {
+        if !visited.insert(folder_path.to_lowercase()) {
+            continue;
+        }
+        let registered = match task_service.tasks(&folder_path, true) {
+            Ok(registered) => registered,
+            Err(error) if NOT_FOUND.contains(&error.code().0) => continue,
+            Err(error) => return Err(TaskError::from(error)),
+        };
+        for task in registered {
+            let path = task.path()?;
+            if desired.contains(&path.to_lowercase()) {
+                continue;
+            }
+            if arguments.dry_run {
+                println!("{}: would be deleted", path);
+            } else {
+                task_service.delete_task(&path)?;
+                println!("{}: deleted", path);
+            }
+        }
+        if arguments.recursive {
+            folders.extend(task_service.folders(&folder_path)?);
+        }
+    }
+    Ok(())
+}
+
+/// A finding with the task it was found in, as it is written with --json
+#[derive(serde::Serialize)]
+struct LintRecord<'a> {
+    task: &'a str,
+    id: &'a str,
+    severity: String,
+    explanation: &'a str,
+}
+
+/// Lints every task in the manifest, which doesn't need Task Scheduler
+fn lint(arguments: &Arguments, manifest: &Manifest) -> Result<(), TaskError> {
+    let linters = [Linter::security(), Linter::configuration()];
+    let mut failed = false;
+    for folder in manifest.folders() {
+        for manifest_task in folder.tasks() {
+            let path = folder.task_path(&arguments.folder, manifest_task);
+            for linter in &linters {
+                for finding in linter.lint(manifest_task.task()) {
+                    failed |= finding.severity() >= Severity::Medium;
+                    if arguments.json {
+                        let record = LintRecord {
+                            task: &path,
+                            id: finding.id(),
+                            severity: finding.severity().to_string(),
+                            explanation: finding.explanation(),
+                        };
+                        let line = serde_json::to_string(&record)
+                            .map_err(|error| TaskError::Error(error.to_string()))?;
+                        println!("{}", line);
+                    } else {
+                        println!("{}: {}", path, finding);
+                    }
+                }
+            }
+        }
+    }
+    if failed {
+        return Err(TaskError::Error(
+            "there are findings of medium severity or higher".to_string(),
+        ));
+    }
+    Ok(())
+}
+
+/// Only lint can run without Task Scheduler
+#[cfg(not(windows))]
+fn run(_arguments: &Arguments, _manifest: &Manifest) -> Result<(), TaskError> {
+    Err(TaskError::Error(
+        "plan, apply, validate and prune need Task Scheduler, which only runs on windows, use lint to check the manifest".to_string(),
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn manifest(folders: &[&str]) -> Manifest {
+        let toml: String = folders
+            .iter()
+            .enumerate()
+            .map(|(index, folder)| {
+                format!(
+                    "[[folder]]\npath = '{}'\n\n[[folder.task]]\nname = 'Task{}'\n\n\
+                     [[folder.task.action]]\ntype = 'exec'\npath = 'task.exe'\n\n",
+                    folder, index
+                )
+            })
+            .collect();
+        Manifest::from_toml(&toml).unwrap()
+    }
+
+    #[test]
+    fn prunes_the_manifest_folders_under_the_root_once() {
+        let folders = manifest(&["\\Backups", "\\Backups\\Nightly", "\\backups", "\\"]);
+        assert_eq!(
+            prune_folders(&folders, "\\MyTeam").unwrap(),
+            [
+                "\\MyTeam\\Backups",
+                "\\MyTeam\\Backups\\Nightly",
+                "\\MyTeam"
+            ]
+        );
+        assert_eq!(
+            prune_folders(&manifest(&["\\Backups"]), "\\").unwrap(),
+            ["\\Backups"]
+        );
+    }
+
+    #[test]
+    fn refuses_to_prune_the_root_folder() {
+        assert!(prune_folders(&manifest(&["\\"]), "\\").is_err());
+        assert!(prune_folders(&manifest(&["\\Backups", "/"]), "/").is_err());
+    }
+
+    #[test]
+    fn only_recurses_when_asked() {
+        let args = |args: &[&str]| {
+            parse_arguments(args.iter().map(|arg| arg.to_string()))
+                .unwrap()
+                .recursive
+        };
+        assert!(!args(&["prune", "manifest.toml"]));
+        assert!(args(&["prune", "manifest.toml", "--recursive"]));
+    }
 }
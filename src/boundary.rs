@@ -0,0 +1,158 @@
+use std::fmt;
+
+use chrono::{
+    DateTime, Duration, FixedOffset, Local, LocalResult, NaiveDateTime, Offset, TimeZone, Timelike,
+    Utc,
+};
+
+/// When a trigger starts or ends, as Task Scheduler stores it.
+///
+/// A local boundary has no offset. Task Scheduler runs it at the same time on the clock
+/// of the computer every day, following daylight saving time, so "every day at 09:00"
+/// stays at 09:00 all year.
+///
+/// A boundary with an offset or in UTC is a point in time, which Task Scheduler calls
+/// synchronizing across time zones. The trigger runs at the same UTC time every day,
+/// so on the clock of the computer it moves by an hour when daylight saving time changes.
+///
+/// https://docs.microsoft.com/en-us/windows/win32/taskschd/taskschedulerschema-startboundary-triggerbasetype-element
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum Boundary {
+    /// A time on the clock of the computer the task runs on
+    Local(NaiveDateTime),
+    /// A point in time, written with its offset from UTC
+    Offset(DateTime<FixedOffset>),
+    /// A point in time, written in UTC
+    Utc(DateTime<Utc>),
+}
+
+impl Boundary {
+    /// The time on the clock of this computer, to the minute. This is where a trigger
+    /// without a start boundary starts, the same as Task Scheduler and schtasks do.
+    pub fn now() -> Boundary {
+        let now = Local::now().naive_local();
+        Boundary::Local(
+            now.with_second(0)
+                .and_then(|now| now.with_nanosecond(0))
+                .unwrap_or(now),
+        )
+    }
+
+    /// Parses an xsd:dateTime, which is local when it doesn't have `Z` or an offset
+    pub fn parse(text: &str) -> Option<Boundary> {
+        let text = text.trim();
+        if let Ok(time) = DateTime::parse_from_rfc3339(text) {
+            return Some(if text.ends_with('Z') || text.ends_with('z') {
+                Boundary::Utc(time.with_timezone(&Utc))
+            } else {
+                Boundary::Offset(time)
+            });
+        }
+        NaiveDateTime::parse_from_str(text, "%Y-%m-%dT%H:%M:%S%.f")
+            .ok()
+            .map(Boundary::Local)
+    }
+
+    /// Formats the boundary as an xsd:dateTime the way the task scheduler schema expects it,
+    /// without an offset for local boundaries
+    pub fn to_xml(&self) -> String {
+        match self {
+            Boundary::Local(time) => time.format("%Y-%m-%dT%H:%M:%S").to_string(),
+            Boundary::Offset(time) => time.format("%Y-%m-%dT%H:%M:%S%:z").to_string(),
+            Boundary::Utc(time) => time.format("%Y-%m-%dT%H:%M:%SZ").to_string(),
+        }
+    }
+
+    pub fn is_local(&self) -> bool {
+        matches!(self, Boundary::Local(_))
+    }
+
+    /// The point in time of the boundary when the computer is in the time zone,
+    /// like `chrono::Local` or a time zone from `chrono-tz`. Only local boundaries
+    /// depend on the time zone.
+    ///
+    /// A local time that daylight saving time skips is moved forward by the change,
+    /// 02:30 becomes 03:30, and a local time that happens twice is the first one.
+    pub fn in_zone<Tz: TimeZone>(&self, zone: &Tz) -> DateTime<Utc> {
+        match self {
+            Boundary::Local(time) => resolve_local(zone, *time),
+            Boundary::Offset(time) => time.with_timezone(&Utc),
+            Boundary::Utc(time) => *time,
+        }
+    }
+
+    /// The point in time of the boundary, in the time zone of this computer for local boundaries
+    pub fn to_utc(&self) -> DateTime<Utc> {
+        self.in_zone(&Local)
+    }
+
+    /// The date and time on the clock the boundary is on, its own offset or UTC,
+    /// or the clock of the computer for local boundaries
+    pub(crate) fn clock_time(&self) -> NaiveDateTime {
+        match self {
+            Boundary::Local(time) => *time,
+            Boundary::Offset(time) => time.naive_local(),
+            Boundary::Utc(time) => time.naive_utc(),
+        }
+    }
+
+    /// The date and time on the clock of this computer
+    pub(crate) fn local_time(&self) -> NaiveDateTime {
+        match self {
+            Boundary::Local(time) => *time,
+            _ => self.to_utc().with_timezone(&Local).naive_local(),
+        }
+    }
+
+    /// The same kind of boundary at another time on its clock
+    pub(crate) fn with_clock_time(&self, time: NaiveDateTime) -> Option<Boundary> {
+        match self {
+            Boundary::Local(_) => Some(Boundary::Local(time)),
+            Boundary::Offset(offset_time) => offset_time
+                .offset()
+                .from_local_datetime(&time)
+                .single()
+                .map(Boundary::Offset),
+            Boundary::Utc(_) => Some(Boundary::Utc(Utc.from_utc_datetime(&time))),
+        }
+    }
+}
+
+impl fmt::Display for Boundary {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.to_xml())
+    }
+}
+
+impl From<DateTime<Utc>> for Boundary {
+    fn from(time: DateTime<Utc>) -> Self {
+        Boundary::Utc(time)
+    }
+}
+
+impl From<DateTime<FixedOffset>> for Boundary {
+    fn from(time: DateTime<FixedOffset>) -> Self {
+        Boundary::Offset(time)
+    }
+}
+
+impl From<NaiveDateTime> for Boundary {
+    fn from(time: NaiveDateTime) -> Self {
+        Boundary::Local(time)
+    }
+}
+
+/// The point in time of a time on the clock in the time zone, see [`Boundary::in_zone`]
+pub(crate) fn resolve_local<Tz: TimeZone>(zone: &Tz, time: NaiveDateTime) -> DateTime<Utc> {
+    match zone.from_local_datetime(&time) {
+        LocalResult::Single(resolved) => resolved.with_timezone(&Utc),
+        LocalResult::Ambiguous(earliest, _) => earliest.with_timezone(&Utc),
+        LocalResult::None => {
+            // the offset from before the change, which puts the time after the gap
+            let before = zone
+                .offset_from_utc_datetime(&(time - Duration::days(1)))
+                .fix();
+            Utc.from_utc_datetime(&(time - Duration::seconds(i64::from(before.local_minus_utc()))))
+        }
+    }
+}
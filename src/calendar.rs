@@ -0,0 +1,339 @@
+use std::{fmt, iter::FromIterator};
+
+use chrono::{Datelike, NaiveDate, Weekday};
+
+/// Every day of the week in the order Task Scheduler numbers them
+pub(crate) const WEEKDAYS: [Weekday; 7] = [
+    Weekday::Sun,
+    Weekday::Mon,
+    Weekday::Tue,
+    Weekday::Wed,
+    Weekday::Thu,
+    Weekday::Fri,
+    Weekday::Sat,
+];
+
+/// Names of the months as they appear in the task scheduler schema
+const MONTH_NAMES: [&str; 12] = [
+    "January",
+    "February",
+    "March",
+    "April",
+    "May",
+    "June",
+    "July",
+    "August",
+    "September",
+    "October",
+    "November",
+    "December",
+];
+
+/// The name of the day of the week as it appears in the task scheduler schema
+pub(crate) fn weekday_name(day: Weekday) -> &'static str {
+    match day {
+        Weekday::Sun => "Sunday",
+        Weekday::Mon => "Monday",
+        Weekday::Tue => "Tuesday",
+        Weekday::Wed => "Wednesday",
+        Weekday::Thu => "Thursday",
+        Weekday::Fri => "Friday",
+        Weekday::Sat => "Saturday",
+    }
+}
+
+/// Parses the full name of a day of the week, ignoring case
+pub(crate) fn weekday_from_name(name: &str) -> Option<Weekday> {
+    WEEKDAYS
+        .iter()
+        .copied()
+        .find(|day| weekday_name(*day).eq_ignore_ascii_case(name))
+}
+
+/// The name of the month, where January is 1
+pub(crate) fn month_name(month: u32) -> &'static str {
+    MONTH_NAMES[(month - 1) as usize]
+}
+
+/// Parses the full name of a month into its number, ignoring case
+pub(crate) fn month_from_name(name: &str) -> Option<u32> {
+    MONTH_NAMES
+        .iter()
+        .position(|month| month.eq_ignore_ascii_case(name))
+        .map(|index| index as u32 + 1)
+}
+
+/// The days of the week that a trigger runs on.
+/// This uses the same bits as Task Scheduler, where Sunday is the lowest bit.
+///
+/// https://docs.microsoft.com/en-us/windows/win32/api/taskschd/nf-taskschd-iweeklytrigger-put_daysofweek
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+pub struct DaysOfWeek(u16);
+
+impl DaysOfWeek {
+    /// No days of the week
+    pub fn new() -> Self {
+        Self(0)
+    }
+
+    /// Every day of the week
+    pub fn all() -> Self {
+        Self(0x7F)
+    }
+
+    /// Adds the day to the set
+    pub fn with(mut self, day: Weekday) -> Self {
+        self.0 |= Self::bit(day);
+        self
+    }
+
+    pub fn contains(&self, day: Weekday) -> bool {
+        self.0 & Self::bit(day) != 0
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.0 == 0
+    }
+
+    /// The days in the set, starting from Sunday
+    pub fn iter(&self) -> impl Iterator<Item = Weekday> + '_ {
+        WEEKDAYS
+            .iter()
+            .copied()
+            .filter(move |day| self.contains(*day))
+    }
+
+    /// Every day moved by a number of days, like the days of a start that is on
+    /// another date in UTC than on its own clock
+    pub(crate) fn shifted(&self, days: i64) -> Self {
+        self.iter()
+            .map(|day| (0..days.rem_euclid(7)).fold(day, |day, _| day.succ()))
+            .collect()
+    }
+
+    /// The days as the bitmask Task Scheduler uses
+    pub fn bits(&self) -> u16 {
+        self.0
+    }
+
+    /// Creates the set from the bitmask Task Scheduler uses, unknown bits are dropped
+    pub fn from_bits(bits: u16) -> Self {
+        Self(bits & 0x7F)
+    }
+
+    fn bit(day: Weekday) -> u16 {
+        1 << day.num_days_from_sunday()
+    }
+}
+
+impl FromIterator<Weekday> for DaysOfWeek {
+    fn from_iter<T: IntoIterator<Item = Weekday>>(iter: T) -> Self {
+        iter.into_iter().fold(DaysOfWeek::new(), DaysOfWeek::with)
+    }
+}
+
+impl fmt::Display for DaysOfWeek {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let days: Vec<&str> = self.iter().map(weekday_name).collect();
+        write!(f, "{}", days.join(", "))
+    }
+}
+
+/// The months of the year that a trigger runs in, where January is 1.
+/// This uses the same bits as Task Scheduler, where January is the lowest bit.
+///
+/// https://docs.microsoft.com/en-us/windows/win32/api/taskschd/nf-taskschd-imonthlytrigger-put_monthsofyear
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+pub struct Months(u16);
+
+impl Months {
+    /// No months
+    pub fn new() -> Self {
+        Self(0)
+    }
+
+    /// Every month of the year
+    pub fn all() -> Self {
+        Self(0xFFF)
+    }
+
+    /// Adds the month to the set, this will panic if the month isn't between 1 and 12
+    pub fn with(mut self, month: u32) -> Self {
+        assert!(
+            (1..=12).contains(&month),
+            "months are numbered from 1 to 12"
+        );
+        self.0 |= 1 << (month - 1);
+        self
+    }
+
+    pub fn contains(&self, month: u32) -> bool {
+        (1..=12).contains(&month) && self.0 & (1 << (month - 1)) != 0
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.0 == 0
+    }
+
+    /// The months in the set, starting from January
+    pub fn iter(&self) -> impl Iterator<Item = u32> + '_ {
+        (1..=12).filter(move |month| self.contains(*month))
+    }
+
+    /// The months as the bitmask Task Scheduler uses
+    pub fn bits(&self) -> u16 {
+        self.0
+    }
+
+    /// Creates the set from the bitmask Task Scheduler uses, unknown bits are dropped
+    pub fn from_bits(bits: u16) -> Self {
+        Self(bits & 0xFFF)
+    }
+}
+
+impl FromIterator<u32> for Months {
+    fn from_iter<T: IntoIterator<Item = u32>>(iter: T) -> Self {
+        iter.into_iter().fold(Months::new(), Months::with)
+    }
+}
+
+impl fmt::Display for Months {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let months: Vec<&str> = self.iter().map(month_name).collect();
+        write!(f, "{}", months.join(", "))
+    }
+}
+
+/// The days of the month that a trigger runs on, from 1 to 31.
+/// The last day of the month is set on the trigger itself since it isn't a fixed day.
+///
+/// https://docs.microsoft.com/en-us/windows/win32/api/taskschd/nf-taskschd-imonthlytrigger-put_daysofmonth
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+pub struct DaysOfMonth(u32);
+
+impl DaysOfMonth {
+    /// No days of the month
+    pub fn new() -> Self {
+        Self(0)
+    }
+
+    /// Adds the day to the set, this will panic if the day isn't between 1 and 31
+    pub fn with(mut self, day: u32) -> Self {
+        assert!(
+            (1..=31).contains(&day),
+            "days of the month are numbered from 1 to 31"
+        );
+        self.0 |= 1 << (day - 1);
+        self
+    }
+
+    pub fn contains(&self, day: u32) -> bool {
+        (1..=31).contains(&day) && self.0 & (1 << (day - 1)) != 0
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.0 == 0
+    }
+
+    /// The days in the set in ascending order
+    pub fn iter(&self) -> impl Iterator<Item = u32> + '_ {
+        (1..=31).filter(move |day| self.contains(*day))
+    }
+
+    /// The days as the bitmask Task Scheduler uses
+    pub fn bits(&self) -> u32 {
+        self.0
+    }
+
+    /// Creates the set from the bitmask Task Scheduler uses, unknown bits are dropped
+    pub fn from_bits(bits: u32) -> Self {
+        Self(bits & 0x7FFF_FFFF)
+    }
+}
+
+impl FromIterator<u32> for DaysOfMonth {
+    fn from_iter<T: IntoIterator<Item = u32>>(iter: T) -> Self {
+        iter.into_iter().fold(DaysOfMonth::new(), DaysOfMonth::with)
+    }
+}
+
+impl fmt::Display for DaysOfMonth {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let days: Vec<String> = self.iter().map(|day| day.to_string()).collect();
+        write!(f, "{}", days.join(", "))
+    }
+}
+
+/// The weeks of the month that a trigger runs in, from 1 to 4.
+/// The last week of the month is set on the trigger itself since it isn't a fixed week.
+///
+/// https://docs.microsoft.com/en-us/windows/win32/api/taskschd/nf-taskschd-imonthlydowtrigger-put_weeksofmonth
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+pub struct WeeksOfMonth(u8);
+
+impl WeeksOfMonth {
+    /// No weeks of the month
+    pub fn new() -> Self {
+        Self(0)
+    }
+
+    /// Adds the week to the set, this will panic if the week isn't between 1 and 4
+    pub fn with(mut self, week: u32) -> Self {
+        assert!(
+            (1..=4).contains(&week),
+            "weeks of the month are numbered from 1 to 4"
+        );
+        self.0 |= 1 << (week - 1);
+        self
+    }
+
+    pub fn contains(&self, week: u32) -> bool {
+        (1..=4).contains(&week) && self.0 & (1 << (week - 1)) != 0
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.0 == 0
+    }
+
+    /// The weeks in the set in ascending order
+    pub fn iter(&self) -> impl Iterator<Item = u32> + '_ {
+        (1..=4).filter(move |week| self.contains(*week))
+    }
+
+    /// The weeks as the bitmask Task Scheduler uses
+    pub fn bits(&self) -> u8 {
+        self.0
+    }
+
+    /// Creates the set from the bitmask Task Scheduler uses, unknown bits are dropped
+    pub fn from_bits(bits: u8) -> Self {
+        Self(bits & 0xF)
+    }
+}
+
+impl FromIterator<u32> for WeeksOfMonth {
+    fn from_iter<T: IntoIterator<Item = u32>>(iter: T) -> Self {
+        iter.into_iter()
+            .fold(WeeksOfMonth::new(), WeeksOfMonth::with)
+    }
+}
+
+impl fmt::Display for WeeksOfMonth {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let weeks: Vec<String> = self.iter().map(|week| week.to_string()).collect();
+        write!(f, "{}", weeks.join(", "))
+    }
+}
+
+/// The number of days in the month, where January is 1
+pub(crate) fn days_in_month(year: i32, month: u32) -> u32 {
+    let (next_year, next_month) = if month == 12 {
+        (year + 1, 1)
+    } else {
+        (year, month + 1)
+    };
+    NaiveDate::from_ymd_opt(next_year, next_month, 1)
+        .and_then(|first| first.pred_opt())
+        .map(|last| last.day())
+        .unwrap_or(31)
+}
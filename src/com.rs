@@ -1,62 +1,216 @@
-use std::{fmt, ptr};
+use std::{marker::PhantomData, ptr};
 
-use bindings::Windows::Win32::Com::{CoInitializeEx, CoUninitialize, COINIT};
+use bindings::Windows::Win32::Com::{
+    CoInitializeEx, CoInitializeSecurity, CoUninitialize, COINIT, EOLE_AUTHENTICATION_CAPABILITIES,
+    RPC_C_AUTHN_LEVEL, RPC_C_IMP_LEVEL,
+};
 use log::error;
 
-use crate::error::{ComError, TaskError, WinError};
+use crate::error::{ComError, TaskError};
 
-pub(crate) struct Com;
+/// HRESULT returned by CoInitializeEx when the thread is already initialized in the apartment
+const S_FALSE: u32 = 0x0000_0001;
+
+/// The concurrency model of the thread that initializes the COM library
+///
+/// https://docs.microsoft.com/en-us/windows/win32/api/objbase/ne-objbase-coinit
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Apartment {
+    /// A single-threaded apartment, which is what the Task Scheduler examples use
+    SingleThreaded,
+    /// The multithreaded apartment of the process
+    MultiThreaded,
+}
+
+impl Apartment {
+    fn coinit(self) -> COINIT {
+        match self {
+            Apartment::SingleThreaded => COINIT::COINIT_APARTMENTTHREADED,
+            Apartment::MultiThreaded => COINIT::COINIT_MULTITHREADED,
+        }
+    }
+}
+
+impl Default for Apartment {
+    fn default() -> Self {
+        Apartment::SingleThreaded
+    }
+}
+
+/// How much of the calls to and from the process is authenticated
+///
+/// https://docs.microsoft.com/en-us/windows/win32/com/com-authentication-level-constants
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum AuthenticationLevel {
+    Default,
+    None,
+    Connect,
+    Call,
+    Packet,
+    PacketIntegrity,
+    PacketPrivacy,
+}
+
+impl AuthenticationLevel {
+    fn level(self) -> RPC_C_AUTHN_LEVEL {
+        match self {
+            AuthenticationLevel::Default => RPC_C_AUTHN_LEVEL::RPC_C_AUTHN_LEVEL_DEFAULT,
+            AuthenticationLevel::None => RPC_C_AUTHN_LEVEL::RPC_C_AUTHN_LEVEL_NONE,
+            AuthenticationLevel::Connect => RPC_C_AUTHN_LEVEL::RPC_C_AUTHN_LEVEL_CONNECT,
+            AuthenticationLevel::Call => RPC_C_AUTHN_LEVEL::RPC_C_AUTHN_LEVEL_CALL,
+            AuthenticationLevel::Packet => RPC_C_AUTHN_LEVEL::RPC_C_AUTHN_LEVEL_PKT,
+            AuthenticationLevel::PacketIntegrity => {
+                RPC_C_AUTHN_LEVEL::RPC_C_AUTHN_LEVEL_PKT_INTEGRITY
+            }
+            AuthenticationLevel::PacketPrivacy => RPC_C_AUTHN_LEVEL::RPC_C_AUTHN_LEVEL_PKT_PRIVACY,
+        }
+    }
+}
+
+/// What a server called by the process is allowed to do with its identity
+///
+/// https://docs.microsoft.com/en-us/windows/win32/com/com-impersonation-level-constants
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ImpersonationLevel {
+    Default,
+    Anonymous,
+    Identify,
+    Impersonate,
+    Delegate,
+}
+
+impl ImpersonationLevel {
+    fn level(self) -> RPC_C_IMP_LEVEL {
+        match self {
+            ImpersonationLevel::Default => RPC_C_IMP_LEVEL::RPC_C_IMP_LEVEL_DEFAULT,
+            ImpersonationLevel::Anonymous => RPC_C_IMP_LEVEL::RPC_C_IMP_LEVEL_ANONYMOUS,
+            ImpersonationLevel::Identify => RPC_C_IMP_LEVEL::RPC_C_IMP_LEVEL_IDENTIFY,
+            ImpersonationLevel::Impersonate => RPC_C_IMP_LEVEL::RPC_C_IMP_LEVEL_IMPERSONATE,
+            ImpersonationLevel::Delegate => RPC_C_IMP_LEVEL::RPC_C_IMP_LEVEL_DELEGATE,
+        }
+    }
+}
+
+/// How [`Com`] initializes the COM library
+///
+/// By default the thread joins a single-threaded apartment and the security of
+/// the process is left to COM, which sets it up on the first call that needs it.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct ComOptions {
+    apartment: Apartment,
+    security: Option<(AuthenticationLevel, ImpersonationLevel)>,
+}
+
+impl ComOptions {
+    /// Initializes the thread in the apartment, without setting up security
+    pub fn new(apartment: Apartment) -> Self {
+        Self {
+            apartment,
+            security: None,
+        }
+    }
+
+    /// Sets up the security of the process with the levels after initializing.
+    /// Security is set once per process, before any interface is marshaled,
+    /// so only the first [`Com`] of the process should ask for it.
+    ///
+    /// https://docs.microsoft.com/en-us/windows/win32/api/combaseapi/nf-combaseapi-coinitializesecurity
+    pub fn with_security(
+        mut self,
+        authentication: AuthenticationLevel,
+        impersonation: ImpersonationLevel,
+    ) -> Self {
+        self.security = Some((authentication, impersonation));
+        self
+    }
+
+    pub fn apartment(&self) -> Apartment {
+        self.apartment
+    }
+
+    pub fn security(&self) -> Option<(AuthenticationLevel, ImpersonationLevel)> {
+        self.security
+    }
+}
+
+/// Initializes the COM library for the current thread and uninitializes it when dropped.
+///
+/// Anything created through the [`TaskService`](crate::TaskService) has to be used
+/// and dropped while this is alive. One guard is meant to be kept for as long as the
+/// thread works with Task Scheduler and lent to everything that needs it, it can't be
+/// sent to or shared with another thread since the initialization belongs to this one.
+///
+/// https://docs.microsoft.com/en-us/windows/win32/api/combaseapi/nf-combaseapi-coinitializeex
+#[derive(Debug)]
+pub struct Com {
+    apartment: Apartment,
+    already_initialized: bool,
+    /// Keeps the guard on the thread that initialized COM
+    _thread: PhantomData<*const ()>,
+}
 
 impl Com {
-    // think about letting the user set the com multithreaded parameter
-    // and implementing a default that chooses the single threaded version
-    // I might split initialization and security initialization into
-    // a builder function; for now this is fine
-    #[allow(clippy::new_ret_no_self)]
+    /// Initializes the thread in a single-threaded apartment, see [`ComOptions`]
     pub fn initialize() -> Result<Self, TaskError> {
-        unsafe {
-            // pvReserved is a reserved paramter and must be null
-            let hr = CoInitializeEx(
-                // pvReserved is a reserved paramter and must be null
-                ptr::null_mut(),
-                // The concurrency model and initialization options for the thread. Values for this parameter are taken from the COINIT enumeration. Any combination of values from COINIT can be used, except that the COINIT_APARTMENTTHREADED and COINIT_MULTITHREADED flags cannot both be set. The default is COINIT_MULTITHREADED.
-                COINIT::COINIT_APARTMENTTHREADED,
-            );
-            if hr.is_err() {
-                error!("Com initialization failed: {:?}", hr);
-                match hr {
-                    _RPC_E_CHANGED_MODE => return Err(TaskError::from(ComError::RpcChangedMode)),
-                    _ => unreachable!(),
-                }
-            }
+        Self::initialize_with(ComOptions::default())
+    }
 
-            // set general COM security levels
-            // let hr = CoInitializeSecurity(
-            //     ptr::null_mut(),
-            //     -1,
-            //     ptr::null_mut(),
-            //     ptr::null_mut(),
-            //     RPC_C_AUTHN_LEVEL_PKT_PRIVACY,
-            //     RPC_C_IMP_LEVEL_IMPERSONATE,
-            //     ptr::null_mut(),
-            //     0,
-            //     // this must be null, it is reserved parameter
-            //     ptr::null_mut(),
-            // );
-            // if FAILED(hr) {
-            //     error!("Com security initialization failed: {:X}", hr);
-            //     match hr {
-            //         RPC_E_TOO_LATE => return Err(TaskError::from(ComError::RpcTooLate)),
-            //         RPC_E_NO_GOOD_SECURITY_PACKAGES => {
-            //             return Err(TaskError::from(ComError::NoGoodSecurityPackages))
-            //         }
-            //         E_OUT_OF_MEMORY => return Err(TaskError::from(WinError::OutOfMemory)),
-            //     }
-            // }
+    /// Initializes the thread with the options.
+    ///
+    /// Initializing a thread that is already in the same apartment succeeds, and
+    /// every guard uninitializes once when it is dropped. Asking for the other
+    /// apartment fails with [`ComError::RpcChangedMode`].
+    pub fn initialize_with(options: ComOptions) -> Result<Self, TaskError> {
+        let hr = unsafe {
+            // pvReserved is a reserved parameter and must be null
+            CoInitializeEx(ptr::null_mut(), options.apartment.coinit())
+        };
+        if let Err(win_error) = hr.ok() {
+            error!("Com initialization failed: {:?}", hr);
+            return Err(TaskError::from(ComError::from(win_error)));
+        }
+        let com = Self {
+            apartment: options.apartment,
+            already_initialized: hr.0 == S_FALSE,
+            _thread: PhantomData,
+        };
+
+        if let Some((authentication, impersonation)) = options.security {
+            let hr = unsafe {
+                CoInitializeSecurity(
+                    ptr::null_mut(),
+                    // -1 lets COM choose the authentication services
+                    -1,
+                    ptr::null_mut(),
+                    ptr::null_mut(),
+                    authentication.level(),
+                    impersonation.level(),
+                    ptr::null_mut(),
+                    EOLE_AUTHENTICATION_CAPABILITIES::EOAC_NONE,
+                    ptr::null_mut(),
+                )
+            };
+            if let Err(win_error) = hr.ok() {
+                error!("Com security initialization failed: {:?}", hr);
+                // the guard is dropped here, which uninitializes the thread again
+                return Err(TaskError::from(ComError::from(win_error)));
+            }
         }
-        Ok(Self)
+        Ok(com)
+    }
+
+    /// The apartment the thread was initialized in
+    pub fn apartment(&self) -> Apartment {
+        self.apartment
+    }
+
+    /// Whether the thread was already initialized before this guard, for example
+    /// by another guard or by code outside this crate
+    pub fn already_initialized(&self) -> bool {
+        self.already_initialized
     }
 }
+
 impl Drop for Com {
     fn drop(&mut self) {
         unsafe {
@@ -0,0 +1,583 @@
+use chrono::{Duration, Local, LocalResult, NaiveDate, NaiveTime, TimeZone, Utc};
+
+use crate::{
+    boundary::Boundary,
+    duration::to_xml_duration,
+    lint::{Finding, LintRule, Severity},
+    task::{IdleConditions, Task, TaskLogon},
+    Repetition, TriggerCommon,
+};
+
+/// How far ahead start times are checked against daylight saving time changes
+const DST_CHECK_DAYS: i64 = 366;
+
+pub(crate) fn rules() -> Vec<Box<dyn LintRule>> {
+    vec![
+        Box::new(EndedTrigger),
+        Box::new(OverlappingRepetition),
+        Box::new(IdleWithoutIdleSettings),
+        Box::new(MissedRunsSkipped),
+        Box::new(InteractiveOnly),
+        Box::new(RandomDelayTooLong),
+        Box::new(DaylightSavingStart),
+    ]
+}
+
+/// The parts of a time based trigger that the rules look at
+struct TimeTrigger<'a> {
+    item: String,
+    start: Option<Boundary>,
+    end: Option<Boundary>,
+    repetition: Option<&'a Repetition>,
+    random_delay: Option<Duration>,
+    execution_time_limit: Option<Duration>,
+    /// The shortest time between the days the trigger runs on, if it runs on more than one
+    period: Option<Duration>,
+}
+
+/// The parts of the trigger, none when it is disabled and never runs
+fn time_trigger<'a>(
+    item: String,
+    common: &TriggerCommon,
+    repetition: Option<&'a Repetition>,
+    period: Option<Duration>,
+) -> Option<TimeTrigger<'a>> {
+    if !common.enabled() {
+        return None;
+    }
+    Some(TimeTrigger {
+        item,
+        start: common.start(),
+        end: common.end(),
+        repetition,
+        random_delay: common.random_delay(),
+        execution_time_limit: common.execution_time_limit(),
+        period,
+    })
+}
+
+fn time_triggers(task: &Task) -> Vec<TimeTrigger<'_>> {
+    let triggers = task.triggers();
+    let mut time_triggers = Vec::new();
+    for (number, trigger) in triggers.specific_times().iter().enumerate() {
+        time_triggers.extend(time_trigger(
+            format!("TimeTrigger {}", number + 1),
+            &trigger.common,
+            trigger.repetition.as_ref(),
+            None,
+        ));
+    }
+    for (number, trigger) in triggers.daily().iter().enumerate() {
+        let days = trigger.interval.unwrap_or(1).max(1);
+        time_triggers.extend(time_trigger(
+            format!("DailyTrigger {}", number + 1),
+            &trigger.common,
+            trigger.repetition.as_ref(),
+            Some(Duration::days(i64::from(days))),
+        ));
+    }
+    for (number, trigger) in triggers.weekly().iter().enumerate() {
+        let weeks = trigger.weeks_interval.unwrap_or(1).max(1);
+        let period = if weeks == 1 && trigger.days_of_week.iter().count() > 1 {
+            Duration::days(1)
+        } else {
+            Duration::weeks(i64::from(weeks))
+        };
+        time_triggers.extend(time_trigger(
+            format!("WeeklyTrigger {}", number + 1),
+            &trigger.common,
+            trigger.repetition.as_ref(),
+            Some(period),
+        ));
+    }
+    for (number, trigger) in triggers.monthly().iter().enumerate() {
+        time_triggers.extend(time_trigger(
+            format!("MonthlyTrigger {}", number + 1),
+            &trigger.common,
+            trigger.repetition.as_ref(),
+            Some(Duration::days(1)),
+        ));
+    }
+    for (number, trigger) in triggers.monthly_dow().iter().enumerate() {
+        time_triggers.extend(time_trigger(
+            format!("MonthlyDowTrigger {}", number + 1),
+            &trigger.common,
+            trigger.repetition.as_ref(),
+            Some(Duration::days(1)),
+        ));
+    }
+    time_triggers
+}
+
+fn is_calendar(trigger: &TimeTrigger) -> bool {
+    trigger.period.is_some()
+}
+
+struct EndedTrigger;
+
+impl LintRule for EndedTrigger {
+    fn id(&self) -> &str {
+        "CFG001"
+    }
+
+    fn check(&self, task: &Task) -> Vec<Finding> {
+        let now = Utc::now();
+        time_triggers(task)
+            .into_iter()
+            .filter_map(|trigger| {
+                let end = trigger.end.filter(|end| end.to_utc() < now)?;
+                Some(Finding::new(
+                    self.id(),
+                    Severity::High,
+                    format!(
+                        "{} ended at {}, so it won't start the task again",
+                        trigger.item, end
+                    ),
+                ))
+            })
+            .collect()
+    }
+}
+
+struct OverlappingRepetition;
+
+impl LintRule for OverlappingRepetition {
+    fn id(&self) -> &str {
+        "CFG002"
+    }
+
+    fn check(&self, task: &Task) -> Vec<Finding> {
+        let task_limit = task.settings().execution_time_limit();
+        time_triggers(task)
+            .into_iter()
+            .filter_map(|trigger| {
+                let interval = trigger.repetition?.interval;
+                // a limit on the trigger stops the runs it starts before the one of the task
+                let limit = trigger.execution_time_limit.or(task_limit);
+                if limit.map_or(false, |limit| limit < interval) {
+                    return None;
+                }
+                let limit = limit.map_or_else(
+                    || "no execution time limit".to_string(),
+                    |limit| format!("an execution time limit of {}", to_xml_duration(&limit)),
+                );
+                Some(Finding::new(
+                    self.id(),
+                    Severity::Medium,
+                    format!(
+                        "{} repeats every {} but has {}, so a run that hangs holds up the next repetitions",
+                        trigger.item,
+                        to_xml_duration(&interval),
+                        limit
+                    ),
+                ))
+            })
+            .collect()
+    }
+}
+
+struct IdleWithoutIdleSettings;
+
+impl LintRule for IdleWithoutIdleSettings {
+    fn id(&self) -> &str {
+        "CFG003"
+    }
+
+    fn check(&self, task: &Task) -> Vec<Finding> {
+        let settings = task.settings();
+        if !settings.run_only_if_idle() || *settings.idle_conditions() != IdleConditions::new() {
+            return Vec::new();
+        }
+        vec![Finding::new(
+            self.id(),
+            Severity::Low,
+            "The task only runs when the computer is idle but keeps the default idle settings, so a run is skipped unless the computer is idle for 10 minutes within an hour".to_string(),
+        )]
+    }
+}
+
+struct MissedRunsSkipped;
+
+impl LintRule for MissedRunsSkipped {
+    fn id(&self) -> &str {
+        "CFG004"
+    }
+
+    fn check(&self, task: &Task) -> Vec<Finding> {
+        if task.settings().start_when_available() {
+            return Vec::new();
+        }
+        time_triggers(task)
+            .into_iter()
+            .filter(|trigger| is_calendar(trigger) && trigger.repetition.is_none())
+            .map(|trigger| {
+                Finding::new(
+                    self.id(),
+                    Severity::Medium,
+                    format!(
+                        "{} runs once a day at most and the task doesn't start when available, so a run missed while the computer is off waits for the next one",
+                        trigger.item
+                    ),
+                )
+            })
+            .collect()
+    }
+}
+
+struct InteractiveOnly;
+
+impl LintRule for InteractiveOnly {
+    fn id(&self) -> &str {
+        "CFG005"
+    }
+
+    fn check(&self, task: &Task) -> Vec<Finding> {
+        if task.principal().logon_type() != Some(TaskLogon::InteractiveToken) {
+            return Vec::new();
+        }
+        let mut findings = Vec::new();
+        if !task.triggers().boot().is_empty() {
+            findings.push(Finding::new(
+                self.id(),
+                Severity::High,
+                "The task has a boot trigger but only runs while its user is logged on, which nobody is at boot".to_string(),
+            ));
+        }
+        if !time_triggers(task).is_empty() {
+            findings.push(Finding::new(
+                self.id(),
+                Severity::Medium,
+                "The task only runs while its user is logged on, so its time triggers do nothing while nobody is".to_string(),
+            ));
+        }
+        findings
+    }
+}
+
+struct RandomDelayTooLong;
+
+impl LintRule for RandomDelayTooLong {
+    fn id(&self) -> &str {
+        "CFG006"
+    }
+
+    fn check(&self, task: &Task) -> Vec<Finding> {
+        time_triggers(task)
+            .into_iter()
+            .filter_map(|trigger| {
+                let delay = trigger.random_delay?;
+                let interval = trigger
+                    .repetition
+                    .map(|repetition| repetition.interval)
+                    .or(trigger.period)?;
+                if delay <= interval {
+                    return None;
+                }
+                Some(Finding::new(
+                    self.id(),
+                    Severity::Medium,
+                    format!(
+                        "{} has a random delay of {}, longer than the {} between its runs, so runs can happen out of order or together",
+                        trigger.item,
+                        to_xml_duration(&delay),
+                        to_xml_duration(&interval)
+                    ),
+                ))
+            })
+            .collect()
+    }
+}
+
+struct DaylightSavingStart;
+
+impl DaylightSavingStart {
+    /// The first day the time of day is skipped or repeated in the time zone
+    fn first_change<Tz: TimeZone>(
+        zone: &Tz,
+        time: NaiveTime,
+        from: NaiveDate,
+    ) -> Option<NaiveDate> {
+        (0..DST_CHECK_DAYS)
+            .map(|days| from + Duration::days(days))
+            .find(|day| {
+                !matches!(
+                    zone.from_local_datetime(&day.and_time(time)),
+                    LocalResult::Single(_)
+                )
+            })
+    }
+
+    /// The findings for a computer in the time zone
+    fn check_in<Tz: TimeZone>(&self, task: &Task, zone: &Tz) -> Vec<Finding> {
+        let today = Utc::now().with_timezone(zone).date_naive();
+        time_triggers(task)
+            .into_iter()
+            .filter(is_calendar)
+            .filter_map(|trigger| {
+                // boundaries in UTC or with an offset run at the same point in time every day
+                let start = trigger.start.filter(Boundary::is_local)?.clock_time();
+                let from = today.max(start.date());
+                let day = Self::first_change(zone, start.time(), from)?;
+                Some(Finding::new(
+                    self.id(),
+                    Severity::Medium,
+                    format!(
+                        "{} starts at {} local time, which is skipped or happens twice when daylight saving time changes on {}",
+                        trigger.item,
+                        start.format("%H:%M"),
+                        day
+                    ),
+                ))
+            })
+            .collect()
+    }
+}
+
+impl LintRule for DaylightSavingStart {
+    fn id(&self) -> &str {
+        "CFG007"
+    }
+
+    fn check(&self, task: &Task) -> Vec<Finding> {
+        self.check_in(task, &Local)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::PathBuf;
+
+    use chrono::NaiveDateTime;
+    use chrono_tz::Europe::Berlin;
+
+    use super::*;
+    use crate::{
+        task::{Settings, TaskPrincipal},
+        Action, Actions, BootTrigger, DailyTrigger, ExecAction, SpecificTimeTrigger,
+        TaskTriggersBuilder,
+    };
+
+    fn time(year: i32, month: u32, day: u32, hour: u32, minute: u32) -> NaiveDateTime {
+        NaiveDate::from_ymd_opt(year, month, day)
+            .unwrap()
+            .and_hms_opt(hour, minute, 0)
+            .unwrap()
+    }
+
+    fn task(triggers: TaskTriggersBuilder) -> Task {
+        Task::new(
+            Actions::from_action(Action::Exec(ExecAction::new(PathBuf::from(
+                "C:\\Tools\\backup.exe",
+            )))),
+            triggers.build(),
+        )
+    }
+
+    fn daily(trigger: DailyTrigger) -> Task {
+        task(TaskTriggersBuilder::new().with_daily(trigger))
+    }
+
+    fn nightly() -> DailyTrigger {
+        DailyTrigger::new("Nightly".to_string()).with_start_time(time(2021, 1, 1, 3, 0))
+    }
+
+    fn explanations(findings: Vec<Finding>) -> Vec<String> {
+        findings
+            .into_iter()
+            .map(|finding| finding.explanation().to_string())
+            .collect()
+    }
+
+    #[test]
+    fn finds_triggers_that_have_ended() {
+        let findings = EndedTrigger.check(&daily(nightly().with_end_time(time(2021, 6, 1, 0, 0))));
+        assert_eq!(findings.len(), 1);
+        assert_eq!(findings[0].id(), "CFG001");
+        assert!(findings[0]
+            .explanation()
+            .starts_with("DailyTrigger 1 ended at"));
+
+        let future = Utc::now().naive_utc() + Duration::days(30);
+        assert!(EndedTrigger
+            .check(&daily(nightly().with_end_time(future)))
+            .is_empty());
+        assert!(EndedTrigger.check(&daily(nightly())).is_empty());
+        // a disabled trigger never runs, so it doesn't matter that it ended
+        let disabled = nightly()
+            .with_end_time(time(2021, 6, 1, 0, 0))
+            .with_common(|common| common.with_enabled(false));
+        assert!(EndedTrigger.check(&daily(disabled)).is_empty());
+    }
+
+    #[test]
+    fn finds_repetitions_that_a_hung_run_holds_up() {
+        let hourly = daily(nightly().with_repetition(Repetition::new(Duration::hours(1))));
+        assert_eq!(
+            explanations(OverlappingRepetition.check(&hourly)),
+            ["DailyTrigger 1 repeats every PT1H but has an execution time limit of P3D, so a run that hangs holds up the next repetitions"]
+        );
+        let unlimited = hourly
+            .clone()
+            .with_settings(Settings::new().with_execution_time_limit(None));
+        assert_eq!(
+            explanations(OverlappingRepetition.check(&unlimited)),
+            ["DailyTrigger 1 repeats every PT1H but has no execution time limit, so a run that hangs holds up the next repetitions"]
+        );
+        let limited = hourly
+            .with_settings(Settings::new().with_execution_time_limit(Some(Duration::minutes(30))));
+        assert!(OverlappingRepetition.check(&limited).is_empty());
+        assert!(OverlappingRepetition.check(&daily(nightly())).is_empty());
+    }
+
+    #[test]
+    fn uses_the_limit_of_the_trigger_over_the_one_of_the_task() {
+        let repeating = |limit: Duration| {
+            nightly()
+                .with_repetition(Repetition::new(Duration::hours(1)))
+                .with_common(|common| common.with_execution_time_limit(limit))
+        };
+        // the task stops a run after 72 hours, but the trigger after 30 minutes
+        assert!(OverlappingRepetition
+            .check(&daily(repeating(Duration::minutes(30))))
+            .is_empty());
+        // the task stops a run after 30 minutes, but the trigger only after 2 hours
+        let task = daily(repeating(Duration::hours(2)))
+            .with_settings(Settings::new().with_execution_time_limit(Some(Duration::minutes(30))));
+        assert_eq!(
+            explanations(OverlappingRepetition.check(&task)),
+            ["DailyTrigger 1 repeats every PT1H but has an execution time limit of PT2H, so a run that hangs holds up the next repetitions"]
+        );
+    }
+
+    #[test]
+    fn finds_idle_tasks_with_the_default_idle_settings() {
+        let idle = daily(nightly()).with_settings(Settings::new().with_run_only_if_idle(true));
+        let findings = IdleWithoutIdleSettings.check(&idle);
+        assert_eq!(findings.len(), 1);
+        assert_eq!(findings[0].id(), "CFG003");
+
+        let configured = daily(nightly()).with_settings(
+            Settings::new()
+                .with_run_only_if_idle(true)
+                .with_idle_conditions(IdleConditions::new().with_duration(Duration::minutes(1))),
+        );
+        assert!(IdleWithoutIdleSettings.check(&configured).is_empty());
+        assert!(IdleWithoutIdleSettings.check(&daily(nightly())).is_empty());
+    }
+
+    #[test]
+    fn finds_calendar_triggers_that_skip_missed_runs() {
+        let findings = MissedRunsSkipped.check(&daily(nightly()));
+        assert_eq!(
+            explanations(findings),
+            ["DailyTrigger 1 runs once a day at most and the task doesn't start when available, so a run missed while the computer is off waits for the next one"]
+        );
+
+        let available =
+            daily(nightly()).with_settings(Settings::new().with_start_when_available(true));
+        assert!(MissedRunsSkipped.check(&available).is_empty());
+        let repeating = daily(nightly().with_repetition(Repetition::new(Duration::hours(1))));
+        assert!(MissedRunsSkipped.check(&repeating).is_empty());
+        let once = task(
+            TaskTriggersBuilder::new().with_specific_time(SpecificTimeTrigger::new(
+                "Once".to_string(),
+                time(2021, 1, 1, 3, 0),
+            )),
+        );
+        assert!(MissedRunsSkipped.check(&once).is_empty());
+    }
+
+    #[test]
+    fn finds_interactive_tasks_with_boot_or_time_triggers() {
+        let interactive = TaskPrincipal::new().with_logon_type(TaskLogon::InteractiveToken);
+        let task = task(
+            TaskTriggersBuilder::new()
+                .with_boot(BootTrigger::new())
+                .with_daily(nightly()),
+        );
+        let findings = InteractiveOnly.check(&task.clone().with_principal(interactive.clone()));
+        let severities: Vec<Severity> = findings.iter().map(Finding::severity).collect();
+        assert_eq!(severities, [Severity::High, Severity::Medium]);
+
+        let boot = self::task(TaskTriggersBuilder::new().with_boot(BootTrigger::new()));
+        assert_eq!(
+            InteractiveOnly
+                .check(&boot.with_principal(interactive))
+                .len(),
+            1
+        );
+        let password = TaskPrincipal::new().with_logon_type(TaskLogon::Password);
+        assert!(InteractiveOnly
+            .check(&task.with_principal(password))
+            .is_empty());
+    }
+
+    #[test]
+    fn finds_random_delays_longer_than_the_time_between_runs() {
+        let delayed =
+            |delay: Duration| nightly().with_common(|common| common.with_random_delay(delay));
+        assert_eq!(
+            explanations(RandomDelayTooLong.check(&daily(delayed(Duration::days(2))))),
+            ["DailyTrigger 1 has a random delay of P2D, longer than the P1D between its runs, so runs can happen out of order or together"]
+        );
+        let repeating =
+            delayed(Duration::minutes(30)).with_repetition(Repetition::new(Duration::minutes(10)));
+        assert_eq!(RandomDelayTooLong.check(&daily(repeating)).len(), 1);
+
+        assert!(RandomDelayTooLong
+            .check(&daily(delayed(Duration::hours(1))))
+            .is_empty());
+        // a trigger that runs once has no time between its runs
+        let once = task(
+            TaskTriggersBuilder::new().with_specific_time(
+                SpecificTimeTrigger::new("Once".to_string(), time(2021, 1, 1, 3, 0))
+                    .with_common(|common| common.with_random_delay(Duration::days(2))),
+            ),
+        );
+        assert!(RandomDelayTooLong.check(&once).is_empty());
+    }
+
+    #[test]
+    fn finds_the_first_day_daylight_saving_time_changes_the_start() {
+        let from = NaiveDate::from_ymd_opt(2021, 1, 1).unwrap();
+        let skipped = NaiveTime::from_hms_opt(2, 30, 0).unwrap();
+        assert_eq!(
+            DaylightSavingStart::first_change(&Berlin, skipped, from),
+            NaiveDate::from_ymd_opt(2021, 3, 28)
+        );
+        // the clock goes back from 03:00 to 02:00, so 02:30 happens twice
+        let after_spring = NaiveDate::from_ymd_opt(2021, 4, 1).unwrap();
+        assert_eq!(
+            DaylightSavingStart::first_change(&Berlin, skipped, after_spring),
+            NaiveDate::from_ymd_opt(2021, 10, 31)
+        );
+        let safe = NaiveTime::from_hms_opt(3, 30, 0).unwrap();
+        assert_eq!(DaylightSavingStart::first_change(&Berlin, safe, from), None);
+        assert_eq!(DaylightSavingStart::first_change(&Utc, skipped, from), None);
+    }
+
+    #[test]
+    fn finds_local_start_times_that_daylight_saving_time_changes() {
+        let at = |hour: u32, minute: u32| {
+            daily(
+                DailyTrigger::new("Nightly".to_string())
+                    .with_start_time(time(2021, 1, 1, hour, minute)),
+            )
+        };
+        let findings = DaylightSavingStart.check_in(&at(2, 30), &Berlin);
+        assert_eq!(findings.len(), 1);
+        assert_eq!(findings[0].id(), "CFG007");
+        assert!(findings[0]
+            .explanation()
+            .starts_with("DailyTrigger 1 starts at 02:30 local time"));
+
+        assert!(DaylightSavingStart.check_in(&at(3, 30), &Berlin).is_empty());
+        assert!(DaylightSavingStart.check_in(&at(2, 30), &Utc).is_empty());
+        // a start in UTC runs at the same point in time every day
+        let utc = daily(
+            DailyTrigger::new("Nightly".to_string())
+                .with_start_time(Utc.from_utc_datetime(&time(2021, 1, 1, 2, 30))),
+        );
+        assert!(DaylightSavingStart.check_in(&utc, &Berlin).is_empty());
+    }
+}
@@ -0,0 +1,687 @@
+use std::collections::BTreeSet;
+
+use chrono::{DateTime, Datelike, Duration, NaiveDate, NaiveTime, Utc, Weekday};
+
+use crate::{
+    boundary::Boundary,
+    calendar::{days_in_month, month_name, weekday_name, WEEKDAYS},
+    error::CronError,
+    DailyTrigger, DaysOfMonth, DaysOfWeek, MonthlyDowTrigger, MonthlyTrigger, Months, Repetition,
+    TaskTriggers, TaskTriggersBuilder, TriggerCommon, WeeklyTrigger, WeeksOfMonth, MAX_TRIGGERS,
+};
+
+const MINUTES_PER_DAY: u32 = 24 * 60;
+
+/// How far ahead the next run is searched for, the same as for triggers
+const SEARCH_DAYS: i64 = 366 * 8;
+
+/// A parsed cron expression.
+///
+/// The expression has five fields, `minute hour day-of-month month day-of-week`,
+/// or six with a seconds field first. Fields can be lists, ranges and steps,
+/// months and days of the week can be names like `JAN` or `MON`, and `?` is the same as `*`.
+/// Days of the week are 0 to 7, where both 0 and 7 are Sunday.
+///
+/// The day of month field can be `L` for the last day of the month, and the
+/// day of week field can have `5L` for the last Friday of the month or `5#2`
+/// for the second Friday of the month.
+///
+/// Like cron, a day runs when it matches either the day of month or the day of week
+/// field, unless one of them starts with `*`, then the day has to match both.
+///
+/// Times are in UTC.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct CronSchedule {
+    second: u32,
+    minutes: u64,
+    hours: u32,
+    days_of_month: DaysOfMonth,
+    last_day_of_month: bool,
+    days_of_month_star: bool,
+    months: Months,
+    days_of_week: DaysOfWeek,
+    /// The weeks of the month for `#`, indexed by days from Sunday
+    nth_days_of_week: [WeeksOfMonth; 7],
+    /// The days of the week with `L`
+    last_days_of_week: DaysOfWeek,
+    days_of_week_star: bool,
+}
+
+/// A time of day and the times after it at a fixed interval, in minutes
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub(crate) struct Progression {
+    pub(crate) start: u32,
+    pub(crate) interval: u32,
+    pub(crate) count: u32,
+}
+
+impl CronSchedule {
+    pub fn parse(expression: &str) -> Result<Self, CronError> {
+        let fields: Vec<&str> = expression.split_whitespace().collect();
+        let (second, fields) = match fields.len() {
+            5 => (0, &fields[..]),
+            6 => (parse_second(fields[0])?, &fields[1..]),
+            count => return Err(CronError::FieldCount(count)),
+        };
+
+        let minutes = parse_field(fields[0], "minute", 0, 59, None)?;
+        let hours = parse_field(fields[1], "hour", 0, 23, None)?;
+        let months = parse_field(fields[3], "month", 1, 12, Some(month_from_abbreviation))?;
+
+        let (days_of_month, last_day_of_month) = parse_days_of_month(fields[2])?;
+        let (days_of_week, nth_days_of_week, last_days_of_week) = parse_days_of_week(fields[4])?;
+
+        Ok(Self {
+            second,
+            minutes,
+            hours: hours as u32,
+            days_of_month,
+            last_day_of_month,
+            days_of_month_star: is_star(fields[2]),
+            months: (1..=12)
+                .filter(|month| months & (1 << month) != 0)
+                .collect(),
+            days_of_week,
+            nth_days_of_week,
+            last_days_of_week,
+            days_of_week_star: is_star(fields[4]),
+        })
+    }
+
+    /// The first time after `after` that matches the expression
+    pub fn next_occurrence(&self, after: DateTime<Utc>) -> Option<DateTime<Utc>> {
+        let times: Vec<NaiveTime> = self
+            .minutes_of_day()
+            .into_iter()
+            .filter_map(|minute| {
+                NaiveTime::from_num_seconds_from_midnight_opt(minute * 60 + self.second, 0)
+            })
+            .collect();
+        let mut day = after.date_naive();
+        let last_day = day + Duration::days(SEARCH_DAYS);
+        while day <= last_day {
+            if self.runs_on(day) {
+                let next = times
+                    .iter()
+                    .map(|time| day.and_time(*time).and_utc())
+                    .find(|time| *time > after);
+                if next.is_some() {
+                    return next;
+                }
+            }
+            day = day.succ_opt()?;
+        }
+        None
+    }
+
+    /// Converts the expression into triggers that start the task at the same
+    /// times, from the start of the `start` day.
+    ///
+    /// The times of day are split into as few repetition patterns as can be found,
+    /// and each of them gets a trigger for every kind of day the expression runs on.
+    /// Which fails if that is more than the 48 triggers a task can have.
+    pub fn to_triggers(&self, start: NaiveDate) -> Result<TaskTriggers, CronError> {
+        let progressions = self.progressions();
+        let days = self.day_patterns()?;
+        let needed = progressions.len() * days.len();
+        if needed > usize::from(MAX_TRIGGERS) {
+            return Err(CronError::TooManyTriggers {
+                needed,
+                times_of_day: progressions.len(),
+                days: days.len(),
+            });
+        }
+
+        let mut builder = TaskTriggersBuilder::new();
+        for progression in &progressions {
+            let seconds = progression.start * 60 + self.second;
+            let start_time = NaiveTime::from_num_seconds_from_midnight_opt(seconds, 0)
+                .map(|time| start.and_time(time).and_utc())
+                .ok_or_else(|| CronError::Unsupported(format!("a start on {}", start)))?;
+            let repetition = progression.repetition();
+            for day in &days {
+                builder = day.add_trigger(builder, start_time.into(), None, repetition.clone());
+            }
+        }
+        Ok(builder.build())
+    }
+
+    fn minutes_of_day(&self) -> Vec<u32> {
+        (0..24)
+            .filter(|hour| self.hours & (1 << hour) != 0)
+            .flat_map(|hour| {
+                (0..60)
+                    .filter(move |minute| self.minutes & (1 << minute) != 0)
+                    .map(move |minute| hour * 60 + minute)
+            })
+            .collect()
+    }
+
+    fn runs_on(&self, day: NaiveDate) -> bool {
+        if !self.months.contains(day.month()) {
+            return false;
+        }
+        let last_day = days_in_month(day.year(), day.month());
+        let day_of_month = self.days_of_month.contains(day.day())
+            || (self.last_day_of_month && day.day() == last_day);
+        let weekday = day.weekday();
+        let day_of_week = self.days_of_week.contains(weekday)
+            || self.nth_days_of_week[weekday.num_days_from_sunday() as usize]
+                .contains((day.day() - 1) / 7 + 1)
+            || (self.last_days_of_week.contains(weekday) && day.day() + 7 > last_day);
+        if self.days_of_month_star || self.days_of_week_star {
+            day_of_month && day_of_week
+        } else {
+            day_of_month || day_of_week
+        }
+    }
+
+    fn progressions(&self) -> Vec<Progression> {
+        let hours: Vec<u32> = (0..24)
+            .filter(|hour| self.hours & (1 << hour) != 0)
+            .collect();
+        let minutes: Vec<u32> = (0..60)
+            .filter(|minute| self.minutes & (1 << minute) != 0)
+            .collect();
+        progressions(&hours, &minutes)
+    }
+
+    /// The kinds of days the expression runs on, each of them a trigger
+    fn day_patterns(&self) -> Result<Vec<DayPattern>, CronError> {
+        let all_days_of_month = (1..=31).all(|day| self.days_of_month.contains(day));
+        let all_days_of_week = self.days_of_week == DaysOfWeek::all();
+        let days_of_month =
+            DayPattern::Monthly(self.days_of_month, self.last_day_of_month, self.months);
+
+        if !self.days_of_month_star && !self.days_of_week_star {
+            let mut patterns = vec![days_of_month];
+            patterns.extend(self.days_of_week_patterns());
+            return Ok(patterns);
+        }
+        // both fields have to match, which triggers can only do when one matches every day
+        match (all_days_of_month, all_days_of_week) {
+            (true, true) if self.months == Months::all() => Ok(vec![DayPattern::Daily(1)]),
+            (true, true) | (false, true) => Ok(vec![days_of_month]),
+            (true, false) => Ok(self.days_of_week_patterns()),
+            (false, false) => Err(CronError::Unsupported(
+                "days that have to match both a day of month and a day of week".to_string(),
+            )),
+        }
+    }
+
+    fn days_of_week_patterns(&self) -> Vec<DayPattern> {
+        weekday_patterns(
+            self.days_of_week,
+            &self.nth_days_of_week,
+            self.last_days_of_week,
+            self.months,
+        )
+    }
+}
+
+/// The patterns for days of the week in the months, for every week of the month,
+/// some weeks of the month indexed by days from Sunday, or the last week of the month
+pub(crate) fn weekday_patterns(
+    days_of_week: DaysOfWeek,
+    nth_days_of_week: &[WeeksOfMonth; 7],
+    last_days_of_week: DaysOfWeek,
+    months: Months,
+) -> Vec<DayPattern> {
+    let mut patterns = Vec::new();
+    if days_of_week.is_empty() {
+        // only days in some weeks
+    } else if months == Months::all() {
+        patterns.push(DayPattern::Weekly(days_of_week, 1));
+    } else {
+        // every week of the month is needed to only run in some months
+        patterns.push(DayPattern::MonthlyDow(
+            days_of_week,
+            (1..=4).collect(),
+            true,
+            months,
+        ));
+    }
+
+    // days with the same weeks share a trigger
+    let mut weeks: Vec<(WeeksOfMonth, bool, DaysOfWeek)> = Vec::new();
+    for day in WEEKDAYS.iter() {
+        let nth = nth_days_of_week[day.num_days_from_sunday() as usize];
+        let last = last_days_of_week.contains(*day);
+        if nth.is_empty() && !last {
+            continue;
+        }
+        match weeks
+            .iter_mut()
+            .find(|(weeks, last_week, _)| *weeks == nth && *last_week == last)
+        {
+            Some((_, _, days)) => *days = days.with(*day),
+            None => weeks.push((nth, last, DaysOfWeek::new().with(*day))),
+        }
+    }
+    patterns.extend(
+        weeks
+            .into_iter()
+            .map(|(weeks, last, days)| DayPattern::MonthlyDow(days, weeks, last, months)),
+    );
+    patterns
+}
+
+/// The smallest of a few ways of splitting the times of day at every minute in every hour
+/// into progressions: one pass over all the times, every minute over the hours,
+/// and every hour over the minutes
+pub(crate) fn progressions(hours: &[u32], minutes: &[u32]) -> Vec<Progression> {
+    let minutes_of_day: Vec<u32> = hours
+        .iter()
+        .flat_map(|hour| minutes.iter().map(move |minute| hour * 60 + minute))
+        .collect();
+    let all = cover(&minutes_of_day);
+    let by_minute: Vec<Progression> = minutes
+        .iter()
+        .flat_map(|minute| {
+            cover(hours).into_iter().map(move |hours| Progression {
+                start: hours.start * 60 + minute,
+                interval: hours.interval * 60,
+                count: hours.count,
+            })
+        })
+        .collect();
+    let by_hour: Vec<Progression> = hours
+        .iter()
+        .flat_map(|hour| {
+            cover(minutes).into_iter().map(move |minutes| Progression {
+                start: hour * 60 + minutes.start,
+                ..minutes
+            })
+        })
+        .collect();
+
+    vec![all, by_minute, by_hour]
+        .into_iter()
+        .min_by_key(|progressions| progressions.len())
+        .unwrap_or_default()
+}
+
+impl TaskTriggers {
+    /// Converts a cron expression into triggers that start the task at the same times,
+    /// see [`CronSchedule`]
+    pub fn from_cron(expression: &str, start: NaiveDate) -> Result<TaskTriggers, CronError> {
+        CronSchedule::parse(expression)?.to_triggers(start)
+    }
+}
+
+/// The days a trigger runs on
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub(crate) enum DayPattern {
+    /// Every few days
+    Daily(u16),
+    /// The days of the week, every few weeks
+    Weekly(DaysOfWeek, u16),
+    /// The days of the month, whether the last day of the month is included and the months
+    Monthly(DaysOfMonth, bool, Months),
+    /// The days of the week, their weeks, whether the last week is included and the months
+    MonthlyDow(DaysOfWeek, WeeksOfMonth, bool, Months),
+}
+
+impl DayPattern {
+    pub(crate) fn add_trigger(
+        &self,
+        builder: TaskTriggersBuilder,
+        start: Boundary,
+        end: Option<Boundary>,
+        repetition: Option<Repetition>,
+    ) -> TaskTriggersBuilder {
+        let common = TriggerCommon {
+            start: Some(start),
+            end,
+            ..TriggerCommon::new()
+        };
+        match *self {
+            DayPattern::Daily(interval) => builder.with_daily(DailyTrigger {
+                common,
+                interval: Some(interval).filter(|interval| *interval > 1),
+                repetition,
+            }),
+            DayPattern::Weekly(days_of_week, weeks_interval) => {
+                builder.with_weekly(WeeklyTrigger {
+                    common,
+                    days_of_week,
+                    weeks_interval: Some(weeks_interval).filter(|interval| *interval > 1),
+                    repetition,
+                })
+            }
+            DayPattern::Monthly(days_of_month, last, months) => {
+                builder.with_monthly(MonthlyTrigger {
+                    common,
+                    days_of_month,
+                    months,
+                    run_on_last_day_of_month: last,
+                    repetition,
+                })
+            }
+            DayPattern::MonthlyDow(days_of_week, weeks_of_month, last, months) => builder
+                .with_monthly_dow(MonthlyDowTrigger {
+                    common,
+                    days_of_week,
+                    weeks_of_month,
+                    months,
+                    run_on_last_week_of_month: last,
+                    repetition,
+                }),
+        }
+    }
+}
+
+impl Progression {
+    pub(crate) fn repetition(&self) -> Option<Repetition> {
+        if self.count < 2 {
+            return None;
+        }
+        let interval = Duration::minutes(i64::from(self.interval));
+        let duration = if self.count * self.interval == MINUTES_PER_DAY {
+            Duration::days(1)
+        } else {
+            // long enough for the last run, but not for another one
+            Duration::minutes(i64::from((self.count - 1) * self.interval + 1))
+        };
+        Some(Repetition::new(interval).with_duration(duration))
+    }
+}
+
+/// Splits sorted values into progressions, taking the longest progression
+/// from the smallest value that is left every time
+fn cover(values: &[u32]) -> Vec<Progression> {
+    let mut left: BTreeSet<u32> = values.iter().copied().collect();
+    let mut progressions = Vec::new();
+    while let Some(&start) = left.iter().next() {
+        let mut best = Progression {
+            start,
+            interval: 1,
+            count: 1,
+        };
+        for &next in left.iter().skip(1) {
+            let interval = next - start;
+            let count = (0..)
+                .take_while(|index| left.contains(&(start + index * interval)))
+                .count() as u32;
+            if count > best.count {
+                best = Progression {
+                    start,
+                    interval,
+                    count,
+                };
+            }
+        }
+        for index in 0..best.count {
+            left.remove(&(best.start + index * best.interval));
+        }
+        progressions.push(best);
+    }
+    progressions
+}
+
+fn is_star(field: &str) -> bool {
+    field.starts_with('*') || field.starts_with('?')
+}
+
+fn syntax_error(field: &str, value: &str) -> CronError {
+    CronError::Syntax {
+        field: field.to_string(),
+        value: value.to_string(),
+    }
+}
+
+fn parse_second(field: &str) -> Result<u32, CronError> {
+    match field.parse::<u32>() {
+        Ok(second) if second < 60 => Ok(second),
+        Ok(_) => Err(syntax_error("second", field)),
+        Err(_) if parse_field(field, "second", 0, 59, None).is_ok() => Err(CronError::Unsupported(
+            "more than one second, repetitions are at least a minute apart".to_string(),
+        )),
+        Err(_) => Err(syntax_error("second", field)),
+    }
+}
+
+fn parse_value(
+    value: &str,
+    name: &str,
+    min: u32,
+    max: u32,
+    from_name: Option<fn(&str) -> Option<u32>>,
+) -> Result<u32, CronError> {
+    let number = value
+        .parse::<u32>()
+        .ok()
+        .or_else(|| from_name.and_then(|from_name| from_name(value)));
+    match number {
+        Some(number) if number >= min && number <= max => Ok(number),
+        _ => Err(syntax_error(name, value)),
+    }
+}
+
+/// Parses a list of values, ranges and steps into a bit for every value
+fn parse_field(
+    field: &str,
+    name: &str,
+    min: u32,
+    max: u32,
+    from_name: Option<fn(&str) -> Option<u32>>,
+) -> Result<u64, CronError> {
+    let mut bits = 0;
+    for item in field.split(',') {
+        let (range, step) = match item.split_once('/') {
+            Some((range, step)) => match step.parse::<u32>() {
+                Ok(step) if step > 0 => (range, Some(step)),
+                _ => return Err(syntax_error(name, item)),
+            },
+            None => (item, None),
+        };
+        let (first, last) = match range {
+            "*" | "?" => (min, max),
+            _ => match range.split_once('-') {
+                Some((first, last)) => (
+                    parse_value(first, name, min, max, from_name)?,
+                    parse_value(last, name, min, max, from_name)?,
+                ),
+                None => {
+                    let first = parse_value(range, name, min, max, from_name)?;
+                    // a single value with a step runs up to the end of the range
+                    (first, if step.is_some() { max } else { first })
+                }
+            },
+        };
+        if first > last {
+            return Err(syntax_error(name, item));
+        }
+        for value in (first..=last).step_by(step.unwrap_or(1) as usize) {
+            bits |= 1 << value;
+        }
+    }
+    Ok(bits)
+}
+
+fn parse_days_of_month(field: &str) -> Result<(DaysOfMonth, bool), CronError> {
+    let mut last = false;
+    let mut days = Vec::new();
+    for item in field.split(',') {
+        if item.eq_ignore_ascii_case("L") {
+            last = true;
+        } else if item.ends_with(['W', 'w']) || item.starts_with(['L', 'l']) {
+            return Err(CronError::Unsupported(format!(
+                "{} in the day of month field",
+                item
+            )));
+        } else {
+            days.push(item);
+        }
+    }
+    let bits = if days.is_empty() {
+        0
+    } else {
+        parse_field(&days.join(","), "day of month", 1, 31, None)?
+    };
+    let days_of_month = (1..=31).filter(|day| bits & (1 << day) != 0).collect();
+    Ok((days_of_month, last))
+}
+
+/// Cron names are the first three letters, like `MON`
+fn is_abbreviation(name: &str, full_name: &str) -> bool {
+    name.len() == 3 && full_name[..3].eq_ignore_ascii_case(name)
+}
+
+fn month_from_abbreviation(name: &str) -> Option<u32> {
+    (1..=12).find(|month| is_abbreviation(name, month_name(*month)))
+}
+
+fn day_of_week_from_abbreviation(name: &str) -> Option<u32> {
+    WEEKDAYS
+        .iter()
+        .find(|day| is_abbreviation(name, weekday_name(**day)))
+        .map(|day| day.num_days_from_sunday())
+}
+
+fn parse_day_of_week(value: &str) -> Result<Weekday, CronError> {
+    parse_value(
+        value,
+        "day of week",
+        0,
+        7,
+        Some(day_of_week_from_abbreviation),
+    )
+    .map(|day| WEEKDAYS[day as usize % 7])
+}
+
+fn parse_days_of_week(
+    field: &str,
+) -> Result<(DaysOfWeek, [WeeksOfMonth; 7], DaysOfWeek), CronError> {
+    let mut nth = [WeeksOfMonth::new(); 7];
+    let mut last = DaysOfWeek::new();
+    let mut days = Vec::new();
+    for item in field.split(',') {
+        if let Some((day, week)) = item.split_once('#') {
+            let day = parse_day_of_week(day)?;
+            let week = match week.parse::<u32>() {
+                Ok(week) if (1..=4).contains(&week) => week,
+                Ok(5) => {
+                    return Err(CronError::Unsupported(format!(
+                        "{}, triggers only have weeks 1 to 4 and the last week",
+                        item
+                    )))
+                }
+                _ => return Err(syntax_error("day of week", item)),
+            };
+            let index = day.num_days_from_sunday() as usize;
+            nth[index] = nth[index].with(week);
+        } else if item.len() > 1 && item.ends_with(['L', 'l']) {
+            last = last.with(parse_day_of_week(&item[..item.len() - 1])?);
+        } else if item.eq_ignore_ascii_case("L") {
+            return Err(CronError::Unsupported(
+                "L in the day of week field needs a day, like 5L".to_string(),
+            ));
+        } else {
+            days.push(item);
+        }
+    }
+    let bits = if days.is_empty() {
+        0
+    } else {
+        parse_field(
+            &days.join(","),
+            "day of week",
+            0,
+            7,
+            Some(day_of_week_from_abbreviation),
+        )?
+    };
+    let days_of_week = (0..=7)
+        .filter(|day| bits & (1 << day) != 0)
+        .map(|day| WEEKDAYS[day % 7])
+        .collect();
+    Ok((days_of_week, nth, last))
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::{DateTime, NaiveDate, Utc};
+
+    use super::CronSchedule;
+    use crate::error::CronError;
+
+    const STEPS: usize = 2000;
+
+    /// Checks that the triggers of the expression run at the same times as the expression
+    /// for the next few thousand runs
+    fn assert_equivalent(expression: &str) {
+        let start = NaiveDate::from_ymd_opt(2024, 1, 1).unwrap();
+        let schedule = CronSchedule::parse(expression).unwrap();
+        let triggers = schedule.to_triggers(start).unwrap();
+        let mut after: DateTime<Utc> =
+            start.and_hms_opt(0, 0, 0).unwrap().and_utc() - chrono::Duration::seconds(1);
+        for step in 0..STEPS {
+            let expected = schedule.next_occurrence(after);
+            let actual = triggers.next_occurrence_in(after, &Utc);
+            assert_eq!(
+                actual, expected,
+                "{} after {} (run {})",
+                expression, after, step
+            );
+            after = match expected {
+                Some(next) => next,
+                None => break,
+            };
+        }
+    }
+
+    #[test]
+    fn every_quarter_hour() {
+        assert_equivalent("*/15 * * * *");
+    }
+
+    #[test]
+    fn weekday_mornings() {
+        assert_equivalent("0 9 * * MON-FRI");
+    }
+
+    #[test]
+    fn last_day_of_month() {
+        assert_equivalent("30 2 L * *");
+    }
+
+    #[test]
+    fn days_of_month_or_day_of_week() {
+        assert_equivalent("0 0 1,15 * 5");
+    }
+
+    #[test]
+    fn nth_day_of_week() {
+        assert_equivalent("0 12 * * 5#2");
+    }
+
+    #[test]
+    fn last_day_of_week_in_a_month() {
+        assert_equivalent("0 12 * 3 5L");
+    }
+
+    #[test]
+    fn leap_day() {
+        assert_equivalent("0 0 29 2 *");
+    }
+
+    #[test]
+    fn rejects_a_step_across_a_range() {
+        assert_eq!(
+            CronSchedule::parse("0 22-2/2 * * *"),
+            Err(CronError::Syntax {
+                field: "hour".to_string(),
+                value: "22-2/2".to_string()
+            })
+        );
+    }
+
+    #[test]
+    fn rejects_l_in_a_list_of_days_of_week() {
+        assert!(matches!(
+            CronSchedule::parse("0 0 * * 1,L"),
+            Err(CronError::Unsupported(_))
+        ));
+    }
+}
@@ -0,0 +1,941 @@
+use std::fmt;
+
+use chrono::Duration;
+
+use crate::{
+    boundary::Boundary,
+    duration::to_xml_duration,
+    task::{MultipleInstancesPolicy, RunLevel, Settings, Task, TaskPrincipal, TaskRegistration},
+    Action, BootTrigger, ComHandlerAction, DailyTrigger, EventTrigger, ExecAction, IdleTrigger,
+    LogonTrigger, MonthlyDowTrigger, MonthlyTrigger, RegistrationTrigger, Repetition,
+    SessionStateChangeTrigger, SpecificTimeTrigger, TaskTriggers, TriggerCommon, WeeklyTrigger,
+};
+
+/// A single field of the model as text, so that different kinds of values can be compared
+pub(crate) struct Field {
+    name: &'static str,
+    value: Option<String>,
+    /// Leaving the field unset keeps whatever Task Scheduler filled in,
+    /// like the start time of a trigger or the user the task runs as
+    keep_when_unset: bool,
+    /// What the field is compared by, when different values mean the same thing
+    key: Option<String>,
+}
+
+impl Field {
+    /// A field where being unset means that it is absent from the task
+    fn value(name: &'static str, value: Option<String>) -> Self {
+        Self {
+            name,
+            key: value.clone(),
+            value,
+            keep_when_unset: false,
+        }
+    }
+
+    /// A field that isn't compared when it is left unset
+    fn unless_unset(name: &'static str, value: Option<String>) -> Self {
+        Self {
+            name,
+            key: value.clone(),
+            value,
+            keep_when_unset: true,
+        }
+    }
+
+    /// Compares the field as an account, see [`account_key`]
+    fn account(mut self) -> Self {
+        self.key = self.value.as_deref().map(account_key);
+        self
+    }
+}
+
+/// Accounts are compared without case, and the well known service accounts by their
+/// security identifier, since Task Scheduler gives back `S-1-5-18` for `SYSTEM`
+fn account_key(account: &str) -> String {
+    let account = account.trim().to_uppercase();
+    let name = account.strip_prefix("NT AUTHORITY\\").unwrap_or(&account);
+    match name {
+        "SYSTEM" | "LOCALSYSTEM" => "S-1-5-18".to_string(),
+        "LOCAL SERVICE" | "LOCALSERVICE" => "S-1-5-19".to_string(),
+        "NETWORK SERVICE" | "NETWORKSERVICE" => "S-1-5-20".to_string(),
+        _ => account,
+    }
+}
+
+/// Lists the fields of a part of the task that are compared in a diff
+pub(crate) trait Fields {
+    fn fields(&self) -> Vec<Field>;
+}
+
+fn text<T: ToString>(value: T) -> Option<String> {
+    Some(value.to_string())
+}
+
+fn date(date: Option<Boundary>) -> Option<String> {
+    date.as_ref().map(Boundary::to_xml)
+}
+
+fn duration(duration: Option<Duration>) -> Option<String> {
+    duration.as_ref().map(to_xml_duration)
+}
+
+/// The fields every trigger has, the id is how triggers are matched so it isn't one
+fn common_fields(common: &TriggerCommon) -> Vec<Field> {
+    vec![
+        Field::unless_unset("start_time", date(common.start)),
+        Field::value("end_time", date(common.end)),
+        Field::value("enabled", text(common.enabled)),
+        Field::value(
+            "execution_time_limit",
+            duration(common.execution_time_limit),
+        ),
+        Field::value("random_delay", duration(common.random_delay)),
+    ]
+}
+
+fn repetition_fields(fields: &mut Vec<Field>, repetition: Option<&Repetition>) {
+    fields.push(Field::value(
+        "repetition.interval",
+        duration(repetition.map(|repetition| repetition.interval)),
+    ));
+    fields.push(Field::value(
+        "repetition.duration",
+        duration(repetition.and_then(|repetition| repetition.duration)),
+    ));
+    fields.push(Field::value(
+        "repetition.stop_at_duration_end",
+        repetition.map(|repetition| repetition.stop_at_duration_end.to_string()),
+    ));
+}
+
+/// A field that is different between the registered task and the desired task
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct FieldChange {
+    field: String,
+    current: Option<String>,
+    desired: Option<String>,
+}
+
+impl FieldChange {
+    pub fn field(&self) -> &str {
+        &self.field
+    }
+
+    /// The value on the registered task, `None` if the field isn't set
+    pub fn current(&self) -> Option<&str> {
+        self.current.as_deref()
+    }
+
+    /// The value on the desired task, `None` if the field isn't set
+    pub fn desired(&self) -> Option<&str> {
+        self.desired.as_deref()
+    }
+}
+
+impl fmt::Display for FieldChange {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{}: {} -> {}",
+            self.field,
+            self.current.as_deref().unwrap_or("(none)"),
+            self.desired.as_deref().unwrap_or("(none)")
+        )
+    }
+}
+
+/// A trigger or action that was added, removed or changed
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum ItemChange {
+    /// Only the desired task has this item
+    Added { kind: String, id: Option<String> },
+    /// Only the registered task has this item
+    Removed { kind: String, id: Option<String> },
+    /// Both tasks have the item but some of its fields are different
+    Changed {
+        kind: String,
+        id: Option<String>,
+        changes: Vec<FieldChange>,
+    },
+}
+
+impl fmt::Display for ItemChange {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let (sign, kind, id) = match self {
+            ItemChange::Added { kind, id } => ("+", kind, id),
+            ItemChange::Removed { kind, id } => ("-", kind, id),
+            ItemChange::Changed { kind, id, .. } => ("~", kind, id),
+        };
+        write!(f, "{} {}", sign, kind)?;
+        if let Some(id) = id {
+            write!(f, " \"{}\"", id)?;
+        }
+        if let ItemChange::Changed { changes, .. } = self {
+            for change in changes {
+                write!(f, "\n    {}", change)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+/// The differences between a registered task and a desired task
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct TaskDiff {
+    registration: Vec<FieldChange>,
+    principal: Vec<FieldChange>,
+    settings: Vec<FieldChange>,
+    triggers: Vec<ItemChange>,
+    actions: Vec<ItemChange>,
+}
+
+impl TaskDiff {
+    /// Re-applying the same task is a no-op when the diff is empty
+    pub fn is_empty(&self) -> bool {
+        self.registration.is_empty()
+            && self.principal.is_empty()
+            && self.settings.is_empty()
+            && self.triggers.is_empty()
+            && self.actions.is_empty()
+    }
+
+    pub fn registration(&self) -> &[FieldChange] {
+        &self.registration
+    }
+
+    pub fn principal(&self) -> &[FieldChange] {
+        &self.principal
+    }
+
+    pub fn settings(&self) -> &[FieldChange] {
+        &self.settings
+    }
+
+    pub fn triggers(&self) -> &[ItemChange] {
+        &self.triggers
+    }
+
+    pub fn actions(&self) -> &[ItemChange] {
+        &self.actions
+    }
+}
+
+impl fmt::Display for TaskDiff {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.is_empty() {
+            return write!(f, "no changes");
+        }
+        let mut lines = Vec::new();
+        for (section, changes) in &[
+            ("registration", &self.registration),
+            ("principal", &self.principal),
+            ("settings", &self.settings),
+        ] {
+            for change in changes.iter() {
+                lines.push(format!("~ {}.{}", section, change));
+            }
+        }
+        for change in &self.triggers {
+            lines.push(format!("{} (trigger)", change));
+        }
+        for change in &self.actions {
+            lines.push(format!("{} (action)", change));
+        }
+        write!(f, "{}", lines.join("\n"))
+    }
+}
+
+/// What registering a task would do
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Plan {
+    /// There isn't a task at the path yet
+    Create,
+    /// The registered task already matches the desired task
+    NoChanges,
+    /// The registered task would be updated
+    Update(TaskDiff),
+}
+
+impl Plan {
+    /// Plans the desired task against the task that is registered at its path, if any
+    pub fn new(desired: &Task, current: Option<&Task>) -> Plan {
+        match current {
+            None => Plan::Create,
+            Some(current) => {
+                let diff = desired.diff(current);
+                if diff.is_empty() {
+                    Plan::NoChanges
+                } else {
+                    Plan::Update(diff)
+                }
+            }
+        }
+    }
+}
+
+impl fmt::Display for Plan {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Plan::Create => write!(f, "the task will be created"),
+            Plan::NoChanges => write!(f, "no changes"),
+            Plan::Update(diff) => write!(f, "the task will be updated:\n{}", diff),
+        }
+    }
+}
+
+impl Task {
+    /// Compares this task, the desired one, against the task that is currently registered.
+    ///
+    /// Fields that this task leaves unset and that Task Scheduler fills in on
+    /// registration, like the start time of a trigger or the user the task runs
+    /// as, are not reported as changes.
+    pub fn diff(&self, current: &Task) -> TaskDiff {
+        TaskDiff {
+            registration: diff_fields(&self.registration.fields(), &current.registration.fields()),
+            principal: diff_fields(&self.principal.fields(), &current.principal.fields()),
+            settings: diff_fields(&self.settings.fields(), &current.settings.fields()),
+            triggers: diff_triggers(&self.triggers, &current.triggers),
+            actions: diff_actions(&self.actions.0, &current.actions.0),
+        }
+    }
+}
+
+fn diff_fields(desired: &[Field], current: &[Field]) -> Vec<FieldChange> {
+    let mut changes = Vec::new();
+    for field in desired {
+        if field.keep_when_unset && field.value.is_none() {
+            continue;
+        }
+        let current = current.iter().find(|current| current.name == field.name);
+        if current.and_then(|current| current.key.as_ref()) != field.key.as_ref() {
+            changes.push(FieldChange {
+                field: field.name.to_string(),
+                current: current.and_then(|current| current.value.clone()),
+                desired: field.value.clone(),
+            });
+        }
+    }
+    changes
+}
+
+/// A trigger flattened into its kind, id and fields
+struct Item {
+    kind: &'static str,
+    id: Option<String>,
+    fields: Vec<Field>,
+}
+
+fn non_empty(id: &str) -> Option<String> {
+    if id.is_empty() {
+        None
+    } else {
+        Some(id.to_string())
+    }
+}
+
+fn trigger_items(triggers: &TaskTriggers) -> Vec<Item> {
+    fn items<'a, T: Fields + 'a>(
+        kind: &'static str,
+        triggers: &'a [T],
+        common: impl Fn(&T) -> &TriggerCommon + 'a,
+    ) -> impl Iterator<Item = Item> + 'a {
+        triggers.iter().map(move |trigger| Item {
+            kind,
+            id: common(trigger).id.as_deref().and_then(non_empty),
+            fields: trigger.fields(),
+        })
+    }
+
+    let mut all = Vec::new();
+    all.extend(items("DailyTrigger", triggers.daily(), |t| &t.common));
+    all.extend(items("TimeTrigger", triggers.specific_times(), |t| {
+        &t.common
+    }));
+    all.extend(items("WeeklyTrigger", triggers.weekly(), |t| &t.common));
+    all.extend(items("MonthlyTrigger", triggers.monthly(), |t| &t.common));
+    all.extend(items("MonthlyDowTrigger", triggers.monthly_dow(), |t| {
+        &t.common
+    }));
+    all.extend(items("EventTrigger", triggers.event(), |t| &t.common));
+    all.extend(items("IdleTrigger", triggers.idle(), |t| &t.common));
+    all.extend(items("RegistrationTrigger", triggers.registration(), |t| {
+        &t.common
+    }));
+    all.extend(items("BootTrigger", triggers.boot(), |t| &t.common));
+    all.extend(items("LogonTrigger", triggers.logon(), |t| &t.common));
+    all.extend(items(
+        "SessionStateChangeTrigger",
+        triggers.session_state_change(),
+        |t| &t.common,
+    ));
+    all
+}
+
+/// Matches desired triggers to registered ones, first by their kind and id,
+/// then by being the same, and finally by their position among triggers of the same kind
+fn diff_triggers(desired: &TaskTriggers, current: &TaskTriggers) -> Vec<ItemChange> {
+    let desired = trigger_items(desired);
+    let current = trigger_items(current);
+    let mut desired_matches: Vec<Option<usize>> = vec![None; desired.len()];
+    let mut current_matched = vec![false; current.len()];
+
+    let mut match_by = |predicate: &dyn Fn(&Item, &Item) -> bool,
+                        desired_matches: &mut Vec<Option<usize>>| {
+        for (desired_index, item) in desired.iter().enumerate() {
+            if desired_matches[desired_index].is_some() {
+                continue;
+            }
+            let found = current.iter().enumerate().find(|(current_index, other)| {
+                !current_matched[*current_index]
+                    && other.kind == item.kind
+                    && predicate(item, other)
+            });
+            if let Some((current_index, _)) = found {
+                current_matched[current_index] = true;
+                desired_matches[desired_index] = Some(current_index);
+            }
+        }
+    };
+    match_by(
+        &|item, other| item.id.is_some() && item.id == other.id,
+        &mut desired_matches,
+    );
+    match_by(
+        &|item, other| diff_fields(&item.fields, &other.fields).is_empty(),
+        &mut desired_matches,
+    );
+    match_by(&|_, _| true, &mut desired_matches);
+
+    let mut changes = Vec::new();
+    for (item, matched) in desired.iter().zip(&desired_matches) {
+        match matched {
+            Some(current_index) => {
+                let field_changes = diff_fields(&item.fields, &current[*current_index].fields);
+                if !field_changes.is_empty() {
+                    changes.push(ItemChange::Changed {
+                        kind: item.kind.to_string(),
+                        id: item.id.clone(),
+                        changes: field_changes,
+                    });
+                }
+            }
+            None => changes.push(ItemChange::Added {
+                kind: item.kind.to_string(),
+                id: item.id.clone(),
+            }),
+        }
+    }
+    for (item, matched) in current.iter().zip(&current_matched) {
+        if !matched {
+            changes.push(ItemChange::Removed {
+                kind: item.kind.to_string(),
+                id: item.id.clone(),
+            });
+        }
+    }
+    changes
+}
+
+fn action_item(action: &Action) -> Item {
+    match action {
+        Action::Exec(exec) => Item {
+            kind: "Exec",
+            id: exec.id.clone(),
+            fields: exec.fields(),
+        },
+        Action::ComHandler(handler) => Item {
+            kind: "ComHandler",
+            id: handler.id.clone(),
+            fields: handler.fields(),
+        },
+    }
+}
+
+/// Actions run in order, so they are compared by their position
+fn diff_actions(desired: &[Action], current: &[Action]) -> Vec<ItemChange> {
+    let mut changes = Vec::new();
+    for index in 0..desired.len().max(current.len()) {
+        let desired = desired.get(index).map(action_item);
+        let current = current.get(index).map(action_item);
+        match (desired, current) {
+            (Some(desired), Some(current)) if desired.kind == current.kind => {
+                let field_changes = diff_fields(&desired.fields, &current.fields);
+                if !field_changes.is_empty() {
+                    changes.push(ItemChange::Changed {
+                        kind: desired.kind.to_string(),
+                        id: desired.id,
+                        changes: field_changes,
+                    });
+                }
+            }
+            (desired, current) => {
+                if let Some(current) = current {
+                    changes.push(ItemChange::Removed {
+                        kind: current.kind.to_string(),
+                        id: current.id,
+                    });
+                }
+                if let Some(desired) = desired {
+                    changes.push(ItemChange::Added {
+                        kind: desired.kind.to_string(),
+                        id: desired.id,
+                    });
+                }
+            }
+        }
+    }
+    changes
+}
+
+impl Fields for TaskRegistration {
+    // the uri and date are filled in by Task Scheduler so they are never compared
+    fn fields(&self) -> Vec<Field> {
+        vec![
+            Field::unless_unset("author", self.author.clone()),
+            Field::unless_unset("description", self.description.clone()),
+            Field::unless_unset("documentation", self.documentation.clone()),
+            Field::unless_unset("source", self.source.clone()),
+            Field::unless_unset("version", self.version.clone()),
+        ]
+    }
+}
+
+impl Fields for TaskPrincipal {
+    fn fields(&self) -> Vec<Field> {
+        vec![
+            Field::unless_unset("user_id", self.user_id.clone()).account(),
+            Field::unless_unset("group_id", self.group_id.clone()).account(),
+            // the XML has no logon type for groups and service accounts
+            Field::unless_unset(
+                "logon_type",
+                self.implied_logon_type()
+                    .map(|logon_type| format!("{:?}", logon_type)),
+            ),
+            Field::value(
+                "run_level",
+                text(match self.run_level {
+                    RunLevel::LeastPrivilege => "LeastPrivilege",
+                    RunLevel::HighestAvailable => "HighestAvailable",
+                }),
+            ),
+            Field::unless_unset(
+                "required_privileges",
+                Some(
+                    self.required_privileges
+                        .iter()
+                        .map(|privilege| privilege.name())
+                        .collect::<Vec<_>>()
+                        .join(", "),
+                )
+                .filter(|privileges| !privileges.is_empty()),
+            ),
+        ]
+    }
+}
+
+impl Fields for Settings {
+    fn fields(&self) -> Vec<Field> {
+        let idle = &self.idle_conditions;
+        vec![
+            Field::value("allow_demand_start", text(self.allow_demand_start)),
+            Field::value("allow_hard_terminate", text(self.allow_hard_terminate)),
+            Field::value(
+                "disallow_start_if_on_batteries",
+                text(self.disallow_start_if_on_batteries),
+            ),
+            Field::value(
+                "stop_if_going_on_batteries",
+                text(self.stop_if_going_on_batteries),
+            ),
+            Field::value("enabled", text(self.enabled)),
+            Field::value("hidden", text(self.hidden)),
+            Field::value("start_when_available", text(self.start_when_available)),
+            Field::value("run_only_if_idle", text(self.run_only_if_idle)),
+            Field::value(
+                "run_only_if_network_available",
+                text(self.run_only_if_network_available),
+            ),
+            Field::value("wake_to_run", text(self.wake_to_run)),
+            Field::value("execution_time_limit", duration(self.execution_time_limit)),
+            Field::value(
+                "delete_expired_task_after",
+                duration(self.delete_expired_task_after),
+            ),
+            Field::value(
+                "multiple_instances",
+                text(match self.multiple_instances {
+                    MultipleInstancesPolicy::Parallel => "Parallel",
+                    MultipleInstancesPolicy::Queue => "Queue",
+                    MultipleInstancesPolicy::IgnoreNew => "IgnoreNew",
+                    MultipleInstancesPolicy::StopExisting => "StopExisting",
+                }),
+            ),
+            Field::value("priority", text(self.priority)),
+            Field::value(
+                "restart_on_failure.interval",
+                duration(
+                    self.restart_on_failure
+                        .as_ref()
+                        .map(|restart| restart.interval),
+                ),
+            ),
+            Field::value(
+                "restart_on_failure.count",
+                self.restart_on_failure
+                    .as_ref()
+                    .map(|restart| restart.count.to_string()),
+            ),
+            Field::value("idle.duration", duration(Some(idle.duration))),
+            Field::value("idle.wait_timeout", duration(Some(idle.wait_timeout))),
+            Field::value("idle.stop_on_idle_end", text(idle.stop_on_idle_end)),
+            Field::value("idle.restart_on_idle", text(idle.restart_on_idle)),
+            Field::value(
+                "use_unified_scheduling_engine",
+                text(self.use_unified_scheduling_engine),
+            ),
+            Field::value(
+                "disallow_start_on_remote_app_session",
+                text(self.disallow_start_on_remote_app_session),
+            ),
+            Field::value("volatile", text(self.volatile)),
+        ]
+    }
+}
+
+impl Fields for DailyTrigger {
+    fn fields(&self) -> Vec<Field> {
+        let mut fields = common_fields(&self.common);
+        fields.push(Field::value("interval", text(self.interval.unwrap_or(1))));
+        repetition_fields(&mut fields, self.repetition.as_ref());
+        fields
+    }
+}
+
+impl Fields for SpecificTimeTrigger {
+    fn fields(&self) -> Vec<Field> {
+        let mut fields = common_fields(&self.common);
+        repetition_fields(&mut fields, self.repetition.as_ref());
+        fields
+    }
+}
+
+impl Fields for WeeklyTrigger {
+    fn fields(&self) -> Vec<Field> {
+        let mut fields = common_fields(&self.common);
+        fields.extend(vec![
+            Field::value("days_of_week", text(self.days_of_week)),
+            Field::value("weeks_interval", text(self.weeks_interval.unwrap_or(1))),
+        ]);
+        repetition_fields(&mut fields, self.repetition.as_ref());
+        fields
+    }
+}
+
+impl Fields for MonthlyTrigger {
+    fn fields(&self) -> Vec<Field> {
+        let mut fields = common_fields(&self.common);
+        fields.extend(vec![
+            Field::value("days_of_month", text(self.days_of_month)),
+            Field::value("months", text(self.months)),
+            Field::value(
+                "run_on_last_day_of_month",
+                text(self.run_on_last_day_of_month),
+            ),
+        ]);
+        repetition_fields(&mut fields, self.repetition.as_ref());
+        fields
+    }
+}
+
+impl Fields for MonthlyDowTrigger {
+    fn fields(&self) -> Vec<Field> {
+        let mut fields = common_fields(&self.common);
+        fields.extend(vec![
+            Field::value("days_of_week", text(self.days_of_week)),
+            Field::value("weeks_of_month", text(self.weeks_of_month)),
+            Field::value("months", text(self.months)),
+            Field::value(
+                "run_on_last_week_of_month",
+                text(self.run_on_last_week_of_month),
+            ),
+        ]);
+        repetition_fields(&mut fields, self.repetition.as_ref());
+        fields
+    }
+}
+
+impl Fields for EventTrigger {
+    fn fields(&self) -> Vec<Field> {
+        let value_queries: Vec<String> = self
+            .value_queries
+            .iter()
+            .map(|(name, query)| format!("{}={}", name, query))
+            .collect();
+        let mut fields = common_fields(&self.common);
+        fields.extend(vec![
+            Field::value("subscription", text(&self.subscription)),
+            Field::value("delay", duration(self.delay)),
+            Field::value("value_queries", text(value_queries.join(", "))),
+        ]);
+        repetition_fields(&mut fields, self.repetition.as_ref());
+        fields
+    }
+}
+
+impl Fields for IdleTrigger {
+    fn fields(&self) -> Vec<Field> {
+        let mut fields = common_fields(&self.common);
+        repetition_fields(&mut fields, self.repetition.as_ref());
+        fields
+    }
+}
+
+impl Fields for RegistrationTrigger {
+    fn fields(&self) -> Vec<Field> {
+        let mut fields = common_fields(&self.common);
+        fields.push(Field::value("delay", duration(self.delay)));
+        fields
+    }
+}
+
+impl Fields for BootTrigger {
+    fn fields(&self) -> Vec<Field> {
+        let mut fields = common_fields(&self.common);
+        fields.push(Field::value("delay", duration(self.delay)));
+        fields
+    }
+}
+
+impl Fields for LogonTrigger {
+    fn fields(&self) -> Vec<Field> {
+        let mut fields = common_fields(&self.common);
+        fields.extend(vec![
+            Field::value("user_id", self.user_id.clone()),
+            Field::value("delay", duration(self.delay)),
+        ]);
+        fields
+    }
+}
+
+impl Fields for SessionStateChangeTrigger {
+    fn fields(&self) -> Vec<Field> {
+        let mut fields = common_fields(&self.common);
+        fields.extend(vec![
+            Field::value("state_change", text(format!("{:?}", self.state_change))),
+            Field::value("user_id", self.user_id.clone()),
+            Field::value("delay", duration(self.delay)),
+        ]);
+        fields
+    }
+}
+
+impl Fields for ExecAction {
+    fn fields(&self) -> Vec<Field> {
+        vec![
+            Field::value("path", text(self.path.to_string_lossy())),
+            Field::value("arguments", self.arguments.clone()),
+            Field::value(
+                "working_directory",
+                self.working_directory
+                    .as_ref()
+                    .map(|directory| directory.to_string_lossy().to_string()),
+            ),
+        ]
+    }
+}
+
+impl Fields for ComHandlerAction {
+    fn fields(&self) -> Vec<Field> {
+        vec![
+            Field::value("class_id", text(&self.class_id)),
+            Field::value("data", self.data.clone()),
+        ]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::PathBuf;
+
+    use chrono::NaiveDate;
+
+    use super::{ItemChange, Plan};
+    use crate::{
+        task::{RunLevel, Task, TaskLogon, TaskPrincipal},
+        Action, Actions, DailyTrigger, ExecAction, TaskTriggersBuilder,
+    };
+
+    fn task(principal: TaskPrincipal) -> Task {
+        let start = NaiveDate::from_ymd_opt(2030, 1, 2)
+            .unwrap()
+            .and_hms_opt(3, 0, 0)
+            .unwrap();
+        Task::new(
+            Actions::from_action(Action::Exec(
+                ExecAction::new(PathBuf::from("C:\\Tools\\backup.exe"))
+                    .with_arguments("--all".to_string()),
+            )),
+            TaskTriggersBuilder::new()
+                .with_daily(DailyTrigger::new("Nightly".to_string()).with_start_time(start))
+                .build(),
+        )
+        .with_principal(principal)
+    }
+
+    fn system() -> TaskPrincipal {
+        TaskPrincipal::new()
+            .with_user_id("SYSTEM".to_string())
+            .with_logon_type(TaskLogon::ServiceAccount)
+            .with_run_level(RunLevel::HighestAvailable)
+    }
+
+    /// The task the way Task Scheduler gives it back, with the principal changed
+    fn registered(task: &Task, principal: &str) -> Task {
+        let xml = task.to_xml();
+        let start = xml.find("<Principal ").unwrap();
+        let end = xml.find("</Principal>").unwrap() + "</Principal>".len();
+        Task::from_xml(&format!("{}{}{}", &xml[..start], principal, &xml[end..])).unwrap()
+    }
+
+    #[test]
+    fn plans_a_create_without_a_registered_task() {
+        assert_eq!(Plan::new(&task(system()), None), Plan::Create);
+    }
+
+    #[test]
+    fn reapplying_a_task_is_a_no_op() {
+        let task = task(system());
+        let current = Task::from_xml(&task.to_xml()).unwrap();
+        assert_eq!(Plan::new(&task, Some(&current)), Plan::NoChanges);
+    }
+
+    #[test]
+    fn service_accounts_match_their_security_identifier() {
+        let task = task(system());
+        for user_id in [
+            "S-1-5-18",
+            "NT AUTHORITY\\SYSTEM",
+            "nt authority\\system",
+            "system",
+        ] {
+            let current = registered(
+                &task,
+                &format!(
+                    "<Principal id=\"Author\"><UserId>{}</UserId><RunLevel>HighestAvailable</RunLevel></Principal>",
+                    user_id
+                ),
+            );
+            assert_eq!(current.principal().logon_type(), None);
+            assert_eq!(
+                Plan::new(&task, Some(&current)),
+                Plan::NoChanges,
+                "{}",
+                user_id
+            );
+        }
+    }
+
+    #[test]
+    fn users_match_without_case() {
+        let task = task(
+            TaskPrincipal::new()
+                .with_user_id("CONTOSO\\Backup".to_string())
+                .with_logon_type(TaskLogon::Password),
+        );
+        let current = registered(
+            &task,
+            "<Principal id=\"Author\"><UserId>contoso\\backup</UserId><LogonType>Password</LogonType></Principal>",
+        );
+        assert_eq!(Plan::new(&task, Some(&current)), Plan::NoChanges);
+    }
+
+    #[test]
+    fn a_different_user_is_a_change() {
+        let task = task(system());
+        let current = registered(
+            &task,
+            "<Principal id=\"Author\"><UserId>S-1-5-20</UserId><RunLevel>HighestAvailable</RunLevel></Principal>",
+        );
+        let diff = task.diff(&current);
+        assert_eq!(diff.principal().len(), 1);
+        let change = &diff.principal()[0];
+        assert_eq!(change.field(), "user_id");
+        assert_eq!(change.current(), Some("S-1-5-20"));
+        assert_eq!(change.desired(), Some("SYSTEM"));
+        assert_eq!(diff.to_string(), "~ principal.user_id: S-1-5-20 -> SYSTEM");
+    }
+
+    #[test]
+    fn unset_fields_keep_what_task_scheduler_filled_in() {
+        let current = task(system());
+        let desired = Task::new(
+            current.actions().clone(),
+            TaskTriggersBuilder::new()
+                .with_daily(DailyTrigger::new("Nightly".to_string()))
+                .build(),
+        )
+        .with_principal(TaskPrincipal::new().with_run_level(RunLevel::HighestAvailable));
+        assert_eq!(Plan::new(&desired, Some(&current)), Plan::NoChanges);
+    }
+
+    #[test]
+    fn triggers_are_matched_by_id() {
+        let desired = task(system());
+        let current = Task::new(
+            desired.actions().clone(),
+            TaskTriggersBuilder::new()
+                .with_daily(
+                    DailyTrigger::new("Nightly".to_string()).with_start_time(
+                        NaiveDate::from_ymd_opt(2030, 1, 2)
+                            .unwrap()
+                            .and_hms_opt(4, 0, 0)
+                            .unwrap(),
+                    ),
+                )
+                .with_daily(DailyTrigger::new("Old".to_string()))
+                .build(),
+        )
+        .with_principal(system());
+        let diff = desired.diff(&current);
+        assert_eq!(diff.triggers().len(), 2);
+        match &diff.triggers()[0] {
+            ItemChange::Changed { id, changes, .. } => {
+                assert_eq!(id.as_deref(), Some("Nightly"));
+                assert_eq!(changes[0].field(), "start_time");
+                assert_eq!(changes[0].current(), Some("2030-01-02T04:00:00"));
+                assert_eq!(changes[0].desired(), Some("2030-01-02T03:00:00"));
+            }
+            change => panic!("expected a changed trigger, got {}", change),
+        }
+        assert_eq!(
+            diff.triggers()[1],
+            ItemChange::Removed {
+                kind: "DailyTrigger".to_string(),
+                id: Some("Old".to_string())
+            }
+        );
+    }
+
+    #[test]
+    fn actions_are_compared_by_position() {
+        let desired = task(system());
+        let current = Task::new(
+            Actions::from_action(Action::Exec(ExecAction::new(PathBuf::from(
+                "C:\\Tools\\backup.exe",
+            ))))
+            .with_action(Action::Exec(ExecAction::new(PathBuf::from("cleanup.exe")))),
+            desired.triggers().clone(),
+        )
+        .with_principal(system());
+        let diff = desired.diff(&current);
+        assert_eq!(
+            diff.to_string(),
+            "~ Exec\n    arguments: (none) -> --all (action)\n- Exec (action)"
+        );
+        match Plan::new(&desired, Some(&current)) {
+            Plan::Update(update) => assert_eq!(update, diff),
+            plan => panic!("expected an update, got {}", plan),
+        }
+    }
+}
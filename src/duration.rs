@@ -0,0 +1,107 @@
+use chrono::Duration;
+
+/// Formats a duration the way the task scheduler schema expects it, which is
+/// an xsd:duration such as `PT5M`, `P1D` or `P3DT12H`
+///
+/// https://docs.microsoft.com/en-us/windows/win32/taskschd/taskschedulerschema-interval-repetitiontype-element
+pub(crate) fn to_xml_duration(duration: &Duration) -> String {
+    let mut seconds = duration.num_seconds().max(0);
+    let days = seconds / 86_400;
+    seconds %= 86_400;
+    let hours = seconds / 3_600;
+    seconds %= 3_600;
+    let minutes = seconds / 60;
+    seconds %= 60;
+
+    let mut formatted = String::from("P");
+    if days > 0 {
+        formatted.push_str(&format!("{}D", days));
+    }
+    if hours > 0 || minutes > 0 || seconds > 0 || days == 0 {
+        formatted.push('T');
+        if hours > 0 {
+            formatted.push_str(&format!("{}H", hours));
+        }
+        if minutes > 0 {
+            formatted.push_str(&format!("{}M", minutes));
+        }
+        if seconds > 0 || (days == 0 && hours == 0 && minutes == 0) {
+            formatted.push_str(&format!("{}S", seconds));
+        }
+    }
+    formatted
+}
+
+/// Parses an xsd:duration such as `PT5M` or `P1DT2H`
+///
+/// Years and months don't have a fixed length, so they are counted as
+/// 365 and 30 days, which is also what Task Scheduler does.
+/// Fractional seconds are dropped.
+pub(crate) fn parse_xml_duration(duration: &str) -> Option<Duration> {
+    let duration = duration.trim();
+    let rest = duration.strip_prefix('P')?;
+    if rest.is_empty() {
+        return None;
+    }
+
+    let (date_part, time_part) = match rest.find('T') {
+        Some(index) => (&rest[..index], Some(&rest[index + 1..])),
+        None => (rest, None),
+    };
+
+    let mut total = Duration::zero();
+    let mut has_component = false;
+    for (value, designator) in components(date_part)? {
+        let days = match designator {
+            'Y' => 365,
+            'M' => 30,
+            'W' => 7,
+            'D' => 1,
+            _ => return None,
+        };
+        total = total + Duration::days(value.trunc() as i64 * days);
+        has_component = true;
+    }
+    if let Some(time_part) = time_part {
+        if time_part.is_empty() {
+            return None;
+        }
+        for (value, designator) in components(time_part)? {
+            let seconds = match designator {
+                'H' => 3_600.0,
+                'M' => 60.0,
+                'S' => 1.0,
+                _ => return None,
+            };
+            total = total + Duration::seconds((value * seconds) as i64);
+            has_component = true;
+        }
+    }
+
+    if has_component {
+        Some(total)
+    } else {
+        None
+    }
+}
+
+/// Splits `1D2H` into `[(1.0, 'D'), (2.0, 'H')]`
+fn components(part: &str) -> Option<Vec<(f64, char)>> {
+    let mut components = Vec::new();
+    let mut number = String::new();
+    for character in part.chars() {
+        if character.is_ascii_digit() || character == '.' || character == ',' {
+            number.push(if character == ',' { '.' } else { character });
+        } else {
+            if number.is_empty() {
+                return None;
+            }
+            components.push((number.parse().ok()?, character));
+            number.clear();
+        }
+    }
+    if !number.is_empty() {
+        return None;
+    }
+    Some(components)
+}
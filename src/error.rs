@@ -1,13 +1,44 @@
 use std::{error::Error, fmt};
 
+use crate::SchemaVersion;
+
 #[derive(Debug)]
 pub enum TaskError {
     WinError(WinError),
     ComError(ComError),
     TaskServiceError(TaskServiceError),
+    XmlError(XmlError),
+    ManifestError(ManifestError),
+    CronError(CronError),
+    RRuleError(RRuleError),
+    JobError(JobError),
+    TaskFileError(TaskFileError),
+    SchtasksError(SchtasksError),
+    HistoryError(HistoryError),
+    SchemaError(SchemaError),
     Error(String),
 }
 
+impl fmt::Display for TaskError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            TaskError::WinError(error) => write!(f, "{}", error),
+            TaskError::ComError(error) => write!(f, "{}", error),
+            TaskError::TaskServiceError(error) => write!(f, "{}", error),
+            TaskError::XmlError(error) => write!(f, "{}", error),
+            TaskError::ManifestError(error) => write!(f, "{}", error),
+            TaskError::CronError(error) => write!(f, "{}", error),
+            TaskError::RRuleError(error) => write!(f, "{}", error),
+            TaskError::JobError(error) => write!(f, "{}", error),
+            TaskError::TaskFileError(error) => write!(f, "{}", error),
+            TaskError::SchtasksError(error) => write!(f, "{}", error),
+            TaskError::HistoryError(error) => write!(f, "{}", error),
+            TaskError::SchemaError(error) => write!(f, "{}", error),
+            TaskError::Error(error) => write!(f, "{}", error),
+        }
+    }
+}
+
 impl From<WinError> for TaskError {
     fn from(error: WinError) -> Self {
         TaskError::WinError(error)
@@ -23,6 +54,57 @@ impl From<TaskServiceError> for TaskError {
         TaskError::TaskServiceError(error)
     }
 }
+impl From<XmlError> for TaskError {
+    fn from(error: XmlError) -> Self {
+        TaskError::XmlError(error)
+    }
+}
+impl From<ManifestError> for TaskError {
+    fn from(error: ManifestError) -> Self {
+        TaskError::ManifestError(error)
+    }
+}
+#[cfg(windows)]
+impl From<windows::Error> for TaskError {
+    fn from(error: windows::Error) -> Self {
+        TaskError::WinError(WinError::from(error))
+    }
+}
+impl From<CronError> for TaskError {
+    fn from(error: CronError) -> Self {
+        TaskError::CronError(error)
+    }
+}
+impl From<RRuleError> for TaskError {
+    fn from(error: RRuleError) -> Self {
+        TaskError::RRuleError(error)
+    }
+}
+impl From<JobError> for TaskError {
+    fn from(error: JobError) -> Self {
+        TaskError::JobError(error)
+    }
+}
+impl From<TaskFileError> for TaskError {
+    fn from(error: TaskFileError) -> Self {
+        TaskError::TaskFileError(error)
+    }
+}
+impl From<SchtasksError> for TaskError {
+    fn from(error: SchtasksError) -> Self {
+        TaskError::SchtasksError(error)
+    }
+}
+impl From<HistoryError> for TaskError {
+    fn from(error: HistoryError) -> Self {
+        TaskError::HistoryError(error)
+    }
+}
+impl From<SchemaError> for TaskError {
+    fn from(error: SchemaError) -> Self {
+        TaskError::SchemaError(error)
+    }
+}
 impl From<String> for TaskError {
     fn from(error: String) -> Self {
         TaskError::Error(error)
@@ -30,10 +112,10 @@ impl From<String> for TaskError {
 }
 #[derive(Debug)]
 pub enum ComError {
-    /// A previous call to CoInitializeEx specified the concurrency model
-    /// for this thread as multithread apartment (MTA). This could also
-    /// indicate that a change from neutral-threaded apartment to single-threaded
-    /// apartment has occurred.
+    /// A previous call to CoInitializeEx specified a different concurrency model
+    /// for this thread, single-threaded apartment (STA) or multithreaded apartment (MTA).
+    /// This could also indicate that a change from neutral-threaded apartment to
+    /// single-threaded apartment has occurred.
     RpcChangedMode,
     /// CoInitializeSecurity has already been called.
     RpcTooLate,
@@ -60,11 +142,32 @@ impl From<WinError> for ComError {
     }
 }
 
+#[cfg(windows)]
+impl From<windows::Error> for ComError {
+    fn from(error: windows::Error) -> Self {
+        match error.code().0 {
+            // RPC_E_CHANGED_MODE
+            0x8001_0106 => ComError::RpcChangedMode,
+            // RPC_E_TOO_LATE
+            0x8001_0119 => ComError::RpcTooLate,
+            // RPC_E_NO_GOOD_SECURITY_PACKAGES
+            0x8001_011A => ComError::NoGoodSecurityPackages,
+            // REGDB_E_CLASSNOTREG
+            0x8004_0154 => ComError::RegdbClassNotReg,
+            // CLASS_E_NOAGGREGATION
+            0x8004_0110 => ComError::ClassNoAggregation,
+            // E_NOINTERFACE
+            0x8000_4002 => ComError::NoInterface,
+            _ => ComError::GeneralError(WinError::from(error)),
+        }
+    }
+}
+
 impl fmt::Display for ComError {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         match self {
             ComError::RpcChangedMode => {
-                write!(f, "A previous call to CoInitializeEx specified the concurrency model for this thread as multithread apartment (MTA). This could also indicate that a change from neutral-threaded apartment to single-threaded apartment has occurred.")
+                write!(f, "A previous call to CoInitializeEx specified a different concurrency model for this thread, single-threaded apartment (STA) or multithreaded apartment (MTA). This could also indicate that a change from neutral-threaded apartment to single-threaded apartment has occurred.")
             }
             ComError::RpcTooLate => {
                 write!(f, "CoInitializeSecurity has already been called.")
@@ -87,6 +190,11 @@ impl fmt::Display for ComError {
         }
     }
 }
+impl Error for ComError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        None
+    }
+}
 /// These are considered common windows errors
 ///
 /// https://docs.microsoft.com/en-us/windows/win32/seccrypto/common-hresult-values
@@ -144,6 +252,24 @@ impl fmt::Display for WinError {
         }
     }
 }
+#[cfg(windows)]
+impl From<windows::Error> for WinError {
+    fn from(error: windows::Error) -> Self {
+        match error.code().0 {
+            0x8007_0057 => WinError::InvalidArg(error.message()),
+            0x8007_000E => WinError::OutOfMemory,
+            0x8000_FFFF => WinError::Unexpected,
+            0x8000_4004 => WinError::Abort,
+            0x8007_0005 => WinError::AccessDenied,
+            0x8000_4005 => WinError::Fail,
+            0x8007_0006 => WinError::Handle,
+            0x8000_4002 => WinError::NoInterface,
+            0x8000_4001 => WinError::NotImpl,
+            0x8000_4003 => WinError::Pointer(error.message()),
+            _ => WinError::UnknownError(error.message()),
+        }
+    }
+}
 impl Error for WinError {
     fn source(&self) -> Option<&(dyn Error + 'static)> {
         None
@@ -168,3 +294,423 @@ pub enum TaskServiceError {
     /// [`ComError`]
     ComError(ComError),
 }
+
+impl fmt::Display for TaskServiceError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            TaskServiceError::AccessDenied => {
+                write!(f, "Access is denied to connect to the Task Scheduler service")
+            }
+            TaskServiceError::SchedulerServiceNotRunning => {
+                write!(f, "The Task Scheduler service is not running")
+            }
+            TaskServiceError::BadNetPath => write!(
+                f,
+                "The computer can't be reached, or the firewall of the computer blocks remote task management"
+            ),
+            TaskServiceError::NotSupported => write!(
+                f,
+                "The user, domain and password can't be given when connecting to the computer"
+            ),
+            TaskServiceError::WinError(error) => write!(f, "{}", error),
+            TaskServiceError::ComError(error) => write!(f, "{}", error),
+        }
+    }
+}
+#[cfg(windows)]
+impl From<windows::Error> for TaskServiceError {
+    fn from(error: windows::Error) -> Self {
+        match error.code().0 {
+            // E_ACCESSDENIED
+            0x8007_0005 => TaskServiceError::AccessDenied,
+            // SCHED_E_SERVICE_NOT_RUNNING
+            0x8004_1315 => TaskServiceError::SchedulerServiceNotRunning,
+            // HRESULT_FROM_WIN32(ERROR_BAD_NETPATH)
+            0x8007_0035 => TaskServiceError::BadNetPath,
+            // HRESULT_FROM_WIN32(ERROR_NOT_SUPPORTED)
+            0x8007_0032 => TaskServiceError::NotSupported,
+            _ => TaskServiceError::WinError(WinError::from(error)),
+        }
+    }
+}
+impl Error for TaskServiceError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        None
+    }
+}
+
+/// Errors from reading task XML, such as the XML of a registered task
+///
+/// https://docs.microsoft.com/en-us/windows/win32/taskschd/task-scheduler-schema
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum XmlError {
+    /// The document isn't well formed XML
+    Malformed(String),
+    /// A required element is missing
+    MissingElement(String),
+    /// An element has a value that doesn't fit the schema
+    InvalidValue { element: String, value: String },
+    /// The element is valid in the schema but this crate can't represent it
+    Unsupported(String),
+}
+
+impl fmt::Display for XmlError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            XmlError::Malformed(description) => {
+                write!(f, "The task XML is malformed: {}", description)
+            }
+            XmlError::MissingElement(element) => {
+                write!(f, "The task XML is missing the {} element", element)
+            }
+            XmlError::InvalidValue { element, value } => {
+                write!(f, "The {} element has an invalid value: {}", element, value)
+            }
+            XmlError::Unsupported(element) => {
+                write!(f, "The {} element is not supported", element)
+            }
+        }
+    }
+}
+impl Error for XmlError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        None
+    }
+}
+
+/// Errors from reading a task manifest
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ManifestError {
+    /// The manifest isn't valid TOML or doesn't have the expected layout
+    Parse(String),
+    /// A value in a task doesn't make sense, like an unknown day of the week
+    InvalidValue {
+        task: String,
+        field: String,
+        value: String,
+    },
+    /// Two tasks in the same folder have the same name
+    DuplicateTask(String),
+    /// A task has no actions, or more actions or triggers than Task Scheduler allows
+    Limit { task: String, description: String },
+}
+
+impl fmt::Display for ManifestError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ManifestError::Parse(description) => {
+                write!(f, "The manifest could not be read: {}", description)
+            }
+            ManifestError::InvalidValue { task, field, value } => {
+                write!(f, "{}: {} has an invalid value: {}", task, field, value)
+            }
+            ManifestError::DuplicateTask(path) => {
+                write!(f, "{} is in the manifest more than once", path)
+            }
+            ManifestError::Limit { task, description } => {
+                write!(f, "{}: {}", task, description)
+            }
+        }
+    }
+}
+impl Error for ManifestError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        None
+    }
+}
+
+/// Errors from converting a cron expression into task triggers
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum CronError {
+    /// The expression doesn't have five fields, or six with seconds first
+    FieldCount(usize),
+    /// A field has a value that isn't valid cron
+    Syntax { field: String, value: String },
+    /// The expression is valid cron but triggers can't reproduce it
+    Unsupported(String),
+    /// Reproducing the expression needs a trigger for every time of day pattern
+    /// on every day pattern, which is more triggers than a task can have
+    TooManyTriggers {
+        needed: usize,
+        times_of_day: usize,
+        days: usize,
+    },
+}
+
+impl fmt::Display for CronError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            CronError::FieldCount(count) => write!(
+                f,
+                "A cron expression has 5 fields, or 6 with seconds first, not {}",
+                count
+            ),
+            CronError::Syntax { field, value } => {
+                write!(f, "The {} field has an invalid value: {}", field, value)
+            }
+            CronError::Unsupported(description) => {
+                write!(f, "The cron expression can't be converted: {}", description)
+            }
+            CronError::TooManyTriggers {
+                needed,
+                times_of_day,
+                days,
+            } => write!(
+                f,
+                "The cron expression needs {} triggers, {} time of day patterns on {} day patterns, but a task can only have 48",
+                needed, times_of_day, days
+            ),
+        }
+    }
+}
+impl Error for CronError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        None
+    }
+}
+
+/// Errors from converting between iCalendar recurrence rules and triggers
+///
+/// https://datatracker.ietf.org/doc/html/rfc5545#section-3.3.10
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum RRuleError {
+    /// A property or rule part has a value that isn't valid iCalendar
+    Syntax { part: String, value: String },
+    /// There is no DTSTART, which the rule takes its defaults from
+    MissingStart,
+    /// The rule is valid but triggers can't reproduce it, or the trigger
+    /// has no recurrence rule
+    Unsupported(String),
+    /// Reproducing the rule needs more triggers than a task can have
+    TooManyTriggers(usize),
+}
+
+impl fmt::Display for RRuleError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            RRuleError::Syntax { part, value } => {
+                write!(f, "{} has an invalid value: {}", part, value)
+            }
+            RRuleError::MissingStart => write!(f, "The recurrence rule has no DTSTART"),
+            RRuleError::Unsupported(description) => {
+                write!(f, "The recurrence rule can't be converted: {}", description)
+            }
+            RRuleError::TooManyTriggers(needed) => write!(
+                f,
+                "The recurrence rule needs {} triggers, but a task can only have 48",
+                needed
+            ),
+        }
+    }
+}
+impl Error for RRuleError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        None
+    }
+}
+
+/// Errors from reading or writing a Task Scheduler 1.0 `.job` file
+///
+/// https://docs.microsoft.com/en-us/openspecs/windows_protocols/ms-tsch
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum JobError {
+    /// The file ends before the field
+    Truncated(String),
+    /// A field has a value that a `.job` file can't have
+    Invalid { field: String, value: String },
+    /// The task has something that a `.job` file can't hold
+    Unsupported(String),
+}
+
+impl fmt::Display for JobError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            JobError::Truncated(field) => write!(f, "The .job file ends before the {}", field),
+            JobError::Invalid { field, value } => {
+                write!(f, "The {} in the .job file is invalid: {}", field, value)
+            }
+            JobError::Unsupported(description) => {
+                write!(f, "The task can't be a .job file: {}", description)
+            }
+        }
+    }
+}
+impl Error for JobError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        None
+    }
+}
+
+/// Errors from reading a task file or folder from a copy of a `System32\Tasks` directory
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum TaskFileError {
+    /// The file or folder couldn't be read
+    Io(String),
+    /// The file isn't UTF-8 or UTF-16 text
+    Encoding(String),
+    /// The file isn't valid task XML
+    Xml(XmlError),
+}
+
+impl fmt::Display for TaskFileError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            TaskFileError::Io(description) => write!(f, "{}", description),
+            TaskFileError::Encoding(description) => {
+                write!(f, "The task file isn't UTF-8 or UTF-16: {}", description)
+            }
+            TaskFileError::Xml(error) => write!(f, "{}", error),
+        }
+    }
+}
+impl Error for TaskFileError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        None
+    }
+}
+impl From<XmlError> for TaskFileError {
+    fn from(error: XmlError) -> Self {
+        TaskFileError::Xml(error)
+    }
+}
+
+/// Errors from reading a `schtasks /create` command line or the output of `schtasks /query`
+///
+/// https://docs.microsoft.com/en-us/windows-server/administration/windows-commands/schtasks-create
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum SchtasksError {
+    /// The command isn't `schtasks /create`, or an argument isn't a switch
+    Syntax(String),
+    /// `schtasks /create` has no such switch
+    UnknownSwitch(String),
+    /// The switch is given more than once
+    Duplicate(String),
+    /// The switch is the last argument but needs a value
+    MissingValue(String),
+    /// The command doesn't have a switch that it needs, like `/tn` or `/sc`
+    Missing(String),
+    /// A switch has a value that isn't valid for it
+    Invalid { switch: String, value: String },
+    /// The switches can't be used together, or the switch isn't valid for the schedule
+    Conflict { switch: String, other: String },
+    /// The switch only works together with another one
+    Requires { switch: String, needs: String },
+    /// The command is valid but can't be turned into a task
+    Unsupported(String),
+    /// A line of the query output isn't valid CSV, or has more or fewer values than the header
+    Csv { line: usize, description: String },
+    /// The header of the query output has no column for a value that is needed, like the task name
+    MissingColumn(String),
+}
+
+impl fmt::Display for SchtasksError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SchtasksError::Syntax(description) => {
+                write!(f, "The command can't be read: {}", description)
+            }
+            SchtasksError::UnknownSwitch(switch) => {
+                write!(f, "schtasks /create has no {} switch", switch)
+            }
+            SchtasksError::Duplicate(switch) => write!(f, "{} is given more than once", switch),
+            SchtasksError::MissingValue(switch) => write!(f, "{} is missing its value", switch),
+            SchtasksError::Missing(switch) => write!(f, "The command needs {}", switch),
+            SchtasksError::Invalid { switch, value } => {
+                write!(f, "{} has an invalid value: {}", switch, value)
+            }
+            SchtasksError::Conflict { switch, other } => {
+                write!(f, "{} can't be used with {}", switch, other)
+            }
+            SchtasksError::Requires { switch, needs } => {
+                write!(f, "{} only works with {}", switch, needs)
+            }
+            SchtasksError::Unsupported(description) => {
+                write!(f, "The command can't be converted: {}", description)
+            }
+            SchtasksError::Csv { line, description } => write!(f, "Line {}: {}", line, description),
+            SchtasksError::MissingColumn(column) => {
+                write!(f, "The output has no {} column", column)
+            }
+        }
+    }
+}
+impl Error for SchtasksError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        None
+    }
+}
+
+/// Errors from reading exported events or an `.evtx` file of the
+/// `Microsoft-Windows-TaskScheduler/Operational` log
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum HistoryError {
+    /// The events aren't well formed XML
+    Malformed(String),
+    /// A Task Scheduler event is missing a value the history needs, or has an invalid one
+    InvalidEvent { record: String, description: String },
+    /// The `.evtx` file is truncated or corrupt at the offset
+    Evtx { offset: usize, description: String },
+}
+
+impl fmt::Display for HistoryError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            HistoryError::Malformed(description) => {
+                write!(f, "The events are malformed: {}", description)
+            }
+            HistoryError::InvalidEvent {
+                record,
+                description,
+            } => write!(f, "Event record {}: {}", record, description),
+            HistoryError::Evtx {
+                offset,
+                description,
+            } => write!(
+                f,
+                "The .evtx file is invalid at {:#x}: {}",
+                offset, description
+            ),
+        }
+    }
+}
+impl Error for HistoryError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        None
+    }
+}
+
+/// Errors from a task that uses settings of a newer schema version than Task Scheduler has
+///
+/// https://docs.microsoft.com/en-us/windows/win32/taskschd/task-scheduler-schema
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum SchemaError {
+    /// The settings need a newer version than the target, each with the version it needs
+    TooNew {
+        target: SchemaVersion,
+        settings: Vec<(String, SchemaVersion)>,
+    },
+}
+
+impl fmt::Display for SchemaError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SchemaError::TooNew { target, settings } => {
+                let settings: Vec<String> = settings
+                    .iter()
+                    .map(|(setting, version)| format!("`{}` needs {}", setting, version))
+                    .collect();
+                write!(
+                    f,
+                    "The task is too new for schema {}: {}",
+                    target,
+                    settings.join(", ")
+                )
+            }
+        }
+    }
+}
+impl Error for SchemaError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        None
+    }
+}
@@ -0,0 +1,665 @@
+use std::{convert::TryInto, fmt::Write};
+
+use chrono::DateTime;
+use log::warn;
+
+use crate::{
+    error::HistoryError,
+    history::{self, TaskHistory},
+    task_xml::escape,
+};
+
+const FILE_SIGNATURE: &[u8] = b"ElfFile\0";
+const CHUNK_SIGNATURE: &[u8] = b"ElfChnk\0";
+const RECORD_SIGNATURE: &[u8] = b"**\0\0";
+
+/// The size of the file header, the chunks follow it
+const FILE_HEADER_SIZE: usize = 0x1000;
+const CHUNK_SIZE: usize = 0x1_0000;
+/// The records of a chunk start after its header and its tables of names and templates
+const CHUNK_HEADER_SIZE: usize = 0x200;
+/// The offset in the chunk header of the end of the last record
+const FREE_SPACE_OFFSET: usize = 0x30;
+/// The signature, size, identifier and time before the binary XML of a record
+const RECORD_HEADER_SIZE: usize = 0x18;
+/// How deep templates can nest, so that a corrupt file can't make them loop
+const MAX_DEPTH: usize = 32;
+/// The seconds from 1601-01-01, the start of a FILETIME, to 1970-01-01
+const FILETIME_EPOCH: i64 = 11_644_473_600;
+
+// binary XML tokens, the MORE_DATA bit is set on some of them
+const END_OF_STREAM: u8 = 0x00;
+const OPEN_START_ELEMENT: u8 = 0x01;
+const CLOSE_START_ELEMENT: u8 = 0x02;
+const CLOSE_EMPTY_ELEMENT: u8 = 0x03;
+const END_ELEMENT: u8 = 0x04;
+const VALUE: u8 = 0x05;
+const ATTRIBUTE: u8 = 0x06;
+const CDATA_SECTION: u8 = 0x07;
+const CHAR_REF: u8 = 0x08;
+const ENTITY_REF: u8 = 0x09;
+const PI_TARGET: u8 = 0x0a;
+const PI_DATA: u8 = 0x0b;
+const TEMPLATE_INSTANCE: u8 = 0x0c;
+const NORMAL_SUBSTITUTION: u8 = 0x0d;
+const OPTIONAL_SUBSTITUTION: u8 = 0x0e;
+const FRAGMENT_HEADER: u8 = 0x0f;
+/// An element with attributes, another attribute follows, or more text follows
+const MORE_DATA: u8 = 0x40;
+
+// the types of values, the ARRAY bit is set on arrays of them
+const NULL: u8 = 0x00;
+const STRING: u8 = 0x01;
+const ANSI_STRING: u8 = 0x02;
+const INT8: u8 = 0x03;
+const UINT8: u8 = 0x04;
+const INT16: u8 = 0x05;
+const UINT16: u8 = 0x06;
+const INT32: u8 = 0x07;
+const UINT32: u8 = 0x08;
+const INT64: u8 = 0x09;
+const UINT64: u8 = 0x0a;
+const REAL32: u8 = 0x0b;
+const REAL64: u8 = 0x0c;
+const BOOL: u8 = 0x0d;
+const BINARY: u8 = 0x0e;
+const GUID: u8 = 0x0f;
+const SIZE_T: u8 = 0x10;
+const FILETIME: u8 = 0x11;
+const SYSTEMTIME: u8 = 0x12;
+const SID: u8 = 0x13;
+const HEX_INT32: u8 = 0x14;
+const HEX_INT64: u8 = 0x15;
+const BIN_XML: u8 = 0x21;
+const ARRAY: u8 = 0x80;
+
+impl TaskHistory {
+    /// Reads the runs from a `Microsoft-Windows-TaskScheduler%4Operational.evtx` file,
+    /// the log file itself rather than events exported from it, so that the history of a
+    /// machine can be read anywhere. Events from other providers and other event ids are
+    /// skipped, like in [`TaskHistory::from_events_xml`].
+    ///
+    /// The records are read up to the end of the last record that the header of their
+    /// chunk has, and chunks that were never written are skipped. A copy of a log that
+    /// was in use can end in a chunk that is truncated or partly written, so reading
+    /// stops at the first record that can't be read and the runs before it are kept.
+    /// Checksums aren't verified.
+    ///
+    /// https://docs.microsoft.com/en-us/openspecs/windows_protocols/ms-even6
+    pub fn from_evtx(bytes: &[u8]) -> Result<TaskHistory, HistoryError> {
+        if !bytes.starts_with(FILE_SIGNATURE) {
+            return Err(HistoryError::Evtx {
+                offset: 0,
+                description: "the file isn't an event log".to_string(),
+            });
+        }
+        let mut events = Vec::new();
+        let mut offset = FILE_HEADER_SIZE;
+        while offset < bytes.len() {
+            let chunk = Chunk {
+                bytes: &bytes[offset..bytes.len().min(offset + CHUNK_SIZE)],
+                offset,
+            };
+            if chunk.bytes.starts_with(CHUNK_SIGNATURE) {
+                let mut xml = String::from("<Events>");
+                let read = chunk.events_xml(&mut xml);
+                xml.push_str("</Events>");
+                history::read_events(&xml, &mut events)?;
+                if let Err(error) = read {
+                    warn!("Stopped reading the event log: {}", error);
+                    break;
+                }
+            }
+            offset += CHUNK_SIZE;
+        }
+        Ok(TaskHistory::from_events(events))
+    }
+}
+
+/// A chunk of an `.evtx` file with its offset in the file.
+/// The names and templates of the binary XML in a chunk are at offsets in the chunk.
+#[derive(Clone, Copy)]
+struct Chunk<'a> {
+    bytes: &'a [u8],
+    offset: usize,
+}
+
+impl<'a> Chunk<'a> {
+    /// Writes the records as `Event` elements, up to the first record that can't be read
+    fn events_xml(self, xml: &mut String) -> Result<(), HistoryError> {
+        if self.bytes.len() < CHUNK_HEADER_SIZE {
+            return Err(self.invalid(0, "the chunk header is truncated"));
+        }
+        let mut header = self.reader(FREE_SPACE_OFFSET, CHUNK_HEADER_SIZE);
+        let end = (header.u32("free space offset")? as usize).clamp(CHUNK_HEADER_SIZE, CHUNK_SIZE);
+        let mut offset = CHUNK_HEADER_SIZE;
+        while self.bytes[offset..end.min(self.bytes.len())].starts_with(RECORD_SIGNATURE) {
+            let size = self
+                .reader(offset + RECORD_SIGNATURE.len(), end)
+                .u32("record size")? as usize;
+            if size < RECORD_HEADER_SIZE + 4 || offset + size > end {
+                return Err(self.invalid(offset, format!("the record size {} is invalid", size)));
+            }
+            if offset + size > self.bytes.len() {
+                return Err(self.invalid(offset, "the record is truncated"));
+            }
+            // the size is also at the end of the record
+            let mut record = self.reader(offset + RECORD_HEADER_SIZE, offset + size - 4);
+            let length = xml.len();
+            if let Err(error) = fragment(&mut record, &[], 0, xml) {
+                xml.truncate(length);
+                return Err(error);
+            }
+            offset += size;
+        }
+        Ok(())
+    }
+
+    fn reader(self, offset: usize, end: usize) -> ChunkReader<'a> {
+        ChunkReader {
+            chunk: self,
+            offset,
+            end,
+        }
+    }
+
+    fn invalid(self, offset: usize, description: impl ToString) -> HistoryError {
+        HistoryError::Evtx {
+            offset: self.offset + offset,
+            description: description.to_string(),
+        }
+    }
+}
+
+/// Reads little endian values from a part of a chunk
+struct ChunkReader<'a> {
+    chunk: Chunk<'a>,
+    offset: usize,
+    end: usize,
+}
+
+impl<'a> ChunkReader<'a> {
+    fn take(&mut self, field: &str, length: usize) -> Result<&'a [u8], HistoryError> {
+        let bytes = self
+            .offset
+            .checked_add(length)
+            .filter(|end| *end <= self.end)
+            .and_then(|end| self.chunk.bytes.get(self.offset..end))
+            .ok_or_else(|| self.invalid(format!("the {} is truncated", field)))?;
+        self.offset += length;
+        Ok(bytes)
+    }
+
+    fn skip(&mut self, field: &str, length: usize) -> Result<(), HistoryError> {
+        self.take(field, length).map(|_| ())
+    }
+
+    fn peek(&self) -> Option<u8> {
+        self.chunk
+            .bytes
+            .get(self.offset)
+            .copied()
+            .filter(|_| self.offset < self.end)
+    }
+
+    fn u8(&mut self, field: &str) -> Result<u8, HistoryError> {
+        Ok(self.take(field, 1)?[0])
+    }
+
+    fn u16(&mut self, field: &str) -> Result<u16, HistoryError> {
+        let bytes = self.take(field, 2)?;
+        Ok(u16::from_le_bytes([bytes[0], bytes[1]]))
+    }
+
+    fn u32(&mut self, field: &str) -> Result<u32, HistoryError> {
+        let bytes = self.take(field, 4)?;
+        Ok(u32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]))
+    }
+
+    /// A UTF-16 string that starts with its length in characters
+    fn string(&mut self, field: &str) -> Result<String, HistoryError> {
+        let length = usize::from(self.u16(field)?);
+        let bytes = self.take(field, length * 2)?;
+        Ok(utf16(bytes).unwrap_or_default())
+    }
+
+    /// The name of an element, attribute or entity at an offset in the chunk.
+    /// A name is written where it is first used, and it is skipped there.
+    fn name(&mut self) -> Result<String, HistoryError> {
+        let offset = self.u32("name offset")? as usize;
+        let mut name = self.chunk.reader(offset, CHUNK_SIZE);
+        // the offset of the next name with the same hash, and the hash
+        name.skip("name", 6)?;
+        let text = name.string("name")?;
+        if offset == self.offset {
+            // and the null at its end
+            self.skip("name", name.offset + 2 - offset)?;
+        }
+        Ok(text)
+    }
+
+    fn invalid(&self, description: impl ToString) -> HistoryError {
+        self.chunk.invalid(self.offset, description)
+    }
+}
+
+/// A value of a template instance, in the chunk
+struct Value {
+    kind: u8,
+    offset: usize,
+    size: usize,
+}
+
+/// Writes binary XML as XML up to the end of the stream, with the values of the
+/// template instance when it is the definition of a template
+fn fragment(
+    reader: &mut ChunkReader,
+    values: &[Value],
+    depth: usize,
+    xml: &mut String,
+) -> Result<(), HistoryError> {
+    if depth > MAX_DEPTH {
+        return Err(reader.invalid("the templates nest too deep"));
+    }
+    let mut elements = Vec::new();
+    while reader.offset < reader.end {
+        let token = reader.u8("token")?;
+        match token & !MORE_DATA {
+            END_OF_STREAM => break,
+            OPEN_START_ELEMENT => {
+                reader.skip("dependency identifier", 2)?;
+                reader.skip("element size", 4)?;
+                let name = reader.name()?;
+                if token & MORE_DATA != 0 {
+                    reader.skip("attribute list size", 4)?;
+                }
+                xml.push('<');
+                xml.push_str(&name);
+                elements.push(name);
+            }
+            CLOSE_START_ELEMENT => xml.push('>'),
+            CLOSE_EMPTY_ELEMENT => {
+                elements.pop();
+                xml.push_str("/>");
+            }
+            END_ELEMENT => {
+                let name = elements
+                    .pop()
+                    .ok_or_else(|| reader.invalid("an element ends that didn't start"))?;
+                write!(xml, "</{}>", name).unwrap();
+            }
+            ATTRIBUTE => {
+                let name = reader.name()?;
+                let mut value = String::new();
+                let mut left_out = false;
+                while let Some(token) = reader.peek().filter(|token| is_text(*token)) {
+                    reader.offset += 1;
+                    left_out |= text(token, reader, values, depth, &mut value)?;
+                }
+                // like wevtutil, an attribute without its optional value is left out
+                if !left_out || !value.is_empty() {
+                    write!(xml, " {}=\"{}\"", name, value).unwrap();
+                }
+            }
+            CDATA_SECTION => {
+                let text = reader.string("CDATA section")?;
+                write!(xml, "<![CDATA[{}]]>", text).unwrap();
+            }
+            PI_TARGET => {
+                let name = reader.name()?;
+                write!(xml, "<?{}", name).unwrap();
+            }
+            PI_DATA => {
+                let data = reader.string("processing instruction")?;
+                write!(xml, " {}?>", data).unwrap();
+            }
+            TEMPLATE_INSTANCE => template(reader, depth, xml)?,
+            FRAGMENT_HEADER => reader.skip("fragment header", 3)?,
+            _ if is_text(token) => {
+                text(token, reader, values, depth, xml)?;
+            }
+            _ => {
+                reader.offset -= 1;
+                return Err(reader.invalid(format!("{:#04x} isn't a binary XML token", token)));
+            }
+        }
+    }
+    match elements.pop() {
+        Some(name) => Err(reader.invalid(format!("the {} element doesn't end", name))),
+        None => Ok(()),
+    }
+}
+
+fn is_text(token: u8) -> bool {
+    matches!(
+        token & !MORE_DATA,
+        VALUE | CHAR_REF | ENTITY_REF | NORMAL_SUBSTITUTION | OPTIONAL_SUBSTITUTION
+    )
+}
+
+/// Writes the text of an element or attribute, `true` when it is an optional
+/// substitution that has no value
+fn text(
+    token: u8,
+    reader: &mut ChunkReader,
+    values: &[Value],
+    depth: usize,
+    xml: &mut String,
+) -> Result<bool, HistoryError> {
+    match token & !MORE_DATA {
+        VALUE => {
+            let kind = reader.u8("value type")?;
+            if kind != STRING {
+                return Err(reader.invalid(format!("a value has the type {:#04x}", kind)));
+            }
+            xml.push_str(&escape(&reader.string("value")?));
+        }
+        CHAR_REF => {
+            let character = reader.u16("character reference")?;
+            write!(xml, "&#{};", character).unwrap();
+        }
+        ENTITY_REF => {
+            let name = reader.name()?;
+            write!(xml, "&{};", name).unwrap();
+        }
+        substitution => {
+            let index = usize::from(reader.u16("substitution")?);
+            reader.skip("substitution type", 1)?;
+            let optional = substitution == OPTIONAL_SUBSTITUTION;
+            match values.get(index) {
+                Some(value) if value.kind == NULL || value.size == 0 => return Ok(optional),
+                Some(value) => value.write(reader.chunk, depth, xml)?,
+                None if optional => return Ok(true),
+                None => return Err(reader.invalid(format!("value {} is missing", index))),
+            }
+        }
+    }
+    Ok(false)
+}
+
+/// Writes a template instance: the offset of its definition, which is written
+/// where the template is first used, and then its values
+fn template(reader: &mut ChunkReader, depth: usize, xml: &mut String) -> Result<(), HistoryError> {
+    reader.skip("template version", 1)?;
+    reader.skip("template identifier", 4)?;
+    let offset = reader.u32("template offset")? as usize;
+    // the offset of the next template with the same hash, and the GUID of the template
+    let mut header = reader.chunk.reader(offset + 20, CHUNK_SIZE);
+    let size = header.u32("template size")? as usize;
+    let mut definition = reader.chunk.reader(header.offset, header.offset + size);
+    if offset == reader.offset {
+        reader.skip("template", header.offset + size - offset)?;
+    }
+
+    let count = reader.u32("number of values")?;
+    let mut descriptors = Vec::new();
+    for _ in 0..count {
+        let size = usize::from(reader.u16("value size")?);
+        let kind = reader.u8("value type")?;
+        reader.skip("value type", 1)?;
+        descriptors.push((kind, size));
+    }
+    let mut values = Vec::with_capacity(descriptors.len());
+    for (kind, size) in descriptors {
+        values.push(Value {
+            kind,
+            offset: reader.offset,
+            size,
+        });
+        reader.skip("value", size)?;
+    }
+    fragment(&mut definition, &values, depth + 1, xml)
+}
+
+impl Value {
+    fn write(&self, chunk: Chunk, depth: usize, xml: &mut String) -> Result<(), HistoryError> {
+        let mut reader = chunk.reader(self.offset, self.offset + self.size);
+        if self.kind == BIN_XML {
+            return fragment(&mut reader, &[], depth + 1, xml);
+        }
+        let bytes = reader.take("value", self.size)?;
+        let text = if self.kind & ARRAY != 0 {
+            array(self.kind & !ARRAY, bytes)
+        } else {
+            scalar(self.kind, bytes)
+        };
+        let text = text.ok_or_else(|| {
+            chunk.invalid(
+                self.offset,
+                format!(
+                    "a value of type {:#04x} can't have {} bytes",
+                    self.kind, self.size
+                ),
+            )
+        })?;
+        xml.push_str(&escape(&text));
+        Ok(())
+    }
+}
+
+/// The text of a value, like wevtutil writes it
+fn scalar(kind: u8, bytes: &[u8]) -> Option<String> {
+    let text = match kind {
+        NULL => String::new(),
+        STRING => utf16(bytes)?.trim_end_matches('\0').to_string(),
+        ANSI_STRING => String::from_utf8_lossy(bytes)
+            .trim_end_matches('\0')
+            .to_string(),
+        INT8 => i8::from_le_bytes(bytes.try_into().ok()?).to_string(),
+        UINT8 => u8::from_le_bytes(bytes.try_into().ok()?).to_string(),
+        INT16 => i16::from_le_bytes(bytes.try_into().ok()?).to_string(),
+        UINT16 => u16::from_le_bytes(bytes.try_into().ok()?).to_string(),
+        INT32 => i32::from_le_bytes(bytes.try_into().ok()?).to_string(),
+        UINT32 => u32::from_le_bytes(bytes.try_into().ok()?).to_string(),
+        INT64 => i64::from_le_bytes(bytes.try_into().ok()?).to_string(),
+        UINT64 => u64::from_le_bytes(bytes.try_into().ok()?).to_string(),
+        REAL32 => f32::from_le_bytes(bytes.try_into().ok()?).to_string(),
+        REAL64 => f64::from_le_bytes(bytes.try_into().ok()?).to_string(),
+        BOOL => (u32::from_le_bytes(bytes.try_into().ok()?) != 0).to_string(),
+        BINARY => bytes.iter().map(|byte| format!("{:02X}", byte)).collect(),
+        GUID => guid(bytes)?,
+        SIZE_T if bytes.len() == 4 => {
+            format!("{:#010x}", u32::from_le_bytes(bytes.try_into().ok()?))
+        }
+        SIZE_T => format!("{:#018x}", u64::from_le_bytes(bytes.try_into().ok()?)),
+        FILETIME => filetime(u64::from_le_bytes(bytes.try_into().ok()?))?,
+        SYSTEMTIME => systemtime(bytes)?,
+        SID => sid(bytes)?,
+        HEX_INT32 => format!("{:#x}", u32::from_le_bytes(bytes.try_into().ok()?)),
+        HEX_INT64 => format!("{:#x}", u64::from_le_bytes(bytes.try_into().ok()?)),
+        _ => return None,
+    };
+    Some(text)
+}
+
+/// The items of an array separated by commas, strings in an array end with a null
+fn array(kind: u8, bytes: &[u8]) -> Option<String> {
+    let items: Vec<String> = match kind {
+        STRING => utf16(bytes)?
+            .trim_end_matches('\0')
+            .split('\0')
+            .map(str::to_string)
+            .collect(),
+        _ => {
+            let size = match kind {
+                INT8 | UINT8 => 1,
+                INT16 | UINT16 => 2,
+                INT32 | UINT32 | REAL32 | BOOL | HEX_INT32 => 4,
+                INT64 | UINT64 | REAL64 | FILETIME | HEX_INT64 => 8,
+                GUID | SYSTEMTIME => 16,
+                _ => return None,
+            };
+            if bytes.len() % size != 0 {
+                return None;
+            }
+            bytes
+                .chunks(size)
+                .map(|item| scalar(kind, item))
+                .collect::<Option<_>>()?
+        }
+    };
+    Some(items.join(", "))
+}
+
+fn utf16(bytes: &[u8]) -> Option<String> {
+    if bytes.len() % 2 != 0 {
+        return None;
+    }
+    let characters: Vec<u16> = bytes
+        .chunks(2)
+        .map(|pair| u16::from_le_bytes([pair[0], pair[1]]))
+        .collect();
+    Some(String::from_utf16_lossy(&characters))
+}
+
+/// Like `{86D35949-83C9-4044-B424-DB363231FD0C}`
+fn guid(bytes: &[u8]) -> Option<String> {
+    let bytes: [u8; 16] = bytes.try_into().ok()?;
+    let mut guid = format!(
+        "{{{:08X}-{:04X}-{:04X}-",
+        u32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]),
+        u16::from_le_bytes([bytes[4], bytes[5]]),
+        u16::from_le_bytes([bytes[6], bytes[7]]),
+    );
+    for (index, byte) in bytes[8..].iter().enumerate() {
+        if index == 2 {
+            guid.push('-');
+        }
+        write!(guid, "{:02X}", byte).unwrap();
+    }
+    guid.push('}');
+    Some(guid)
+}
+
+/// Like `2024-05-01T02:00:00.1234567Z`, in 100 nanosecond intervals since 1601
+fn filetime(intervals: u64) -> Option<String> {
+    let seconds = (intervals / 10_000_000) as i64 - FILETIME_EPOCH;
+    let time = DateTime::from_timestamp(seconds, 0)?;
+    Some(format!(
+        "{}.{:07}Z",
+        time.format("%Y-%m-%dT%H:%M:%S"),
+        intervals % 10_000_000
+    ))
+}
+
+/// Like `2024-05-01T02:00:00.123Z`
+fn systemtime(bytes: &[u8]) -> Option<String> {
+    let bytes: [u8; 16] = bytes.try_into().ok()?;
+    let field = |index: usize| u16::from_le_bytes([bytes[index * 2], bytes[index * 2 + 1]]);
+    // the day of the week is the third field
+    Some(format!(
+        "{:04}-{:02}-{:02}T{:02}:{:02}:{:02}.{:03}Z",
+        field(0),
+        field(1),
+        field(3),
+        field(4),
+        field(5),
+        field(6),
+        field(7)
+    ))
+}
+
+/// Like `S-1-5-18`
+fn sid(bytes: &[u8]) -> Option<String> {
+    let (revision, count) = (*bytes.first()?, usize::from(*bytes.get(1)?));
+    if bytes.len() != 8 + count * 4 {
+        return None;
+    }
+    let authority = bytes[2..8]
+        .iter()
+        .fold(0u64, |authority, byte| authority << 8 | u64::from(*byte));
+    let mut sid = format!("S-{}-{}", revision, authority);
+    for sub_authority in bytes[8..].chunks(4) {
+        let sub_authority = u32::from_le_bytes(sub_authority.try_into().ok()?);
+        write!(sid, "-{}", sub_authority).unwrap();
+    }
+    Some(sid)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const LOG: &[u8] = include_bytes!("../tests/fixtures/evtx/operational.evtx");
+    /// The log cut off in the middle of its seventh record
+    const TRUNCATED: &[u8] = include_bytes!("../tests/fixtures/evtx/truncated.evtx");
+    /// Where the ninth record of the log starts
+    const NINTH_RECORD: usize = FILE_HEADER_SIZE + 0x107e;
+
+    fn records(history: &TaskHistory) -> Vec<Vec<(u64, u32)>> {
+        history
+            .runs()
+            .iter()
+            .map(|run| {
+                run.events()
+                    .iter()
+                    .map(|event| (event.record(), event.event_id()))
+                    .collect()
+            })
+            .collect()
+    }
+
+    #[test]
+    fn reads_the_runs_of_a_log() {
+        let history = TaskHistory::from_evtx(LOG).unwrap();
+        let tasks: Vec<&str> = history.runs().iter().map(|run| run.task()).collect();
+        assert_eq!(tasks, ["\\Backup", "\\backup", "\\Other"]);
+        assert_eq!(
+            records(&history),
+            [
+                vec![(1, 107), (2, 100), (3, 129), (4, 200), (5, 201), (6, 102)],
+                vec![(7, 110)],
+                vec![(9, 101)],
+            ]
+        );
+
+        let run = &history.runs()[0];
+        assert_eq!(
+            run.instance_id(),
+            Some("{11111111-2222-3333-4444-555555555555}")
+        );
+        assert_eq!(run.duration(), Some(chrono::Duration::minutes(10)));
+        assert_eq!(run.result().map(|result| result.code()), Some(0x8007_0002));
+        assert_eq!(
+            history.runs()[2].result().map(|result| result.code()),
+            Some(0x8007_04DD)
+        );
+    }
+
+    #[test]
+    fn keeps_the_runs_before_a_truncated_chunk() {
+        let history = TaskHistory::from_evtx(TRUNCATED).unwrap();
+        assert_eq!(
+            records(&history),
+            [vec![
+                (1, 107),
+                (2, 100),
+                (3, 129),
+                (4, 200),
+                (5, 201),
+                (6, 102)
+            ]]
+        );
+    }
+
+    #[test]
+    fn keeps_the_runs_before_a_partly_written_record() {
+        let mut log = LOG.to_vec();
+        // a token that doesn't exist where the binary XML of the record starts
+        log[NINTH_RECORD + RECORD_HEADER_SIZE] = 0x77;
+        let history = TaskHistory::from_evtx(&log).unwrap();
+        let tasks: Vec<&str> = history.runs().iter().map(|run| run.task()).collect();
+        assert_eq!(tasks, ["\\Backup", "\\backup"]);
+    }
+
+    #[test]
+    fn rejects_a_file_that_isnt_an_event_log() {
+        assert_eq!(
+            TaskHistory::from_evtx(b"<Events/>").unwrap_err(),
+            HistoryError::Evtx {
+                offset: 0,
+                description: "the file isn't an event log".to_string(),
+            }
+        );
+    }
+}
@@ -0,0 +1,1136 @@
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    fmt,
+};
+
+use chrono::{DateTime, Datelike, Duration, NaiveDate, Timelike, Weekday};
+
+use crate::{
+    boundary::Boundary, calendar::weekday_name, task::Task, task_xml::escape, Action, DaysOfMonth,
+    DaysOfWeek, ExecAction, Months, Repetition, TaskTriggers, TriggerCommon, WeeksOfMonth,
+};
+
+const SECONDS_PER_DAY: i64 = 86_400;
+
+/// A task written out for another scheduler, with everything that couldn't be carried over
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Export {
+    pub(crate) files: Vec<ExportFile>,
+    pub(crate) warnings: Vec<ExportWarning>,
+}
+
+impl Export {
+    pub fn files(&self) -> &[ExportFile] {
+        &self.files
+    }
+
+    /// The contents of the file with the name, like `backup.timer`
+    pub fn file(&self, name: &str) -> Option<&str> {
+        self.files
+            .iter()
+            .find(|file| file.name == name)
+            .map(|file| file.contents.as_str())
+    }
+
+    /// The triggers and actions that have no equivalent, or only a close one
+    pub fn warnings(&self) -> &[ExportWarning] {
+        &self.warnings
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ExportFile {
+    pub(crate) name: String,
+    pub(crate) contents: String,
+}
+
+impl ExportFile {
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    pub fn contents(&self) -> &str {
+        &self.contents
+    }
+}
+
+/// A trigger or action that was left out of an export or changed by it
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ExportWarning {
+    item: String,
+    reason: String,
+}
+
+impl ExportWarning {
+    pub(crate) fn new(item: &str, reason: impl Into<String>) -> Self {
+        Self {
+            item: item.to_string(),
+            reason: reason.into(),
+        }
+    }
+
+    /// The kind of trigger or action, like `EventTrigger`
+    pub fn item(&self) -> &str {
+        &self.item
+    }
+
+    pub fn reason(&self) -> &str {
+        &self.reason
+    }
+}
+
+impl fmt::Display for ExportWarning {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}: {}", self.item, self.reason)
+    }
+}
+
+impl Task {
+    /// Writes the time based triggers and exec actions of the task as crontab lines.
+    ///
+    /// The times are in UTC, which `CRON_TZ=UTC` sets for crons that support it.
+    /// The last day of the month and weeks of the month are written as `L` and `#`,
+    /// like [`CronSchedule`](crate::CronSchedule) reads them, which not every cron supports.
+    pub fn to_crontab(&self) -> Export {
+        let schedule = Schedule::new(self.triggers());
+        let mut warnings = schedule.warnings;
+        let command = shell_command(self, &mut warnings);
+
+        let mut lines = Vec::new();
+        for rule in &schedule.rules {
+            let days = match cron_days(&rule.days) {
+                Ok(days) => days,
+                Err(reason) => {
+                    warnings.push(ExportWarning::new(rule.item, reason));
+                    continue;
+                }
+            };
+            if days.contains('L') || days.contains('#') {
+                warnings.push(ExportWarning::new(
+                    rule.item,
+                    "uses L or #, which not every cron supports",
+                ));
+            }
+            if rule.times.iter().any(|times| times.second != 0) {
+                warnings.push(ExportWarning::new(
+                    rule.item,
+                    "cron runs on whole minutes, the seconds are dropped",
+                ));
+            }
+            if rule.random_delay.is_some() {
+                warnings.push(ExportWarning::new(rule.item, "cron has no random delay"));
+            }
+            for times in &rule.times {
+                lines.push(format!(
+                    "{} {} {} {}",
+                    cron_list(&times.minutes, 0, 59),
+                    cron_list(&times.hours, 0, 23),
+                    days,
+                    command
+                ));
+            }
+        }
+        if !lines.is_empty() {
+            lines.insert(0, "CRON_TZ=UTC".to_string());
+        }
+        for delay in &schedule.boot {
+            match delay {
+                Some(delay) => lines.push(format!(
+                    "@reboot sleep {} && {}",
+                    delay.num_seconds(),
+                    command
+                )),
+                None => lines.push(format!("@reboot {}", command)),
+            }
+        }
+
+        let mut contents = lines.join("\n");
+        contents.push('\n');
+        Export {
+            files: vec![ExportFile {
+                name: "crontab".to_string(),
+                contents,
+            }],
+            warnings,
+        }
+    }
+
+    /// Writes the task as a systemd `<unit>.timer` and the `<unit>.service` it starts.
+    ///
+    /// Calendar triggers become `OnCalendar=` in UTC, boot triggers `OnBootSec=`,
+    /// the random delay `RandomizedDelaySec=` and [`Settings::start_when_available`](crate::Settings)
+    /// `Persistent=`. The service runs the exec actions in order.
+    ///
+    /// https://www.freedesktop.org/software/systemd/man/systemd.timer.html
+    pub fn to_systemd(&self, unit: &str) -> Export {
+        let schedule = Schedule::new(self.triggers());
+        let mut warnings = schedule.warnings;
+        let description = self
+            .registration()
+            .description()
+            .unwrap_or(unit)
+            .replace('\n', " ")
+            // % starts a specifier in unit files
+            .replace('%', "%%");
+
+        let mut timer = format!("[Unit]\nDescription={}\n\n[Timer]\n", description);
+        for rule in &schedule.rules {
+            for times in &rule.times {
+                for day in systemd_days(&rule.days) {
+                    timer.push_str(&format!(
+                        "OnCalendar={} {}:{}:{:02} UTC\n",
+                        day,
+                        systemd_list(&times.hours, 0, 23),
+                        systemd_list(&times.minutes, 0, 59),
+                        times.second
+                    ));
+                }
+            }
+        }
+        for delay in &schedule.boot {
+            let seconds = delay.map_or(0, |delay| delay.num_seconds());
+            timer.push_str(&format!("OnBootSec={}\n", seconds));
+        }
+
+        // a timer only has one random delay for all of its schedules
+        let random_delays: BTreeSet<i64> = schedule
+            .rules
+            .iter()
+            .map(|rule| rule.random_delay.map_or(0, |delay| delay.num_seconds()))
+            .collect();
+        if let Some(random_delay) = random_delays.iter().max().filter(|delay| **delay > 0) {
+            timer.push_str(&format!("RandomizedDelaySec={}\n", random_delay));
+            if random_delays.len() > 1 {
+                warnings.push(ExportWarning::new(
+                    "Triggers",
+                    "the triggers have different random delays, the longest is used",
+                ));
+            }
+        }
+        if self.settings().start_when_available() {
+            timer.push_str("Persistent=true\n");
+        }
+        timer.push_str("\n[Install]\nWantedBy=timers.target\n");
+
+        let mut service = format!(
+            "[Unit]\nDescription={}\n\n[Service]\nType=oneshot\n",
+            description
+        );
+        let actions = exec_actions(self, &mut warnings);
+        if let Some(working_directory) = working_directory(&actions, &mut warnings) {
+            service.push_str(&format!("WorkingDirectory={}\n", working_directory));
+        }
+        for action in &actions {
+            // % starts a specifier and $ a variable in unit files
+            let command = command_line(action).replace('%', "%%").replace('$', "$$");
+            service.push_str(&format!("ExecStart={}\n", command));
+        }
+        match self.settings().execution_time_limit() {
+            Some(limit) => service.push_str(&format!("TimeoutStartSec={}\n", limit.num_seconds())),
+            None => service.push_str("TimeoutStartSec=infinity\n"),
+        }
+
+        Export {
+            files: vec![
+                ExportFile {
+                    name: format!("{}.timer", unit),
+                    contents: timer,
+                },
+                ExportFile {
+                    name: format!("{}.service", unit),
+                    contents: service,
+                },
+            ],
+            warnings,
+        }
+    }
+
+    /// Writes the task as a launchd property list with the label, as `<label>.plist`.
+    ///
+    /// Calendar triggers become `StartCalendarInterval` entries and boot triggers
+    /// `RunAtLoad`. launchd runs them in the local time of the computer,
+    /// the times are written as they are in UTC.
+    ///
+    /// https://developer.apple.com/library/archive/documentation/MacOSX/Conceptual/BPSystemStartup/Chapters/ScheduledJobs.html
+    pub fn to_launchd(&self, label: &str) -> Export {
+        let schedule = Schedule::new(self.triggers());
+        let mut warnings = schedule.warnings;
+
+        let mut intervals = Vec::new();
+        for rule in &schedule.rules {
+            let days = match launchd_days(&rule.days) {
+                Ok(days) => days,
+                Err(reason) => {
+                    warnings.push(ExportWarning::new(rule.item, reason));
+                    continue;
+                }
+            };
+            if rule.times.iter().any(|times| times.second != 0) {
+                warnings.push(ExportWarning::new(
+                    rule.item,
+                    "launchd runs on whole minutes, the seconds are dropped",
+                ));
+            }
+            if rule.random_delay.is_some() {
+                warnings.push(ExportWarning::new(rule.item, "launchd has no random delay"));
+            }
+            for times in &rule.times {
+                for hour in launchd_values(&times.hours, 24) {
+                    for minute in launchd_values(&times.minutes, 60) {
+                        for day in &days {
+                            let mut interval = day.clone();
+                            if let Some(hour) = hour {
+                                interval.push(("Hour", hour));
+                            }
+                            if let Some(minute) = minute {
+                                interval.push(("Minute", minute));
+                            }
+                            intervals.push(interval);
+                        }
+                    }
+                }
+            }
+        }
+        if !intervals.is_empty() {
+            warnings.push(ExportWarning::new(
+                "Triggers",
+                "launchd uses local time, the times are written as they are in UTC",
+            ));
+        }
+        for delay in schedule.boot.iter().flatten() {
+            warnings.push(ExportWarning::new(
+                "BootTrigger",
+                format!(
+                    "launchd has no delay after loading, the {} second delay is dropped",
+                    delay.num_seconds()
+                ),
+            ));
+        }
+
+        let mut plist = String::from(
+            "<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n\
+             <!DOCTYPE plist PUBLIC \"-//Apple//DTD PLIST 1.0//EN\" \"http://www.apple.com/DTDs/PropertyList-1.0.dtd\">\n\
+             <plist version=\"1.0\">\n<dict>\n",
+        );
+        plist.push_str(&format!(
+            "  <key>Label</key>\n  <string>{}</string>\n",
+            escape(label)
+        ));
+        let actions = exec_actions(self, &mut warnings);
+        if actions.len() > 1 {
+            warnings.push(ExportWarning::new(
+                "ExecAction",
+                "launchd runs a single program, only the first action is used",
+            ));
+        }
+        if let Some(action) = actions.first() {
+            plist.push_str("  <key>ProgramArguments</key>\n  <array>\n");
+            let mut arguments = vec![action.path().to_string_lossy().into_owned()];
+            arguments.extend(split_arguments(action.arguments().unwrap_or("")));
+            for argument in arguments {
+                plist.push_str(&format!("    <string>{}</string>\n", escape(&argument)));
+            }
+            plist.push_str("  </array>\n");
+            if let Some(working_directory) = action.working_directory() {
+                plist.push_str(&format!(
+                    "  <key>WorkingDirectory</key>\n  <string>{}</string>\n",
+                    escape(&working_directory.to_string_lossy())
+                ));
+            }
+        }
+        if !intervals.is_empty() {
+            plist.push_str("  <key>StartCalendarInterval</key>\n  <array>\n");
+            for interval in intervals {
+                plist.push_str("    <dict>\n");
+                for (key, value) in interval {
+                    plist.push_str(&format!(
+                        "      <key>{}</key>\n      <integer>{}</integer>\n",
+                        key, value
+                    ));
+                }
+                plist.push_str("    </dict>\n");
+            }
+            plist.push_str("  </array>\n");
+        }
+        if !schedule.boot.is_empty() {
+            plist.push_str("  <key>RunAtLoad</key>\n  <true/>\n");
+        }
+        plist.push_str("</dict>\n</plist>\n");
+
+        Export {
+            files: vec![ExportFile {
+                name: format!("{}.plist", label),
+                contents: plist,
+            }],
+            warnings,
+        }
+    }
+}
+
+/// The days a calendar trigger runs on
+#[derive(Clone, Debug, PartialEq, Eq)]
+enum Days {
+    Every,
+    Weekly(DaysOfWeek),
+    /// The days, whether the last day of the month is included and the months
+    Monthly(DaysOfMonth, bool, Months),
+    /// The days of the week, their weeks, whether the last week is included and the months
+    MonthlyDow(DaysOfWeek, WeeksOfMonth, bool, Months),
+    Once(NaiveDate),
+}
+
+/// Hours that run at the same minutes and second
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub(crate) struct Times {
+    pub(crate) hours: Vec<u32>,
+    pub(crate) minutes: Vec<u32>,
+    pub(crate) second: u32,
+}
+
+/// A trigger that runs on some days at some times of day
+struct Rule {
+    item: &'static str,
+    days: Days,
+    times: Vec<Times>,
+    random_delay: Option<Duration>,
+}
+
+/// The triggers of a task in a form that other schedulers can express
+struct Schedule {
+    rules: Vec<Rule>,
+    /// The delay of every boot trigger
+    boot: Vec<Option<Duration>>,
+    warnings: Vec<ExportWarning>,
+}
+
+impl Schedule {
+    fn new(triggers: &TaskTriggers) -> Self {
+        let mut schedule = Self {
+            rules: Vec::new(),
+            boot: triggers
+                .boot()
+                .iter()
+                .filter(|trigger| trigger.common.enabled())
+                .map(|trigger| trigger.delay)
+                .collect(),
+            warnings: Vec::new(),
+        };
+
+        for trigger in triggers.daily() {
+            if trigger.interval.unwrap_or(1) > 1 {
+                schedule.unsupported(
+                    "DailyTrigger",
+                    "runs every few days from its start date, which has no equivalent",
+                );
+                continue;
+            }
+            schedule.add(
+                "DailyTrigger",
+                Days::Every,
+                &trigger.common,
+                trigger.repetition.as_ref(),
+            );
+        }
+        for trigger in triggers.weekly() {
+            if trigger.weeks_interval.unwrap_or(1) > 1 {
+                schedule.unsupported(
+                    "WeeklyTrigger",
+                    "runs every few weeks from its start date, which has no equivalent",
+                );
+                continue;
+            }
+            schedule.add(
+                "WeeklyTrigger",
+                Days::Weekly(trigger.days_of_week),
+                &trigger.common,
+                trigger.repetition.as_ref(),
+            );
+        }
+        for trigger in triggers.monthly() {
+            schedule.add(
+                "MonthlyTrigger",
+                Days::Monthly(
+                    trigger.days_of_month,
+                    trigger.run_on_last_day_of_month,
+                    trigger.months,
+                ),
+                &trigger.common,
+                trigger.repetition.as_ref(),
+            );
+        }
+        for trigger in triggers.monthly_dow() {
+            schedule.add(
+                "MonthlyDowTrigger",
+                Days::MonthlyDow(
+                    trigger.days_of_week,
+                    trigger.weeks_of_month,
+                    trigger.run_on_last_week_of_month,
+                    trigger.months,
+                ),
+                &trigger.common,
+                trigger.repetition.as_ref(),
+            );
+        }
+        for trigger in triggers.specific_times() {
+            schedule.add(
+                "TimeTrigger",
+                Days::Once(trigger.boundary().to_utc().date_naive()),
+                &trigger.common,
+                trigger.repetition.as_ref(),
+            );
+        }
+
+        let unsupported = [
+            ("EventTrigger", triggers.event().len()),
+            ("IdleTrigger", triggers.idle().len()),
+            (
+                "SessionStateChangeTrigger",
+                triggers.session_state_change().len(),
+            ),
+            ("LogonTrigger", triggers.logon().len()),
+            ("RegistrationTrigger", triggers.registration().len()),
+        ];
+        for (item, count) in unsupported.iter() {
+            for _ in 0..*count {
+                schedule.unsupported(item, "has no equivalent");
+            }
+        }
+        schedule
+    }
+
+    fn unsupported(&mut self, item: &'static str, reason: &str) {
+        self.warnings.push(ExportWarning::new(item, reason));
+    }
+
+    fn add(
+        &mut self,
+        item: &'static str,
+        days: Days,
+        common: &TriggerCommon,
+        repetition: Option<&Repetition>,
+    ) {
+        if !common.enabled() {
+            return self.unsupported(item, "is disabled and is left out");
+        }
+        // a trigger without a start boundary starts now, in UTC like next_occurrence counts it
+        let start = common
+            .start()
+            .unwrap_or_else(|| Boundary::Utc(Boundary::now().to_utc()));
+        if start.is_local() {
+            self.unsupported(
+                item,
+                "the local start time is written in UTC for the time zone of this computer, so it won't follow daylight saving time",
+            );
+        }
+        // the days are on the clock of the start, which can be a day before or after UTC
+        let shift = (start.to_utc().date_naive() - start.clock_time().date()).num_days();
+        let days = match days {
+            Days::Weekly(days_of_week) => Days::Weekly(days_of_week.shifted(shift)),
+            Days::Monthly(..) | Days::MonthlyDow(..) if shift != 0 => {
+                return self.unsupported(
+                    item,
+                    "starts on another day in UTC than on its own clock, so its days of the month can't be written in UTC",
+                );
+            }
+            days => days,
+        };
+        let start = start.to_utc();
+        let first = i64::from(start.num_seconds_from_midnight());
+        let times = match seconds_of_day(first, repetition, days == Days::Every) {
+            Ok(times) => times,
+            Err(reason) => return self.unsupported(item, &reason),
+        };
+        if common.end().is_some() {
+            self.unsupported(item, "the end boundary has no equivalent and is dropped");
+        }
+        if common.execution_time_limit().is_some() {
+            self.unsupported(
+                item,
+                "the execution time limit of the trigger has no equivalent and is dropped",
+            );
+        }
+        self.rules.push(Rule {
+            item,
+            days,
+            times: group_times(&times),
+            random_delay: common.random_delay(),
+        });
+    }
+}
+
+/// The seconds of the day a trigger that first runs at `first` runs at, with its repetition.
+///
+/// A trigger that runs every day can repeat past midnight, since the next day
+/// then runs at the same times. Other triggers have to stay within their day.
+pub(crate) fn seconds_of_day(
+    first: i64,
+    repetition: Option<&Repetition>,
+    every_day: bool,
+) -> Result<Vec<i64>, String> {
+    let repetition = match repetition {
+        Some(repetition) if repetition.interval.num_seconds() > 0 => repetition,
+        _ => return Ok(vec![first]),
+    };
+    let interval = repetition.interval.num_seconds();
+    let times: Vec<i64> = match repetition.duration {
+        Some(duration) => (0..)
+            .map(|index| index * interval)
+            .take_while(|offset| *offset < duration.num_seconds())
+            .map(|offset| first + offset)
+            .collect(),
+        // repeating forever runs at every multiple of the interval in the day
+        None if every_day => {
+            let step = gcd(interval, SECONDS_PER_DAY);
+            let first = first % step;
+            (0..SECONDS_PER_DAY / step)
+                .map(|index| first + index * step)
+                .collect()
+        }
+        None => return Err("repeats indefinitely past the day it runs on".to_string()),
+    };
+    if times.iter().any(|time| *time >= SECONDS_PER_DAY) {
+        if !every_day {
+            return Err("repeats past the end of the day it runs on".to_string());
+        }
+        let times: BTreeSet<i64> = times.iter().map(|time| time % SECONDS_PER_DAY).collect();
+        return Ok(times.into_iter().collect());
+    }
+    Ok(times)
+}
+
+fn gcd(a: i64, b: i64) -> i64 {
+    if b == 0 {
+        a
+    } else {
+        gcd(b, a % b)
+    }
+}
+
+/// Groups the times by second, then hours that have the same minutes
+pub(crate) fn group_times(times: &[i64]) -> Vec<Times> {
+    let mut minutes: BTreeMap<(u32, u32), BTreeSet<u32>> = BTreeMap::new();
+    for time in times {
+        let time = *time as u32;
+        minutes
+            .entry((time % 60, time / 3600))
+            .or_default()
+            .insert(time / 60 % 60);
+    }
+    let mut groups: Vec<Times> = Vec::new();
+    for ((second, hour), minutes) in minutes {
+        let minutes: Vec<u32> = minutes.into_iter().collect();
+        match groups
+            .iter_mut()
+            .find(|group| group.second == second && group.minutes == minutes)
+        {
+            Some(group) => group.hours.push(hour),
+            None => groups.push(Times {
+                hours: vec![hour],
+                minutes,
+                second,
+            }),
+        }
+    }
+    groups
+}
+
+/// Writes values as a list, with `*` for all of them and ranges for runs
+fn cron_list(values: &[u32], min: u32, max: u32) -> String {
+    if values.len() == (max - min + 1) as usize {
+        return "*".to_string();
+    }
+    list(values, "-", |value| value.to_string())
+}
+
+fn systemd_list(values: &[u32], min: u32, max: u32) -> String {
+    if values.len() == (max - min + 1) as usize {
+        return "*".to_string();
+    }
+    list(values, "..", |value| format!("{:02}", value))
+}
+
+/// Joins values with commas, three or more values in a row are written as a range
+fn list(values: &[u32], range: &str, format: impl Fn(u32) -> String) -> String {
+    let mut items = Vec::new();
+    let mut index = 0;
+    while index < values.len() {
+        let mut end = index;
+        while end + 1 < values.len() && values[end + 1] == values[end] + 1 {
+            end += 1;
+        }
+        if end - index >= 2 {
+            items.push(format!(
+                "{}{}{}",
+                format(values[index]),
+                range,
+                format(values[end])
+            ));
+        } else {
+            items.extend(values[index..=end].iter().map(|value| format(*value)));
+        }
+        index = end + 1;
+    }
+    items.join(",")
+}
+
+fn months_list(months: Months) -> Vec<u32> {
+    months.iter().collect()
+}
+
+fn weekday_numbers(days: DaysOfWeek) -> Vec<u32> {
+    days.iter().map(|day| day.num_days_from_sunday()).collect()
+}
+
+/// The day of month, month and day of week fields
+fn cron_days(days: &Days) -> Result<String, String> {
+    match days {
+        Days::Every => Ok("* * *".to_string()),
+        Days::Weekly(days) => Ok(format!("* * {}", cron_list(&weekday_numbers(*days), 0, 6))),
+        Days::Monthly(days, last, months) => {
+            let mut items: Vec<String> = Vec::new();
+            let days: Vec<u32> = days.iter().collect();
+            if !days.is_empty() {
+                items.push(cron_list(&days, 1, 31));
+            }
+            if *last {
+                items.push("L".to_string());
+            }
+            Ok(format!(
+                "{} {} *",
+                items.join(","),
+                cron_list(&months_list(*months), 1, 12)
+            ))
+        }
+        Days::MonthlyDow(days, weeks, last, months) => {
+            let months = cron_list(&months_list(*months), 1, 12);
+            if weeks.iter().count() == 4 && *last {
+                return Ok(format!(
+                    "* {} {}",
+                    months,
+                    cron_list(&weekday_numbers(*days), 0, 6)
+                ));
+            }
+            let mut items = Vec::new();
+            for day in weekday_numbers(*days) {
+                items.extend(weeks.iter().map(|week| format!("{}#{}", day, week)));
+                if *last {
+                    items.push(format!("{}L", day));
+                }
+            }
+            Ok(format!("* {} {}", months, items.join(",")))
+        }
+        Days::Once(date) => Err(format!(
+            "runs once on {}, cron has no schedules for a single date",
+            date
+        )),
+    }
+}
+
+fn systemd_weekdays(days: DaysOfWeek) -> String {
+    // systemd weeks start on Monday
+    let mut names: Vec<&str> = days.iter().map(|day| &weekday_name(day)[..3]).collect();
+    if days.contains(Weekday::Sun) {
+        names.remove(0);
+        names.push("Sun");
+    }
+    names.join(",")
+}
+
+/// The day of week and date parts of `OnCalendar=`, one for each of them that is needed
+fn systemd_days(days: &Days) -> Vec<String> {
+    match days {
+        Days::Every => vec!["*-*-*".to_string()],
+        Days::Weekly(days) => vec![format!("{} *-*-*", systemd_weekdays(*days))],
+        Days::Monthly(days, last, months) => {
+            let months = systemd_list(&months_list(*months), 1, 12);
+            let days: Vec<u32> = days.iter().collect();
+            let mut dates = Vec::new();
+            if !days.is_empty() {
+                dates.push(format!("*-{}-{}", months, systemd_list(&days, 1, 31)));
+            }
+            if *last {
+                dates.push(format!("*-{}~01", months));
+            }
+            dates
+        }
+        Days::MonthlyDow(days, weeks, last, months) => {
+            let months = systemd_list(&months_list(*months), 1, 12);
+            let weekdays = systemd_weekdays(*days);
+            let mut dates = Vec::new();
+            if !weeks.is_empty() {
+                let ranges: Vec<String> = weeks
+                    .iter()
+                    .map(|week| format!("{:02}..{:02}", week * 7 - 6, week * 7))
+                    .collect();
+                dates.push(format!("{} *-{}-{}", weekdays, months, ranges.join(",")));
+            }
+            if *last {
+                dates.push(format!("{} *-{}~07/1", weekdays, months));
+            }
+            dates
+        }
+        Days::Once(date) => vec![date.format("%Y-%m-%d").to_string()],
+    }
+}
+
+/// The `Weekday`, `Day` and `Month` keys of each calendar interval for the days
+fn launchd_days(days: &Days) -> Result<Vec<Vec<(&'static str, u32)>>, String> {
+    let with_months = |entries: Vec<Vec<(&'static str, u32)>>, months: &Months| {
+        if *months == Months::all() {
+            return entries;
+        }
+        months
+            .iter()
+            .flat_map(|month| {
+                entries.iter().map(move |entry| {
+                    let mut entry = entry.clone();
+                    entry.insert(0, ("Month", month));
+                    entry
+                })
+            })
+            .collect()
+    };
+    let weekdays = |days: &DaysOfWeek| -> Vec<Vec<(&'static str, u32)>> {
+        weekday_numbers(*days)
+            .into_iter()
+            .map(|day| vec![("Weekday", day)])
+            .collect()
+    };
+
+    match days {
+        Days::Every => Ok(vec![Vec::new()]),
+        Days::Weekly(days) => Ok(weekdays(days)),
+        Days::Monthly(_, true, _) => {
+            Err("launchd can't run on the last day of the month".to_string())
+        }
+        Days::Monthly(days, false, months) => Ok(with_months(
+            days.iter().map(|day| vec![("Day", day)]).collect(),
+            months,
+        )),
+        Days::MonthlyDow(days, weeks, last, months) if weeks.iter().count() == 4 && *last => {
+            Ok(with_months(weekdays(days), months))
+        }
+        Days::MonthlyDow(..) => {
+            Err("launchd can't run on a day of the week in some weeks of the month".to_string())
+        }
+        Days::Once(date) => Err(format!(
+            "runs once on {}, launchd has no schedules for a single date",
+            date
+        )),
+    }
+}
+
+/// Every value on its own, or a single `None` for all of them since launchd
+/// treats a missing key as every value
+fn launchd_values(values: &[u32], all: usize) -> Vec<Option<u32>> {
+    if values.len() == all {
+        vec![None]
+    } else {
+        values.iter().map(|value| Some(*value)).collect()
+    }
+}
+
+fn exec_actions<'a>(task: &'a Task, warnings: &mut Vec<ExportWarning>) -> Vec<&'a ExecAction> {
+    let mut actions = Vec::new();
+    for action in task.actions().iter() {
+        match action {
+            Action::Exec(action) => actions.push(action),
+            Action::ComHandler(_) => warnings.push(ExportWarning::new(
+                "ComHandlerAction",
+                "COM handlers only exist on Windows",
+            )),
+        }
+    }
+    actions
+}
+
+/// The working directory of the actions, they can only have one between them
+fn working_directory(actions: &[&ExecAction], warnings: &mut Vec<ExportWarning>) -> Option<String> {
+    let directories: BTreeSet<String> = actions
+        .iter()
+        .filter_map(|action| action.working_directory())
+        .map(|directory| directory.to_string_lossy().into_owned())
+        .collect();
+    if directories.len() > 1 {
+        warnings.push(ExportWarning::new(
+            "ExecAction",
+            "the actions have different working directories, only the first is used",
+        ));
+    }
+    directories.into_iter().next()
+}
+
+fn command_line(action: &ExecAction) -> String {
+    let path = shell_quote(&action.path().to_string_lossy());
+    match action.arguments() {
+        Some(arguments) if !arguments.is_empty() => format!("{} {}", path, arguments),
+        _ => path,
+    }
+}
+
+/// The exec actions as one shell command, run one after the other like Task Scheduler does
+fn shell_command(task: &Task, warnings: &mut Vec<ExportWarning>) -> String {
+    let commands: Vec<String> = exec_actions(task, warnings)
+        .into_iter()
+        .map(|action| {
+            let command = command_line(action);
+            match action.working_directory() {
+                Some(directory) => format!(
+                    "(cd {} && {})",
+                    shell_quote(&directory.to_string_lossy()),
+                    command
+                ),
+                None => command,
+            }
+        })
+        .collect();
+    // % starts a new line in crontab
+    commands.join("; ").replace('%', "\\%")
+}
+
+fn shell_quote(text: &str) -> String {
+    if !text.is_empty()
+        && text
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || "/\\:._-+=,@".contains(c))
+    {
+        text.to_string()
+    } else {
+        format!("'{}'", text.replace('\'', "'\\''"))
+    }
+}
+
+/// Splits arguments on spaces, except for spaces inside double quotes
+fn split_arguments(arguments: &str) -> Vec<String> {
+    let mut split = Vec::new();
+    let mut current = String::new();
+    let mut quoted = false;
+    let mut started = false;
+    for c in arguments.chars() {
+        match c {
+            '"' => {
+                quoted = !quoted;
+                started = true;
+            }
+            c if c.is_whitespace() && !quoted => {
+                if started {
+                    split.push(std::mem::take(&mut current));
+                    started = false;
+                }
+            }
+            c => {
+                current.push(c);
+                started = true;
+            }
+        }
+    }
+    if started {
+        split.push(current);
+    }
+    split
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::PathBuf;
+
+    use chrono::{FixedOffset, TimeZone, Utc};
+
+    use super::*;
+    use crate::{
+        task::{Settings, TaskRegistration},
+        Actions, BootTrigger, DailyTrigger, IdleTrigger, MonthlyTrigger, TaskTriggersBuilder,
+        WeeklyTrigger,
+    };
+
+    /// Runs on Monday and Friday at 09:30 UTC, and a minute after boot
+    fn task() -> Task {
+        let weekly = WeeklyTrigger::new(DaysOfWeek::new().with(Weekday::Mon).with(Weekday::Fri))
+            .with_start_time(Utc.with_ymd_and_hms(2021, 1, 4, 9, 30, 0).unwrap())
+            .with_common(|common| common.with_random_delay(Duration::minutes(5)));
+        Task::new(
+            Actions::from_action(Action::Exec(
+                ExecAction::new(PathBuf::from("/usr/bin/backup"))
+                    .with_arguments("--to $HOME/100%".to_string()),
+            )),
+            TaskTriggersBuilder::new()
+                .with_weekly(weekly)
+                .with_boot(BootTrigger::new().with_delay(Duration::minutes(1)))
+                .build(),
+        )
+        .with_settings(Settings::new().with_start_when_available(true))
+    }
+
+    #[test]
+    fn writes_a_crontab() {
+        let export = task().to_crontab();
+        assert_eq!(
+            export.file("crontab"),
+            Some(
+                "CRON_TZ=UTC\n\
+                 30 9 * * 1,5 /usr/bin/backup --to $HOME/100\\%\n\
+                 @reboot sleep 60 && /usr/bin/backup --to $HOME/100\\%\n"
+            )
+        );
+        assert_eq!(
+            export.warnings(),
+            [ExportWarning::new(
+                "WeeklyTrigger",
+                "cron has no random delay"
+            )]
+        );
+    }
+
+    #[test]
+    fn writes_a_systemd_timer_and_service() {
+        let export = task().to_systemd("backup");
+        assert_eq!(
+            export.file("backup.timer"),
+            Some(
+                "[Unit]\nDescription=backup\n\n\
+                 [Timer]\n\
+                 OnCalendar=Mon,Fri *-*-* 09:30:00 UTC\n\
+                 OnBootSec=60\n\
+                 RandomizedDelaySec=300\n\
+                 Persistent=true\n\n\
+                 [Install]\nWantedBy=timers.target\n"
+            )
+        );
+        assert_eq!(
+            export.file("backup.service"),
+            Some(
+                "[Unit]\nDescription=backup\n\n\
+                 [Service]\n\
+                 Type=oneshot\n\
+                 ExecStart=/usr/bin/backup --to $$HOME/100%%\n\
+                 TimeoutStartSec=259200\n"
+            )
+        );
+        assert!(export.warnings().is_empty());
+    }
+
+    #[test]
+    fn writes_a_launchd_plist() {
+        let export = task().to_launchd("com.example.backup");
+        let plist = export.file("com.example.backup.plist").unwrap();
+        assert!(plist.contains(
+            "  <key>ProgramArguments</key>\n  <array>\n\
+             \x20   <string>/usr/bin/backup</string>\n\
+             \x20   <string>--to</string>\n\
+             \x20   <string>$HOME/100%</string>\n  </array>\n"
+        ));
+        assert!(plist.contains(
+            "    <dict>\n\
+             \x20     <key>Weekday</key>\n      <integer>5</integer>\n\
+             \x20     <key>Hour</key>\n      <integer>9</integer>\n\
+             \x20     <key>Minute</key>\n      <integer>30</integer>\n\
+             \x20   </dict>\n"
+        ));
+        assert!(plist.contains("  <key>RunAtLoad</key>\n  <true/>\n"));
+        assert_eq!(
+            export.warnings(),
+            [
+                ExportWarning::new("WeeklyTrigger", "launchd has no random delay"),
+                ExportWarning::new(
+                    "Triggers",
+                    "launchd uses local time, the times are written as they are in UTC"
+                ),
+                ExportWarning::new(
+                    "BootTrigger",
+                    "launchd has no delay after loading, the 60 second delay is dropped"
+                ),
+            ]
+        );
+    }
+
+    #[test]
+    fn flags_triggers_without_an_equivalent() {
+        let task = Task::new(
+            Actions::from_action(Action::Exec(ExecAction::new(PathBuf::from(
+                "/usr/bin/true",
+            )))),
+            TaskTriggersBuilder::new()
+                .with_idle(IdleTrigger::new())
+                .build(),
+        );
+        let warnings = [ExportWarning::new("IdleTrigger", "has no equivalent")];
+        assert_eq!(task.to_crontab().warnings(), warnings);
+        assert_eq!(task.to_systemd("idle").warnings(), warnings);
+        assert_eq!(task.to_launchd("idle").warnings(), warnings);
+    }
+
+    fn with_triggers(triggers: TaskTriggersBuilder) -> Task {
+        Task::new(
+            Actions::from_action(Action::Exec(ExecAction::new(PathBuf::from(
+                "/usr/bin/true",
+            )))),
+            triggers.build(),
+        )
+    }
+
+    #[test]
+    fn moves_the_days_of_a_start_that_is_on_another_day_in_utc() {
+        // Monday 02:00 in Tokyo is Sunday 17:00 in UTC
+        let start = FixedOffset::east_opt(9 * 3600)
+            .unwrap()
+            .with_ymd_and_hms(2021, 1, 4, 2, 0, 0)
+            .unwrap();
+        let task = with_triggers(TaskTriggersBuilder::new().with_weekly(
+            WeeklyTrigger::new(DaysOfWeek::new().with(Weekday::Mon)).with_start_time(start),
+        ));
+        assert_eq!(
+            task.to_crontab().file("crontab"),
+            Some("CRON_TZ=UTC\n0 17 * * 0 /usr/bin/true\n")
+        );
+        let timer = task.to_systemd("tokyo");
+        assert!(timer
+            .file("tokyo.timer")
+            .unwrap()
+            .contains("OnCalendar=Sun *-*-* 17:00:00 UTC\n"));
+        assert_eq!(
+            task.triggers()
+                .next_occurrence_in(Utc.with_ymd_and_hms(2021, 1, 1, 0, 0, 0).unwrap(), &Utc),
+            Some(Utc.with_ymd_and_hms(2021, 1, 3, 17, 0, 0).unwrap())
+        );
+    }
+
+    #[test]
+    fn leaves_out_days_of_the_month_that_are_on_another_day_in_utc() {
+        let start = FixedOffset::east_opt(9 * 3600)
+            .unwrap()
+            .with_ymd_and_hms(2021, 1, 1, 2, 0, 0)
+            .unwrap();
+        let task = with_triggers(TaskTriggersBuilder::new().with_monthly(
+            MonthlyTrigger::new(DaysOfMonth::new().with(1), Months::all()).with_start_time(start),
+        ));
+        let export = task.to_crontab();
+        assert_eq!(export.file("crontab"), Some("\n"));
+        assert_eq!(
+            export.warnings(),
+            [ExportWarning::new(
+                "MonthlyTrigger",
+                "starts on another day in UTC than on its own clock, so its days of the month can't be written in UTC"
+            )]
+        );
+    }
+
+    #[test]
+    fn starts_a_trigger_without_a_start_in_utc() {
+        let task = with_triggers(
+            TaskTriggersBuilder::new().with_daily(DailyTrigger::new("Daily".to_string())),
+        );
+        assert!(task.to_systemd("daily").warnings().is_empty());
+    }
+
+    #[test]
+    fn escapes_specifiers_in_the_systemd_description() {
+        let task = task().with_registration(
+            TaskRegistration::new().with_description("Fills the disk to 100%".to_string()),
+        );
+        assert!(task
+            .to_systemd("backup")
+            .file("backup.timer")
+            .unwrap()
+            .starts_with("[Unit]\nDescription=Fills the disk to 100%%\n"));
+    }
+}
@@ -0,0 +1,600 @@
+use std::collections::HashMap;
+
+use chrono::{DateTime, Duration, Utc};
+use roxmltree::{Document, Node};
+
+use crate::{error::HistoryError, TaskResult};
+
+/// The provider of the events in the `Microsoft-Windows-TaskScheduler/Operational` log
+const PROVIDER: &str = "Microsoft-Windows-TaskScheduler";
+
+/// The event ids that are part of a run, other events are skipped
+const RUN_EVENTS: [u32; 12] = [100, 101, 102, 103, 107, 110, 111, 129, 200, 201, 203, 322];
+
+/// An event of a run with the path of its task and the instance id of the run
+pub(crate) type RunEvent = (TaskRunEvent, String, Option<String>);
+
+/// The runs of tasks in exported events of the `Microsoft-Windows-TaskScheduler/Operational` log,
+/// like the output of `wevtutil qe Microsoft-Windows-TaskScheduler/Operational /f:xml`,
+/// or in the `.evtx` file of the log.
+///
+/// Events are grouped into runs by the task name and the instance id of the run.
+/// The events that have no instance id are a run of their own, except for the
+/// process that was created, which belongs to the last run of the task.
+///
+/// https://docs.microsoft.com/en-us/windows/win32/taskschd/task-scheduler-start-page
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct TaskHistory {
+    runs: Vec<TaskRun>,
+}
+
+impl TaskHistory {
+    /// Reads events as `wevtutil qe /f:xml` writes them, one `Event` element after another,
+    /// or inside an `Events` element like the Event Viewer saves them.
+    /// Events from other providers and other event ids are skipped.
+    pub fn from_events_xml(xml: &str) -> Result<TaskHistory, HistoryError> {
+        let mut xml = xml.trim_start_matches('\u{feff}').trim_start();
+        if xml.starts_with("<?xml") {
+            xml = xml
+                .find("?>")
+                .map_or("", |end| &xml[end + 2..])
+                .trim_start();
+        }
+        // wevtutil writes the events without a root element
+        let wrapped;
+        if !xml.starts_with("<Events") {
+            wrapped = format!("<Events>{}</Events>", xml);
+            xml = &wrapped;
+        }
+        let mut events = Vec::new();
+        read_events(xml, &mut events)?;
+        Ok(TaskHistory::from_events(events))
+    }
+
+    /// The runs in the order they started
+    pub fn runs(&self) -> &[TaskRun] {
+        &self.runs
+    }
+
+    /// The runs of the task with the path, like `\Backup\Nightly`.
+    /// Task paths aren't case sensitive.
+    pub fn runs_of<'a>(&'a self, task: &'a str) -> impl Iterator<Item = &'a TaskRun> + 'a {
+        self.runs
+            .iter()
+            .filter(move |run| run.task.eq_ignore_ascii_case(task))
+    }
+
+    /// Groups the events into runs, in any order
+    pub(crate) fn from_events(mut events: Vec<RunEvent>) -> TaskHistory {
+        events.sort_by(|a, b| (a.0.time, a.0.record).cmp(&(b.0.time, b.0.record)));
+        let mut runs: Vec<TaskRun> = Vec::new();
+        let mut instances: HashMap<(String, String), usize> = HashMap::new();
+        for (event, task, instance_id) in events {
+            let index = match &instance_id {
+                Some(instance_id) => {
+                    let key = (task.to_lowercase(), instance_id.to_lowercase());
+                    *instances.entry(key).or_insert_with(|| {
+                        runs.push(TaskRun::new(&task, Some(instance_id)));
+                        runs.len() - 1
+                    })
+                }
+                // the process belongs to the last run of the task that doesn't have one yet
+                None if matches!(event.kind, TaskRunEventKind::ProcessCreated { .. }) => {
+                    let last = runs.iter().rposition(|run| {
+                        run.task.eq_ignore_ascii_case(&task)
+                            && run.ended().is_none()
+                            && !run.events.iter().any(|event| {
+                                matches!(event.kind, TaskRunEventKind::ProcessCreated { .. })
+                            })
+                    });
+                    last.unwrap_or_else(|| {
+                        runs.push(TaskRun::new(&task, None));
+                        runs.len() - 1
+                    })
+                }
+                None => {
+                    runs.push(TaskRun::new(&task, None));
+                    runs.len() - 1
+                }
+            };
+            runs[index].events.push(event);
+        }
+        runs.sort_by_key(|run| run.events.first().map(|event| event.time));
+        TaskHistory { runs }
+    }
+}
+
+/// A run of a task, from the trigger to the end
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct TaskRun {
+    task: String,
+    instance_id: Option<String>,
+    events: Vec<TaskRunEvent>,
+}
+
+impl TaskRun {
+    fn new(task: &str, instance_id: Option<&String>) -> TaskRun {
+        TaskRun {
+            task: task.to_string(),
+            instance_id: instance_id.cloned(),
+            events: Vec::new(),
+        }
+    }
+
+    /// The path of the task, like `\Backup\Nightly`
+    pub fn task(&self) -> &str {
+        &self.task
+    }
+
+    /// The GUID of the run, `None` for a run that failed to start
+    pub fn instance_id(&self) -> Option<&str> {
+        self.instance_id.as_deref()
+    }
+
+    /// The events of the run in the order they happened
+    pub fn events(&self) -> &[TaskRunEvent] {
+        &self.events
+    }
+
+    pub fn triggered(&self) -> Option<DateTime<Utc>> {
+        self.time_of(|kind| matches!(kind, TaskRunEventKind::Triggered { .. }))
+    }
+
+    pub fn started(&self) -> Option<DateTime<Utc>> {
+        self.time_of(|kind| matches!(kind, TaskRunEventKind::Started { .. }))
+    }
+
+    pub fn ended(&self) -> Option<DateTime<Utc>> {
+        self.time_of(|kind| matches!(kind, TaskRunEventKind::Ended))
+    }
+
+    /// How long the run took from its start to its end
+    pub fn duration(&self) -> Option<Duration> {
+        Some(self.ended()? - self.started()?)
+    }
+
+    /// The result of the run: the result of a failure when something failed,
+    /// otherwise the exit code of the last action that completed
+    pub fn result(&self) -> Option<TaskResult> {
+        let failure = self
+            .events
+            .iter()
+            .rev()
+            .find_map(|event| match &event.kind {
+                TaskRunEventKind::StartFailed { result }
+                | TaskRunEventKind::ActionFailed { result, .. } => Some(*result),
+                _ => None,
+            });
+        failure.or_else(|| {
+            self.events
+                .iter()
+                .rev()
+                .find_map(|event| match &event.kind {
+                    TaskRunEventKind::ActionCompleted { result, .. } => Some(*result),
+                    _ => None,
+                })
+        })
+    }
+
+    fn time_of(&self, kind: impl Fn(&TaskRunEventKind) -> bool) -> Option<DateTime<Utc>> {
+        self.events
+            .iter()
+            .find(|event| kind(&event.kind))
+            .map(|event| event.time)
+    }
+}
+
+/// An event in the run of a task
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct TaskRunEvent {
+    record: u64,
+    event_id: u32,
+    time: DateTime<Utc>,
+    kind: TaskRunEventKind,
+}
+
+impl TaskRunEvent {
+    /// The number of the event in the log
+    pub fn record(&self) -> u64 {
+        self.record
+    }
+
+    pub fn event_id(&self) -> u32 {
+        self.event_id
+    }
+
+    pub fn time(&self) -> DateTime<Utc> {
+        self.time
+    }
+
+    pub fn kind(&self) -> &TaskRunEventKind {
+        &self.kind
+    }
+}
+
+/// What happened in a run, with the event ids it is read from
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum TaskRunEventKind {
+    /// 107 when the schedule triggered the task, or 110 when a user did
+    Triggered { user: Option<String> },
+    /// 100, the task started in the context of the user
+    Started { user: Option<String> },
+    /// 101, the task couldn't start
+    StartFailed { result: TaskResult },
+    /// 129, the process of an action was created
+    ProcessCreated {
+        path: Option<String>,
+        process_id: Option<u32>,
+    },
+    /// 200
+    ActionStarted { action: String },
+    /// 201, the action completed with its exit code
+    ActionCompleted { action: String, result: TaskResult },
+    /// 103 or 203, the action couldn't start
+    ActionFailed { action: String, result: TaskResult },
+    /// 111, a user terminated the task
+    Terminated,
+    /// 322, the task didn't start because an instance is already running
+    LaunchIgnored,
+    /// 102
+    Ended,
+}
+
+/// Reads the events of runs from an `Events` element with `Event` elements
+pub(crate) fn read_events(xml: &str, events: &mut Vec<RunEvent>) -> Result<(), HistoryError> {
+    let document =
+        Document::parse(xml).map_err(|error| HistoryError::Malformed(error.to_string()))?;
+    for node in document
+        .root_element()
+        .children()
+        .filter(|node| node.tag_name().name() == "Event")
+    {
+        if let Some(event) = read_event(node)? {
+            events.push(event);
+        }
+    }
+    Ok(())
+}
+
+/// Reads a Task Scheduler event of a run with its task name and instance id,
+/// `None` for any other event
+fn read_event(event: Node) -> Result<Option<RunEvent>, HistoryError> {
+    let system = match child(event, "System") {
+        Some(system) => system,
+        None => return Ok(None),
+    };
+    let provider = child(system, "Provider").and_then(|provider| provider.attribute("Name"));
+    if provider.map_or(false, |provider| provider != PROVIDER) {
+        return Ok(None);
+    }
+    let event_id = match text(system, "EventID").and_then(|id| id.parse().ok()) {
+        Some(event_id) if RUN_EVENTS.contains(&event_id) => event_id,
+        _ => return Ok(None),
+    };
+    let record = text(system, "EventRecordID").unwrap_or_default();
+    let invalid = |description: &str| HistoryError::InvalidEvent {
+        record: record.to_string(),
+        description: description.to_string(),
+    };
+    let time = child(system, "TimeCreated")
+        .and_then(|time| time.attribute("SystemTime"))
+        .ok_or_else(|| invalid("the event has no time"))?;
+    let time = DateTime::parse_from_rfc3339(time)
+        .map_err(|_| invalid(&format!("{} isn't a time", time)))?
+        .with_timezone(&Utc);
+
+    let data: Vec<(&str, &str)> = child(event, "EventData")
+        .map(|data| {
+            data.children()
+                .filter(|node| node.tag_name().name() == "Data")
+                .map(|node| {
+                    (
+                        node.attribute("Name").unwrap_or(""),
+                        node.text().unwrap_or("").trim(),
+                    )
+                })
+                .collect()
+        })
+        .unwrap_or_default();
+    let value = |name: &str| {
+        data.iter()
+            .find(|(key, _)| *key == name)
+            .map(|(_, value)| *value)
+            .filter(|value| !value.is_empty())
+    };
+    let task = value("TaskName").ok_or_else(|| invalid("the event has no task name"))?;
+    let result = || {
+        value("ResultCode")
+            .and_then(TaskResult::parse)
+            .ok_or_else(|| invalid("the event has no result code"))
+    };
+    let action = || value("ActionName").unwrap_or_default().to_string();
+    let user = || value("UserContext").map(str::to_string);
+
+    let kind = match event_id {
+        100 => TaskRunEventKind::Started { user: user() },
+        101 => TaskRunEventKind::StartFailed { result: result()? },
+        102 => TaskRunEventKind::Ended,
+        103 | 203 => TaskRunEventKind::ActionFailed {
+            action: action(),
+            result: result()?,
+        },
+        107 => TaskRunEventKind::Triggered { user: None },
+        110 => TaskRunEventKind::Triggered { user: user() },
+        111 => TaskRunEventKind::Terminated,
+        129 => TaskRunEventKind::ProcessCreated {
+            path: value("Path").map(str::to_string),
+            process_id: value("ProcessID").and_then(|id| id.parse().ok()),
+        },
+        200 => TaskRunEventKind::ActionStarted { action: action() },
+        201 => TaskRunEventKind::ActionCompleted {
+            action: action(),
+            result: result()?,
+        },
+        _ => TaskRunEventKind::LaunchIgnored,
+    };
+
+    // the instance id is also the activity id the events are correlated with
+    let instance_id = value("InstanceId")
+        .or_else(|| value("TaskInstanceId"))
+        .or_else(|| {
+            child(system, "Correlation").and_then(|correlation| correlation.attribute("ActivityID"))
+        })
+        .filter(|id| !id.trim_matches(|c| "{}-0".contains(c)).is_empty())
+        .map(str::to_string);
+    let event = TaskRunEvent {
+        record: record.parse().unwrap_or_default(),
+        event_id,
+        time,
+        kind,
+    };
+    Ok(Some((event, task.to_string(), instance_id)))
+}
+
+fn child<'a, 'input>(node: Node<'a, 'input>, name: &str) -> Option<Node<'a, 'input>> {
+    node.children()
+        .find(|child| child.tag_name().name() == name)
+}
+
+fn text(node: Node, name: &str) -> Option<String> {
+    child(node, name).map(|child| child.text().unwrap_or("").trim().to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const BACKUP_RUN: &str = "{11111111-2222-3333-4444-555555555555}";
+    const OTHER_RUN: &str = "{66666666-7777-8888-9999-000000000000}";
+
+    /// An event as `wevtutil qe /f:xml` writes it, at the second of the minute
+    fn event(record: u64, event_id: u32, second: u32, data: &[(&str, &str)]) -> String {
+        event_of(PROVIDER, record, event_id, second, data)
+    }
+
+    fn event_of(
+        provider: &str,
+        record: u64,
+        event_id: u32,
+        second: u32,
+        data: &[(&str, &str)],
+    ) -> String {
+        let data: String = data
+            .iter()
+            .map(|(name, value)| format!("<Data Name='{}'>{}</Data>", name, value))
+            .collect();
+        format!(
+            "<Event xmlns='http://schemas.microsoft.com/win/2004/08/events/event'>\
+             <System><Provider Name='{}'/><EventID>{}</EventID>\
+             <TimeCreated SystemTime='2021-01-01T03:00:{:02}.000Z'/>\
+             <EventRecordID>{}</EventRecordID></System>\
+             <EventData>{}</EventData></Event>",
+            provider, event_id, second, record, data
+        )
+    }
+
+    fn records(history: &TaskHistory) -> Vec<Vec<u64>> {
+        history
+            .runs()
+            .iter()
+            .map(|run| run.events().iter().map(TaskRunEvent::record).collect())
+            .collect()
+    }
+
+    /// A run of `\Backup` that was triggered, ran its action and ended,
+    /// with the process created event that has no instance id
+    fn backup_run() -> Vec<String> {
+        vec![
+            event(
+                1,
+                107,
+                0,
+                &[("TaskName", "\\Backup"), ("InstanceId", BACKUP_RUN)],
+            ),
+            event(
+                2,
+                100,
+                1,
+                &[
+                    ("TaskName", "\\Backup"),
+                    ("UserContext", "DESKTOP-1\\admin"),
+                    ("InstanceId", BACKUP_RUN),
+                ],
+            ),
+            event(
+                3,
+                129,
+                2,
+                &[
+                    ("TaskName", "\\Backup"),
+                    ("Path", "C:\\backup.exe"),
+                    ("ProcessID", "4242"),
+                ],
+            ),
+            event(
+                4,
+                201,
+                30,
+                &[
+                    ("TaskName", "\\Backup"),
+                    ("TaskInstanceId", BACKUP_RUN),
+                    ("ActionName", "C:\\backup.exe"),
+                    ("ResultCode", "2"),
+                ],
+            ),
+            event(
+                5,
+                102,
+                31,
+                &[("TaskName", "\\Backup"), ("InstanceId", BACKUP_RUN)],
+            ),
+        ]
+    }
+
+    #[test]
+    fn groups_events_into_runs_by_instance_id() {
+        let mut events = backup_run();
+        events.insert(
+            2,
+            event(
+                6,
+                107,
+                1,
+                &[("TaskName", "\\Other"), ("InstanceId", OTHER_RUN)],
+            ),
+        );
+        let history = TaskHistory::from_events_xml(&events.concat()).unwrap();
+        assert_eq!(records(&history), [vec![1, 2, 3, 4, 5], vec![6]]);
+
+        let run = &history.runs()[0];
+        assert_eq!(run.task(), "\\Backup");
+        assert_eq!(run.instance_id(), Some(BACKUP_RUN));
+        assert_eq!(run.duration(), Some(Duration::seconds(30)));
+        assert_eq!(run.result(), Some(TaskResult(2)));
+        assert_eq!(
+            run.events()[2].kind(),
+            &TaskRunEventKind::ProcessCreated {
+                path: Some("C:\\backup.exe".to_string()),
+                process_id: Some(4242),
+            }
+        );
+        assert_eq!(history.runs_of("\\backup").count(), 1);
+        assert_eq!(history.runs_of("\\Other").count(), 1);
+    }
+
+    #[test]
+    fn gives_events_without_an_instance_id_a_run_of_their_own() {
+        let failed = |record, second| {
+            event(
+                record,
+                101,
+                second,
+                &[("TaskName", "\\Backup"), ("ResultCode", "2147942402")],
+            )
+        };
+        let history = TaskHistory::from_events_xml(&[failed(1, 0), failed(2, 5)].concat()).unwrap();
+        assert_eq!(records(&history), [vec![1], vec![2]]);
+        assert_eq!(history.runs()[0].instance_id(), None);
+        assert_eq!(history.runs()[0].result(), Some(TaskResult(0x8007_0002)));
+    }
+
+    #[test]
+    fn joins_a_created_process_to_the_last_open_run() {
+        let process = |record, second| {
+            event(
+                record,
+                129,
+                second,
+                &[("TaskName", "\\Backup"), ("ProcessID", "1")],
+            )
+        };
+        let mut events = backup_run();
+        events.remove(2);
+        // the first run has ended, so the process starts a run of its own
+        events.push(process(7, 40));
+        // the second run is open and takes the first process, but not the second
+        events.push(event(
+            8,
+            100,
+            50,
+            &[("TaskName", "\\Backup"), ("InstanceId", OTHER_RUN)],
+        ));
+        events.push(process(9, 51));
+        events.push(process(10, 52));
+        let history = TaskHistory::from_events_xml(&events.concat()).unwrap();
+        assert_eq!(
+            records(&history),
+            [vec![1, 2, 4, 5], vec![7], vec![8, 9], vec![10]]
+        );
+    }
+
+    #[test]
+    fn orders_events_by_their_time() {
+        let mut events = backup_run();
+        events.reverse();
+        let history = TaskHistory::from_events_xml(&events.concat()).unwrap();
+        assert_eq!(records(&history), [vec![1, 2, 3, 4, 5]]);
+        assert_eq!(
+            TaskHistory::from_events_xml(&backup_run().concat()).unwrap(),
+            history
+        );
+    }
+
+    #[test]
+    fn reads_events_with_or_without_a_declaration_and_root_element() {
+        let events = backup_run().concat();
+        let expected = TaskHistory::from_events_xml(&events).unwrap();
+        assert_eq!(records(&expected), [vec![1, 2, 3, 4, 5]]);
+        for xml in [
+            format!("<?xml version='1.0' encoding='UTF-8'?>\r\n{}", events),
+            format!("<Events>{}</Events>", events),
+            format!(
+                "\u{feff}<?xml version=\"1.0\" encoding=\"utf-8\" standalone=\"yes\"?>\
+                 <Events>{}</Events>",
+                events
+            ),
+        ] {
+            assert_eq!(TaskHistory::from_events_xml(&xml).unwrap(), expected);
+        }
+    }
+
+    #[test]
+    fn skips_other_providers_and_events() {
+        let mut events = backup_run();
+        events.push(event_of(
+            "Microsoft-Windows-Kernel-General",
+            6,
+            100,
+            40,
+            &[("TaskName", "\\Backup")],
+        ));
+        // 140 is an update of the task, not part of a run
+        events.push(event(7, 140, 41, &[("TaskName", "\\Backup")]));
+        events.push("<Event><EventData/></Event>".to_string());
+        let history = TaskHistory::from_events_xml(&events.concat()).unwrap();
+        assert_eq!(records(&history), [vec![1, 2, 3, 4, 5]]);
+    }
+
+    #[test]
+    fn rejects_events_that_are_missing_values() {
+        assert_eq!(
+            TaskHistory::from_events_xml(&event(1, 100, 0, &[])).unwrap_err(),
+            HistoryError::InvalidEvent {
+                record: "1".to_string(),
+                description: "the event has no task name".to_string(),
+            }
+        );
+        assert_eq!(
+            TaskHistory::from_events_xml(&event(2, 201, 0, &[("TaskName", "\\Backup")]))
+                .unwrap_err(),
+            HistoryError::InvalidEvent {
+                record: "2".to_string(),
+                description: "the event has no result code".to_string(),
+            }
+        );
+        assert!(matches!(
+            TaskHistory::from_events_xml("<Event>"),
+            Err(HistoryError::Malformed(_))
+        ));
+    }
+}
@@ -0,0 +1,922 @@
+use std::{
+    collections::hash_map::RandomState,
+    convert::TryFrom,
+    hash::{BuildHasher, Hasher},
+    path::PathBuf,
+};
+
+use chrono::{
+    DateTime, Datelike, Duration, Local, NaiveDate, NaiveDateTime, NaiveTime, TimeZone, Timelike,
+};
+
+use crate::{
+    boundary::Boundary,
+    error::JobError,
+    task::{RunLevel, Settings, Task, TaskLogon, TaskPrincipal, TaskRegistration},
+    Action, Actions, BootTrigger, DailyTrigger, DaysOfMonth, DaysOfWeek, ExecAction, IdleTrigger,
+    LogonTrigger, MonthlyDowTrigger, MonthlyTrigger, Months, Repetition, SpecificTimeTrigger,
+    TaskTriggers, TaskTriggersBuilder, TriggerCommon, WeeklyTrigger, WeeksOfMonth, MAX_TRIGGERS,
+};
+
+/// The size of the fixed length section at the start of the file
+const FIXED_LENGTH: usize = 0x44;
+/// The size of every trigger in the triggers array
+const TRIGGER_LENGTH: u16 = 0x30;
+
+/// Windows XP, the last version where `.job` files are how tasks are stored
+const PRODUCT_VERSION: u16 = 0x0501;
+const FILE_VERSION: u16 = 0x0001;
+
+const SCHED_S_TASK_HAS_NOT_RUN: u32 = 0x0004_1303;
+
+const IDLE_PRIORITY_CLASS: u32 = 0x40;
+const NORMAL_PRIORITY_CLASS: u32 = 0x20;
+const HIGH_PRIORITY_CLASS: u32 = 0x80;
+const REALTIME_PRIORITY_CLASS: u32 = 0x100;
+
+/// The maximum run time that means the task can run indefinitely
+const INFINITE: u32 = 0xFFFF_FFFF;
+
+const TASK_FLAG_DELETE_WHEN_DONE: u32 = 0x2;
+const TASK_FLAG_DISABLED: u32 = 0x4;
+const TASK_FLAG_START_ONLY_IF_IDLE: u32 = 0x10;
+const TASK_FLAG_KILL_ON_IDLE_END: u32 = 0x20;
+const TASK_FLAG_DONT_START_IF_ON_BATTERIES: u32 = 0x40;
+const TASK_FLAG_KILL_IF_GOING_ON_BATTERIES: u32 = 0x80;
+const TASK_FLAG_HIDDEN: u32 = 0x200;
+const TASK_FLAG_RUN_IF_CONNECTED_TO_INTERNET: u32 = 0x400;
+const TASK_FLAG_RESTART_ON_IDLE_RESUME: u32 = 0x800;
+const TASK_FLAG_SYSTEM_REQUIRED: u32 = 0x1000;
+const TASK_FLAG_RUN_ONLY_IF_LOGGED_ON: u32 = 0x2000;
+
+const TASK_TRIGGER_FLAG_HAS_END_DATE: u32 = 0x1;
+const TASK_TRIGGER_FLAG_KILL_AT_DURATION_END: u32 = 0x2;
+const TASK_TRIGGER_FLAG_DISABLED: u32 = 0x4;
+
+const TASK_TIME_TRIGGER_ONCE: u32 = 0;
+const TASK_TIME_TRIGGER_DAILY: u32 = 1;
+const TASK_TIME_TRIGGER_WEEKLY: u32 = 2;
+const TASK_TIME_TRIGGER_MONTHLYDATE: u32 = 3;
+const TASK_TIME_TRIGGER_MONTHLYDOW: u32 = 4;
+const TASK_EVENT_TRIGGER_ON_IDLE: u32 = 5;
+const TASK_EVENT_TRIGGER_AT_SYSTEMSTART: u32 = 6;
+const TASK_EVENT_TRIGGER_AT_LOGON: u32 = 7;
+
+/// The week of the month that means the last week
+const TASK_LAST_WEEK: u16 = 5;
+
+impl Task {
+    /// Reads a task from a Task Scheduler 1.0 `.job` file, so that it can be
+    /// registered as a Task Scheduler 2.0 task.
+    ///
+    /// Times in a `.job` file are in local time. The comment becomes the description
+    /// and the application becomes an exec action. Disabled triggers are kept as
+    /// disabled triggers, and the account the job runs as isn't in the file so the
+    /// principal is the default.
+    ///
+    /// https://docs.microsoft.com/en-us/openspecs/windows_protocols/ms-tsch
+    pub fn from_job(bytes: &[u8]) -> Result<Task, JobError> {
+        let mut header = JobReader::new(bytes, 0);
+        header.skip("product version", 2)?;
+        let file_version = header.u16("file version")?;
+        if file_version != FILE_VERSION {
+            return Err(invalid("file version", file_version));
+        }
+        header.skip("job uuid", 16)?;
+        let application_name_offset = header.u16("application name offset")?;
+        let triggers_offset = header.u16("triggers offset")?;
+        header.skip("error retry count and interval", 4)?;
+        let idle_deadline = header.u16("idle deadline")?;
+        let idle_wait = header.u16("idle wait")?;
+        let priority = header.u32("priority")?;
+        let maximum_run_time = header.u32("maximum run time")?;
+        header.skip("exit code and status", 8)?;
+        let flags = header.u32("flags")?;
+
+        let mut data = JobReader::new(bytes, usize::from(application_name_offset));
+        let application_name = data.string("application name")?;
+        let parameters = data.string("parameters")?;
+        let working_directory = data.string("working directory")?;
+        let author = data.string("author")?;
+        let comment = data.string("comment")?;
+
+        if application_name.is_empty() {
+            return Err(invalid("application name", ""));
+        }
+        let mut action = ExecAction::new(PathBuf::from(application_name));
+        if !parameters.is_empty() {
+            action = action.with_arguments(parameters);
+        }
+        if !working_directory.is_empty() {
+            action = action.with_working_directory(PathBuf::from(working_directory));
+        }
+
+        let mut registration = TaskRegistration::new();
+        if !author.is_empty() {
+            registration = registration.with_author(author);
+        }
+        if !comment.is_empty() {
+            registration = registration.with_description(comment);
+        }
+
+        let mut principal = TaskPrincipal::new();
+        if flags & TASK_FLAG_RUN_ONLY_IF_LOGGED_ON != 0 {
+            principal = principal.with_logon_type(TaskLogon::InteractiveToken);
+        }
+
+        let mut settings = Settings::new()
+            .with_enabled(flags & TASK_FLAG_DISABLED == 0)
+            .with_hidden(flags & TASK_FLAG_HIDDEN != 0)
+            .with_run_only_if_idle(flags & TASK_FLAG_START_ONLY_IF_IDLE != 0)
+            .with_disallow_start_if_on_batteries(flags & TASK_FLAG_DONT_START_IF_ON_BATTERIES != 0)
+            .with_stop_if_going_on_batteries(flags & TASK_FLAG_KILL_IF_GOING_ON_BATTERIES != 0)
+            .with_run_only_if_network_available(flags & TASK_FLAG_RUN_IF_CONNECTED_TO_INTERNET != 0)
+            .with_wake_to_run(flags & TASK_FLAG_SYSTEM_REQUIRED != 0)
+            .with_execution_time_limit(match maximum_run_time {
+                INFINITE => None,
+                milliseconds => Some(Duration::milliseconds(i64::from(milliseconds))),
+            })
+            .with_priority(match priority {
+                REALTIME_PRIORITY_CLASS => 0,
+                HIGH_PRIORITY_CLASS => 1,
+                NORMAL_PRIORITY_CLASS => 7,
+                IDLE_PRIORITY_CLASS => 10,
+                priority => return Err(invalid("priority", format!("{:#x}", priority))),
+            });
+        settings.idle_conditions = settings
+            .idle_conditions
+            .with_duration(Duration::minutes(i64::from(idle_wait)))
+            .with_wait_timeout(Duration::minutes(i64::from(idle_deadline)))
+            .with_stop_on_idle_end(flags & TASK_FLAG_KILL_ON_IDLE_END != 0)
+            .with_restart_on_idle(flags & TASK_FLAG_RESTART_ON_IDLE_RESUME != 0);
+        if flags & TASK_FLAG_DELETE_WHEN_DONE != 0 {
+            settings = settings.with_delete_expired_task_after(Duration::zero());
+        }
+
+        let mut reader = JobReader::new(bytes, usize::from(triggers_offset));
+        let count = reader.u16("trigger count")?;
+        if count > u16::from(MAX_TRIGGERS) {
+            return Err(JobError::Unsupported(format!(
+                "{} triggers, a task can only have 48",
+                count
+            )));
+        }
+        let mut builder = TaskTriggersBuilder::new();
+        for _ in 0..count {
+            builder = read_trigger(&mut reader, builder)?;
+        }
+
+        Ok(Task::new(
+            Actions::from_action(action.into()),
+            TaskTriggers::from_builder(builder),
+        )
+        .with_registration(registration)
+        .with_principal(principal)
+        .with_settings(settings))
+    }
+
+    /// Writes the task as a Task Scheduler 1.0 `.job` file, for machines and
+    /// installers that still use them.
+    ///
+    /// A `.job` file runs a single program, in local time, with times to the minute.
+    /// Monthly day of week triggers become a trigger for each week, and end boundaries
+    /// become the day they are on. Anything else the file can't hold, like event
+    /// triggers, random delays or repetitions that never end, returns [`JobError::Unsupported`].
+    pub fn to_job(&self) -> Result<Vec<u8>, JobError> {
+        let action = match self.actions.iter().collect::<Vec<_>>().as_slice() {
+            [Action::Exec(action)] => action.clone(),
+            [Action::ComHandler(_)] => {
+                return Err(JobError::Unsupported(
+                    "COM handler actions, a .job file can only run a program".to_string(),
+                ))
+            }
+            actions => {
+                return Err(JobError::Unsupported(format!(
+                    "{} actions, a .job file runs a single program",
+                    actions.len()
+                )))
+            }
+        };
+        if self.principal.run_level == RunLevel::HighestAvailable {
+            return Err(JobError::Unsupported(
+                "running with the highest privileges".to_string(),
+            ));
+        }
+
+        let triggers = job_triggers(&self.triggers)?;
+        let settings = &self.settings;
+
+        let mut data = JobWriter::new();
+        // the running instance count
+        data.u16(0);
+        data.string(&action.path().to_string_lossy());
+        data.string(action.arguments().unwrap_or(""));
+        data.string(
+            &action
+                .working_directory()
+                .map(|directory| directory.to_string_lossy().into_owned())
+                .unwrap_or_default(),
+        );
+        data.string(self.registration.author.as_deref().unwrap_or(""));
+        data.string(self.registration.description.as_deref().unwrap_or(""));
+        // no user data
+        data.u16(0);
+        // the reserved data is the start error and the task flags, which are both unused
+        data.u16(8);
+        data.u32(0);
+        data.u32(0);
+        let triggers_offset = FIXED_LENGTH + data.bytes.len();
+        data.u16(triggers.len() as u16);
+        for trigger in &triggers {
+            trigger.write(&mut data);
+        }
+
+        let mut header = JobWriter::new();
+        header.u16(PRODUCT_VERSION);
+        header.u16(FILE_VERSION);
+        header.bytes.extend_from_slice(&job_uuid());
+        // the application name length comes after the running instance count
+        header.u16(FIXED_LENGTH as u16 + 2);
+        header.u16(u16::try_from(triggers_offset).map_err(|_| {
+            JobError::Unsupported("strings that are longer than a .job file can hold".to_string())
+        })?);
+        // the error retry count and interval aren't used
+        header.u16(0);
+        header.u16(0);
+        header.u16(minutes(
+            "idle deadline",
+            settings.idle_conditions.wait_timeout,
+        )?);
+        header.u16(minutes("idle wait", settings.idle_conditions.duration)?);
+        header.u32(match settings.priority {
+            0 => REALTIME_PRIORITY_CLASS,
+            1 => HIGH_PRIORITY_CLASS,
+            2..=8 => NORMAL_PRIORITY_CLASS,
+            _ => IDLE_PRIORITY_CLASS,
+        });
+        header.u32(match settings.execution_time_limit {
+            None => INFINITE,
+            Some(limit) => u32::try_from(limit.num_milliseconds())
+                .ok()
+                .filter(|milliseconds| *milliseconds != INFINITE)
+                .ok_or_else(|| {
+                    JobError::Unsupported("an execution time limit longer than 49 days".to_string())
+                })?,
+        });
+        // the exit code and status of a task that hasn't run
+        header.u32(0);
+        header.u32(SCHED_S_TASK_HAS_NOT_RUN);
+        header.u32(job_flags(self));
+        // the last run time
+        header.bytes.extend_from_slice(&[0; 16]);
+
+        header.bytes.extend(data.bytes);
+        Ok(header.bytes)
+    }
+}
+
+fn invalid(field: &str, value: impl ToString) -> JobError {
+    JobError::Invalid {
+        field: field.to_string(),
+        value: value.to_string(),
+    }
+}
+
+/// Reads little endian values from a `.job` file
+struct JobReader<'a> {
+    bytes: &'a [u8],
+    offset: usize,
+}
+
+impl<'a> JobReader<'a> {
+    fn new(bytes: &'a [u8], offset: usize) -> Self {
+        Self { bytes, offset }
+    }
+
+    fn take(&mut self, field: &str, length: usize) -> Result<&'a [u8], JobError> {
+        let end = self.offset + length;
+        let bytes = self
+            .bytes
+            .get(self.offset..end)
+            .ok_or_else(|| JobError::Truncated(field.to_string()))?;
+        self.offset = end;
+        Ok(bytes)
+    }
+
+    fn skip(&mut self, field: &str, length: usize) -> Result<(), JobError> {
+        self.take(field, length).map(|_| ())
+    }
+
+    fn u16(&mut self, field: &str) -> Result<u16, JobError> {
+        let bytes = self.take(field, 2)?;
+        Ok(u16::from_le_bytes([bytes[0], bytes[1]]))
+    }
+
+    fn u32(&mut self, field: &str) -> Result<u32, JobError> {
+        let bytes = self.take(field, 4)?;
+        Ok(u32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]))
+    }
+
+    /// A UTF-16 string that starts with its length in characters, including the null
+    fn string(&mut self, field: &str) -> Result<String, JobError> {
+        let length = usize::from(self.u16(field)?);
+        let bytes = self.take(field, length * 2)?;
+        let characters: Vec<u16> = bytes
+            .chunks(2)
+            .map(|pair| u16::from_le_bytes([pair[0], pair[1]]))
+            .take_while(|character| *character != 0)
+            .collect();
+        String::from_utf16(&characters).map_err(|error| invalid(field, error))
+    }
+}
+
+/// Writes little endian values of a `.job` file
+struct JobWriter {
+    bytes: Vec<u8>,
+}
+
+impl JobWriter {
+    fn new() -> Self {
+        Self { bytes: Vec::new() }
+    }
+
+    fn u16(&mut self, value: u16) {
+        self.bytes.extend_from_slice(&value.to_le_bytes());
+    }
+
+    fn u32(&mut self, value: u32) {
+        self.bytes.extend_from_slice(&value.to_le_bytes());
+    }
+
+    /// Empty strings are written as a length of 0 without a null
+    fn string(&mut self, value: &str) {
+        let characters: Vec<u16> = value.encode_utf16().collect();
+        if characters.is_empty() {
+            self.u16(0);
+            return;
+        }
+        self.u16(characters.len() as u16 + 1);
+        for character in characters.into_iter().chain(std::iter::once(0)) {
+            self.u16(character);
+        }
+    }
+}
+
+/// A random version 4 uuid, which identifies the job
+fn job_uuid() -> [u8; 16] {
+    let mut uuid = [0; 16];
+    for half in uuid.chunks_mut(8) {
+        let random = RandomState::new().build_hasher().finish();
+        half.copy_from_slice(&random.to_le_bytes());
+    }
+    uuid[7] = (uuid[7] & 0x0F) | 0x40;
+    uuid[8] = (uuid[8] & 0x3F) | 0x80;
+    uuid
+}
+
+fn job_flags(task: &Task) -> u32 {
+    let settings = &task.settings;
+    let idle = &settings.idle_conditions;
+    [
+        (
+            settings.delete_expired_task_after.is_some(),
+            TASK_FLAG_DELETE_WHEN_DONE,
+        ),
+        (!settings.enabled, TASK_FLAG_DISABLED),
+        (settings.run_only_if_idle, TASK_FLAG_START_ONLY_IF_IDLE),
+        (idle.stop_on_idle_end, TASK_FLAG_KILL_ON_IDLE_END),
+        (
+            settings.disallow_start_if_on_batteries,
+            TASK_FLAG_DONT_START_IF_ON_BATTERIES,
+        ),
+        (
+            settings.stop_if_going_on_batteries,
+            TASK_FLAG_KILL_IF_GOING_ON_BATTERIES,
+        ),
+        (settings.hidden, TASK_FLAG_HIDDEN),
+        (
+            settings.run_only_if_network_available,
+            TASK_FLAG_RUN_IF_CONNECTED_TO_INTERNET,
+        ),
+        (idle.restart_on_idle, TASK_FLAG_RESTART_ON_IDLE_RESUME),
+        (settings.wake_to_run, TASK_FLAG_SYSTEM_REQUIRED),
+        (
+            task.principal.logon_type == Some(TaskLogon::InteractiveToken),
+            TASK_FLAG_RUN_ONLY_IF_LOGGED_ON,
+        ),
+    ]
+    .iter()
+    .filter(|(set, _)| *set)
+    .fold(0, |flags, (_, flag)| flags | flag)
+}
+
+fn minutes(field: &str, duration: Duration) -> Result<u16, JobError> {
+    if duration.num_seconds() % 60 != 0 {
+        return Err(JobError::Unsupported(format!(
+            "an {} that isn't whole minutes",
+            field
+        )));
+    }
+    u16::try_from(duration.num_minutes())
+        .map_err(|_| JobError::Unsupported(format!("an {} longer than 65535 minutes", field)))
+}
+
+fn local_time(date: NaiveDate, hour: u16, minute: u16) -> Result<Boundary, JobError> {
+    let time = NaiveTime::from_hms_opt(u32::from(hour), u32::from(minute), 0)
+        .ok_or_else(|| invalid("trigger start time", format!("{}:{}", hour, minute)))?;
+    Ok(Boundary::Local(date.and_time(time)))
+}
+
+fn read_date(reader: &mut JobReader, field: &str) -> Result<Option<NaiveDate>, JobError> {
+    let year = reader.u16(field)?;
+    let month = reader.u16(field)?;
+    let day = reader.u16(field)?;
+    if year == 0 && month == 0 && day == 0 {
+        return Ok(None);
+    }
+    NaiveDate::from_ymd_opt(i32::from(year), u32::from(month), u32::from(day))
+        .map(Some)
+        .ok_or_else(|| invalid(field, format!("{}-{}-{}", year, month, day)))
+}
+
+fn read_trigger(
+    reader: &mut JobReader,
+    builder: TaskTriggersBuilder,
+) -> Result<TaskTriggersBuilder, JobError> {
+    let size = reader.u16("trigger size")?;
+    if size != TRIGGER_LENGTH {
+        return Err(invalid("trigger size", size));
+    }
+    reader.skip("trigger", 2)?;
+    let begin = read_date(reader, "trigger begin date")?;
+    let end = read_date(reader, "trigger end date")?;
+    let hour = reader.u16("trigger start hour")?;
+    let minute = reader.u16("trigger start minute")?;
+    let duration = reader.u32("trigger duration")?;
+    let interval = reader.u32("trigger interval")?;
+    let flags = reader.u32("trigger flags")?;
+    let kind = reader.u32("trigger type")?;
+    let specific = [
+        reader.u16("trigger")?,
+        reader.u16("trigger")?,
+        reader.u16("trigger")?,
+    ];
+    reader.skip("trigger", 6)?;
+
+    let start = match begin {
+        Some(begin) => Some(local_time(begin, hour, minute)?),
+        None => None,
+    };
+    // the end date is the last day the trigger runs on
+    let end = match end {
+        Some(end) if flags & TASK_TRIGGER_FLAG_HAS_END_DATE != 0 => {
+            end.and_hms_opt(23, 59, 59).map(Boundary::Local)
+        }
+        _ => None,
+    };
+    let common = TriggerCommon {
+        enabled: flags & TASK_TRIGGER_FLAG_DISABLED == 0,
+        start,
+        end,
+        ..TriggerCommon::new()
+    };
+    let repetition = if interval > 0 {
+        let mut repetition = Repetition::new(Duration::minutes(i64::from(interval)))
+            .with_duration(Duration::minutes(i64::from(duration)));
+        if flags & TASK_TRIGGER_FLAG_KILL_AT_DURATION_END != 0 {
+            repetition = repetition.stop_at_duration_end();
+        }
+        Some(repetition)
+    } else {
+        None
+    };
+    let months = Months::from_bits(specific[2]);
+    // event triggers don't use the dates
+    let event_common = TriggerCommon::new().with_enabled(common.enabled);
+
+    let builder = match kind {
+        TASK_TIME_TRIGGER_ONCE => {
+            if start.is_none() {
+                return Err(invalid("trigger begin date", "0-0-0"));
+            }
+            builder.with_specific_time(SpecificTimeTrigger { common, repetition })
+        }
+        TASK_TIME_TRIGGER_DAILY => {
+            let mut trigger = DailyTrigger::new(String::new());
+            trigger.common = common;
+            trigger.interval = Some(specific[0]).filter(|interval| *interval > 1);
+            trigger.repetition = repetition;
+            builder.with_daily(trigger)
+        }
+        TASK_TIME_TRIGGER_WEEKLY => {
+            let mut trigger = WeeklyTrigger::new(DaysOfWeek::from_bits(specific[1]));
+            trigger.common = common;
+            trigger.weeks_interval = Some(specific[0]).filter(|interval| *interval > 1);
+            trigger.repetition = repetition;
+            builder.with_weekly(trigger)
+        }
+        TASK_TIME_TRIGGER_MONTHLYDATE => {
+            let days = u32::from(specific[0]) | u32::from(specific[1]) << 16;
+            let mut trigger = MonthlyTrigger::new(DaysOfMonth::from_bits(days), months);
+            trigger.common = common;
+            trigger.repetition = repetition;
+            builder.with_monthly(trigger)
+        }
+        TASK_TIME_TRIGGER_MONTHLYDOW => {
+            let days = DaysOfWeek::from_bits(specific[1]);
+            let mut trigger = match specific[0] {
+                week @ 1..=4 => {
+                    MonthlyDowTrigger::new(days, WeeksOfMonth::new().with(u32::from(week)), months)
+                }
+                TASK_LAST_WEEK => MonthlyDowTrigger::new(days, WeeksOfMonth::new(), months)
+                    .on_last_week_of_month(),
+                week => return Err(invalid("trigger week of the month", week)),
+            };
+            trigger.common = common;
+            trigger.repetition = repetition;
+            builder.with_monthly_dow(trigger)
+        }
+        TASK_EVENT_TRIGGER_ON_IDLE => builder.with_idle(IdleTrigger {
+            common: event_common,
+            ..IdleTrigger::new()
+        }),
+        TASK_EVENT_TRIGGER_AT_SYSTEMSTART => builder.with_boot(BootTrigger {
+            common: event_common,
+            ..BootTrigger::new()
+        }),
+        TASK_EVENT_TRIGGER_AT_LOGON => builder.with_logon(LogonTrigger {
+            common: event_common,
+            ..LogonTrigger::new()
+        }),
+        kind => return Err(invalid("trigger type", kind)),
+    };
+    Ok(builder)
+}
+
+/// A trigger as it is stored in a `.job` file
+struct JobTrigger {
+    enabled: bool,
+    start: NaiveDateTime,
+    end: Option<NaiveDate>,
+    repetition: Option<(u32, u32, bool)>,
+    kind: u32,
+    specific: [u16; 3],
+}
+
+impl JobTrigger {
+    /// A trigger that runs at a time, from its start boundary or from now
+    fn timed(
+        kind: u32,
+        specific: [u16; 3],
+        common: &TriggerCommon,
+        repetition: Option<&Repetition>,
+    ) -> Result<Self, JobError> {
+        if common.random_delay.is_some() {
+            return Err(JobError::Unsupported("random delays".to_string()));
+        }
+        check_execution_time_limit(common)?;
+        // .job files only have local times
+        let start = common.start.unwrap_or_else(Boundary::now).local_time();
+        if start.second() != 0 || start.nanosecond() != 0 {
+            return Err(JobError::Unsupported(format!(
+                "a start time of {}, which isn't on a whole minute",
+                start
+            )));
+        }
+        let repetition = match repetition {
+            None => None,
+            Some(repetition) => {
+                let duration = repetition.duration.ok_or_else(|| {
+                    JobError::Unsupported("a repetition that never ends".to_string())
+                })?;
+                Some((
+                    u32::from(minutes("repetition interval", repetition.interval)?),
+                    u32::try_from(duration.num_minutes())
+                        .ok()
+                        .filter(|_| duration.num_seconds() % 60 == 0)
+                        .ok_or_else(|| {
+                            JobError::Unsupported(
+                                "a repetition duration that isn't whole minutes".to_string(),
+                            )
+                        })?,
+                    repetition.stop_at_duration_end,
+                ))
+            }
+        };
+        Ok(Self {
+            enabled: common.enabled,
+            start,
+            end: common.end.map(|end| end.local_time().date()),
+            repetition,
+            kind,
+            specific,
+        })
+    }
+
+    /// A trigger that runs on an event, which only uses the type
+    fn event(kind: u32, common: &TriggerCommon) -> Result<Self, JobError> {
+        check_execution_time_limit(common)?;
+        Ok(Self {
+            enabled: common.enabled,
+            start: NaiveDateTime::default(),
+            end: None,
+            repetition: None,
+            kind,
+            specific: [0; 3],
+        })
+    }
+
+    fn write(&self, writer: &mut JobWriter) {
+        writer.u16(TRIGGER_LENGTH);
+        writer.u16(0);
+        if self.kind >= TASK_EVENT_TRIGGER_ON_IDLE {
+            writer.bytes.extend_from_slice(&[0; 16]);
+        } else {
+            writer.u16(self.start.year() as u16);
+            writer.u16(self.start.month() as u16);
+            writer.u16(self.start.day() as u16);
+            let end = self.end.unwrap_or_default();
+            writer.u16(self.end.map_or(0, |_| end.year() as u16));
+            writer.u16(self.end.map_or(0, |_| end.month() as u16));
+            writer.u16(self.end.map_or(0, |_| end.day() as u16));
+            writer.u16(self.start.hour() as u16);
+            writer.u16(self.start.minute() as u16);
+        }
+        let (interval, duration, stop) = self.repetition.unwrap_or((0, 0, false));
+        writer.u32(duration);
+        writer.u32(interval);
+        let mut flags = 0;
+        if self.end.is_some() {
+            flags |= TASK_TRIGGER_FLAG_HAS_END_DATE;
+        }
+        if stop {
+            flags |= TASK_TRIGGER_FLAG_KILL_AT_DURATION_END;
+        }
+        if !self.enabled {
+            flags |= TASK_TRIGGER_FLAG_DISABLED;
+        }
+        writer.u32(flags);
+        writer.u32(self.kind);
+        for specific in &self.specific {
+            writer.u16(*specific);
+        }
+        // padding and reserved
+        writer.bytes.extend_from_slice(&[0; 6]);
+    }
+}
+
+fn check_execution_time_limit(common: &TriggerCommon) -> Result<(), JobError> {
+    match common.execution_time_limit {
+        Some(_) => Err(JobError::Unsupported(
+            "execution time limits on triggers".to_string(),
+        )),
+        None => Ok(()),
+    }
+}
+
+fn job_triggers(triggers: &TaskTriggers) -> Result<Vec<JobTrigger>, JobError> {
+    let unsupported = [
+        ("event triggers", triggers.event().len()),
+        ("registration triggers", triggers.registration().len()),
+        (
+            "session state change triggers",
+            triggers.session_state_change().len(),
+        ),
+    ];
+    if let Some((name, _)) = unsupported.iter().find(|(_, count)| *count > 0) {
+        return Err(JobError::Unsupported(name.to_string()));
+    }
+
+    let mut job_triggers = Vec::new();
+    for trigger in triggers.specific_times() {
+        job_triggers.push(JobTrigger::timed(
+            TASK_TIME_TRIGGER_ONCE,
+            [0; 3],
+            &trigger.common,
+            trigger.repetition.as_ref(),
+        )?);
+    }
+    for trigger in triggers.daily() {
+        job_triggers.push(JobTrigger::timed(
+            TASK_TIME_TRIGGER_DAILY,
+            [trigger.interval.unwrap_or(1).max(1), 0, 0],
+            &trigger.common,
+            trigger.repetition.as_ref(),
+        )?);
+    }
+    for trigger in triggers.weekly() {
+        job_triggers.push(JobTrigger::timed(
+            TASK_TIME_TRIGGER_WEEKLY,
+            [
+                trigger.weeks_interval.unwrap_or(1).max(1),
+                trigger.days_of_week.bits(),
+                0,
+            ],
+            &trigger.common,
+            trigger.repetition.as_ref(),
+        )?);
+    }
+    for trigger in triggers.monthly() {
+        if trigger.run_on_last_day_of_month {
+            return Err(JobError::Unsupported(
+                "monthly triggers on the last day of the month".to_string(),
+            ));
+        }
+        let days = trigger.days_of_month.bits();
+        job_triggers.push(JobTrigger::timed(
+            TASK_TIME_TRIGGER_MONTHLYDATE,
+            [days as u16, (days >> 16) as u16, trigger.months.bits()],
+            &trigger.common,
+            trigger.repetition.as_ref(),
+        )?);
+    }
+    for trigger in triggers.monthly_dow() {
+        // a .job trigger is for a single week of the month
+        let last_week = Some(TASK_LAST_WEEK).filter(|_| trigger.run_on_last_week_of_month);
+        let weeks = trigger
+            .weeks_of_month
+            .iter()
+            .map(|week| week as u16)
+            .chain(last_week);
+        for week in weeks {
+            job_triggers.push(JobTrigger::timed(
+                TASK_TIME_TRIGGER_MONTHLYDOW,
+                [week, trigger.days_of_week.bits(), trigger.months.bits()],
+                &trigger.common,
+                trigger.repetition.as_ref(),
+            )?);
+        }
+    }
+    for trigger in triggers.idle() {
+        if trigger.repetition.is_some() {
+            return Err(JobError::Unsupported(
+                "idle triggers with a repetition".to_string(),
+            ));
+        }
+        job_triggers.push(JobTrigger::event(
+            TASK_EVENT_TRIGGER_ON_IDLE,
+            &trigger.common,
+        )?);
+    }
+    for trigger in triggers.boot() {
+        if trigger.delay.is_some() {
+            return Err(JobError::Unsupported(
+                "boot triggers with a delay".to_string(),
+            ));
+        }
+        job_triggers.push(JobTrigger::event(
+            TASK_EVENT_TRIGGER_AT_SYSTEMSTART,
+            &trigger.common,
+        )?);
+    }
+    for trigger in triggers.logon() {
+        if trigger.delay.is_some() || trigger.user_id.is_some() {
+            return Err(JobError::Unsupported(
+                "logon triggers with a delay or for a single user".to_string(),
+            ));
+        }
+        job_triggers.push(JobTrigger::event(
+            TASK_EVENT_TRIGGER_AT_LOGON,
+            &trigger.common,
+        )?);
+    }
+    Ok(job_triggers)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::Path;
+
+    use chrono::Weekday;
+
+    use super::*;
+
+    fn job(bytes: &[u8]) -> Task {
+        Task::from_job(bytes).unwrap()
+    }
+
+    fn local(year: i32, month: u32, day: u32, hour: u32, minute: u32) -> Boundary {
+        Boundary::Local(
+            NaiveDate::from_ymd_opt(year, month, day)
+                .unwrap()
+                .and_hms_opt(hour, minute, 0)
+                .unwrap(),
+        )
+    }
+
+    fn exec(task: &Task) -> &ExecAction {
+        match task.actions.iter().collect::<Vec<_>>().as_slice() {
+            [Action::Exec(action)] => action,
+            actions => panic!("expected a single exec action, got {:?}", actions),
+        }
+    }
+
+    #[test]
+    fn reads_a_daily_job() {
+        let task = job(include_bytes!("../tests/fixtures/job/daily.job"));
+        let action = exec(&task);
+        assert_eq!(
+            action.path(),
+            Path::new(r"C:\Windows\System32\cleanmgr.exe")
+        );
+        assert_eq!(action.arguments(), Some("/sagerun:1"));
+        assert_eq!(action.working_directory(), Some(Path::new(r"C:\Windows")));
+        assert_eq!(task.registration.author.as_deref(), Some("Administrator"));
+        assert_eq!(
+            task.registration.description.as_deref(),
+            Some("Cleans up the disk")
+        );
+        assert_eq!(
+            task.settings.execution_time_limit,
+            Some(Duration::hours(72))
+        );
+
+        let daily = task.triggers.daily();
+        assert_eq!(daily.len(), 1);
+        assert!(daily[0].common.enabled);
+        assert_eq!(daily[0].common.start, Some(local(2024, 1, 1, 9, 0)));
+        assert_eq!(daily[0].common.end, None);
+        assert_eq!(daily[0].interval, Some(2));
+    }
+
+    #[test]
+    fn reads_a_weekly_job() {
+        let task = job(include_bytes!("../tests/fixtures/job/weekly.job"));
+        assert_eq!(exec(&task).arguments(), None);
+
+        let weekly = task.triggers.weekly();
+        assert_eq!(weekly.len(), 1);
+        assert_eq!(
+            weekly[0].days_of_week,
+            DaysOfWeek::new().with(Weekday::Mon).with(Weekday::Fri)
+        );
+        assert_eq!(weekly[0].weeks_interval, None);
+        assert_eq!(weekly[0].common.start, Some(local(2024, 1, 1, 8, 30)));
+        // the end date is the last day the trigger runs on
+        assert_eq!(
+            weekly[0].common.end,
+            NaiveDate::from_ymd_opt(2024, 12, 31)
+                .and_then(|end| end.and_hms_opt(23, 59, 59))
+                .map(Boundary::Local)
+        );
+    }
+
+    #[test]
+    fn reads_a_monthly_day_of_week_job() {
+        let task = job(include_bytes!("../tests/fixtures/job/monthly_dow.job"));
+        let months = Months::new().with(1).with(7);
+
+        let monthly_dow = task.triggers.monthly_dow();
+        assert_eq!(monthly_dow.len(), 2);
+        assert_eq!(
+            monthly_dow[0].days_of_week,
+            DaysOfWeek::new().with(Weekday::Tue)
+        );
+        assert_eq!(monthly_dow[0].weeks_of_month, WeeksOfMonth::new().with(2));
+        assert!(!monthly_dow[0].run_on_last_week_of_month);
+        assert_eq!(monthly_dow[0].months, months);
+
+        assert_eq!(
+            monthly_dow[1].days_of_week,
+            DaysOfWeek::new().with(Weekday::Fri)
+        );
+        assert!(monthly_dow[1].weeks_of_month.is_empty());
+        assert!(monthly_dow[1].run_on_last_week_of_month);
+        assert_eq!(monthly_dow[1].months, months);
+    }
+
+    #[test]
+    fn keeps_disabled_triggers() {
+        let task = job(include_bytes!("../tests/fixtures/job/disabled_trigger.job"));
+
+        let daily = task.triggers.daily();
+        assert_eq!(daily.len(), 1);
+        assert!(daily[0].common.enabled);
+
+        let specific_times = task.triggers.specific_times();
+        assert_eq!(specific_times.len(), 1);
+        assert!(!specific_times[0].common.enabled);
+        assert_eq!(
+            specific_times[0].common.start,
+            Some(local(2024, 6, 1, 18, 45))
+        );
+        let repetition = specific_times[0].repetition.as_ref().unwrap();
+        assert_eq!(repetition.interval, Duration::minutes(15));
+        assert_eq!(repetition.duration, Some(Duration::hours(1)));
+    }
+
+    #[test]
+    fn writes_disabled_triggers_back() {
+        let task = job(include_bytes!("../tests/fixtures/job/disabled_trigger.job"));
+        let written = job(&task.to_job().unwrap());
+        assert_eq!(written.triggers, task.triggers);
+    }
+
+    #[test]
+    fn rejects_a_truncated_job() {
+        assert_eq!(
+            Task::from_job(include_bytes!("../tests/fixtures/job/truncated.job")).unwrap_err(),
+            JobError::Truncated("trigger flags".to_string())
+        );
+    }
+}
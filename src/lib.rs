@@ -1,4 +1,5 @@
 #![allow(warnings)]
+#[cfg(windows)]
 use bindings::Windows::Win32::{
     Automation::BSTR,
     TaskScheduler::{
@@ -6,158 +7,248 @@ use bindings::Windows::Win32::{
         TASK_LOGON_TYPE, TASK_TRIGGER_TYPE, TASK_TRIGGER_TYPE2,
     },
 };
+#[cfg(windows)]
 use bindings::Windows::Win32::{Automation::VARIANT, Com::CoCreateInstance};
 use core::default::Default;
-use error::{TaskError, WinError};
 use iter::once;
 use log::error;
+#[cfg(windows)]
 use windows::{Interface, IntoParam};
 // use principal::TaskLogon;
+use std::iter;
+#[cfg(windows)]
+use std::os::windows::ffi::OsStrExt;
 use std::{convert::TryFrom, error::Error, fmt, path::Path, ptr, unreachable};
 use std::{ffi::OsStr, path::PathBuf};
-use std::{iter, os::windows::ffi::OsStrExt};
 // use trigger_collection::{TaskTriggerType, TriggerCollection};
 
 // use task_scheduler::Task;
 
+mod boundary;
+mod calendar;
+#[cfg(windows)]
 mod com;
+mod config_lint;
+mod cron;
+mod diff;
+mod duration;
 mod error;
+mod evtx;
+mod export;
+mod history;
+#[cfg(windows)]
 mod idle_settings;
+mod job;
+mod lint;
+#[cfg(feature = "manifest")]
+mod manifest;
+mod occurrence;
+mod offline;
+mod powershell;
+#[cfg(windows)]
 mod principal;
+#[cfg(windows)]
+mod registered_task;
+#[cfg(windows)]
 mod registration_info;
+mod rrule;
+#[cfg(windows)]
+mod running_task;
+mod schema_version;
+mod schtasks;
+mod schtasks_query;
+mod security_lint;
+#[cfg(feature = "serde")]
+mod serde_format;
+mod task;
+#[cfg(windows)]
 mod task_definition;
+#[cfg(windows)]
 mod task_folder;
+mod task_result;
+#[cfg(windows)]
 mod task_service;
+#[cfg(windows)]
 mod task_settings;
+mod task_state;
+mod task_xml;
+#[cfg(windows)]
 mod trigger_collection;
+#[cfg(windows)]
+mod variant;
 
+pub use boundary::Boundary;
+pub use calendar::{DaysOfMonth, DaysOfWeek, Months, WeeksOfMonth};
 /// Re-exported from chrono for convenience
 pub use chrono::DateTime;
 /// Re-exported from chrono for convenience
 pub use chrono::Duration;
 /// Re-exported from chrono for convenience
 pub use chrono::Utc;
+/// Re-exported from chrono for convenience
+pub use chrono::Weekday;
 /// Small wrapper over some of the com base apis
-use com::Com;
+#[cfg(windows)]
+pub use com::{Apartment, AuthenticationLevel, Com, ComOptions, ImpersonationLevel};
+pub use cron::CronSchedule;
+pub use diff::{FieldChange, ItemChange, Plan, TaskDiff};
+pub use error::{
+    ComError, CronError, HistoryError, JobError, ManifestError, RRuleError, SchemaError,
+    SchtasksError, TaskError, TaskFileError, TaskServiceError, WinError, XmlError,
+};
+pub use export::{Export, ExportFile, ExportWarning};
+pub use history::{TaskHistory, TaskRun, TaskRunEvent, TaskRunEventKind};
+pub use lint::{Finding, LintRule, Linter, Severity};
+#[cfg(feature = "manifest")]
+pub use manifest::{Manifest, ManifestFolder, ManifestTask};
+pub use offline::{OfflineFailure, OfflineTask, OfflineTasks};
+/// Wrapper over IRegisteredTask class
+#[cfg(windows)]
+pub use registered_task::{RegisteredTask, RunFlags};
+/// Wrapper over IRunningTask class
+#[cfg(windows)]
+pub use running_task::RunningTask;
+pub use schema_version::SchemaVersion;
+pub use schtasks::SchtasksCommand;
+pub use schtasks_query::{SchtasksColumn, SchtasksQuery, SchtasksRecord};
+pub use task::{
+    IdleConditions, MultipleInstancesPolicy, Privilege, RestartOnFailure, RunLevel, Settings, Task,
+    TaskLogon, TaskPrincipal, TaskRegistration,
+};
+/// Wrapper over ITaskDefinition class
+#[cfg(windows)]
+pub use task_definition::TaskDefinition;
+#[cfg(windows)]
+pub use task_folder::{RegistrationMode, RegistrationOptions};
+pub use task_result::TaskResult;
 /// Wrapper over ITaskService class
-use task_service::TaskService;
+#[cfg(windows)]
+pub use task_service::{Credentials, TaskService};
+pub use task_state::TaskState;
 
+#[cfg(windows)]
 use crate::{
     idle_settings::IdleSettings,
-    principal::{Principal, TaskLogon},
+    principal::Principal,
     registration_info::RegistrationInfo,
-    task_definition::TaskDefinition,
     task_folder::TaskFolder,
     task_settings::TaskSettings,
     trigger_collection::{TaskTriggerType, TriggerCollection},
 };
 
+/// VARIANT_BOOL value for true
+pub(crate) const VARIANT_TRUE: i16 = -1;
+/// VARIANT_BOOL value for false
+pub(crate) const VARIANT_FALSE: i16 = 0;
+
 /// Turns a string into a windows string
+#[cfg(windows)]
 fn to_win_str(string: &str) -> Vec<u16> {
     OsStr::new(string).encode_wide().chain(once(0)).collect()
 }
 
-// pub struct Task {
-//     exe_path: PathBuf,
-//     task_name: String,
-//     triggers: TaskTriggers,
-// }
-
-// impl Task {
-
 /// Use this function to schedule a task
 /// For now the task will only be to start an executable
 /// If the start time is not after the time this function is called
 /// or the end time is before the start time then this function will fail
 /// The task name can be anything you want, but it cannot start with a "."
+///
+/// The options decide whether an existing task with the same name is updated
+/// in place, which keeps its run history, or whether registration fails instead
+///
+/// The COM library is initialized by the caller, so one [`Com`] can be
+/// kept for every task that is scheduled on this thread, and it has to stay
+/// alive while the returned task is used.
+/// Nothing is returned when the options only validate the task, and any
+/// failure along the way is returned instead of panicking
 
-pub fn execute(task_path: PathBuf, task_name: &str) {
-    let _com = Com::initialize().unwrap();
-
-    let task_service = TaskService::new();
+#[cfg(windows)]
+pub fn execute(
+    _com: &Com,
+    task_path: PathBuf,
+    task_name: &str,
+    options: RegistrationOptions,
+) -> Result<Option<RegisteredTask>, TaskError> {
+    let task_service = TaskService::new()?;
     // task_service.0.Connect(None, None, None, None).unwrap();
-    task_service.connect().unwrap();
+    task_service.connect()?;
 
     // task_service.0.GetFolder(to_win_str("\\").as_mut_ptr())
     // let mut task_folder: *mut ITaskFolder = std::ptr::null_mut();
-    let task_folder = TaskFolder::new(task_service.get_folder().unwrap());
-
-    // delete tasks if it exists
-    // let mut err = task_folder.DeleteTask(BSTR::try_from(task_name).unwrap(), 0);
-    // println!("{}", err.message());
-    task_folder.delete_task(task_name);
+    let task_folder = TaskFolder::new(task_service.get_folder()?);
 
-    let task = TaskDefinition::new(task_service.new_task().unwrap());
+    let task = TaskDefinition::new(task_service.new_task()?);
 
-    let registration_info = RegistrationInfo::new(task.get_registration_info().unwrap());
-    registration_info.put_author("Author").unwrap();
+    let registration_info = RegistrationInfo::new(task.get_registration_info()?);
+    registration_info.put_author("Author")?;
 
-    let principal = Principal::new(task.get_principal().unwrap());
+    let principal = Principal::new(task.get_principal()?);
 
-    principal
-        .put_logon_type(TaskLogon::InteractiveToken)
-        .unwrap();
+    principal.put_logon_type(TaskLogon::InteractiveToken)?;
 
-    let task_settings = TaskSettings::new(task.get_settings().unwrap());
+    let task_settings = TaskSettings::new(task.get_settings()?);
 
-    const VARIANT_TRUE: i16 = -1;
-    task_settings
-        .put_start_when_available(VARIANT_TRUE)
-        .unwrap();
+    task_settings.put_start_when_available(VARIANT_TRUE)?;
 
-    let idle_settings = IdleSettings::new(task_settings.get_idle_settings().unwrap());
+    let idle_settings = IdleSettings::new(task_settings.get_idle_settings()?);
 
-    idle_settings
-        .put_wait_timeout(Duration::seconds(5))
-        .unwrap();
+    idle_settings.put_wait_timeout(Duration::seconds(5))?;
 
-    let trigger_collection = TriggerCollection::new(task.get_triggers().unwrap());
+    let trigger_collection = TriggerCollection::new(task.get_triggers()?);
 
     // let trigger = trigger_collection.create(TaskTriggerType::SpecificTime())
-    let trigger = trigger_collection
-        .create(TASK_TRIGGER_TYPE2::TASK_TRIGGER_TIME)
-        .unwrap();
+    let trigger = trigger_collection.create(TASK_TRIGGER_TYPE2::TASK_TRIGGER_TIME)?;
 
-    let time_trigger = trigger.cast::<ITimeTrigger>().unwrap();
+    let time_trigger = trigger.cast::<ITimeTrigger>()?;
     // for trigger in time_triggers {
     //     let trigger_collection = TriggerCollection::new(&task_definition)?;
     //     let trigger = trigger_collection.create(TaskTriggerType::SpecificTime(trigger))?;
     // }
     unsafe {
-        time_trigger.put_Id(BSTR::from("Trigger1")).unwrap();
+        time_trigger.put_Id(BSTR::from("Trigger1")).ok()?;
         let start = Utc::now() + chrono::Duration::seconds(2);
         let end = Utc::now() + chrono::Duration::seconds(60);
         time_trigger
             .put_EndBoundary(BSTR::from(end.to_rfc3339()))
-            .unwrap();
+            .ok()?;
         time_trigger
             .put_StartBoundary(BSTR::from(start.to_rfc3339()))
-            .unwrap();
+            .ok()?;
 
         let mut action_collection = None;
-        task.0.get_Actions(&mut action_collection).unwrap();
+        task.0.get_Actions(&mut action_collection).ok()?;
         let action_collection = action_collection.unwrap();
 
         let mut action = None;
         action_collection
             .Create(TASK_ACTION_TYPE::TASK_ACTION_EXEC, &mut action)
-            .unwrap();
+            .ok()?;
         let action = action.unwrap();
 
-        let exec_action = action.cast::<IExecAction>().unwrap();
+        let exec_action = action.cast::<IExecAction>()?;
         exec_action
-            .put_Path(BSTR::from(task_path.to_str().unwrap()))
-            .unwrap();
+            .put_Path(BSTR::from(&*task_path.to_string_lossy()))
+            .ok()?;
 
-        task_folder.register_task(task_name, task.0).unwrap();
+        let registered = task_folder.register_task(task_name, task.0, options)?;
+        Ok(registered)
     }
 
     // path for notepad program
     // let mut exe_path = to_win_str(actions.0[0].to_str().unwrap());
 }
 
-#[derive(Debug)]
-pub struct Actions(Vec<PathBuf>);
+/// There can only be up to 32 actions per task
+const MAX_ACTIONS: usize = 32;
+
+/// The actions that are performed when a task runs
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(
+    feature = "serde",
+    serde(try_from = "Vec<Action>", into = "Vec<Action>")
+)]
+pub struct Actions(Vec<Action>);
 
 /// There can only be up to 32 actions per task
 impl Actions {
@@ -168,21 +259,197 @@ impl Actions {
             // ));
             panic!("This should absolutely be a file");
         }
-        Self(vec![path])
+        Self(vec![Action::Exec(ExecAction::new(path))])
+    }
+
+    /// Creates the actions starting with a single action.
+    /// Unlike [`Actions::new`] this doesn't check that an executable exists,
+    /// which is what you want when describing a task for another machine.
+    pub fn from_action(action: Action) -> Self {
+        Self(vec![action])
+    }
+
+    /// Adds another action, they are performed in the order they are added.
+    /// If you go over the limit of 32 actions then this will panic!
+    pub fn with_action(mut self, action: Action) -> Self {
+        if self.0.len() >= MAX_ACTIONS {
+            panic!("You can only have up to 32 actions on a task");
+        }
+        self.0.push(action);
+        self
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = &Action> {
+        self.0.iter()
+    }
+
+    pub fn len(&self) -> usize {
+        self.0.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
     }
 }
 
-#[derive(Debug)]
+/// A single action that is performed when a task runs
+///
+/// https://docs.microsoft.com/en-us/windows/win32/taskschd/taskschedulerschema-actions-tasktype-element
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(tag = "type", rename_all = "snake_case"))]
+pub enum Action {
+    /// Starts an executable
+    Exec(ExecAction),
+    /// Fires a COM handler
+    ComHandler(ComHandlerAction),
+}
+
+impl From<ExecAction> for Action {
+    fn from(action: ExecAction) -> Self {
+        Action::Exec(action)
+    }
+}
+
+impl From<ComHandlerAction> for Action {
+    fn from(action: ComHandlerAction) -> Self {
+        Action::ComHandler(action)
+    }
+}
+
+/// Starts an executable with optional arguments and working directory
+///
+/// https://docs.microsoft.com/en-us/windows/win32/taskschd/taskschedulerschema-exec-actiongroup-element
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct ExecAction {
+    id: Option<String>,
+    path: PathBuf,
+    arguments: Option<String>,
+    working_directory: Option<PathBuf>,
+}
+
+impl ExecAction {
+    /// The path can contain environment variables like `%windir%` which are
+    /// expanded when the task runs
+    pub fn new(path: PathBuf) -> Self {
+        Self {
+            id: None,
+            path,
+            arguments: None,
+            working_directory: None,
+        }
+    }
+
+    pub fn with_id(mut self, id: String) -> Self {
+        self.id = Some(id);
+        self
+    }
+
+    /// The arguments can contain `$(Arg0)` through `$(Arg32)` which are
+    /// replaced with the parameters the task is run with
+    pub fn with_arguments(mut self, arguments: String) -> Self {
+        self.arguments = Some(arguments);
+        self
+    }
+
+    pub fn with_working_directory(mut self, working_directory: PathBuf) -> Self {
+        self.working_directory = Some(working_directory);
+        self
+    }
+
+    pub fn id(&self) -> Option<&str> {
+        self.id.as_deref()
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    pub fn arguments(&self) -> Option<&str> {
+        self.arguments.as_deref()
+    }
+
+    pub fn working_directory(&self) -> Option<&Path> {
+        self.working_directory.as_deref()
+    }
+}
+
+/// Fires a COM handler, identified by its class id, with optional data
+///
+/// https://docs.microsoft.com/en-us/windows/win32/taskschd/taskschedulerschema-comhandler-actiongroup-element
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct ComHandlerAction {
+    id: Option<String>,
+    class_id: String,
+    data: Option<String>,
+}
+
+impl ComHandlerAction {
+    pub fn new(class_id: String) -> Self {
+        Self {
+            id: None,
+            class_id,
+            data: None,
+        }
+    }
+
+    pub fn with_id(mut self, id: String) -> Self {
+        self.id = Some(id);
+        self
+    }
+
+    pub fn with_data(mut self, data: String) -> Self {
+        self.data = Some(data);
+        self
+    }
+
+    pub fn id(&self) -> Option<&str> {
+        self.id.as_deref()
+    }
+
+    pub fn class_id(&self) -> &str {
+        &self.class_id
+    }
+
+    pub fn data(&self) -> Option<&str> {
+        self.data.as_deref()
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(
+    feature = "serde",
+    serde(try_from = "crate::serde_format::TriggerLists")
+)]
 pub struct TaskTriggers {
+    #[cfg_attr(feature = "serde", serde(skip_serializing_if = "Option::is_none"))]
     daily: Option<Vec<DailyTrigger>>,
+    #[cfg_attr(feature = "serde", serde(skip_serializing_if = "Option::is_none"))]
     event: Option<Vec<EventTrigger>>,
-    idle: Option<IdleTrigger>,
-    registration: Option<RegistrationTrigger>,
+    #[cfg_attr(feature = "serde", serde(skip_serializing_if = "Option::is_none"))]
+    idle: Option<Vec<IdleTrigger>>,
+    #[cfg_attr(feature = "serde", serde(skip_serializing_if = "Option::is_none"))]
+    registration: Option<Vec<RegistrationTrigger>>,
+    #[cfg_attr(
+        feature = "serde",
+        serde(rename = "specific_times", skip_serializing_if = "Option::is_none")
+    )]
     time: Option<Vec<SpecificTimeTrigger>>,
-    logon: Option<LogonTrigger>,
-    boot: Option<BootTrigger>,
+    #[cfg_attr(feature = "serde", serde(skip_serializing_if = "Option::is_none"))]
+    logon: Option<Vec<LogonTrigger>>,
+    #[cfg_attr(feature = "serde", serde(skip_serializing_if = "Option::is_none"))]
+    boot: Option<Vec<BootTrigger>>,
+    #[cfg_attr(feature = "serde", serde(skip_serializing_if = "Option::is_none"))]
     monthly: Option<Vec<MonthlyTrigger>>,
+    #[cfg_attr(feature = "serde", serde(skip_serializing_if = "Option::is_none"))]
+    monthly_dow: Option<Vec<MonthlyDowTrigger>>,
+    #[cfg_attr(feature = "serde", serde(skip_serializing_if = "Option::is_none"))]
     weekly: Option<Vec<WeeklyTrigger>>,
+    #[cfg_attr(feature = "serde", serde(skip_serializing_if = "Option::is_none"))]
+    session_state_change: Option<Vec<SessionStateChangeTrigger>>,
 }
 
 impl TaskTriggers {
@@ -190,6 +457,11 @@ impl TaskTriggers {
         if builder.number_of_triggers == 0 {
             panic!("There needs to be at least one trigger");
         }
+        Self::from_builder(builder)
+    }
+
+    /// Registered tasks don't need any triggers, they can still be run on demand
+    pub(crate) fn from_builder(builder: TaskTriggersBuilder) -> Self {
         Self {
             daily: builder.daily,
             event: builder.event,
@@ -199,9 +471,74 @@ impl TaskTriggers {
             logon: builder.logon,
             boot: builder.boot,
             monthly: builder.monthly,
+            monthly_dow: builder.monthly_dow,
             weekly: builder.weekly,
+            session_state_change: builder.session_state_change,
         }
     }
+
+    pub fn daily(&self) -> &[DailyTrigger] {
+        self.daily.as_deref().unwrap_or(&[])
+    }
+
+    pub fn event(&self) -> &[EventTrigger] {
+        self.event.as_deref().unwrap_or(&[])
+    }
+
+    pub fn idle(&self) -> &[IdleTrigger] {
+        self.idle.as_deref().unwrap_or(&[])
+    }
+
+    pub fn registration(&self) -> &[RegistrationTrigger] {
+        self.registration.as_deref().unwrap_or(&[])
+    }
+
+    pub fn specific_times(&self) -> &[SpecificTimeTrigger] {
+        self.time.as_deref().unwrap_or(&[])
+    }
+
+    pub fn logon(&self) -> &[LogonTrigger] {
+        self.logon.as_deref().unwrap_or(&[])
+    }
+
+    pub fn boot(&self) -> &[BootTrigger] {
+        self.boot.as_deref().unwrap_or(&[])
+    }
+
+    pub fn monthly(&self) -> &[MonthlyTrigger] {
+        self.monthly.as_deref().unwrap_or(&[])
+    }
+
+    pub fn monthly_dow(&self) -> &[MonthlyDowTrigger] {
+        self.monthly_dow.as_deref().unwrap_or(&[])
+    }
+
+    pub fn weekly(&self) -> &[WeeklyTrigger] {
+        self.weekly.as_deref().unwrap_or(&[])
+    }
+
+    pub fn session_state_change(&self) -> &[SessionStateChangeTrigger] {
+        self.session_state_change.as_deref().unwrap_or(&[])
+    }
+
+    /// The total number of triggers of every kind
+    pub fn len(&self) -> usize {
+        self.daily().len()
+            + self.event().len()
+            + self.idle().len()
+            + self.registration().len()
+            + self.specific_times().len()
+            + self.logon().len()
+            + self.boot().len()
+            + self.monthly().len()
+            + self.monthly_dow().len()
+            + self.weekly().len()
+            + self.session_state_change().len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
 }
 /// A task can only have up to 48 triggers
 /// If you go over that limit then this will panic!
@@ -210,17 +547,32 @@ pub struct TaskTriggersBuilder {
     number_of_triggers: u8,
     daily: Option<Vec<DailyTrigger>>,
     event: Option<Vec<EventTrigger>>,
-    idle: Option<IdleTrigger>,
-    registration: Option<RegistrationTrigger>,
+    idle: Option<Vec<IdleTrigger>>,
+    registration: Option<Vec<RegistrationTrigger>>,
     specific_times: Option<Vec<SpecificTimeTrigger>>,
-    logon: Option<LogonTrigger>,
-    boot: Option<BootTrigger>,
+    logon: Option<Vec<LogonTrigger>>,
+    boot: Option<Vec<BootTrigger>>,
     monthly: Option<Vec<MonthlyTrigger>>,
+    monthly_dow: Option<Vec<MonthlyDowTrigger>>,
     weekly: Option<Vec<WeeklyTrigger>>,
+    session_state_change: Option<Vec<SessionStateChangeTrigger>>,
 }
 
 const MAX_TRIGGERS: u8 = 48;
 
+/// Adds a trigger to the list of triggers of its kind
+fn push_trigger<T>(number_of_triggers: &mut u8, triggers: &mut Option<Vec<T>>, trigger: T) {
+    if *number_of_triggers >= MAX_TRIGGERS {
+        panic!("You can only have up to 48 triggers on a task");
+    }
+
+    match triggers {
+        Some(ref mut triggers) => triggers.push(trigger),
+        None => *triggers = Some(vec![trigger]),
+    }
+    *number_of_triggers += 1;
+}
+
 impl TaskTriggersBuilder {
     pub fn new() -> Self {
         Self {
@@ -233,41 +585,83 @@ impl TaskTriggersBuilder {
             logon: None,
             boot: None,
             monthly: None,
+            monthly_dow: None,
             weekly: None,
+            session_state_change: None,
         }
     }
 
     pub fn with_daily(mut self, daily: DailyTrigger) -> Self {
-        if self.number_of_triggers >= MAX_TRIGGERS {
-            panic!("You can only have up to 48 triggers on a task");
-        }
-
-        match self.daily {
-            Some(ref mut daily_triggers) => daily_triggers.push(daily),
-            None => {
-                let mut daily_triggers = Vec::new();
-                daily_triggers.push(daily);
-                self.daily = Some(daily_triggers);
-            }
-        }
-        self.number_of_triggers += 1;
+        push_trigger(&mut self.number_of_triggers, &mut self.daily, daily);
         self
     }
 
     pub fn with_specific_time(mut self, specific_time: SpecificTimeTrigger) -> Self {
-        if self.number_of_triggers >= MAX_TRIGGERS {
-            panic!("You can only have up to 48 triggers on a task");
-        }
+        push_trigger(
+            &mut self.number_of_triggers,
+            &mut self.specific_times,
+            specific_time,
+        );
+        self
+    }
 
-        match self.specific_times {
-            Some(ref mut specific_time_triggers) => specific_time_triggers.push(specific_time),
-            None => {
-                let mut specific_time_triggers = Vec::new();
-                specific_time_triggers.push(specific_time);
-                self.specific_times = Some(specific_time_triggers);
-            }
-        }
-        self.number_of_triggers += 1;
+    pub fn with_weekly(mut self, weekly: WeeklyTrigger) -> Self {
+        push_trigger(&mut self.number_of_triggers, &mut self.weekly, weekly);
+        self
+    }
+
+    pub fn with_monthly(mut self, monthly: MonthlyTrigger) -> Self {
+        push_trigger(&mut self.number_of_triggers, &mut self.monthly, monthly);
+        self
+    }
+
+    pub fn with_monthly_dow(mut self, monthly_dow: MonthlyDowTrigger) -> Self {
+        push_trigger(
+            &mut self.number_of_triggers,
+            &mut self.monthly_dow,
+            monthly_dow,
+        );
+        self
+    }
+
+    pub fn with_event(mut self, event: EventTrigger) -> Self {
+        push_trigger(&mut self.number_of_triggers, &mut self.event, event);
+        self
+    }
+
+    pub fn with_idle(mut self, idle: IdleTrigger) -> Self {
+        push_trigger(&mut self.number_of_triggers, &mut self.idle, idle);
+        self
+    }
+
+    pub fn with_registration(mut self, registration: RegistrationTrigger) -> Self {
+        push_trigger(
+            &mut self.number_of_triggers,
+            &mut self.registration,
+            registration,
+        );
+        self
+    }
+
+    pub fn with_logon(mut self, logon: LogonTrigger) -> Self {
+        push_trigger(&mut self.number_of_triggers, &mut self.logon, logon);
+        self
+    }
+
+    pub fn with_boot(mut self, boot: BootTrigger) -> Self {
+        push_trigger(&mut self.number_of_triggers, &mut self.boot, boot);
+        self
+    }
+
+    pub fn with_session_state_change(
+        mut self,
+        session_state_change: SessionStateChangeTrigger,
+    ) -> Self {
+        push_trigger(
+            &mut self.number_of_triggers,
+            &mut self.session_state_change,
+            session_state_change,
+        );
         self
     }
 
@@ -276,36 +670,235 @@ impl TaskTriggersBuilder {
     }
 }
 
-#[derive(Clone, Debug)]
+/// How often a task is restarted after a trigger fires, and for how long
+///
+/// https://docs.microsoft.com/en-us/windows/win32/taskschd/taskschedulerschema-repetition-triggerbasetype-element
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Repetition {
+    #[cfg_attr(feature = "serde", serde(with = "crate::serde_format::duration"))]
+    interval: Duration,
+    #[cfg_attr(
+        feature = "serde",
+        serde(default, with = "crate::serde_format::optional_duration")
+    )]
+    duration: Option<Duration>,
+    stop_at_duration_end: bool,
+}
+
+impl Repetition {
+    /// The interval has to be between 1 minute and 31 days.
+    /// Without a duration the task repeats indefinitely
+    pub fn new(interval: Duration) -> Self {
+        Self {
+            interval,
+            duration: None,
+            stop_at_duration_end: false,
+        }
+    }
+
+    /// How long the pattern is repeated for, this has to be longer than the interval
+    pub fn with_duration(mut self, duration: Duration) -> Self {
+        self.duration = Some(duration);
+        self
+    }
+
+    /// Running instances of the task are stopped at the end of the repetition duration
+    pub fn stop_at_duration_end(mut self) -> Self {
+        self.stop_at_duration_end = true;
+        self
+    }
+
+    pub fn interval(&self) -> Duration {
+        self.interval
+    }
+
+    pub fn duration(&self) -> Option<Duration> {
+        self.duration
+    }
+
+    pub fn stops_at_duration_end(&self) -> bool {
+        self.stop_at_duration_end
+    }
+}
+
+/// The parts every kind of trigger has. Every trigger has a `with_common` method that
+/// changes them, starting from the ones already set, e.g.
+/// `trigger.with_common(|common| common.with_execution_time_limit(Duration::hours(1)))`
+///
+/// https://docs.microsoft.com/en-us/windows/win32/taskschd/trigger
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(default))]
+pub struct TriggerCommon {
+    id: Option<String>,
+    enabled: bool,
+    start: Option<Boundary>,
+    end: Option<Boundary>,
+    #[cfg_attr(
+        feature = "serde",
+        serde(with = "crate::serde_format::optional_duration")
+    )]
+    execution_time_limit: Option<Duration>,
+    #[cfg_attr(
+        feature = "serde",
+        serde(with = "crate::serde_format::optional_duration")
+    )]
+    random_delay: Option<Duration>,
+}
+
+impl TriggerCommon {
+    /// An enabled trigger without an id, boundaries, time limit or random delay
+    pub fn new() -> Self {
+        Self {
+            id: None,
+            enabled: true,
+            start: None,
+            end: None,
+            execution_time_limit: None,
+            random_delay: None,
+        }
+    }
+
+    /// An empty id is the same as no id
+    pub fn with_id(mut self, id: String) -> Self {
+        self.id = Some(id).filter(|id| !id.is_empty());
+        self
+    }
+
+    /// A disabled trigger stays on the task but doesn't start it
+    pub fn with_enabled(mut self, enabled: bool) -> Self {
+        self.enabled = enabled;
+        self
+    }
+
+    /// The trigger is active from the start boundary, which calendar triggers
+    /// also take the time of day they run at from
+    pub fn with_start(mut self, start: impl Into<Boundary>) -> Self {
+        self.start = Some(start.into());
+        self
+    }
+
+    /// The trigger is active until the end boundary
+    pub fn with_end(mut self, end: impl Into<Boundary>) -> Self {
+        self.end = Some(end.into());
+        self
+    }
+
+    /// Stops a run the trigger started after this long, instead of
+    /// the execution time limit in the task [`Settings`]
+    pub fn with_execution_time_limit(mut self, execution_time_limit: Duration) -> Self {
+        self.execution_time_limit = Some(execution_time_limit);
+        self
+    }
+
+    /// Delays each start by a random time of up to the delay, so that tasks
+    /// with the same schedule on many computers don't all start at once.
+    ///
+    /// Task Scheduler only has random delays on time and calendar triggers,
+    /// it is left out of the other triggers with a warning when the task is written.
+    pub fn with_random_delay(mut self, random_delay: Duration) -> Self {
+        self.random_delay = Some(random_delay);
+        self
+    }
+
+    pub fn id(&self) -> Option<&str> {
+        self.id.as_deref()
+    }
+
+    pub fn enabled(&self) -> bool {
+        self.enabled
+    }
+
+    pub fn start(&self) -> Option<Boundary> {
+        self.start
+    }
+
+    pub fn end(&self) -> Option<Boundary> {
+        self.end
+    }
+
+    pub fn execution_time_limit(&self) -> Option<Duration> {
+        self.execution_time_limit
+    }
+
+    pub fn random_delay(&self) -> Option<Duration> {
+        self.random_delay
+    }
+}
+
+impl Default for TriggerCommon {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Adds `common` and `with_common` to the triggers, see [`TriggerCommon`]
+macro_rules! trigger_common {
+    ($($trigger:ty),*) => {
+        $(
+            impl $trigger {
+                pub fn common(&self) -> &TriggerCommon {
+                    &self.common
+                }
+
+                /// Changes the parts every trigger has, see [`TriggerCommon`]
+                pub fn with_common(
+                    mut self,
+                    common: impl FnOnce(TriggerCommon) -> TriggerCommon,
+                ) -> Self {
+                    self.common = common(self.common);
+                    self
+                }
+            }
+        )*
+    };
+}
+
+trigger_common!(
+    BootTrigger,
+    DailyTrigger,
+    EventTrigger,
+    IdleTrigger,
+    LogonTrigger,
+    MonthlyDowTrigger,
+    MonthlyTrigger,
+    RegistrationTrigger,
+    SessionStateChangeTrigger,
+    SpecificTimeTrigger,
+    WeeklyTrigger
+);
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct DailyTrigger {
     // validate that end time is after start time
-    start_time: Option<DateTime<Utc>>,
-    end_time: Option<DateTime<Utc>>,
+    #[cfg_attr(feature = "serde", serde(flatten))]
+    common: TriggerCommon,
     interval: Option<u16>,
-    id: String,
+    repetition: Option<Repetition>,
 }
 impl DailyTrigger {
-    /// The start time will be Utc::now() and the end time will be 100 years
-    /// afterwards. The default interval will be every day if not specified
+    /// Without a start time the trigger starts when it is written, on the clock of the
+    /// computer, and it has no end. The default interval will be every day if not specified
     pub fn new(id: String) -> Self {
         Self {
-            id,
-            start_time: None,
-            end_time: None,
+            common: TriggerCommon::new().with_id(id),
             interval: None,
+            repetition: None,
         }
     }
 
     /// The start time is also the time the task will be executed daily
-    /// If this isn't set then the start time will be set with Utc::now()
-    pub fn with_start_time(mut self, start: DateTime<Utc>) -> Self {
-        self.start_time = Some(start);
+    /// If this isn't set then the trigger starts at the current local time
+    pub fn with_start_time(mut self, start: impl Into<Boundary>) -> Self {
+        self.common.start = Some(start.into());
         self
     }
     /// End time specifies the date that the task will stop activating.
-    /// If this isn't set then the end date will be ~100 years after Utc::now()
-    pub fn with_end_time(mut self, end: DateTime<Utc>) -> Self {
-        self.end_time = Some(end);
+    /// If this isn't set then the trigger doesn't stop activating
+    pub fn with_end_time(mut self, end: impl Into<Boundary>) -> Self {
+        self.common.end = Some(end.into());
         self
     }
 
@@ -316,41 +909,640 @@ impl DailyTrigger {
         self.interval = Some(interval);
         self
     }
+
+    /// Repeats the task after it starts each day
+    pub fn with_repetition(mut self, repetition: Repetition) -> Self {
+        self.repetition = Some(repetition);
+        self
+    }
+
+    pub fn id(&self) -> &str {
+        self.common.id().unwrap_or("")
+    }
+
+    /// The start time as a point in time, in the time zone of this computer if it's local
+    pub fn start_time(&self) -> Option<DateTime<Utc>> {
+        self.common.start.map(|start| start.to_utc())
+    }
+
+    /// The end time as a point in time, in the time zone of this computer if it's local
+    pub fn end_time(&self) -> Option<DateTime<Utc>> {
+        self.common.end.map(|end| end.to_utc())
+    }
+
+    /// The start time as it is stored, local or a point in time
+    pub fn start_boundary(&self) -> Option<Boundary> {
+        self.common.start
+    }
+
+    pub fn end_boundary(&self) -> Option<Boundary> {
+        self.common.end
+    }
+
+    /// The number of days between runs, every day if this isn't set
+    pub fn interval(&self) -> Option<u16> {
+        self.interval
+    }
+
+    pub fn repetition(&self) -> Option<&Repetition> {
+        self.repetition.as_ref()
+    }
+
+    pub fn random_delay(&self) -> Option<Duration> {
+        self.common.random_delay
+    }
 }
-#[derive(Debug)]
-pub struct EventTrigger {}
-#[derive(Debug)]
-pub struct IdleTrigger {}
-#[derive(Debug)]
-pub struct RegistrationTrigger {}
-#[derive(Debug)]
+/// Triggers the task when an event that matches the subscription is logged
+///
+/// https://docs.microsoft.com/en-us/windows/win32/taskschd/eventtrigger
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct EventTrigger {
+    #[cfg_attr(feature = "serde", serde(flatten))]
+    common: TriggerCommon,
+    subscription: String,
+    #[cfg_attr(
+        feature = "serde",
+        serde(default, with = "crate::serde_format::optional_duration")
+    )]
+    delay: Option<Duration>,
+    #[cfg_attr(
+        feature = "serde",
+        serde(default, with = "crate::serde_format::value_queries")
+    )]
+    value_queries: Vec<(String, String)>,
+    repetition: Option<Repetition>,
+}
+impl EventTrigger {
+    /// The subscription is an XPath query over the event log, e.g.
+    /// `<QueryList><Query Id="0" Path="System"><Select Path="System">*[System[EventID=41]]</Select></Query></QueryList>`
+    pub fn new(subscription: String) -> Self {
+        Self {
+            common: TriggerCommon::new(),
+            subscription,
+            delay: None,
+            value_queries: Vec::new(),
+            repetition: None,
+        }
+    }
+
+    /// How long to wait after the event before starting the task
+    pub fn with_delay(mut self, delay: Duration) -> Self {
+        self.delay = Some(delay);
+        self
+    }
+
+    /// Names a value out of the event with an XPath query, so it can
+    /// be used as `$(name)` in the task actions
+    pub fn with_value_query(mut self, name: String, query: String) -> Self {
+        self.value_queries.push((name, query));
+        self
+    }
+
+    pub fn with_repetition(mut self, repetition: Repetition) -> Self {
+        self.repetition = Some(repetition);
+        self
+    }
+
+    pub fn subscription(&self) -> &str {
+        &self.subscription
+    }
+
+    pub fn delay(&self) -> Option<Duration> {
+        self.delay
+    }
+
+    pub fn value_queries(&self) -> &[(String, String)] {
+        &self.value_queries
+    }
+
+    pub fn repetition(&self) -> Option<&Repetition> {
+        self.repetition.as_ref()
+    }
+}
+/// Triggers the task when the computer goes into an idle state.
+/// The idle conditions are part of the task [`Settings`]
+///
+/// https://docs.microsoft.com/en-us/windows/win32/taskschd/idletrigger
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(default))]
+pub struct IdleTrigger {
+    #[cfg_attr(feature = "serde", serde(flatten))]
+    common: TriggerCommon,
+    repetition: Option<Repetition>,
+}
+impl IdleTrigger {
+    pub fn new() -> Self {
+        Self {
+            common: TriggerCommon::new(),
+            repetition: None,
+        }
+    }
+
+    pub fn with_repetition(mut self, repetition: Repetition) -> Self {
+        self.repetition = Some(repetition);
+        self
+    }
+
+    pub fn repetition(&self) -> Option<&Repetition> {
+        self.repetition.as_ref()
+    }
+}
+/// Triggers the task when the task is registered or updated
+///
+/// https://docs.microsoft.com/en-us/windows/win32/taskschd/registrationtrigger
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(default))]
+pub struct RegistrationTrigger {
+    #[cfg_attr(feature = "serde", serde(flatten))]
+    common: TriggerCommon,
+    #[cfg_attr(
+        feature = "serde",
+        serde(with = "crate::serde_format::optional_duration")
+    )]
+    delay: Option<Duration>,
+}
+impl RegistrationTrigger {
+    pub fn new() -> Self {
+        Self {
+            common: TriggerCommon::new(),
+            delay: None,
+        }
+    }
+
+    /// How long to wait after registration before starting the task
+    pub fn with_delay(mut self, delay: Duration) -> Self {
+        self.delay = Some(delay);
+        self
+    }
+
+    pub fn delay(&self) -> Option<Duration> {
+        self.delay
+    }
+}
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct SpecificTimeTrigger {
-    id: String,
-    time: DateTime<Utc>,
-    deactivate_date: Option<DateTime<Utc>>,
+    /// The start boundary is the time
+    #[cfg_attr(feature = "serde", serde(flatten))]
+    common: TriggerCommon,
+    repetition: Option<Repetition>,
 }
 impl SpecificTimeTrigger {
-    pub fn new(id: String, time: DateTime<Utc>) -> Self {
+    pub fn new(id: String, time: impl Into<Boundary>) -> Self {
+        Self {
+            common: TriggerCommon::new().with_id(id).with_start(time),
+            repetition: None,
+        }
+    }
+
+    pub fn deactivate_date(mut self, time: impl Into<Boundary>) -> Self {
+        self.common.end = Some(time.into());
+        self
+    }
+
+    /// Repeats the task after the time it first runs
+    pub fn with_repetition(mut self, repetition: Repetition) -> Self {
+        self.repetition = Some(repetition);
+        self
+    }
+
+    pub fn id(&self) -> &str {
+        self.common.id().unwrap_or("")
+    }
+
+    /// The time as a point in time, in the time zone of this computer if it's local
+    pub fn time(&self) -> DateTime<Utc> {
+        self.boundary().to_utc()
+    }
+
+    pub fn deactivation_date(&self) -> Option<DateTime<Utc>> {
+        self.common.end.map(|date| date.to_utc())
+    }
+
+    /// The time as it is stored, local or a point in time
+    pub fn boundary(&self) -> Boundary {
+        self.common.start.unwrap_or_else(Boundary::now)
+    }
+
+    pub fn deactivation_boundary(&self) -> Option<Boundary> {
+        self.common.end
+    }
+
+    pub fn repetition(&self) -> Option<&Repetition> {
+        self.repetition.as_ref()
+    }
+
+    pub fn random_delay(&self) -> Option<Duration> {
+        self.common.random_delay
+    }
+}
+/// Triggers the task when a user logs on
+///
+/// https://docs.microsoft.com/en-us/windows/win32/taskschd/logontrigger
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(default))]
+pub struct LogonTrigger {
+    #[cfg_attr(feature = "serde", serde(flatten))]
+    common: TriggerCommon,
+    user_id: Option<String>,
+    #[cfg_attr(
+        feature = "serde",
+        serde(with = "crate::serde_format::optional_duration")
+    )]
+    delay: Option<Duration>,
+}
+impl LogonTrigger {
+    /// Triggers when any user logs on
+    pub fn new() -> Self {
+        Self {
+            common: TriggerCommon::new(),
+            user_id: None,
+            delay: None,
+        }
+    }
+
+    /// Only triggers when this user logs on, e.g. `DOMAIN\user`
+    pub fn with_user_id(mut self, user_id: String) -> Self {
+        self.user_id = Some(user_id);
+        self
+    }
+
+    /// How long to wait after the user logs on before starting the task
+    pub fn with_delay(mut self, delay: Duration) -> Self {
+        self.delay = Some(delay);
+        self
+    }
+
+    pub fn user_id(&self) -> Option<&str> {
+        self.user_id.as_deref()
+    }
+
+    pub fn delay(&self) -> Option<Duration> {
+        self.delay
+    }
+}
+/// Triggers the task when the computer boots
+///
+/// https://docs.microsoft.com/en-us/windows/win32/taskschd/boottrigger
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(default))]
+pub struct BootTrigger {
+    #[cfg_attr(feature = "serde", serde(flatten))]
+    common: TriggerCommon,
+    #[cfg_attr(
+        feature = "serde",
+        serde(with = "crate::serde_format::optional_duration")
+    )]
+    delay: Option<Duration>,
+}
+impl BootTrigger {
+    pub fn new() -> Self {
         Self {
-            id,
-            time,
-            deactivate_date: None,
+            common: TriggerCommon::new(),
+            delay: None,
         }
     }
 
-    pub fn deactivate_date(mut self, time: DateTime<Utc>) -> Self {
-        self.deactivate_date = Some(time);
+    /// How long to wait after the computer boots before starting the task
+    pub fn with_delay(mut self, delay: Duration) -> Self {
+        self.delay = Some(delay);
         self
     }
+
+    pub fn delay(&self) -> Option<Duration> {
+        self.delay
+    }
+}
+/// Triggers the task on specific days of specific months
+///
+/// https://docs.microsoft.com/en-us/windows/win32/taskschd/monthlytrigger
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct MonthlyTrigger {
+    #[cfg_attr(feature = "serde", serde(flatten))]
+    common: TriggerCommon,
+    days_of_month: DaysOfMonth,
+    months: Months,
+    run_on_last_day_of_month: bool,
+    repetition: Option<Repetition>,
+}
+impl MonthlyTrigger {
+    pub fn new(days_of_month: DaysOfMonth, months: Months) -> Self {
+        Self {
+            common: TriggerCommon::new(),
+            days_of_month,
+            months,
+            run_on_last_day_of_month: false,
+            repetition: None,
+        }
+    }
+
+    /// The start time is also the time of day the task will be executed
+    pub fn with_start_time(mut self, start: impl Into<Boundary>) -> Self {
+        self.common.start = Some(start.into());
+        self
+    }
+
+    pub fn with_end_time(mut self, end: impl Into<Boundary>) -> Self {
+        self.common.end = Some(end.into());
+        self
+    }
+
+    /// Also runs on the last day of each month, whichever day that is
+    pub fn on_last_day_of_month(mut self) -> Self {
+        self.run_on_last_day_of_month = true;
+        self
+    }
+
+    pub fn with_repetition(mut self, repetition: Repetition) -> Self {
+        self.repetition = Some(repetition);
+        self
+    }
+
+    /// The start time as a point in time, in the time zone of this computer if it's local
+    pub fn start_time(&self) -> Option<DateTime<Utc>> {
+        self.common.start.map(|start| start.to_utc())
+    }
+
+    /// The end time as a point in time, in the time zone of this computer if it's local
+    pub fn end_time(&self) -> Option<DateTime<Utc>> {
+        self.common.end.map(|end| end.to_utc())
+    }
+
+    /// The start time as it is stored, local or a point in time
+    pub fn start_boundary(&self) -> Option<Boundary> {
+        self.common.start
+    }
+
+    pub fn end_boundary(&self) -> Option<Boundary> {
+        self.common.end
+    }
+
+    pub fn days_of_month(&self) -> DaysOfMonth {
+        self.days_of_month
+    }
+
+    pub fn months(&self) -> Months {
+        self.months
+    }
+
+    pub fn runs_on_last_day_of_month(&self) -> bool {
+        self.run_on_last_day_of_month
+    }
+
+    pub fn repetition(&self) -> Option<&Repetition> {
+        self.repetition.as_ref()
+    }
+
+    pub fn random_delay(&self) -> Option<Duration> {
+        self.common.random_delay
+    }
+}
+/// Triggers the task on specific days of the week in specific weeks of specific months
+///
+/// https://docs.microsoft.com/en-us/windows/win32/taskschd/monthlydowtrigger
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct MonthlyDowTrigger {
+    #[cfg_attr(feature = "serde", serde(flatten))]
+    common: TriggerCommon,
+    days_of_week: DaysOfWeek,
+    weeks_of_month: WeeksOfMonth,
+    months: Months,
+    run_on_last_week_of_month: bool,
+    repetition: Option<Repetition>,
+}
+impl MonthlyDowTrigger {
+    pub fn new(days_of_week: DaysOfWeek, weeks_of_month: WeeksOfMonth, months: Months) -> Self {
+        Self {
+            common: TriggerCommon::new(),
+            days_of_week,
+            weeks_of_month,
+            months,
+            run_on_last_week_of_month: false,
+            repetition: None,
+        }
+    }
+
+    /// The start time is also the time of day the task will be executed
+    pub fn with_start_time(mut self, start: impl Into<Boundary>) -> Self {
+        self.common.start = Some(start.into());
+        self
+    }
+
+    pub fn with_end_time(mut self, end: impl Into<Boundary>) -> Self {
+        self.common.end = Some(end.into());
+        self
+    }
+
+    /// Also runs in the last week of each month
+    pub fn on_last_week_of_month(mut self) -> Self {
+        self.run_on_last_week_of_month = true;
+        self
+    }
+
+    pub fn with_repetition(mut self, repetition: Repetition) -> Self {
+        self.repetition = Some(repetition);
+        self
+    }
+
+    /// The start time as a point in time, in the time zone of this computer if it's local
+    pub fn start_time(&self) -> Option<DateTime<Utc>> {
+        self.common.start.map(|start| start.to_utc())
+    }
+
+    /// The end time as a point in time, in the time zone of this computer if it's local
+    pub fn end_time(&self) -> Option<DateTime<Utc>> {
+        self.common.end.map(|end| end.to_utc())
+    }
+
+    /// The start time as it is stored, local or a point in time
+    pub fn start_boundary(&self) -> Option<Boundary> {
+        self.common.start
+    }
+
+    pub fn end_boundary(&self) -> Option<Boundary> {
+        self.common.end
+    }
+
+    pub fn days_of_week(&self) -> DaysOfWeek {
+        self.days_of_week
+    }
+
+    pub fn weeks_of_month(&self) -> WeeksOfMonth {
+        self.weeks_of_month
+    }
+
+    pub fn months(&self) -> Months {
+        self.months
+    }
+
+    pub fn runs_on_last_week_of_month(&self) -> bool {
+        self.run_on_last_week_of_month
+    }
+
+    pub fn repetition(&self) -> Option<&Repetition> {
+        self.repetition.as_ref()
+    }
+
+    pub fn random_delay(&self) -> Option<Duration> {
+        self.common.random_delay
+    }
+}
+/// Triggers the task on specific days of the week every week or every few weeks
+///
+/// https://docs.microsoft.com/en-us/windows/win32/taskschd/weeklytrigger
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct WeeklyTrigger {
+    #[cfg_attr(feature = "serde", serde(flatten))]
+    common: TriggerCommon,
+    days_of_week: DaysOfWeek,
+    weeks_interval: Option<u16>,
+    repetition: Option<Repetition>,
+}
+impl WeeklyTrigger {
+    pub fn new(days_of_week: DaysOfWeek) -> Self {
+        Self {
+            common: TriggerCommon::new(),
+            days_of_week,
+            weeks_interval: None,
+            repetition: None,
+        }
+    }
+
+    /// The start time is also the time of day the task will be executed
+    pub fn with_start_time(mut self, start: impl Into<Boundary>) -> Self {
+        self.common.start = Some(start.into());
+        self
+    }
+
+    pub fn with_end_time(mut self, end: impl Into<Boundary>) -> Self {
+        self.common.end = Some(end.into());
+        self
+    }
+
+    /// The number of weeks between runs, every week if this isn't set
+    pub fn with_weeks_interval(mut self, weeks_interval: u16) -> Self {
+        self.weeks_interval = Some(weeks_interval);
+        self
+    }
+
+    pub fn with_repetition(mut self, repetition: Repetition) -> Self {
+        self.repetition = Some(repetition);
+        self
+    }
+
+    /// The start time as a point in time, in the time zone of this computer if it's local
+    pub fn start_time(&self) -> Option<DateTime<Utc>> {
+        self.common.start.map(|start| start.to_utc())
+    }
+
+    /// The end time as a point in time, in the time zone of this computer if it's local
+    pub fn end_time(&self) -> Option<DateTime<Utc>> {
+        self.common.end.map(|end| end.to_utc())
+    }
+
+    /// The start time as it is stored, local or a point in time
+    pub fn start_boundary(&self) -> Option<Boundary> {
+        self.common.start
+    }
+
+    pub fn end_boundary(&self) -> Option<Boundary> {
+        self.common.end
+    }
+
+    pub fn days_of_week(&self) -> DaysOfWeek {
+        self.days_of_week
+    }
+
+    pub fn weeks_interval(&self) -> Option<u16> {
+        self.weeks_interval
+    }
+
+    pub fn repetition(&self) -> Option<&Repetition> {
+        self.repetition.as_ref()
+    }
+
+    pub fn random_delay(&self) -> Option<Duration> {
+        self.common.random_delay
+    }
+}
+/// Triggers the task when a user session connects, disconnects, locks or unlocks
+///
+/// https://docs.microsoft.com/en-us/windows/win32/taskschd/sessionstatechangetrigger
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct SessionStateChangeTrigger {
+    #[cfg_attr(feature = "serde", serde(flatten))]
+    common: TriggerCommon,
+    state_change: SessionStateChange,
+    user_id: Option<String>,
+    #[cfg_attr(
+        feature = "serde",
+        serde(default, with = "crate::serde_format::optional_duration")
+    )]
+    delay: Option<Duration>,
+}
+impl SessionStateChangeTrigger {
+    pub fn new(state_change: SessionStateChange) -> Self {
+        Self {
+            common: TriggerCommon::new(),
+            state_change,
+            user_id: None,
+            delay: None,
+        }
+    }
+
+    /// Only triggers for the session of this user
+    pub fn with_user_id(mut self, user_id: String) -> Self {
+        self.user_id = Some(user_id);
+        self
+    }
+
+    pub fn with_delay(mut self, delay: Duration) -> Self {
+        self.delay = Some(delay);
+        self
+    }
+
+    pub fn state_change(&self) -> SessionStateChange {
+        self.state_change
+    }
+
+    pub fn user_id(&self) -> Option<&str> {
+        self.user_id.as_deref()
+    }
+
+    pub fn delay(&self) -> Option<Duration> {
+        self.delay
+    }
+}
+
+/// The kind of session state change that triggers a task
+///
+/// https://docs.microsoft.com/en-us/windows/win32/api/taskschd/ne-taskschd-task_session_state_change_type
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "snake_case"))]
+pub enum SessionStateChange {
+    /// A user session connects to the local computer.
+    ConsoleConnect,
+    /// A user session disconnects from the local computer.
+    ConsoleDisconnect,
+    /// A user session connects to a remote computer.
+    RemoteConnect,
+    /// A user session disconnects from a remote computer.
+    RemoteDisconnect,
+    /// The workstation is locked.
+    SessionLock,
+    /// The workstation is unlocked.
+    SessionUnlock,
 }
-#[derive(Debug)]
-pub struct LogonTrigger {}
-#[derive(Debug)]
-pub struct BootTrigger {}
-#[derive(Debug)]
-pub struct MonthlyTrigger {}
-#[derive(Debug)]
-pub struct WeeklyTrigger {}
 
 // #[cfg(test)]
 // mod tests {
@@ -0,0 +1,156 @@
+use std::fmt;
+
+use crate::{config_lint, security_lint, task::Task};
+
+/// How much a finding matters
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "lowercase"))]
+pub enum Severity {
+    Low,
+    Medium,
+    High,
+}
+
+impl fmt::Display for Severity {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            Severity::Low => "low",
+            Severity::Medium => "medium",
+            Severity::High => "high",
+        };
+        write!(f, "{}", name)
+    }
+}
+
+/// Something a lint rule found in a task definition
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct Finding {
+    id: String,
+    severity: Severity,
+    explanation: String,
+}
+
+impl Finding {
+    /// The id is the id of the rule that found it
+    pub fn new(id: &str, severity: Severity, explanation: String) -> Self {
+        Self {
+            id: id.to_string(),
+            severity,
+            explanation,
+        }
+    }
+
+    pub fn id(&self) -> &str {
+        &self.id
+    }
+
+    pub fn severity(&self) -> Severity {
+        self.severity
+    }
+
+    /// What was found in the task and why it matters
+    pub fn explanation(&self) -> &str {
+        &self.explanation
+    }
+}
+
+impl fmt::Display for Finding {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} {}: {}", self.severity, self.id, self.explanation)
+    }
+}
+
+/// A check over a task definition. Implement this to add your own rules to a [`Linter`].
+pub trait LintRule {
+    /// A short id that stays the same between versions, like `SEC001`
+    fn id(&self) -> &str;
+
+    /// The findings in the task, none when the task passes the rule
+    fn check(&self, task: &Task) -> Vec<Finding>;
+}
+
+/// Runs a set of lint rules over task definitions
+pub struct Linter {
+    rules: Vec<Box<dyn LintRule>>,
+}
+
+impl Linter {
+    /// A linter without any rules
+    pub fn new() -> Self {
+        Self { rules: Vec::new() }
+    }
+
+    /// The rules for definitions that look like persistence or abuse, which are
+    ///
+    /// - `SEC001` programs in user writable or temporary directories
+    /// - `SEC002` encoded PowerShell command lines
+    /// - `SEC003` hidden tasks that run as SYSTEM
+    /// - `SEC004` tasks without an author or description
+    /// - `SEC005` logon triggers for every user that run with the highest privileges
+    /// - `SEC006` programs that are commonly used to run code while looking legitimate
+    pub fn security() -> Self {
+        security_lint::rules()
+            .into_iter()
+            .fold(Self::new(), |linter, rule| linter.with_boxed_rule(rule))
+    }
+
+    /// The rules for definitions that won't run the way they were meant to, which are
+    ///
+    /// - `CFG001` triggers whose end boundary has passed
+    /// - `CFG002` execution time limits that aren't shorter than the repetition interval
+    /// - `CFG003` running only when idle with the default idle settings
+    /// - `CFG004` triggers that run once a day at most without start when available
+    /// - `CFG005` interactive logons on tasks with boot or time triggers
+    /// - `CFG006` random delays longer than the time between runs
+    /// - `CFG007` start times that daylight saving time skips or repeats in local time
+    pub fn configuration() -> Self {
+        config_lint::rules()
+            .into_iter()
+            .fold(Self::new(), |linter, rule| linter.with_boxed_rule(rule))
+    }
+
+    pub fn with_rule(self, rule: impl LintRule + 'static) -> Self {
+        self.with_boxed_rule(Box::new(rule))
+    }
+
+    /// Removes the rule with the id, for rules that don't apply to your tasks
+    pub fn without_rule(mut self, id: &str) -> Self {
+        self.rules.retain(|rule| rule.id() != id);
+        self
+    }
+
+    /// The ids of the rules, in the order they run
+    pub fn rule_ids(&self) -> impl Iterator<Item = &str> {
+        self.rules.iter().map(|rule| rule.id())
+    }
+
+    /// Runs every rule over the task, the findings are sorted with the most severe first
+    pub fn lint(&self, task: &Task) -> Vec<Finding> {
+        let mut findings: Vec<Finding> = self
+            .rules
+            .iter()
+            .flat_map(|rule| rule.check(task))
+            .collect();
+        findings.sort_by(|a, b| b.severity.cmp(&a.severity));
+        findings
+    }
+
+    fn with_boxed_rule(mut self, rule: Box<dyn LintRule>) -> Self {
+        self.rules.push(rule);
+        self
+    }
+}
+
+impl Default for Linter {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl fmt::Debug for Linter {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_list().entries(self.rule_ids()).finish()
+    }
+}
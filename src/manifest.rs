@@ -0,0 +1,1366 @@
+use std::{collections::HashMap, path::PathBuf};
+
+use chrono::Duration;
+use serde::Deserialize;
+
+use crate::{
+    boundary::Boundary,
+    calendar::{month_name, weekday_name, WEEKDAYS},
+    duration::parse_xml_duration,
+    error::ManifestError,
+    task::{
+        IdleConditions, MultipleInstancesPolicy, Privilege, RestartOnFailure, RunLevel, Settings,
+        Task, TaskLogon, TaskPrincipal, TaskRegistration,
+    },
+    Action, Actions, BootTrigger, ComHandlerAction, DailyTrigger, DaysOfMonth, DaysOfWeek,
+    EventTrigger, ExecAction, IdleTrigger, LogonTrigger, MonthlyDowTrigger, MonthlyTrigger, Months,
+    RegistrationTrigger, Repetition, SessionStateChange, SessionStateChangeTrigger,
+    SpecificTimeTrigger, TaskTriggers, TaskTriggersBuilder, TriggerCommon, WeeklyTrigger,
+    WeeksOfMonth, MAX_ACTIONS, MAX_TRIGGERS,
+};
+
+/// Folders of tasks described in TOML, so scheduled tasks can be kept in
+/// source control and applied to machines.
+///
+/// Dates are RFC 3339 strings, or local time without an offset, and durations
+/// are xsd:duration strings such as `PT5M`. Every trigger takes `id`, `enabled`,
+/// `start`, `end` and `execution_time_limit`, and time and calendar triggers
+/// also take `random_delay`. Days, months and privileges are named like in the
+/// task scheduler schema, e.g. `Monday` or `SeBackupPrivilege`, or in lowercase.
+///
+/// ```toml
+/// [[folder]]
+/// path = "\\Backups"
+///
+/// [[folder.task]]
+/// name = "Nightly"
+/// description = "Copies the documents to the backup drive"
+///
+/// [folder.task.principal]
+/// user_id = "SYSTEM"
+/// logon_type = "service_account"
+/// required_privileges = ["SeBackupPrivilege"]
+///
+/// [folder.task.settings]
+/// execution_time_limit = "PT2H"
+/// start_when_available = true
+///
+/// [[folder.task.trigger]]
+/// type = "daily"
+/// start = "2021-01-01T02:00:00"
+///
+/// [[folder.task.action]]
+/// type = "exec"
+/// path = "C:\\Tools\\backup.exe"
+/// arguments = "--all"
+/// ```
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Manifest {
+    folders: Vec<ManifestFolder>,
+}
+
+impl Manifest {
+    /// Reads and checks a manifest, every task is turned into a [`Task`]
+    pub fn from_toml(toml: &str) -> Result<Manifest, ManifestError> {
+        let raw: RawManifest =
+            toml::from_str(toml).map_err(|error| ManifestError::Parse(error.to_string()))?;
+
+        let mut folders: Vec<ManifestFolder> = Vec::new();
+        let mut seen = HashMap::new();
+        for raw_folder in raw.folders {
+            let path = normalize_folder(&raw_folder.path);
+            let mut tasks = Vec::new();
+            for raw_task in raw_folder.tasks {
+                let task_path = join_path(&path, &raw_task.name);
+                // a name with a \ would put the task in another folder
+                if raw_task.name.is_empty() || raw_task.name.contains(INVALID_NAME_CHARACTERS) {
+                    return Err(ManifestError::InvalidValue {
+                        task: task_path,
+                        field: "name".to_string(),
+                        value: raw_task.name,
+                    });
+                }
+                if seen.insert(task_path.to_lowercase(), ()).is_some() {
+                    return Err(ManifestError::DuplicateTask(task_path));
+                }
+                let name = raw_task.name.clone();
+                let task = raw_task.into_task(&task_path)?;
+                tasks.push(ManifestTask { name, task });
+            }
+            // the same folder can be listed more than once, and paths ignore case
+            match folders
+                .iter_mut()
+                .find(|folder| folder.path.eq_ignore_ascii_case(&path))
+            {
+                Some(folder) => folder.tasks.extend(tasks),
+                None => folders.push(ManifestFolder { path, tasks }),
+            }
+        }
+        Ok(Manifest { folders })
+    }
+
+    pub fn folders(&self) -> &[ManifestFolder] {
+        &self.folders
+    }
+
+    /// A JSON Schema of the manifest format, made from the same types that read manifests.
+    /// Editors can check a manifest against it once the TOML is turned into JSON,
+    /// values such as durations and dates are matched by patterns.
+    #[cfg(feature = "schemars")]
+    pub fn json_schema() -> serde_json::Value {
+        schemars::schema_for!(RawManifest).to_value()
+    }
+}
+
+/// A folder of tasks in a manifest
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ManifestFolder {
+    path: String,
+    tasks: Vec<ManifestTask>,
+}
+
+impl ManifestFolder {
+    /// The path of the folder as written in the manifest, always starting with `\`
+    pub fn path(&self) -> &str {
+        &self.path
+    }
+
+    /// The path of the folder when the manifest is applied under the root folder
+    pub fn path_under(&self, root: &str) -> String {
+        let root = normalize_folder(root);
+        if self.path == "\\" {
+            root
+        } else if root == "\\" {
+            self.path.clone()
+        } else {
+            format!("{}{}", root, self.path)
+        }
+    }
+
+    pub fn tasks(&self) -> &[ManifestTask] {
+        &self.tasks
+    }
+
+    /// The path of a task in this folder when the manifest is applied under the root folder
+    pub fn task_path(&self, root: &str, task: &ManifestTask) -> String {
+        join_path(&self.path_under(root), &task.name)
+    }
+}
+
+/// A named task in a manifest folder
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ManifestTask {
+    name: String,
+    task: Task,
+}
+
+impl ManifestTask {
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    pub fn task(&self) -> &Task {
+        &self.task
+    }
+}
+
+/// Joins a folder and the name of a task or folder inside it
+fn join_path(folder: &str, name: &str) -> String {
+    let folder = normalize_folder(folder);
+    if folder == "\\" {
+        format!("\\{}", name)
+    } else {
+        format!("{}\\{}", folder, name)
+    }
+}
+
+/// The item with the name, written in the case the schema uses or in lowercase
+fn from_name<T: Copy>(
+    items: impl IntoIterator<Item = T>,
+    name: impl Fn(T) -> &'static str,
+    value: &str,
+) -> Option<T> {
+    items.into_iter().find(|item| {
+        let name = name(*item);
+        name == value || name.to_lowercase() == value
+    })
+}
+
+/// The characters a task name can't have, the same as the `TaskName` pattern of the schema
+const INVALID_NAME_CHARACTERS: &[char] = &['\\', '/', ':', '*', '?', '"', '<', '>', '|'];
+
+/// Makes a folder path start with a single `\` and not end with one
+fn normalize_folder(path: &str) -> String {
+    let trimmed = path.trim().trim_matches(|c| c == '\\' || c == '/');
+    format!("\\{}", trimmed.replace('/', "\\"))
+}
+
+#[derive(Deserialize)]
+#[cfg_attr(
+    feature = "schemars",
+    derive(schemars::JsonSchema),
+    schemars(rename = "Manifest")
+)]
+#[serde(deny_unknown_fields)]
+struct RawManifest {
+    #[serde(default, rename = "folder")]
+    folders: Vec<RawFolder>,
+}
+
+#[derive(Deserialize)]
+#[cfg_attr(
+    feature = "schemars",
+    derive(schemars::JsonSchema),
+    schemars(rename = "Folder")
+)]
+#[serde(deny_unknown_fields)]
+struct RawFolder {
+    #[cfg_attr(feature = "schemars", schemars(with = "schema::FolderPath"))]
+    path: String,
+    #[serde(default, rename = "task")]
+    tasks: Vec<RawTask>,
+}
+
+#[derive(Deserialize)]
+#[cfg_attr(
+    feature = "schemars",
+    derive(schemars::JsonSchema),
+    schemars(rename = "Task")
+)]
+#[serde(deny_unknown_fields)]
+struct RawTask {
+    #[cfg_attr(feature = "schemars", schemars(with = "schema::TaskName"))]
+    name: String,
+    author: Option<String>,
+    description: Option<String>,
+    documentation: Option<String>,
+    source: Option<String>,
+    version: Option<String>,
+    principal: Option<RawPrincipal>,
+    settings: Option<RawSettings>,
+    #[serde(default, rename = "trigger")]
+    triggers: Vec<RawTrigger>,
+    #[serde(default, rename = "action")]
+    actions: Vec<RawAction>,
+}
+
+#[derive(Deserialize)]
+#[cfg_attr(
+    feature = "schemars",
+    derive(schemars::JsonSchema),
+    schemars(rename = "Principal")
+)]
+#[serde(deny_unknown_fields)]
+struct RawPrincipal {
+    user_id: Option<String>,
+    group_id: Option<String>,
+    logon_type: Option<RawLogonType>,
+    run_level: Option<RawRunLevel>,
+    #[serde(default)]
+    #[cfg_attr(feature = "schemars", schemars(with = "Vec<schema::Privilege>"))]
+    required_privileges: Vec<String>,
+}
+
+#[derive(Deserialize)]
+#[cfg_attr(
+    feature = "schemars",
+    derive(schemars::JsonSchema),
+    schemars(rename = "LogonType")
+)]
+#[serde(rename_all = "snake_case")]
+enum RawLogonType {
+    None,
+    Password,
+    S4u,
+    InteractiveToken,
+    Group,
+    ServiceAccount,
+    InteractiveTokenOrPassword,
+}
+
+#[derive(Deserialize)]
+#[cfg_attr(
+    feature = "schemars",
+    derive(schemars::JsonSchema),
+    schemars(rename = "RunLevel")
+)]
+#[serde(rename_all = "snake_case")]
+enum RawRunLevel {
+    LeastPrivilege,
+    HighestAvailable,
+}
+
+#[derive(Deserialize)]
+#[cfg_attr(
+    feature = "schemars",
+    derive(schemars::JsonSchema),
+    schemars(rename = "MultipleInstances")
+)]
+#[serde(rename_all = "snake_case")]
+enum RawMultipleInstances {
+    Parallel,
+    Queue,
+    IgnoreNew,
+    StopExisting,
+}
+
+#[derive(Deserialize)]
+#[cfg_attr(
+    feature = "schemars",
+    derive(schemars::JsonSchema),
+    schemars(rename = "Settings")
+)]
+#[serde(deny_unknown_fields)]
+struct RawSettings {
+    allow_demand_start: Option<bool>,
+    allow_hard_terminate: Option<bool>,
+    disallow_start_if_on_batteries: Option<bool>,
+    stop_if_going_on_batteries: Option<bool>,
+    enabled: Option<bool>,
+    hidden: Option<bool>,
+    start_when_available: Option<bool>,
+    run_only_if_idle: Option<bool>,
+    run_only_if_network_available: Option<bool>,
+    wake_to_run: Option<bool>,
+    use_unified_scheduling_engine: Option<bool>,
+    disallow_start_on_remote_app_session: Option<bool>,
+    volatile: Option<bool>,
+    /// `PT0S` lets the task run indefinitely
+    #[cfg_attr(feature = "schemars", schemars(with = "Option<schema::Duration>"))]
+    execution_time_limit: Option<String>,
+    #[cfg_attr(feature = "schemars", schemars(with = "Option<schema::Duration>"))]
+    delete_expired_task_after: Option<String>,
+    multiple_instances: Option<RawMultipleInstances>,
+    #[cfg_attr(feature = "schemars", schemars(range(max = 10)))]
+    priority: Option<u8>,
+    restart_on_failure: Option<RawRestartOnFailure>,
+    idle: Option<RawIdle>,
+}
+
+#[derive(Deserialize)]
+#[cfg_attr(
+    feature = "schemars",
+    derive(schemars::JsonSchema),
+    schemars(rename = "RestartOnFailure")
+)]
+#[serde(deny_unknown_fields)]
+struct RawRestartOnFailure {
+    #[cfg_attr(feature = "schemars", schemars(with = "schema::Duration"))]
+    interval: String,
+    count: u32,
+}
+
+#[derive(Deserialize)]
+#[cfg_attr(
+    feature = "schemars",
+    derive(schemars::JsonSchema),
+    schemars(rename = "Idle")
+)]
+#[serde(deny_unknown_fields)]
+struct RawIdle {
+    #[cfg_attr(feature = "schemars", schemars(with = "Option<schema::Duration>"))]
+    duration: Option<String>,
+    #[cfg_attr(feature = "schemars", schemars(with = "Option<schema::Duration>"))]
+    wait_timeout: Option<String>,
+    stop_on_idle_end: Option<bool>,
+    restart_on_idle: Option<bool>,
+}
+
+#[derive(Deserialize)]
+#[cfg_attr(
+    feature = "schemars",
+    derive(schemars::JsonSchema),
+    schemars(rename = "Repetition")
+)]
+#[serde(deny_unknown_fields)]
+struct RawRepetition {
+    #[cfg_attr(feature = "schemars", schemars(with = "schema::Duration"))]
+    interval: String,
+    #[cfg_attr(feature = "schemars", schemars(with = "Option<schema::Duration>"))]
+    duration: Option<String>,
+    #[serde(default)]
+    stop_at_duration_end: bool,
+}
+
+#[derive(Deserialize)]
+#[cfg_attr(
+    feature = "schemars",
+    derive(schemars::JsonSchema),
+    schemars(rename = "Trigger")
+)]
+#[serde(tag = "type", rename_all = "snake_case", deny_unknown_fields)]
+enum RawTrigger {
+    Daily {
+        id: Option<String>,
+        enabled: Option<bool>,
+        #[cfg_attr(feature = "schemars", schemars(with = "Option<schema::DateTime>"))]
+        start: Option<String>,
+        #[cfg_attr(feature = "schemars", schemars(with = "Option<schema::DateTime>"))]
+        end: Option<String>,
+        #[cfg_attr(feature = "schemars", schemars(with = "Option<schema::Duration>"))]
+        execution_time_limit: Option<String>,
+        #[cfg_attr(feature = "schemars", schemars(with = "Option<schema::Duration>"))]
+        random_delay: Option<String>,
+        interval: Option<u16>,
+        repetition: Option<RawRepetition>,
+    },
+    Weekly {
+        id: Option<String>,
+        enabled: Option<bool>,
+        #[cfg_attr(feature = "schemars", schemars(with = "Option<schema::DateTime>"))]
+        start: Option<String>,
+        #[cfg_attr(feature = "schemars", schemars(with = "Option<schema::DateTime>"))]
+        end: Option<String>,
+        #[cfg_attr(feature = "schemars", schemars(with = "Option<schema::Duration>"))]
+        execution_time_limit: Option<String>,
+        #[cfg_attr(feature = "schemars", schemars(with = "Option<schema::Duration>"))]
+        random_delay: Option<String>,
+        #[cfg_attr(feature = "schemars", schemars(with = "Vec<schema::Weekday>"))]
+        days: Vec<String>,
+        weeks_interval: Option<u16>,
+        repetition: Option<RawRepetition>,
+    },
+    Monthly {
+        id: Option<String>,
+        enabled: Option<bool>,
+        #[cfg_attr(feature = "schemars", schemars(with = "Option<schema::DateTime>"))]
+        start: Option<String>,
+        #[cfg_attr(feature = "schemars", schemars(with = "Option<schema::DateTime>"))]
+        end: Option<String>,
+        #[cfg_attr(feature = "schemars", schemars(with = "Option<schema::Duration>"))]
+        execution_time_limit: Option<String>,
+        #[cfg_attr(feature = "schemars", schemars(with = "Option<schema::Duration>"))]
+        random_delay: Option<String>,
+        #[serde(default)]
+        #[cfg_attr(feature = "schemars", schemars(inner(range(min = 1, max = 31))))]
+        days: Vec<u32>,
+        #[serde(default)]
+        last_day: bool,
+        #[cfg_attr(feature = "schemars", schemars(with = "Option<Vec<schema::Month>>"))]
+        months: Option<Vec<String>>,
+        repetition: Option<RawRepetition>,
+    },
+    MonthlyDow {
+        id: Option<String>,
+        enabled: Option<bool>,
+        #[cfg_attr(feature = "schemars", schemars(with = "Option<schema::DateTime>"))]
+        start: Option<String>,
+        #[cfg_attr(feature = "schemars", schemars(with = "Option<schema::DateTime>"))]
+        end: Option<String>,
+        #[cfg_attr(feature = "schemars", schemars(with = "Option<schema::Duration>"))]
+        execution_time_limit: Option<String>,
+        #[cfg_attr(feature = "schemars", schemars(with = "Option<schema::Duration>"))]
+        random_delay: Option<String>,
+        #[cfg_attr(feature = "schemars", schemars(with = "Vec<schema::Weekday>"))]
+        days: Vec<String>,
+        #[serde(default)]
+        #[cfg_attr(feature = "schemars", schemars(inner(range(min = 1, max = 4))))]
+        weeks: Vec<u32>,
+        #[serde(default)]
+        last_week: bool,
+        #[cfg_attr(feature = "schemars", schemars(with = "Option<Vec<schema::Month>>"))]
+        months: Option<Vec<String>>,
+        repetition: Option<RawRepetition>,
+    },
+    /// The start is `at`, which is required
+    Time {
+        id: Option<String>,
+        enabled: Option<bool>,
+        #[cfg_attr(feature = "schemars", schemars(with = "schema::DateTime"))]
+        at: String,
+        #[cfg_attr(feature = "schemars", schemars(with = "Option<schema::DateTime>"))]
+        end: Option<String>,
+        #[cfg_attr(feature = "schemars", schemars(with = "Option<schema::Duration>"))]
+        execution_time_limit: Option<String>,
+        #[cfg_attr(feature = "schemars", schemars(with = "Option<schema::Duration>"))]
+        random_delay: Option<String>,
+        repetition: Option<RawRepetition>,
+    },
+    Event {
+        id: Option<String>,
+        enabled: Option<bool>,
+        #[cfg_attr(feature = "schemars", schemars(with = "Option<schema::DateTime>"))]
+        start: Option<String>,
+        #[cfg_attr(feature = "schemars", schemars(with = "Option<schema::DateTime>"))]
+        end: Option<String>,
+        #[cfg_attr(feature = "schemars", schemars(with = "Option<schema::Duration>"))]
+        execution_time_limit: Option<String>,
+        subscription: String,
+        #[cfg_attr(feature = "schemars", schemars(with = "Option<schema::Duration>"))]
+        delay: Option<String>,
+        #[serde(default)]
+        value_queries: HashMap<String, String>,
+        repetition: Option<RawRepetition>,
+    },
+    Idle {
+        id: Option<String>,
+        enabled: Option<bool>,
+        #[cfg_attr(feature = "schemars", schemars(with = "Option<schema::DateTime>"))]
+        start: Option<String>,
+        #[cfg_attr(feature = "schemars", schemars(with = "Option<schema::DateTime>"))]
+        end: Option<String>,
+        #[cfg_attr(feature = "schemars", schemars(with = "Option<schema::Duration>"))]
+        execution_time_limit: Option<String>,
+        repetition: Option<RawRepetition>,
+    },
+    Registration {
+        id: Option<String>,
+        enabled: Option<bool>,
+        #[cfg_attr(feature = "schemars", schemars(with = "Option<schema::DateTime>"))]
+        start: Option<String>,
+        #[cfg_attr(feature = "schemars", schemars(with = "Option<schema::DateTime>"))]
+        end: Option<String>,
+        #[cfg_attr(feature = "schemars", schemars(with = "Option<schema::Duration>"))]
+        execution_time_limit: Option<String>,
+        #[cfg_attr(feature = "schemars", schemars(with = "Option<schema::Duration>"))]
+        delay: Option<String>,
+    },
+    Boot {
+        id: Option<String>,
+        enabled: Option<bool>,
+        #[cfg_attr(feature = "schemars", schemars(with = "Option<schema::DateTime>"))]
+        start: Option<String>,
+        #[cfg_attr(feature = "schemars", schemars(with = "Option<schema::DateTime>"))]
+        end: Option<String>,
+        #[cfg_attr(feature = "schemars", schemars(with = "Option<schema::Duration>"))]
+        execution_time_limit: Option<String>,
+        #[cfg_attr(feature = "schemars", schemars(with = "Option<schema::Duration>"))]
+        delay: Option<String>,
+    },
+    Logon {
+        id: Option<String>,
+        enabled: Option<bool>,
+        #[cfg_attr(feature = "schemars", schemars(with = "Option<schema::DateTime>"))]
+        start: Option<String>,
+        #[cfg_attr(feature = "schemars", schemars(with = "Option<schema::DateTime>"))]
+        end: Option<String>,
+        #[cfg_attr(feature = "schemars", schemars(with = "Option<schema::Duration>"))]
+        execution_time_limit: Option<String>,
+        user_id: Option<String>,
+        #[cfg_attr(feature = "schemars", schemars(with = "Option<schema::Duration>"))]
+        delay: Option<String>,
+    },
+    SessionStateChange {
+        id: Option<String>,
+        enabled: Option<bool>,
+        #[cfg_attr(feature = "schemars", schemars(with = "Option<schema::DateTime>"))]
+        start: Option<String>,
+        #[cfg_attr(feature = "schemars", schemars(with = "Option<schema::DateTime>"))]
+        end: Option<String>,
+        #[cfg_attr(feature = "schemars", schemars(with = "Option<schema::Duration>"))]
+        execution_time_limit: Option<String>,
+        state_change: RawSessionStateChange,
+        user_id: Option<String>,
+        #[cfg_attr(feature = "schemars", schemars(with = "Option<schema::Duration>"))]
+        delay: Option<String>,
+    },
+}
+
+/// The values of a trigger that go into its [`TriggerCommon`]
+struct RawCommon<'a> {
+    id: Option<&'a String>,
+    enabled: Option<bool>,
+    start: Option<&'a String>,
+    end: Option<&'a String>,
+    execution_time_limit: Option<&'a String>,
+    random_delay: Option<&'a String>,
+}
+
+impl RawTrigger {
+    fn common(&self) -> RawCommon<'_> {
+        match self {
+            RawTrigger::Daily {
+                id,
+                enabled,
+                start,
+                end,
+                execution_time_limit,
+                random_delay,
+                ..
+            }
+            | RawTrigger::Weekly {
+                id,
+                enabled,
+                start,
+                end,
+                execution_time_limit,
+                random_delay,
+                ..
+            }
+            | RawTrigger::Monthly {
+                id,
+                enabled,
+                start,
+                end,
+                execution_time_limit,
+                random_delay,
+                ..
+            }
+            | RawTrigger::MonthlyDow {
+                id,
+                enabled,
+                start,
+                end,
+                execution_time_limit,
+                random_delay,
+                ..
+            } => RawCommon {
+                id: id.as_ref(),
+                enabled: *enabled,
+                start: start.as_ref(),
+                end: end.as_ref(),
+                execution_time_limit: execution_time_limit.as_ref(),
+                random_delay: random_delay.as_ref(),
+            },
+            RawTrigger::Time {
+                id,
+                enabled,
+                at,
+                end,
+                execution_time_limit,
+                random_delay,
+                ..
+            } => RawCommon {
+                id: id.as_ref(),
+                enabled: *enabled,
+                start: Some(at),
+                end: end.as_ref(),
+                execution_time_limit: execution_time_limit.as_ref(),
+                random_delay: random_delay.as_ref(),
+            },
+            RawTrigger::Event {
+                id,
+                enabled,
+                start,
+                end,
+                execution_time_limit,
+                ..
+            }
+            | RawTrigger::Idle {
+                id,
+                enabled,
+                start,
+                end,
+                execution_time_limit,
+                ..
+            }
+            | RawTrigger::Registration {
+                id,
+                enabled,
+                start,
+                end,
+                execution_time_limit,
+                ..
+            }
+            | RawTrigger::Boot {
+                id,
+                enabled,
+                start,
+                end,
+                execution_time_limit,
+                ..
+            }
+            | RawTrigger::Logon {
+                id,
+                enabled,
+                start,
+                end,
+                execution_time_limit,
+                ..
+            }
+            | RawTrigger::SessionStateChange {
+                id,
+                enabled,
+                start,
+                end,
+                execution_time_limit,
+                ..
+            } => RawCommon {
+                id: id.as_ref(),
+                enabled: *enabled,
+                start: start.as_ref(),
+                end: end.as_ref(),
+                execution_time_limit: execution_time_limit.as_ref(),
+                random_delay: None,
+            },
+        }
+    }
+}
+
+#[derive(Deserialize)]
+#[cfg_attr(
+    feature = "schemars",
+    derive(schemars::JsonSchema),
+    schemars(rename = "SessionStateChange")
+)]
+#[serde(rename_all = "snake_case")]
+enum RawSessionStateChange {
+    ConsoleConnect,
+    ConsoleDisconnect,
+    RemoteConnect,
+    RemoteDisconnect,
+    SessionLock,
+    SessionUnlock,
+}
+
+#[derive(Deserialize)]
+#[cfg_attr(
+    feature = "schemars",
+    derive(schemars::JsonSchema),
+    schemars(rename = "Action")
+)]
+#[serde(tag = "type", rename_all = "snake_case", deny_unknown_fields)]
+enum RawAction {
+    Exec {
+        id: Option<String>,
+        path: String,
+        arguments: Option<String>,
+        working_directory: Option<String>,
+    },
+    ComHandler {
+        id: Option<String>,
+        class_id: String,
+        data: Option<String>,
+    },
+}
+
+/// Converts the text values of one task, remembering which task they belong to for errors
+struct Values<'a> {
+    task: &'a str,
+}
+
+impl Values<'_> {
+    fn invalid(&self, field: &str, value: &str) -> ManifestError {
+        ManifestError::InvalidValue {
+            task: self.task.to_string(),
+            field: field.to_string(),
+            value: value.to_string(),
+        }
+    }
+
+    fn duration(&self, field: &str, value: &str) -> Result<Duration, ManifestError> {
+        parse_xml_duration(value).ok_or_else(|| self.invalid(field, value))
+    }
+
+    fn optional_duration(
+        &self,
+        field: &str,
+        value: Option<&String>,
+    ) -> Result<Option<Duration>, ManifestError> {
+        value.map(|value| self.duration(field, value)).transpose()
+    }
+
+    fn date(&self, field: &str, value: Option<&String>) -> Result<Option<Boundary>, ManifestError> {
+        value
+            .map(|value| Boundary::parse(value).ok_or_else(|| self.invalid(field, value)))
+            .transpose()
+    }
+
+    /// The start of a time trigger is called `at`
+    fn common(&self, raw: RawCommon, start_field: &str) -> Result<TriggerCommon, ManifestError> {
+        Ok(TriggerCommon {
+            id: raw.id.cloned().filter(|id| !id.is_empty()),
+            enabled: raw.enabled.unwrap_or(true),
+            start: self.date(start_field, raw.start)?,
+            end: self.date("end", raw.end)?,
+            execution_time_limit: self
+                .optional_duration("execution_time_limit", raw.execution_time_limit)?,
+            random_delay: self.optional_duration("random_delay", raw.random_delay)?,
+        })
+    }
+
+    fn repetition(
+        &self,
+        repetition: Option<&RawRepetition>,
+    ) -> Result<Option<Repetition>, ManifestError> {
+        let repetition = match repetition {
+            Some(repetition) => repetition,
+            None => return Ok(None),
+        };
+        Ok(Some(Repetition {
+            interval: self.duration("repetition.interval", &repetition.interval)?,
+            duration: self
+                .optional_duration("repetition.duration", repetition.duration.as_ref())?,
+            stop_at_duration_end: repetition.stop_at_duration_end,
+        }))
+    }
+
+    fn days_of_week(&self, days: &[String]) -> Result<DaysOfWeek, ManifestError> {
+        days.iter().try_fold(DaysOfWeek::new(), |set, day| {
+            from_name(WEEKDAYS.iter().copied(), weekday_name, day)
+                .map(|day| set.with(day))
+                .ok_or_else(|| self.invalid("days", day))
+        })
+    }
+
+    /// Every month when the months are left out
+    fn months(&self, months: Option<&Vec<String>>) -> Result<Months, ManifestError> {
+        match months {
+            None => Ok(Months::all()),
+            Some(months) => months.iter().try_fold(Months::new(), |set, month| {
+                from_name(1..=12, month_name, month)
+                    .map(|month| set.with(month))
+                    .ok_or_else(|| self.invalid("months", month))
+            }),
+        }
+    }
+}
+
+impl RawTask {
+    fn into_task(self, path: &str) -> Result<Task, ManifestError> {
+        let values = Values { task: path };
+
+        let registration = TaskRegistration {
+            author: self.author,
+            description: self.description,
+            documentation: self.documentation,
+            source: self.source,
+            version: self.version,
+            uri: None,
+            date: None,
+        };
+
+        let mut principal = TaskPrincipal::new();
+        if let Some(raw) = self.principal {
+            principal.user_id = raw.user_id;
+            principal.group_id = raw.group_id;
+            principal.logon_type = raw.logon_type.map(|logon_type| match logon_type {
+                RawLogonType::None => TaskLogon::None,
+                RawLogonType::Password => TaskLogon::Password,
+                RawLogonType::S4u => TaskLogon::S4U,
+                RawLogonType::InteractiveToken => TaskLogon::InteractiveToken,
+                RawLogonType::Group => TaskLogon::Group,
+                RawLogonType::ServiceAccount => TaskLogon::ServiceAccount,
+                RawLogonType::InteractiveTokenOrPassword => TaskLogon::InteractiveTokenOrPassword,
+            });
+            if let Some(run_level) = raw.run_level {
+                principal.run_level = match run_level {
+                    RawRunLevel::LeastPrivilege => RunLevel::LeastPrivilege,
+                    RawRunLevel::HighestAvailable => RunLevel::HighestAvailable,
+                };
+            }
+            principal.required_privileges = raw
+                .required_privileges
+                .iter()
+                .map(|name| {
+                    from_name(Privilege::ALL.iter().copied(), Privilege::name, name)
+                        .ok_or_else(|| values.invalid("principal.required_privileges", name))
+                })
+                .collect::<Result<_, _>>()?;
+        }
+
+        let settings = match self.settings {
+            Some(raw) => raw.into_settings(&values)?,
+            None => Settings::default(),
+        };
+
+        if self.triggers.len() > MAX_TRIGGERS as usize {
+            return Err(ManifestError::Limit {
+                task: path.to_string(),
+                description: format!("a task can only have up to {} triggers", MAX_TRIGGERS),
+            });
+        }
+        let mut triggers = TaskTriggersBuilder::new();
+        for trigger in &self.triggers {
+            triggers = add_trigger(&values, triggers, trigger)?;
+        }
+
+        if self.actions.is_empty() || self.actions.len() > MAX_ACTIONS {
+            return Err(ManifestError::Limit {
+                task: path.to_string(),
+                description: format!("a task needs between 1 and {} actions", MAX_ACTIONS),
+            });
+        }
+        let mut actions = self.actions.into_iter().map(|action| match action {
+            RawAction::Exec {
+                id,
+                path,
+                arguments,
+                working_directory,
+            } => Action::Exec(ExecAction {
+                id,
+                path: PathBuf::from(path),
+                arguments,
+                working_directory: working_directory.map(PathBuf::from),
+            }),
+            RawAction::ComHandler { id, class_id, data } => {
+                Action::ComHandler(ComHandlerAction { id, class_id, data })
+            }
+        });
+        let first = actions.next().unwrap();
+        let actions = actions.fold(Actions::from_action(first), Actions::with_action);
+
+        Ok(Task::new(actions, TaskTriggers::from_builder(triggers))
+            .with_registration(registration)
+            .with_principal(principal)
+            .with_settings(settings))
+    }
+}
+
+impl RawSettings {
+    fn into_settings(self, values: &Values) -> Result<Settings, ManifestError> {
+        let mut settings = Settings::default();
+        macro_rules! copy {
+            ($($field:ident),*) => {
+                $(if let Some(value) = self.$field {
+                    settings.$field = value;
+                })*
+            };
+        }
+        copy!(
+            allow_demand_start,
+            allow_hard_terminate,
+            disallow_start_if_on_batteries,
+            stop_if_going_on_batteries,
+            enabled,
+            hidden,
+            start_when_available,
+            run_only_if_idle,
+            run_only_if_network_available,
+            wake_to_run,
+            use_unified_scheduling_engine,
+            disallow_start_on_remote_app_session,
+            volatile,
+            priority
+        );
+        if let Some(limit) = &self.execution_time_limit {
+            let limit = values.duration("execution_time_limit", limit)?;
+            settings.execution_time_limit = if limit.is_zero() { None } else { Some(limit) };
+        }
+        settings.delete_expired_task_after = values.optional_duration(
+            "delete_expired_task_after",
+            self.delete_expired_task_after.as_ref(),
+        )?;
+        if let Some(policy) = self.multiple_instances {
+            settings.multiple_instances = match policy {
+                RawMultipleInstances::Parallel => MultipleInstancesPolicy::Parallel,
+                RawMultipleInstances::Queue => MultipleInstancesPolicy::Queue,
+                RawMultipleInstances::IgnoreNew => MultipleInstancesPolicy::IgnoreNew,
+                RawMultipleInstances::StopExisting => MultipleInstancesPolicy::StopExisting,
+            };
+        }
+        if settings.priority > 10 {
+            return Err(values.invalid("priority", &settings.priority.to_string()));
+        }
+        if let Some(restart) = &self.restart_on_failure {
+            settings.restart_on_failure = Some(RestartOnFailure {
+                interval: values.duration("restart_on_failure.interval", &restart.interval)?,
+                count: restart.count,
+            });
+        }
+        if let Some(idle) = &self.idle {
+            let mut conditions = IdleConditions::new();
+            if let Some(duration) = &idle.duration {
+                conditions.duration = values.duration("idle.duration", duration)?;
+            }
+            if let Some(wait_timeout) = &idle.wait_timeout {
+                conditions.wait_timeout = values.duration("idle.wait_timeout", wait_timeout)?;
+            }
+            if let Some(stop_on_idle_end) = idle.stop_on_idle_end {
+                conditions.stop_on_idle_end = stop_on_idle_end;
+            }
+            if let Some(restart_on_idle) = idle.restart_on_idle {
+                conditions.restart_on_idle = restart_on_idle;
+            }
+            settings.idle_conditions = conditions;
+        }
+        Ok(settings)
+    }
+}
+
+fn add_trigger(
+    values: &Values,
+    builder: TaskTriggersBuilder,
+    trigger: &RawTrigger,
+) -> Result<TaskTriggersBuilder, ManifestError> {
+    let start_field = match trigger {
+        RawTrigger::Time { .. } => "at",
+        _ => "start",
+    };
+    let common = values.common(trigger.common(), start_field)?;
+    let builder = match trigger {
+        RawTrigger::Daily {
+            interval,
+            repetition,
+            ..
+        } => builder.with_daily(DailyTrigger {
+            common,
+            interval: *interval,
+            repetition: values.repetition(repetition.as_ref())?,
+        }),
+        RawTrigger::Weekly {
+            days,
+            weeks_interval,
+            repetition,
+            ..
+        } => builder.with_weekly(WeeklyTrigger {
+            common,
+            days_of_week: values.days_of_week(days)?,
+            weeks_interval: *weeks_interval,
+            repetition: values.repetition(repetition.as_ref())?,
+        }),
+        RawTrigger::Monthly {
+            days,
+            last_day,
+            months,
+            repetition,
+            ..
+        } => {
+            let mut days_of_month = DaysOfMonth::new();
+            for day in days {
+                if !(1..=31).contains(day) {
+                    return Err(values.invalid("days", &day.to_string()));
+                }
+                days_of_month = days_of_month.with(*day);
+            }
+            builder.with_monthly(MonthlyTrigger {
+                common,
+                days_of_month,
+                months: values.months(months.as_ref())?,
+                run_on_last_day_of_month: *last_day,
+                repetition: values.repetition(repetition.as_ref())?,
+            })
+        }
+        RawTrigger::MonthlyDow {
+            days,
+            weeks,
+            last_week,
+            months,
+            repetition,
+            ..
+        } => {
+            let mut weeks_of_month = WeeksOfMonth::new();
+            for week in weeks {
+                if !(1..=4).contains(week) {
+                    return Err(values.invalid("weeks", &week.to_string()));
+                }
+                weeks_of_month = weeks_of_month.with(*week);
+            }
+            builder.with_monthly_dow(MonthlyDowTrigger {
+                common,
+                days_of_week: values.days_of_week(days)?,
+                weeks_of_month,
+                months: values.months(months.as_ref())?,
+                run_on_last_week_of_month: *last_week,
+                repetition: values.repetition(repetition.as_ref())?,
+            })
+        }
+        RawTrigger::Time { repetition, .. } => builder.with_specific_time(SpecificTimeTrigger {
+            common,
+            repetition: values.repetition(repetition.as_ref())?,
+        }),
+        RawTrigger::Event {
+            subscription,
+            delay,
+            value_queries,
+            repetition,
+            ..
+        } => {
+            // sorted so that the same manifest always gives the same task
+            let mut value_queries: Vec<(String, String)> = value_queries
+                .iter()
+                .map(|(name, query)| (name.clone(), query.clone()))
+                .collect();
+            value_queries.sort();
+            builder.with_event(EventTrigger {
+                common,
+                subscription: subscription.clone(),
+                delay: values.optional_duration("delay", delay.as_ref())?,
+                value_queries,
+                repetition: values.repetition(repetition.as_ref())?,
+            })
+        }
+        RawTrigger::Idle { repetition, .. } => builder.with_idle(IdleTrigger {
+            common,
+            repetition: values.repetition(repetition.as_ref())?,
+        }),
+        RawTrigger::Registration { delay, .. } => builder.with_registration(RegistrationTrigger {
+            common,
+            delay: values.optional_duration("delay", delay.as_ref())?,
+        }),
+        RawTrigger::Boot { delay, .. } => builder.with_boot(BootTrigger {
+            common,
+            delay: values.optional_duration("delay", delay.as_ref())?,
+        }),
+        RawTrigger::Logon { user_id, delay, .. } => builder.with_logon(LogonTrigger {
+            common,
+            user_id: user_id.clone(),
+            delay: values.optional_duration("delay", delay.as_ref())?,
+        }),
+        RawTrigger::SessionStateChange {
+            state_change,
+            user_id,
+            delay,
+            ..
+        } => builder.with_session_state_change(SessionStateChangeTrigger {
+            common,
+            state_change: match state_change {
+                RawSessionStateChange::ConsoleConnect => SessionStateChange::ConsoleConnect,
+                RawSessionStateChange::ConsoleDisconnect => SessionStateChange::ConsoleDisconnect,
+                RawSessionStateChange::RemoteConnect => SessionStateChange::RemoteConnect,
+                RawSessionStateChange::RemoteDisconnect => SessionStateChange::RemoteDisconnect,
+                RawSessionStateChange::SessionLock => SessionStateChange::SessionLock,
+                RawSessionStateChange::SessionUnlock => SessionStateChange::SessionUnlock,
+            },
+            user_id: user_id.clone(),
+            delay: values.optional_duration("delay", delay.as_ref())?,
+        }),
+    };
+    Ok(builder)
+}
+
+/// Schemas for the text values of a manifest, which are checked when the tasks are built
+#[cfg(feature = "schemars")]
+mod schema {
+    use std::borrow::Cow;
+
+    use schemars::{json_schema, JsonSchema, Schema, SchemaGenerator};
+
+    use crate::calendar::{month_name, weekday_name, WEEKDAYS};
+
+    /// A string schema with a pattern, which is shared through `$defs`
+    macro_rules! string_schema {
+        ($name:ident, $description:expr, $pattern:expr) => {
+            pub(super) struct $name;
+
+            impl JsonSchema for $name {
+                fn schema_name() -> Cow<'static, str> {
+                    stringify!($name).into()
+                }
+
+                fn json_schema(_: &mut SchemaGenerator) -> Schema {
+                    json_schema!({
+                        "type": "string",
+                        "description": $description,
+                        "pattern": $pattern,
+                    })
+                }
+            }
+        };
+    }
+
+    string_schema!(
+        Duration,
+        "An xsd:duration such as PT5M",
+        r"^P(?!$)(\d+Y)?(\d+M)?(\d+W)?(\d+D)?(T(?=\d)(\d+H)?(\d+M)?(\d+(\.\d+)?S)?)?$"
+    );
+    string_schema!(
+        DateTime,
+        "An xsd:dateTime, local time when it has no offset",
+        r"^\d{4}-\d{2}-\d{2}T\d{2}:\d{2}:\d{2}(\.\d+)?([Zz]|[+-]\d{2}:\d{2})?$"
+    );
+    string_schema!(
+        FolderPath,
+        "A folder path such as \\Backups, separated by \\ or /",
+        r#"^[^:*?"<>|]*$"#
+    );
+    string_schema!(
+        TaskName,
+        "The name of a task, without a path",
+        r#"^[^\\/:*?"<>|]+$"#
+    );
+
+    /// A name from the list, in the case the schema uses or in lowercase like manifests take it
+    fn names_schema(description: &str, names: Vec<&str>) -> Schema {
+        let lowercase = names.iter().map(|name| name.to_lowercase());
+        let names: Vec<String> = names
+            .iter()
+            .map(|name| name.to_string())
+            .chain(lowercase)
+            .collect();
+        json_schema!({
+            "type": "string",
+            "description": description,
+            "enum": names,
+        })
+    }
+
+    pub(super) struct Weekday;
+
+    impl JsonSchema for Weekday {
+        fn schema_name() -> Cow<'static, str> {
+            "Weekday".into()
+        }
+
+        fn json_schema(_: &mut SchemaGenerator) -> Schema {
+            names_schema(
+                "A day of the week",
+                WEEKDAYS.iter().copied().map(weekday_name).collect(),
+            )
+        }
+    }
+
+    pub(super) struct Privilege;
+
+    impl JsonSchema for Privilege {
+        fn schema_name() -> Cow<'static, str> {
+            "Privilege".into()
+        }
+
+        fn json_schema(_: &mut SchemaGenerator) -> Schema {
+            names_schema(
+                "A privilege the task runs with, e.g. SeBackupPrivilege",
+                crate::task::Privilege::ALL
+                    .iter()
+                    .copied()
+                    .map(crate::task::Privilege::name)
+                    .collect(),
+            )
+        }
+    }
+
+    pub(super) struct Month;
+
+    impl JsonSchema for Month {
+        fn schema_name() -> Cow<'static, str> {
+            "Month".into()
+        }
+
+        fn json_schema(_: &mut SchemaGenerator) -> Schema {
+            names_schema("A month of the year", (1..=12).map(month_name).collect())
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::Weekday;
+
+    use super::*;
+
+    fn weekly(days: &str) -> Result<Manifest, ManifestError> {
+        Manifest::from_toml(&format!(
+            r#"
+[[folder]]
+path = "\\Reports"
+
+[[folder.task]]
+name = "Weekly"
+
+[[folder.task.trigger]]
+type = "weekly"
+start = "2021-01-04T09:00:00"
+days = [{}]
+
+[[folder.task.action]]
+type = "exec"
+path = "report.exe"
+"#,
+            days
+        ))
+    }
+
+    #[test]
+    fn reads_required_privileges() {
+        let manifest = Manifest::from_toml(
+            r#"
+[[folder]]
+path = "\\Backups"
+
+[[folder.task]]
+name = "Nightly"
+
+[folder.task.principal]
+user_id = "SYSTEM"
+required_privileges = ["SeBackupPrivilege", "serestoreprivilege"]
+
+[[folder.task.action]]
+type = "exec"
+path = "backup.exe"
+"#,
+        )
+        .unwrap();
+        let task = manifest.folders()[0].tasks()[0].task();
+        assert_eq!(
+            task.principal.required_privileges(),
+            [Privilege::Backup, Privilege::Restore]
+        );
+        let xml = task.to_xml();
+        assert!(xml.contains("<Privilege>SeBackupPrivilege</Privilege>"));
+        assert!(xml.contains("<Privilege>SeRestorePrivilege</Privilege>"));
+        assert!(xml.contains("version=\"1.3\""));
+        assert_eq!(Task::from_xml(&xml).unwrap(), *task);
+    }
+
+    #[test]
+    fn takes_names_like_the_schema_or_in_lowercase() {
+        let manifest = weekly(r#""Monday", "friday""#).unwrap();
+        let days = manifest.folders()[0].tasks()[0].task().triggers().weekly()[0].days_of_week();
+        assert_eq!(
+            days,
+            DaysOfWeek::new().with(Weekday::Mon).with(Weekday::Fri)
+        );
+        assert_eq!(
+            weekly(r#""MONDAY""#),
+            Err(ManifestError::InvalidValue {
+                task: "\\Reports\\Weekly".to_string(),
+                field: "days".to_string(),
+                value: "MONDAY".to_string(),
+            })
+        );
+    }
+
+    #[test]
+    fn rejects_a_task_name_with_a_path() {
+        let manifest = Manifest::from_toml(
+            r#"
+[[folder]]
+path = "\\Reports"
+
+[[folder.task]]
+name = "Weekly\\Nested"
+
+[[folder.task.action]]
+type = "exec"
+path = "report.exe"
+"#,
+        );
+        assert_eq!(
+            manifest,
+            Err(ManifestError::InvalidValue {
+                task: "\\Reports\\Weekly\\Nested".to_string(),
+                field: "name".to_string(),
+                value: "Weekly\\Nested".to_string(),
+            })
+        );
+    }
+
+    #[test]
+    fn merges_folders_that_differ_in_case() {
+        let manifest = Manifest::from_toml(
+            r#"
+[[folder]]
+path = "\\Reports"
+
+[[folder.task]]
+name = "Daily"
+
+[[folder.task.action]]
+type = "exec"
+path = "report.exe"
+
+[[folder]]
+path = "\\reports"
+
+[[folder.task]]
+name = "Weekly"
+
+[[folder.task.action]]
+type = "exec"
+path = "report.exe"
+"#,
+        )
+        .unwrap();
+        assert_eq!(manifest.folders().len(), 1);
+        assert_eq!(manifest.folders()[0].path(), "\\Reports");
+        assert_eq!(manifest.folders()[0].tasks().len(), 2);
+    }
+
+    #[cfg(feature = "schemars")]
+    #[test]
+    fn the_schema_is_checked_in() {
+        let schema: serde_json::Value =
+            serde_json::from_str(include_str!("../tests/fixtures/manifest/schema.json")).unwrap();
+        assert_eq!(Manifest::json_schema(), schema);
+    }
+}
@@ -0,0 +1,436 @@
+use chrono::{
+    DateTime, Datelike, Duration, FixedOffset, Local, NaiveDate, NaiveDateTime, Offset, TimeZone,
+    Utc,
+};
+
+use crate::{
+    boundary::{resolve_local, Boundary},
+    calendar::days_in_month,
+    DailyTrigger, MonthlyDowTrigger, MonthlyTrigger, Repetition, SpecificTimeTrigger, TaskTriggers,
+    TriggerCommon, WeeklyTrigger,
+};
+
+/// How far ahead the next run is searched for, long enough for a run on February 29
+const SEARCH_DAYS: i64 = 366 * 8;
+
+/// How far back days are looked at for repetitions that never end
+const INDEFINITE_LOOKBACK_DAYS: i64 = 366;
+
+impl TaskTriggers {
+    /// The first time after `after` that one of the time based triggers starts the task.
+    ///
+    /// Triggers that depend on events, like boot or logon triggers, and disabled
+    /// triggers are ignored.
+    /// A calendar trigger without a start time is treated as starting at `after`.
+    /// Repetitions fire while the time since the trigger started is less than
+    /// the repetition duration.
+    ///
+    /// Local boundaries are in the time zone of this computer, see [`next_occurrence_in`](Self::next_occurrence_in).
+    pub fn next_occurrence(&self, after: DateTime<Utc>) -> Option<DateTime<Utc>> {
+        self.next_occurrence_in(after, &Local)
+    }
+
+    /// The first time after `after` that one of the time based triggers starts the task
+    /// on a computer in the time zone, like a time zone from `chrono-tz`.
+    ///
+    /// A calendar trigger runs at the time of day of its start boundary on the clock the
+    /// boundary is on. With a local boundary that is the clock of the time zone, so a
+    /// trigger at 09:00 runs at 09:00 in summer and in winter. On the day daylight saving
+    /// time starts, a time that is skipped runs after the change, 02:30 runs at 03:30,
+    /// and on the day it ends a time that happens twice runs the first time only.
+    /// With a boundary in UTC or with an offset the trigger runs at the same UTC time
+    /// every day, whatever the time zone. Repetitions are counted in elapsed time,
+    /// so they keep their interval across a change.
+    pub fn next_occurrence_in<Tz: TimeZone>(
+        &self,
+        after: DateTime<Utc>,
+        zone: &Tz,
+    ) -> Option<DateTime<Utc>> {
+        let calendar = self
+            .daily()
+            .iter()
+            .map(|trigger| trigger as &dyn CalendarSchedule)
+            .chain(
+                self.weekly()
+                    .iter()
+                    .map(|trigger| trigger as &dyn CalendarSchedule),
+            )
+            .chain(
+                self.monthly()
+                    .iter()
+                    .map(|trigger| trigger as &dyn CalendarSchedule),
+            )
+            .chain(
+                self.monthly_dow()
+                    .iter()
+                    .map(|trigger| trigger as &dyn CalendarSchedule),
+            )
+            .filter_map(|trigger| next_calendar_occurrence(trigger, after, zone));
+        let specific = self
+            .specific_times()
+            .iter()
+            .filter_map(|trigger| next_specific_occurrence(trigger, after, zone));
+        calendar.chain(specific).min()
+    }
+
+    /// Every time from `from`, exclusive, to `until`, inclusive, that one of the
+    /// time based triggers starts the task, in order and without duplicates.
+    /// Local boundaries are in the time zone of this computer.
+    pub fn occurrences(&self, from: DateTime<Utc>, until: DateTime<Utc>) -> Vec<DateTime<Utc>> {
+        self.occurrences_in(from, until, &Local)
+    }
+
+    /// The occurrences from `from`, exclusive, to `until`, inclusive, on a computer
+    /// in the time zone, see [`next_occurrence_in`](Self::next_occurrence_in)
+    pub fn occurrences_in<Tz: TimeZone>(
+        &self,
+        from: DateTime<Utc>,
+        until: DateTime<Utc>,
+        zone: &Tz,
+    ) -> Vec<DateTime<Utc>> {
+        let mut occurrences = Vec::new();
+        let mut after = from;
+        while let Some(next) = self.next_occurrence_in(after, zone) {
+            if next > until {
+                break;
+            }
+            occurrences.push(next);
+            after = next;
+        }
+        occurrences
+    }
+}
+
+/// A trigger that runs on some days at the time of day of its start boundary
+trait CalendarSchedule {
+    fn common(&self) -> &TriggerCommon;
+    fn repetition(&self) -> Option<&Repetition>;
+    /// Whether the trigger runs on the day, given the day it starts on
+    fn runs_on(&self, day: NaiveDate, start_day: NaiveDate) -> bool;
+}
+
+impl CalendarSchedule for DailyTrigger {
+    fn common(&self) -> &TriggerCommon {
+        &self.common
+    }
+
+    fn repetition(&self) -> Option<&Repetition> {
+        self.repetition.as_ref()
+    }
+
+    fn runs_on(&self, day: NaiveDate, start_day: NaiveDate) -> bool {
+        let interval = i64::from(self.interval.unwrap_or(1).max(1));
+        (day - start_day).num_days() % interval == 0
+    }
+}
+
+impl CalendarSchedule for WeeklyTrigger {
+    fn common(&self) -> &TriggerCommon {
+        &self.common
+    }
+
+    fn repetition(&self) -> Option<&Repetition> {
+        self.repetition.as_ref()
+    }
+
+    fn runs_on(&self, day: NaiveDate, start_day: NaiveDate) -> bool {
+        let interval = i64::from(self.weeks_interval.unwrap_or(1).max(1));
+        let weeks = (week_start(day) - week_start(start_day)).num_days() / 7;
+        weeks % interval == 0 && self.days_of_week.contains(day.weekday())
+    }
+}
+
+impl CalendarSchedule for MonthlyTrigger {
+    fn common(&self) -> &TriggerCommon {
+        &self.common
+    }
+
+    fn repetition(&self) -> Option<&Repetition> {
+        self.repetition.as_ref()
+    }
+
+    fn runs_on(&self, day: NaiveDate, _start_day: NaiveDate) -> bool {
+        let last_day = days_in_month(day.year(), day.month());
+        self.months.contains(day.month())
+            && (self.days_of_month.contains(day.day())
+                || (self.run_on_last_day_of_month && day.day() == last_day))
+    }
+}
+
+impl CalendarSchedule for MonthlyDowTrigger {
+    fn common(&self) -> &TriggerCommon {
+        &self.common
+    }
+
+    fn repetition(&self) -> Option<&Repetition> {
+        self.repetition.as_ref()
+    }
+
+    fn runs_on(&self, day: NaiveDate, _start_day: NaiveDate) -> bool {
+        // the first week of the month is the first seven days
+        let week = (day.day() - 1) / 7 + 1;
+        let last_week = day.day() + 7 > days_in_month(day.year(), day.month());
+        self.months.contains(day.month())
+            && self.days_of_week.contains(day.weekday())
+            && ((week <= 4 && self.weeks_of_month.contains(week))
+                || (self.run_on_last_week_of_month && last_week))
+    }
+}
+
+/// The clock the days and time of day of a calendar trigger are counted on
+enum Clock<'a, Tz: TimeZone> {
+    Zone(&'a Tz),
+    Fixed(FixedOffset),
+}
+
+impl<'a, Tz: TimeZone> Clock<'a, Tz> {
+    /// Local boundaries are on the clock of the time zone, the others on their own
+    /// offset, and triggers without a start boundary are in UTC like they are written
+    fn of(start: Option<Boundary>, zone: &'a Tz) -> Self {
+        match start {
+            Some(Boundary::Local(_)) => Clock::Zone(zone),
+            Some(Boundary::Offset(start)) => Clock::Fixed(*start.offset()),
+            Some(Boundary::Utc(_)) | None => Clock::Fixed(Utc.fix()),
+        }
+    }
+
+    fn clock_time(&self, time: DateTime<Utc>) -> NaiveDateTime {
+        match self {
+            Clock::Zone(zone) => time.with_timezone(*zone).naive_local(),
+            Clock::Fixed(offset) => time.with_timezone(offset).naive_local(),
+        }
+    }
+
+    fn to_utc(&self, time: NaiveDateTime) -> DateTime<Utc> {
+        match self {
+            Clock::Zone(zone) => resolve_local(*zone, time),
+            Clock::Fixed(offset) => Utc.from_utc_datetime(
+                &(time - Duration::seconds(i64::from(offset.local_minus_utc()))),
+            ),
+        }
+    }
+}
+
+/// The Sunday that starts the week of the day
+fn week_start(day: NaiveDate) -> NaiveDate {
+    day - Duration::days(i64::from(day.weekday().num_days_from_sunday()))
+}
+
+/// The first run after `after` that a start at `base` leads to, including its repetitions
+fn next_from_base(
+    base: DateTime<Utc>,
+    repetition: Option<&Repetition>,
+    after: DateTime<Utc>,
+) -> Option<DateTime<Utc>> {
+    if base > after {
+        return Some(base);
+    }
+    let repetition = repetition?;
+    let interval = repetition.interval.num_seconds();
+    if interval <= 0 {
+        return None;
+    }
+    let repeats = (after - base).num_seconds() / interval + 1;
+    let offset = Duration::seconds(repeats * interval);
+    match repetition.duration {
+        Some(duration) if offset >= duration => None,
+        _ => Some(base + offset),
+    }
+}
+
+fn within_end(time: DateTime<Utc>, end: Option<DateTime<Utc>>) -> Option<DateTime<Utc>> {
+    match end {
+        Some(end) if time > end => None,
+        _ => Some(time),
+    }
+}
+
+fn next_calendar_occurrence<Tz: TimeZone>(
+    trigger: &dyn CalendarSchedule,
+    after: DateTime<Utc>,
+    zone: &Tz,
+) -> Option<DateTime<Utc>> {
+    let common = trigger.common();
+    if !common.enabled {
+        return None;
+    }
+    let clock = Clock::of(common.start, zone);
+    let start = common
+        .start
+        .map_or_else(|| clock.clock_time(after), |start| start.clock_time());
+    let start_day = start.date();
+    let time_of_day = start.time();
+    let end = common.end.map(|end| end.in_zone(zone));
+    let repetition = trigger.repetition();
+    let after_day = clock.clock_time(after).date();
+
+    // a repetition that started on an earlier day can still be running
+    let lookback = match repetition {
+        Some(repetition) => match repetition.duration {
+            Some(duration) => duration.num_days() + 1,
+            None => INDEFINITE_LOOKBACK_DAYS,
+        },
+        None => 0,
+    };
+    let first_day = std::cmp::max(start_day, after_day - Duration::days(lookback));
+
+    let mut next: Option<DateTime<Utc>> = None;
+    let mut day = first_day;
+    let last_day = after_day + Duration::days(SEARCH_DAYS);
+    while day <= last_day {
+        let base = clock.to_utc(day.and_time(time_of_day));
+        if let Some(next) = next {
+            // every later day starts after the best run so far
+            if base > next {
+                break;
+            }
+        }
+        if trigger.runs_on(day, start_day) {
+            if let Some(end) = end {
+                if base > end {
+                    break;
+                }
+            }
+            let candidate =
+                next_from_base(base, repetition, after).and_then(|time| within_end(time, end));
+            if let Some(candidate) = candidate {
+                next = Some(next.map_or(candidate, |next| next.min(candidate)));
+            }
+        }
+        day = match day.succ_opt() {
+            Some(day) => day,
+            None => break,
+        };
+    }
+    next
+}
+
+fn next_specific_occurrence<Tz: TimeZone>(
+    trigger: &SpecificTimeTrigger,
+    after: DateTime<Utc>,
+    zone: &Tz,
+) -> Option<DateTime<Utc>> {
+    if !trigger.common.enabled {
+        return None;
+    }
+    let end = trigger.common.end.map(|end| end.in_zone(zone));
+    next_from_base(
+        trigger.boundary().in_zone(zone),
+        trigger.repetition.as_ref(),
+        after,
+    )
+    .and_then(|time| within_end(time, end))
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::{DateTime, NaiveDate, NaiveDateTime, TimeZone, Utc};
+    use chrono_tz::America::New_York;
+
+    use crate::{boundary::Boundary, DailyTrigger, TaskTriggers, TaskTriggersBuilder};
+
+    fn daily(start: impl Into<Boundary>) -> TaskTriggers {
+        TaskTriggersBuilder::new()
+            .with_daily(DailyTrigger::new("Daily".to_string()).with_start_time(start))
+            .build()
+    }
+
+    fn local(year: i32, month: u32, day: u32, hour: u32, minute: u32) -> NaiveDateTime {
+        NaiveDate::from_ymd_opt(year, month, day)
+            .unwrap()
+            .and_hms_opt(hour, minute, 0)
+            .unwrap()
+    }
+
+    fn utc(year: i32, month: u32, day: u32, hour: u32, minute: u32) -> DateTime<Utc> {
+        Utc.from_utc_datetime(&local(year, month, day, hour, minute))
+    }
+
+    /// The runs on three days around a change in New York, in UTC
+    fn runs(triggers: &TaskTriggers, from: DateTime<Utc>) -> Vec<DateTime<Utc>> {
+        triggers.occurrences_in(from, from + chrono::Duration::days(3), &New_York)
+    }
+
+    #[test]
+    fn local_time_skipped_by_spring_forward_runs_after_the_gap() {
+        // clocks go from 02:00 to 03:00 on March 10 2024
+        let triggers = daily(local(2024, 3, 1, 2, 30));
+        assert_eq!(
+            runs(&triggers, utc(2024, 3, 9, 0, 0)),
+            vec![
+                // 02:30 EST
+                utc(2024, 3, 9, 7, 30),
+                // 02:30 doesn't exist, it runs at 03:30 EDT
+                utc(2024, 3, 10, 7, 30),
+                // 02:30 EDT
+                utc(2024, 3, 11, 6, 30),
+            ]
+        );
+    }
+
+    #[test]
+    fn local_time_repeated_by_fall_back_runs_once() {
+        // clocks go from 02:00 back to 01:00 on November 3 2024
+        let triggers = daily(local(2024, 11, 1, 1, 30));
+        assert_eq!(
+            runs(&triggers, utc(2024, 11, 2, 0, 0)),
+            vec![
+                // 01:30 EDT
+                utc(2024, 11, 2, 5, 30),
+                // the first 01:30, which is EDT
+                utc(2024, 11, 3, 5, 30),
+                // 01:30 EST
+                utc(2024, 11, 4, 6, 30),
+            ]
+        );
+    }
+
+    #[test]
+    fn local_time_stays_on_the_clock() {
+        let triggers = daily(local(2024, 3, 1, 2, 30));
+        for run in runs(&triggers, utc(2024, 3, 11, 0, 0)) {
+            assert_eq!(
+                run.with_timezone(&New_York).format("%H:%M").to_string(),
+                "02:30"
+            );
+        }
+    }
+
+    #[test]
+    fn utc_time_moves_on_the_clock_at_spring_forward() {
+        let triggers = daily(utc(2024, 3, 1, 7, 30));
+        let runs = runs(&triggers, utc(2024, 3, 9, 0, 0));
+        assert_eq!(
+            runs,
+            vec![
+                utc(2024, 3, 9, 7, 30),
+                utc(2024, 3, 10, 7, 30),
+                utc(2024, 3, 11, 7, 30),
+            ]
+        );
+        let clock: Vec<String> = runs
+            .iter()
+            .map(|run| run.with_timezone(&New_York).format("%H:%M").to_string())
+            .collect();
+        assert_eq!(clock, vec!["02:30", "03:30", "03:30"]);
+    }
+
+    #[test]
+    fn utc_time_moves_on_the_clock_at_fall_back() {
+        let triggers = daily(utc(2024, 11, 1, 5, 30));
+        let runs = runs(&triggers, utc(2024, 11, 2, 0, 0));
+        assert_eq!(
+            runs,
+            vec![
+                utc(2024, 11, 2, 5, 30),
+                utc(2024, 11, 3, 5, 30),
+                utc(2024, 11, 4, 5, 30),
+            ]
+        );
+        let clock: Vec<String> = runs
+            .iter()
+            .map(|run| run.with_timezone(&New_York).format("%H:%M").to_string())
+            .collect();
+        assert_eq!(clock, vec!["01:30", "01:30", "00:30"]);
+    }
+}
@@ -0,0 +1,360 @@
+use std::{
+    fmt, fs,
+    path::{Path, PathBuf},
+};
+
+use crate::{error::TaskFileError, task::Task};
+
+/// The tasks in a copy of a `C:\Windows\System32\Tasks` directory, such as one
+/// collected from another machine.
+///
+/// Every file in the directory tree is a task, and every directory is a task folder.
+/// Files that can't be read or aren't task XML don't stop the scan, they are
+/// collected as failures next to the tasks that could be read.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct OfflineTasks {
+    tasks: Vec<OfflineTask>,
+    failures: Vec<OfflineFailure>,
+}
+
+impl OfflineTasks {
+    /// Reads every task under the directory, which can be on any platform.
+    /// Tasks are sorted by their task path.
+    ///
+    /// This only fails when the directory itself can't be read.
+    pub fn read_dir(root: &Path) -> Result<OfflineTasks, TaskFileError> {
+        let entries = fs::read_dir(root).map_err(|error| io_error(root, error))?;
+        let mut tasks = OfflineTasks::default();
+        tasks.read_entries(entries.collect(), "");
+        tasks.tasks.sort_by(|a, b| a.path.cmp(&b.path));
+        tasks.failures.sort_by(|a, b| a.path.cmp(&b.path));
+        Ok(tasks)
+    }
+
+    pub fn tasks(&self) -> &[OfflineTask] {
+        &self.tasks
+    }
+
+    /// The files and folders that couldn't be read as tasks
+    pub fn failures(&self) -> &[OfflineFailure] {
+        &self.failures
+    }
+
+    /// Finds a task by its task path, like `\Microsoft\Windows\Defrag\ScheduledDefrag`.
+    /// Task paths aren't case sensitive.
+    pub fn task(&self, path: &str) -> Option<&OfflineTask> {
+        self.tasks
+            .iter()
+            .find(|task| task.path.eq_ignore_ascii_case(path))
+    }
+
+    fn read_entries(&mut self, entries: Vec<std::io::Result<fs::DirEntry>>, folder: &str) {
+        for entry in entries {
+            let entry = match entry {
+                Ok(entry) => entry,
+                Err(error) => {
+                    self.failures.push(OfflineFailure {
+                        path: folder_path(folder),
+                        file: PathBuf::new(),
+                        error: TaskFileError::Io(error.to_string()),
+                    });
+                    continue;
+                }
+            };
+            let file = entry.path();
+            let path = format!("{}\\{}", folder, entry.file_name().to_string_lossy());
+            let is_dir = match entry.file_type() {
+                Ok(file_type) => file_type.is_dir(),
+                Err(error) => {
+                    self.fail(path, file, io_error(&entry.path(), error));
+                    continue;
+                }
+            };
+            if is_dir {
+                match fs::read_dir(&file) {
+                    Ok(entries) => self.read_entries(entries.collect(), &path),
+                    Err(error) => {
+                        let error = io_error(&file, error);
+                        self.fail(path, file, error);
+                    }
+                }
+                continue;
+            }
+            match read_task(&file) {
+                Ok(task) => self.tasks.push(OfflineTask { path, file, task }),
+                Err(error) => self.fail(path, file, error),
+            }
+        }
+    }
+
+    fn fail(&mut self, path: String, file: PathBuf, error: TaskFileError) {
+        self.failures.push(OfflineFailure { path, file, error });
+    }
+}
+
+/// A task read from a tasks directory, with the task path it was registered at
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct OfflineTask {
+    path: String,
+    file: PathBuf,
+    task: Task,
+}
+
+impl OfflineTask {
+    /// The task path, like `\Microsoft\Windows\Defrag\ScheduledDefrag`
+    pub fn path(&self) -> &str {
+        &self.path
+    }
+
+    /// The folder the task is in, `\` for the root folder
+    pub fn folder(&self) -> &str {
+        match self.path.rfind('\\') {
+            Some(0) | None => "\\",
+            Some(index) => &self.path[..index],
+        }
+    }
+
+    pub fn name(&self) -> &str {
+        self.path.rsplit('\\').next().unwrap_or(&self.path)
+    }
+
+    /// The file the task was read from
+    pub fn file(&self) -> &Path {
+        &self.file
+    }
+
+    pub fn task(&self) -> &Task {
+        &self.task
+    }
+}
+
+/// A file or folder in a tasks directory that couldn't be read as a task
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct OfflineFailure {
+    path: String,
+    file: PathBuf,
+    error: TaskFileError,
+}
+
+impl OfflineFailure {
+    /// The task or folder path the file would have had
+    pub fn path(&self) -> &str {
+        &self.path
+    }
+
+    pub fn file(&self) -> &Path {
+        &self.file
+    }
+
+    pub fn error(&self) -> &TaskFileError {
+        &self.error
+    }
+}
+
+impl fmt::Display for OfflineFailure {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}: {}", self.path, self.error)
+    }
+}
+
+fn folder_path(folder: &str) -> String {
+    if folder.is_empty() {
+        "\\".to_string()
+    } else {
+        folder.to_string()
+    }
+}
+
+fn io_error(path: &Path, error: std::io::Error) -> TaskFileError {
+    TaskFileError::Io(format!("{}: {}", path.display(), error))
+}
+
+fn read_task(file: &Path) -> Result<Task, TaskFileError> {
+    let bytes = fs::read(file).map_err(|error| io_error(file, error))?;
+    let xml = decode(&bytes)?;
+    Ok(Task::from_xml(&xml)?)
+}
+
+/// Decodes task XML, which Task Scheduler writes as UTF-16 with a byte order mark.
+/// Files without one are UTF-16 if they start with `<` as UTF-16, and UTF-8 otherwise.
+fn decode(bytes: &[u8]) -> Result<String, TaskFileError> {
+    match bytes {
+        [0xFF, 0xFE, rest @ ..] => decode_utf16(rest, u16::from_le_bytes),
+        [0xFE, 0xFF, rest @ ..] => decode_utf16(rest, u16::from_be_bytes),
+        [0xEF, 0xBB, 0xBF, rest @ ..] => decode_utf8(rest),
+        [b'<', 0, ..] => decode_utf16(bytes, u16::from_le_bytes),
+        [0, b'<', ..] => decode_utf16(bytes, u16::from_be_bytes),
+        _ => decode_utf8(bytes),
+    }
+}
+
+fn decode_utf8(bytes: &[u8]) -> Result<String, TaskFileError> {
+    String::from_utf8(bytes.to_vec()).map_err(|error| TaskFileError::Encoding(error.to_string()))
+}
+
+fn decode_utf16(bytes: &[u8], from_bytes: fn([u8; 2]) -> u16) -> Result<String, TaskFileError> {
+    if bytes.len() % 2 != 0 {
+        return Err(TaskFileError::Encoding(
+            "UTF-16 with an odd number of bytes".to_string(),
+        ));
+    }
+    let characters: Vec<u16> = bytes
+        .chunks(2)
+        .map(|pair| from_bytes([pair[0], pair[1]]))
+        .collect();
+    String::from_utf16(&characters).map_err(|error| TaskFileError::Encoding(error.to_string()))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{env, process};
+
+    use chrono::NaiveDate;
+
+    use super::*;
+    use crate::{Action, Actions, DailyTrigger, ExecAction, TaskTriggersBuilder};
+
+    /// A directory under the temp directory that is removed when it is dropped
+    struct TempDir(PathBuf);
+
+    impl TempDir {
+        fn new(name: &str) -> Self {
+            let dir = env::temp_dir().join(format!("task_scheduler-{}-{}", name, process::id()));
+            let _ = fs::remove_dir_all(&dir);
+            fs::create_dir_all(&dir).unwrap();
+            TempDir(dir)
+        }
+
+        fn write(&self, path: &str, bytes: &[u8]) {
+            let file = self.0.join(path);
+            fs::create_dir_all(file.parent().unwrap()).unwrap();
+            fs::write(file, bytes).unwrap();
+        }
+    }
+
+    impl Drop for TempDir {
+        fn drop(&mut self) {
+            let _ = fs::remove_dir_all(&self.0);
+        }
+    }
+
+    fn task() -> Task {
+        let start = NaiveDate::from_ymd_opt(2021, 1, 1)
+            .unwrap()
+            .and_hms_opt(3, 0, 0)
+            .unwrap();
+        Task::new(
+            Actions::from_action(Action::Exec(ExecAction::new(PathBuf::from(
+                "C:\\Windows\\System32\\defrag.exe",
+            )))),
+            TaskTriggersBuilder::new()
+                .with_daily(
+                    DailyTrigger::new("Daily".to_string())
+                        .with_start_time(start)
+                        .with_interval(1),
+                )
+                .build(),
+        )
+    }
+
+    /// The XML as Task Scheduler writes it, UTF-16 little endian with a byte order mark
+    fn utf16_le(text: &str) -> Vec<u8> {
+        let mut bytes = vec![0xFF, 0xFE];
+        bytes.extend(text.encode_utf16().flat_map(u16::to_le_bytes));
+        bytes
+    }
+
+    #[test]
+    fn decodes_utf16_with_a_byte_order_mark() {
+        assert_eq!(decode(&utf16_le("<Task/>")).unwrap(), "<Task/>");
+        let mut big_endian = vec![0xFE, 0xFF];
+        big_endian.extend("<Task/>".encode_utf16().flat_map(u16::to_be_bytes));
+        assert_eq!(decode(&big_endian).unwrap(), "<Task/>");
+    }
+
+    #[test]
+    fn decodes_utf16_without_a_byte_order_mark() {
+        let little_endian: Vec<u8> = "<Task/>"
+            .encode_utf16()
+            .flat_map(u16::to_le_bytes)
+            .collect();
+        assert_eq!(decode(&little_endian).unwrap(), "<Task/>");
+        let big_endian: Vec<u8> = "<Task/>"
+            .encode_utf16()
+            .flat_map(u16::to_be_bytes)
+            .collect();
+        assert_eq!(decode(&big_endian).unwrap(), "<Task/>");
+    }
+
+    #[test]
+    fn decodes_utf8_with_and_without_a_byte_order_mark() {
+        assert_eq!(decode(b"\xEF\xBB\xBF<Task/>").unwrap(), "<Task/>");
+        assert_eq!(decode(b"<Task/>").unwrap(), "<Task/>");
+    }
+
+    #[test]
+    fn rejects_utf16_with_an_odd_number_of_bytes() {
+        assert_eq!(
+            decode(&[0xFF, 0xFE, b'<', 0, b'T']),
+            Err(TaskFileError::Encoding(
+                "UTF-16 with an odd number of bytes".to_string()
+            ))
+        );
+    }
+
+    #[test]
+    fn reads_the_task_paths_of_nested_folders() {
+        let dir = TempDir::new("nested");
+        let xml = utf16_le(&task().to_xml());
+        dir.write("Backup", &xml);
+        dir.write("Microsoft/Windows/Defrag/ScheduledDefrag", &xml);
+
+        let tasks = OfflineTasks::read_dir(&dir.0).unwrap();
+        assert!(tasks.failures().is_empty());
+        let paths: Vec<&str> = tasks.tasks().iter().map(OfflineTask::path).collect();
+        assert_eq!(
+            paths,
+            ["\\Backup", "\\Microsoft\\Windows\\Defrag\\ScheduledDefrag"]
+        );
+
+        let defrag = tasks
+            .task("\\microsoft\\windows\\defrag\\scheduleddefrag")
+            .unwrap();
+        assert_eq!(defrag.folder(), "\\Microsoft\\Windows\\Defrag");
+        assert_eq!(defrag.name(), "ScheduledDefrag");
+        assert_eq!(defrag.task(), &task());
+
+        let backup = tasks.task("\\Backup").unwrap();
+        assert_eq!(backup.folder(), "\\");
+        assert_eq!(backup.name(), "Backup");
+    }
+
+    #[test]
+    fn keeps_scanning_past_files_that_fail() {
+        let dir = TempDir::new("failures");
+        dir.write("A", b"not xml");
+        dir.write("B", &utf16_le(&task().to_xml()));
+        dir.write("Folder/C", &[0xFF, 0xFE, b'<']);
+        dir.write("Folder/D", &utf16_le(&task().to_xml()));
+
+        let tasks = OfflineTasks::read_dir(&dir.0).unwrap();
+        let paths: Vec<&str> = tasks.tasks().iter().map(OfflineTask::path).collect();
+        assert_eq!(paths, ["\\B", "\\Folder\\D"]);
+        let failures: Vec<&str> = tasks.failures().iter().map(OfflineFailure::path).collect();
+        assert_eq!(failures, ["\\A", "\\Folder\\C"]);
+        assert!(matches!(tasks.failures()[0].error(), TaskFileError::Xml(_)));
+        assert!(matches!(
+            tasks.failures()[1].error(),
+            TaskFileError::Encoding(_)
+        ));
+    }
+
+    #[test]
+    fn fails_on_a_directory_that_doesnt_exist() {
+        let dir = TempDir::new("missing");
+        assert!(matches!(
+            OfflineTasks::read_dir(&dir.0.join("missing")),
+            Err(TaskFileError::Io(_))
+        ));
+    }
+}
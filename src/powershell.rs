@@ -0,0 +1,1032 @@
+use chrono::Duration;
+
+use crate::{
+    calendar::weekday_name,
+    duration::to_xml_duration,
+    export::{Export, ExportFile, ExportWarning},
+    task::{
+        MultipleInstancesPolicy, RunLevel, Settings, Task, TaskLogon, TaskPrincipal,
+        TaskRegistration,
+    },
+    Action, Actions, Repetition, SchemaVersion, SessionStateChange, TaskTriggers, TriggerCommon,
+};
+
+/// The CIM namespace of the classes behind the ScheduledTasks cmdlets
+const NAMESPACE: &str = "Root/Microsoft/Windows/TaskScheduler";
+
+impl Task {
+    /// Writes the task as a PowerShell script that registers it at the path with
+    /// `Register-ScheduledTask`, in a `<name>.ps1` file named after the task.
+    ///
+    /// The triggers, actions, principal and settings are created with the
+    /// `New-ScheduledTask*` cmdlets. What the cmdlets have no parameter for, like an
+    /// end boundary or repetition on a daily trigger, is set on the CIM instances
+    /// they return, and triggers without a cmdlet, like event triggers, are created
+    /// as CIM instances. The cmdlets have no monthly triggers, so a task with one is
+    /// written to `<name>.xml` and registered with `-Xml`, and the warnings say why.
+    ///
+    /// A user with the `Password` logon type is asked for the password when the script runs.
+    ///
+    /// https://docs.microsoft.com/en-us/powershell/module/scheduledtasks/register-scheduledtask
+    pub fn to_powershell(&self, path: &str) -> Export {
+        let path = path.trim_start_matches(|c| c == '\\' || c == '/');
+        let (folder, name) = match path.rfind(|c| c == '\\' || c == '/') {
+            Some(index) => (&path[..index], &path[index + 1..]),
+            None => ("", path),
+        };
+        let name = if name.is_empty() { "task" } else { name };
+        let mut register = format!("Register-ScheduledTask -TaskName {}", quote(name));
+        if !folder.is_empty() {
+            let folder = folder.replace('/', "\\");
+            register.push_str(&format!(" -TaskPath {}", quote(&format!("\\{}\\", folder))));
+        }
+
+        let mut script = Script::default();
+        script.line("$ErrorActionPreference = 'Stop'");
+        script.line("");
+        let fallback = fallback_reasons(self);
+        let password = script.password(&self.principal);
+        let mut files = Vec::new();
+        let warnings = if fallback.is_empty() {
+            let triggers = script.triggers(&self.triggers);
+            let actions = script.actions(&self.actions);
+            let principal = script.principal(&self.principal);
+            script.settings(&self.settings);
+            script.task(&self.registration, &triggers, &actions, principal);
+            register.push_str(" -InputObject $task");
+            std::mem::take(&mut script.lost)
+        } else {
+            let xml_name = format!("{}.xml", name);
+            script.line(
+                "# The task is registered from its XML, which has what the cmdlets can't express",
+            );
+            script.line(&format!(
+                "$xml = Get-Content -LiteralPath (Join-Path $PSScriptRoot {}) -Raw",
+                quote(&xml_name)
+            ));
+            register.push_str(" -Xml $xml");
+            let mut warnings = fallback;
+            warnings.push(ExportWarning::new(
+                "Task",
+                format!("is registered from {} with -Xml", xml_name),
+            ));
+            files.push(ExportFile {
+                name: xml_name,
+                contents: self.to_xml(),
+            });
+            warnings
+        };
+        if password {
+            register.push_str(
+                " -User $credential.UserName -Password $credential.GetNetworkCredential().Password",
+            );
+        }
+        register.push_str(" -Force");
+        script.line("");
+        script.line(&register);
+
+        let mut contents = script.lines.join("\r\n");
+        contents.push_str("\r\n");
+        // Windows PowerShell reads a script without a byte order mark in the ANSI code page
+        if !contents.is_ascii() {
+            contents.insert(0, '\u{feff}');
+        }
+        files.insert(
+            0,
+            ExportFile {
+                name: format!("{}.ps1", name),
+                contents,
+            },
+        );
+        Export { files, warnings }
+    }
+}
+
+/// The parts of the task that only `-Xml` can register
+fn fallback_reasons(task: &Task) -> Vec<ExportWarning> {
+    let mut reasons = Vec::new();
+    if !task.triggers.monthly().is_empty() {
+        reasons.push(ExportWarning::new(
+            "MonthlyTrigger",
+            "New-ScheduledTaskTrigger has no monthly triggers",
+        ));
+    }
+    if !task.triggers.monthly_dow().is_empty() {
+        reasons.push(ExportWarning::new(
+            "MonthlyDowTrigger",
+            "New-ScheduledTaskTrigger has no monthly triggers",
+        ));
+    }
+    if task
+        .triggers
+        .weekly()
+        .iter()
+        .any(|trigger| trigger.days_of_week.is_empty())
+    {
+        reasons.push(ExportWarning::new(
+            "WeeklyTrigger",
+            "runs on no days of the week, -DaysOfWeek needs at least one",
+        ));
+    }
+    if task.actions.is_empty() {
+        reasons.push(ExportWarning::new(
+            "Actions",
+            "the task has no actions, New-ScheduledTask needs at least one",
+        ));
+    }
+    if task.schema_version() > SchemaVersion::V1_2 {
+        reasons.push(ExportWarning::new(
+            "Settings",
+            format!(
+                "the task needs schema {}, its newer settings are only registered from the XML",
+                task.schema_version()
+            ),
+        ));
+    }
+    reasons
+}
+
+/// The lines of a script, and what they can't express
+#[derive(Default)]
+struct Script {
+    lines: Vec<String>,
+    /// Parts of the task that have no parameter or property and are dropped
+    lost: Vec<ExportWarning>,
+    /// The comment on why the start boundary is set after `-At` has been written
+    start_explained: bool,
+}
+
+impl Script {
+    fn line(&mut self, line: &str) {
+        self.lines.push(line.to_string());
+    }
+
+    fn lost(&mut self, item: &str, reason: impl Into<String>) {
+        self.lost.push(ExportWarning::new(item, reason));
+    }
+
+    /// Asks for the password of a user with the `Password` logon type,
+    /// which `Register-ScheduledTask` needs
+    fn password(&mut self, principal: &TaskPrincipal) -> bool {
+        let user_id = match (&principal.user_id, principal.logon_type) {
+            (
+                Some(user_id),
+                Some(TaskLogon::Password) | Some(TaskLogon::InteractiveTokenOrPassword),
+            ) => user_id,
+            _ => return false,
+        };
+        self.line(&format!(
+            "$credential = Get-Credential -UserName {} -Message {}",
+            quote(user_id),
+            quote(&format!("The password of {} to run the task with", user_id))
+        ));
+        self.line("");
+        true
+    }
+
+    /// Writes the triggers and returns their variables
+    fn triggers(&mut self, triggers: &TaskTriggers) -> Vec<String> {
+        let mut variables = Vec::new();
+        for trigger in triggers.boot() {
+            let variable = self.next_trigger(&mut variables, triggers.len());
+            self.line(&format!(
+                "{} = New-ScheduledTaskTrigger -AtStartup",
+                variable
+            ));
+            self.untimed("BootTrigger", &variable, &trigger.common);
+            self.property(&variable, "Delay", trigger.delay.map(duration));
+        }
+        for trigger in triggers.registration() {
+            let variable = self.next_trigger(&mut variables, triggers.len());
+            self.cim_trigger(&variable, "MSFT_TaskRegistrationTrigger", "registration");
+            self.untimed("RegistrationTrigger", &variable, &trigger.common);
+            self.property(&variable, "Delay", trigger.delay.map(duration));
+        }
+        for trigger in triggers.idle() {
+            let variable = self.next_trigger(&mut variables, triggers.len());
+            self.cim_trigger(&variable, "MSFT_TaskIdleTrigger", "idle");
+            self.untimed("IdleTrigger", &variable, &trigger.common);
+            self.repetition(&variable, "idle", trigger.repetition.as_ref());
+        }
+        for trigger in triggers.logon() {
+            let variable = self.next_trigger(&mut variables, triggers.len());
+            let mut line = format!("{} = New-ScheduledTaskTrigger -AtLogOn", variable);
+            if let Some(user_id) = &trigger.user_id {
+                line.push_str(&format!(" -User {}", quote(user_id)));
+            }
+            self.line(&line);
+            self.untimed("LogonTrigger", &variable, &trigger.common);
+            self.property(&variable, "Delay", trigger.delay.map(duration));
+        }
+        for trigger in triggers.session_state_change() {
+            let variable = self.next_trigger(&mut variables, triggers.len());
+            self.cim_trigger(
+                &variable,
+                "MSFT_TaskSessionStateChangeTrigger",
+                "session state change",
+            );
+            self.untimed("SessionStateChangeTrigger", &variable, &trigger.common);
+            let (value, name) = session_state_change(trigger.state_change);
+            self.line(&format!("{}.StateChange = {} # {}", variable, value, name));
+            self.property(&variable, "UserId", trigger.user_id.as_deref().map(quote));
+            self.property(&variable, "Delay", trigger.delay.map(duration));
+        }
+        for trigger in triggers.event() {
+            let variable = self.next_trigger(&mut variables, triggers.len());
+            self.cim_trigger(&variable, "MSFT_TaskEventTrigger", "event");
+            self.untimed("EventTrigger", &variable, &trigger.common);
+            self.property(
+                &variable,
+                "Subscription",
+                Some(quote(&trigger.subscription)),
+            );
+            self.property(&variable, "Delay", trigger.delay.map(duration));
+            if !trigger.value_queries.is_empty() {
+                self.line(&format!("{}.ValueQueries = @(", variable));
+                for (name, query) in &trigger.value_queries {
+                    self.line(&format!(
+                        "    New-CimInstance -CimClass (Get-CimClass -Namespace {} -ClassName MSFT_TaskNamedValue) -ClientOnly -Property @{{ Name = {}; Value = {} }}",
+                        NAMESPACE,
+                        quote(name),
+                        quote(query)
+                    ));
+                }
+                self.line(")");
+            }
+            self.repetition(&variable, "event", trigger.repetition.as_ref());
+        }
+        for trigger in triggers.specific_times() {
+            let variable = self.next_trigger(&mut variables, triggers.len());
+            let mut line = format!(
+                "{} = New-ScheduledTaskTrigger -Once -At {}",
+                variable,
+                at(&trigger.common)
+            );
+            // only the trigger that runs once takes the repetition as parameters
+            if let Some(repetition) = &trigger.repetition {
+                line.push_str(&format!(
+                    " -RepetitionInterval {}",
+                    timespan(repetition.interval)
+                ));
+                if let Some(duration) = repetition.duration {
+                    line.push_str(&format!(" -RepetitionDuration {}", timespan(duration)));
+                }
+            }
+            self.timed(&variable, line, &trigger.common);
+            if trigger
+                .repetition
+                .as_ref()
+                .map_or(false, |repetition| repetition.stop_at_duration_end)
+            {
+                self.line(&format!(
+                    "{}.Repetition.StopAtDurationEnd = $true",
+                    variable
+                ));
+            }
+        }
+        for trigger in triggers.daily() {
+            let variable = self.next_trigger(&mut variables, triggers.len());
+            let mut line = format!(
+                "{} = New-ScheduledTaskTrigger -Daily -At {}",
+                variable,
+                at(&trigger.common)
+            );
+            if let Some(interval) = trigger.interval.filter(|interval| *interval > 1) {
+                line.push_str(&format!(" -DaysInterval {}", interval));
+            }
+            self.timed(&variable, line, &trigger.common);
+            self.repetition(&variable, "daily", trigger.repetition.as_ref());
+        }
+        for trigger in triggers.weekly() {
+            let variable = self.next_trigger(&mut variables, triggers.len());
+            let days: Vec<&str> = trigger.days_of_week.iter().map(weekday_name).collect();
+            let mut line = format!(
+                "{} = New-ScheduledTaskTrigger -Weekly -At {} -DaysOfWeek {}",
+                variable,
+                at(&trigger.common),
+                days.join(", ")
+            );
+            if let Some(interval) = trigger.weeks_interval.filter(|interval| *interval > 1) {
+                line.push_str(&format!(" -WeeksInterval {}", interval));
+            }
+            self.timed(&variable, line, &trigger.common);
+            self.repetition(&variable, "weekly", trigger.repetition.as_ref());
+        }
+        if !variables.is_empty() {
+            self.line("");
+        }
+        variables
+    }
+
+    /// The variable of the next trigger, triggers are numbered when there are several
+    fn next_trigger(&mut self, variables: &mut Vec<String>, count: usize) -> String {
+        if count == 1 {
+            variables.push("$trigger".to_string());
+        } else {
+            if !variables.is_empty() {
+                self.line("");
+            }
+            variables.push(format!("$trigger{}", variables.len() + 1));
+        }
+        variables[variables.len() - 1].clone()
+    }
+
+    /// Creates a trigger that has no cmdlet from its CIM class
+    fn cim_trigger(&mut self, variable: &str, class: &str, kind: &str) {
+        self.line(&format!(
+            "# New-ScheduledTaskTrigger has no {} trigger, so it is created from its CIM class",
+            kind
+        ));
+        self.line(&format!(
+            "{} = New-CimInstance -CimClass (Get-CimClass -Namespace {} -ClassName {}) -ClientOnly",
+            variable, NAMESPACE, class
+        ));
+    }
+
+    /// A trigger that runs at a time, which takes a random delay
+    fn timed(&mut self, variable: &str, mut line: String, common: &TriggerCommon) {
+        if let Some(random_delay) = common.random_delay {
+            line.push_str(&format!(" -RandomDelay {}", timespan(random_delay)));
+        }
+        self.line(&line);
+        if let Some(start) = common.start {
+            if !self.start_explained {
+                self.line("# -At adds the UTC offset of this computer, which would synchronize the start across time zones");
+                self.start_explained = true;
+            }
+            self.property(variable, "StartBoundary", Some(quote(&start.to_xml())));
+        }
+        self.common(variable, common);
+    }
+
+    /// A trigger that runs on an event, which only has a start and end when it is set
+    fn untimed(&mut self, item: &str, variable: &str, common: &TriggerCommon) {
+        if common.random_delay.is_some() {
+            self.lost(
+                item,
+                "only time and calendar triggers have a random delay, it is dropped",
+            );
+        }
+        if let Some(start) = common.start {
+            self.property(variable, "StartBoundary", Some(quote(&start.to_xml())));
+        }
+        self.common(variable, common);
+    }
+
+    /// The properties of every trigger that the cmdlet has no parameter for
+    fn common(&mut self, variable: &str, common: &TriggerCommon) {
+        self.property(variable, "Id", common.id.as_deref().map(quote));
+        self.property(
+            variable,
+            "EndBoundary",
+            common.end.map(|end| quote(&end.to_xml())),
+        );
+        self.property(
+            variable,
+            "ExecutionTimeLimit",
+            common.execution_time_limit.map(duration),
+        );
+        if !common.enabled {
+            self.property(variable, "Enabled", Some("$false".to_string()));
+        }
+    }
+
+    /// The repetition of a trigger whose cmdlet doesn't take one,
+    /// taken from a trigger that runs once
+    fn repetition(&mut self, variable: &str, kind: &str, repetition: Option<&Repetition>) {
+        let repetition = match repetition {
+            Some(repetition) => repetition,
+            None => return,
+        };
+        let mut donor = format!(
+            "New-ScheduledTaskTrigger -Once -At '00:00' -RepetitionInterval {}",
+            timespan(repetition.interval)
+        );
+        if let Some(duration) = repetition.duration {
+            donor.push_str(&format!(" -RepetitionDuration {}", timespan(duration)));
+        }
+        self.line(&format!(
+            "# The {} trigger has no repetition parameters, so it gets the repetition of a trigger that runs once",
+            kind
+        ));
+        self.line(&format!("{}.Repetition = ({}).Repetition", variable, donor));
+        if repetition.stop_at_duration_end {
+            self.line(&format!(
+                "{}.Repetition.StopAtDurationEnd = $true",
+                variable
+            ));
+        }
+    }
+
+    fn property(&mut self, variable: &str, property: &str, value: Option<String>) {
+        if let Some(value) = value {
+            self.line(&format!("{}.{} = {}", variable, property, value));
+        }
+    }
+
+    /// Writes the actions and returns their variables
+    fn actions(&mut self, actions: &Actions) -> Vec<String> {
+        let mut variables = Vec::new();
+        for action in actions.iter() {
+            let variable = if actions.len() == 1 {
+                "$action".to_string()
+            } else {
+                format!("$action{}", variables.len() + 1)
+            };
+            match action {
+                Action::Exec(action) => {
+                    let mut line = format!(
+                        "{} = New-ScheduledTaskAction -Execute {}",
+                        variable,
+                        quote(&action.path.to_string_lossy())
+                    );
+                    if let Some(arguments) = action
+                        .arguments
+                        .as_deref()
+                        .filter(|arguments| !arguments.is_empty())
+                    {
+                        line.push_str(&format!(" -Argument {}", quote(arguments)));
+                    }
+                    if let Some(working_directory) = &action.working_directory {
+                        line.push_str(&format!(
+                            " -WorkingDirectory {}",
+                            quote(&working_directory.to_string_lossy())
+                        ));
+                    }
+                    if let Some(id) = &action.id {
+                        line.push_str(&format!(" -Id {}", quote(id)));
+                    }
+                    self.line(&line);
+                }
+                Action::ComHandler(handler) => {
+                    self.line("# New-ScheduledTaskAction only runs programs, so the COM handler is created from its CIM class");
+                    self.line(&format!(
+                        "{} = New-CimInstance -CimClass (Get-CimClass -Namespace {} -ClassName MSFT_TaskComHandlerAction) -ClientOnly",
+                        variable, NAMESPACE
+                    ));
+                    self.property(&variable, "ClassId", Some(quote(&handler.class_id)));
+                    self.property(&variable, "Data", handler.data.as_deref().map(quote));
+                    self.property(&variable, "Id", handler.id.as_deref().map(quote));
+                }
+            }
+            variables.push(variable);
+        }
+        self.line("");
+        variables
+    }
+
+    /// Writes the principal, the task runs as the user who registers it when there is none
+    fn principal(&mut self, principal: &TaskPrincipal) -> bool {
+        if principal.id != "Author" {
+            self.lost(
+                "Principal",
+                format!(
+                    "New-ScheduledTask uses Author for the context of the actions, the id {} is dropped",
+                    principal.id
+                ),
+            );
+        }
+        let mut line = match (&principal.group_id, &principal.user_id) {
+            (Some(group_id), _) => format!(
+                "$principal = New-ScheduledTaskPrincipal -GroupId {}",
+                quote(group_id)
+            ),
+            (None, Some(user_id)) => format!(
+                "$principal = New-ScheduledTaskPrincipal -UserId {}",
+                quote(user_id)
+            ),
+            (None, None)
+                if principal.logon_type.is_none()
+                    && principal.run_level == RunLevel::LeastPrivilege =>
+            {
+                return false
+            }
+            (None, None) => {
+                "$principal = New-ScheduledTaskPrincipal -UserId \"$env:USERDOMAIN\\$env:USERNAME\""
+                    .to_string()
+            }
+        };
+        if principal.group_id.is_none() {
+            let logon_type = match principal.logon_type {
+                Some(TaskLogon::None) => Some("None"),
+                Some(TaskLogon::Password) | Some(TaskLogon::InteractiveTokenOrPassword) => {
+                    Some("Password")
+                }
+                Some(TaskLogon::S4U) => Some("S4U"),
+                Some(TaskLogon::InteractiveToken) => Some("Interactive"),
+                Some(TaskLogon::Group) => Some("Group"),
+                Some(TaskLogon::ServiceAccount) => Some("ServiceAccount"),
+                None => None,
+            };
+            if let Some(logon_type) = logon_type {
+                line.push_str(&format!(" -LogonType {}", logon_type));
+            }
+        }
+        if principal.run_level == RunLevel::HighestAvailable {
+            line.push_str(" -RunLevel Highest");
+        }
+        self.line(&line);
+        true
+    }
+
+    /// Writes the settings with the parameters for everything that isn't the default
+    fn settings(&mut self, settings: &Settings) {
+        let default = Settings::default();
+        let idle = &settings.idle_conditions;
+        let switches = [
+            ("-DisallowDemandStart", !settings.allow_demand_start),
+            ("-DisallowHardTerminate", !settings.allow_hard_terminate),
+            (
+                "-AllowStartIfOnBatteries",
+                !settings.disallow_start_if_on_batteries,
+            ),
+            (
+                "-DontStopIfGoingOnBatteries",
+                !settings.stop_if_going_on_batteries,
+            ),
+            ("-Disable", !settings.enabled),
+            ("-Hidden", settings.hidden),
+            ("-StartWhenAvailable", settings.start_when_available),
+            ("-RunOnlyIfIdle", settings.run_only_if_idle),
+            (
+                "-RunOnlyIfNetworkAvailable",
+                settings.run_only_if_network_available,
+            ),
+            ("-WakeToRun", settings.wake_to_run),
+            ("-DontStopOnIdleEnd", !idle.stop_on_idle_end),
+            ("-RestartOnIdle", idle.restart_on_idle),
+        ];
+        let mut line = "$settings = New-ScheduledTaskSettingsSet".to_string();
+        for (switch, set) in switches.iter() {
+            if *set {
+                line.push(' ');
+                line.push_str(switch);
+            }
+        }
+        if settings.execution_time_limit != default.execution_time_limit {
+            // a zero time span lets the task run indefinitely
+            let limit = settings.execution_time_limit.unwrap_or_else(Duration::zero);
+            line.push_str(&format!(" -ExecutionTimeLimit {}", timespan(limit)));
+        }
+        if let Some(after) = settings.delete_expired_task_after {
+            line.push_str(&format!(" -DeleteExpiredTaskAfter {}", timespan(after)));
+        }
+        let multiple_instances = match settings.multiple_instances {
+            MultipleInstancesPolicy::Parallel => Some("Parallel"),
+            MultipleInstancesPolicy::Queue => Some("Queue"),
+            MultipleInstancesPolicy::IgnoreNew | MultipleInstancesPolicy::StopExisting => None,
+        };
+        if let Some(multiple_instances) = multiple_instances {
+            line.push_str(&format!(" -MultipleInstances {}", multiple_instances));
+        }
+        if settings.priority != default.priority {
+            line.push_str(&format!(" -Priority {}", settings.priority));
+        }
+        if let Some(restart) = &settings.restart_on_failure {
+            line.push_str(&format!(
+                " -RestartCount {} -RestartInterval {}",
+                restart.count,
+                timespan(restart.interval)
+            ));
+        }
+        if idle.duration != default.idle_conditions.duration {
+            line.push_str(&format!(" -IdleDuration {}", timespan(idle.duration)));
+        }
+        if idle.wait_timeout != default.idle_conditions.wait_timeout {
+            line.push_str(&format!(
+                " -IdleWaitTimeout {}",
+                timespan(idle.wait_timeout)
+            ));
+        }
+        self.line(&line);
+        if settings.multiple_instances == MultipleInstancesPolicy::StopExisting {
+            self.line("# -MultipleInstances has no StopExisting, so it is set by its value");
+            self.line("$settings.CimInstanceProperties.Item('MultipleInstances').Value = 3");
+        }
+    }
+
+    /// Puts the task together and sets the registration info that has no parameter
+    fn task(
+        &mut self,
+        registration: &TaskRegistration,
+        triggers: &[String],
+        actions: &[String],
+        principal: bool,
+    ) {
+        let mut line = format!("$task = New-ScheduledTask -Action {}", actions.join(", "));
+        if !triggers.is_empty() {
+            line.push_str(&format!(" -Trigger {}", triggers.join(", ")));
+        }
+        if principal {
+            line.push_str(" -Principal $principal");
+        }
+        line.push_str(" -Settings $settings");
+        if let Some(description) = &registration.description {
+            line.push_str(&format!(" -Description {}", quote(description)));
+        }
+        self.line("");
+        self.line(&line);
+        self.property("$task", "Author", registration.author.as_deref().map(quote));
+        self.property(
+            "$task",
+            "Documentation",
+            registration.documentation.as_deref().map(quote),
+        );
+        self.property("$task", "Source", registration.source.as_deref().map(quote));
+        self.property(
+            "$task",
+            "Version",
+            registration.version.as_deref().map(quote),
+        );
+    }
+}
+
+/// The time for `-At`, the trigger starts when the script runs when it has no start
+fn at(common: &TriggerCommon) -> String {
+    match common.start {
+        Some(start) => quote(&start.clock_time().format("%Y-%m-%dT%H:%M:%S").to_string()),
+        None => "(Get-Date)".to_string(),
+    }
+}
+
+/// The value of `TASK_SESSION_STATE_CHANGE_TYPE` for the CIM property, and its name
+fn session_state_change(state_change: SessionStateChange) -> (u8, &'static str) {
+    match state_change {
+        SessionStateChange::ConsoleConnect => (1, "ConsoleConnect"),
+        SessionStateChange::ConsoleDisconnect => (2, "ConsoleDisconnect"),
+        SessionStateChange::RemoteConnect => (3, "RemoteConnect"),
+        SessionStateChange::RemoteDisconnect => (4, "RemoteDisconnect"),
+        SessionStateChange::SessionLock => (7, "SessionLock"),
+        SessionStateChange::SessionUnlock => (8, "SessionUnlock"),
+    }
+}
+
+/// A duration for a CIM property, which takes the XML format
+fn duration(duration: Duration) -> String {
+    quote(&to_xml_duration(&duration))
+}
+
+/// A duration for a cmdlet parameter, which takes a `TimeSpan`
+fn timespan(duration: Duration) -> String {
+    let seconds = duration.num_seconds();
+    let parts = [
+        ("Days", seconds / 86_400),
+        ("Hours", seconds % 86_400 / 3600),
+        ("Minutes", seconds % 3600 / 60),
+        ("Seconds", seconds % 60),
+    ];
+    let parts: Vec<String> = parts
+        .iter()
+        .filter(|(_, value)| *value != 0)
+        .map(|(unit, value)| format!("-{} {}", unit, value))
+        .collect();
+    if parts.is_empty() {
+        return "([TimeSpan]::Zero)".to_string();
+    }
+    format!("(New-TimeSpan {})", parts.join(" "))
+}
+
+/// Quotes text as a single quoted string, where nothing is expanded.
+/// PowerShell also ends these strings at the typographic single quotes,
+/// so those are doubled as well.
+fn quote(text: &str) -> String {
+    let mut quoted = String::with_capacity(text.len() + 2);
+    quoted.push('\'');
+    for c in text.chars() {
+        if matches!(c, '\'' | '\u{2018}' | '\u{2019}' | '\u{201A}' | '\u{201B}') {
+            quoted.push(c);
+        }
+        quoted.push(c);
+    }
+    quoted.push('\'');
+    quoted
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::PathBuf;
+
+    use chrono::NaiveDate;
+
+    use super::*;
+    use crate::{
+        task::RestartOnFailure, DailyTrigger, DaysOfMonth, DaysOfWeek, EventTrigger, ExecAction,
+        LogonTrigger, MonthlyTrigger, Months, SpecificTimeTrigger, TaskTriggersBuilder, Weekday,
+        WeeklyTrigger,
+    };
+
+    fn start() -> chrono::NaiveDateTime {
+        NaiveDate::from_ymd_opt(2021, 1, 4)
+            .unwrap()
+            .and_hms_opt(9, 30, 0)
+            .unwrap()
+    }
+
+    fn exec(path: &str, arguments: &str) -> Actions {
+        Actions::from_action(Action::Exec(
+            ExecAction::new(PathBuf::from(path)).with_arguments(arguments.to_string()),
+        ))
+    }
+
+    fn task(actions: Actions, triggers: TaskTriggersBuilder) -> Task {
+        Task::new(actions, triggers.build())
+    }
+
+    fn weekly() -> TaskTriggersBuilder {
+        TaskTriggersBuilder::new().with_weekly(
+            WeeklyTrigger::new(DaysOfWeek::new().with(Weekday::Mon).with(Weekday::Fri))
+                .with_start_time(start()),
+        )
+    }
+
+    fn lines(export: &Export, file: &str) -> Vec<String> {
+        let script = export.file(file).unwrap();
+        assert!(script.ends_with("\r\n"));
+        script.lines().map(str::to_string).collect()
+    }
+
+    /// Reads the single quoted string at the start of the text the way PowerShell does,
+    /// with the rest of the text
+    fn unquote(text: &str) -> (String, &str) {
+        let is_quote =
+            |c: char| matches!(c, '\'' | '\u{2018}' | '\u{2019}' | '\u{201A}' | '\u{201B}');
+        let mut chars = text.char_indices().peekable();
+        assert!(chars.next().map_or(false, |(_, c)| is_quote(c)), "{}", text);
+        let mut value = String::new();
+        while let Some((index, c)) = chars.next() {
+            if !is_quote(c) {
+                value.push(c);
+            } else if chars.peek().map_or(false, |(_, next)| is_quote(*next)) {
+                value.push(chars.next().unwrap().1);
+            } else {
+                return (value, &text[index + c.len_utf8()..]);
+            }
+        }
+        panic!("the string isn't closed: {}", text);
+    }
+
+    /// The value of the parameter in the first line of the script that has it
+    fn parameter(script: &str, name: &str) -> String {
+        let name = format!(" -{} ", name);
+        let start = script.find(&name).unwrap_or_else(|| panic!("no {}", name));
+        unquote(&script[start + name.len()..]).0
+    }
+
+    #[test]
+    fn writes_a_script_that_registers_the_task() {
+        let task = task(
+            exec("C:\\Program Files\\Backup\\backup.exe", "--all"),
+            weekly().with_logon(
+                LogonTrigger::new()
+                    .with_user_id("DESKTOP-1\\admin".to_string())
+                    .with_delay(Duration::minutes(1)),
+            ),
+        );
+        let export = task.to_powershell("\\Backup\\Nightly");
+        assert_eq!(
+            lines(&export, "Nightly.ps1"),
+            [
+                "$ErrorActionPreference = 'Stop'",
+                "",
+                "$trigger1 = New-ScheduledTaskTrigger -AtLogOn -User 'DESKTOP-1\\admin'",
+                "$trigger1.Delay = 'PT1M'",
+                "",
+                "$trigger2 = New-ScheduledTaskTrigger -Weekly -At '2021-01-04T09:30:00' -DaysOfWeek Monday, Friday",
+                "# -At adds the UTC offset of this computer, which would synchronize the start across time zones",
+                "$trigger2.StartBoundary = '2021-01-04T09:30:00'",
+                "",
+                "$action = New-ScheduledTaskAction -Execute 'C:\\Program Files\\Backup\\backup.exe' -Argument '--all'",
+                "",
+                "$settings = New-ScheduledTaskSettingsSet",
+                "",
+                "$task = New-ScheduledTask -Action $action -Trigger $trigger1, $trigger2 -Settings $settings",
+                "",
+                "Register-ScheduledTask -TaskName 'Nightly' -TaskPath '\\Backup\\' -InputObject $task -Force",
+            ]
+        );
+        assert_eq!(export.files().len(), 1);
+        assert!(export.warnings().is_empty());
+    }
+
+    #[test]
+    fn round_trips_quotes_and_dollars_in_arguments() {
+        for arguments in [
+            "--to '$HOME'",
+            "-Command \"$env:PATH\" $(Get-Date)",
+            "it's `n and ''",
+            "\u{2018}smart\u{2019} \u{201A}low\u{201B} quotes",
+            "'",
+        ] {
+            let export =
+                task(exec("C:\\$Tools\\o'brien.exe", arguments), weekly()).to_powershell("Backup");
+            let script = export.file("Backup.ps1").unwrap();
+            assert_eq!(parameter(script, "Argument"), arguments);
+            assert_eq!(parameter(script, "Execute"), "C:\\$Tools\\o'brien.exe");
+        }
+    }
+
+    #[test]
+    fn round_trips_quotes_and_dollars_in_the_task_path() {
+        let export = task(exec("backup.exe", "--all"), weekly())
+            .to_powershell("/O'Brien's $Tasks/Nightly '$x'");
+        let script = export.file("Nightly '$x'.ps1").unwrap();
+        assert_eq!(parameter(script, "TaskName"), "Nightly '$x'");
+        assert_eq!(parameter(script, "TaskPath"), "\\O'Brien's $Tasks\\");
+    }
+
+    #[test]
+    fn sets_what_the_trigger_cmdlet_has_no_parameter_for() {
+        let daily = DailyTrigger::new("Nightly".to_string())
+            .with_start_time(start())
+            .with_interval(2)
+            .with_repetition(
+                Repetition::new(Duration::hours(1))
+                    .with_duration(Duration::hours(12))
+                    .stop_at_duration_end(),
+            )
+            .with_common(|common| {
+                common
+                    .with_end(start() + Duration::days(30))
+                    .with_random_delay(Duration::minutes(90))
+                    .with_enabled(false)
+            });
+        let export = task(
+            exec("backup.exe", ""),
+            TaskTriggersBuilder::new().with_daily(daily),
+        )
+        .to_powershell("Backup");
+        let lines = lines(&export, "Backup.ps1");
+        assert_eq!(
+            lines[2..10],
+            [
+                "$trigger = New-ScheduledTaskTrigger -Daily -At '2021-01-04T09:30:00' -DaysInterval 2 -RandomDelay (New-TimeSpan -Hours 1 -Minutes 30)",
+                "# -At adds the UTC offset of this computer, which would synchronize the start across time zones",
+                "$trigger.StartBoundary = '2021-01-04T09:30:00'",
+                "$trigger.Id = 'Nightly'",
+                "$trigger.EndBoundary = '2021-02-03T09:30:00'",
+                "$trigger.Enabled = $false",
+                "# The daily trigger has no repetition parameters, so it gets the repetition of a trigger that runs once",
+                "$trigger.Repetition = (New-ScheduledTaskTrigger -Once -At '00:00' -RepetitionInterval (New-TimeSpan -Hours 1) -RepetitionDuration (New-TimeSpan -Hours 12)).Repetition",
+            ]
+        );
+        assert_eq!(lines[10], "$trigger.Repetition.StopAtDurationEnd = $true");
+        // an empty argument isn't written
+        assert_eq!(
+            lines[12],
+            "$action = New-ScheduledTaskAction -Execute 'backup.exe'"
+        );
+    }
+
+    #[test]
+    fn writes_repetition_as_parameters_of_a_trigger_that_runs_once() {
+        let once = SpecificTimeTrigger::new("Once".to_string(), start()).with_repetition(
+            Repetition::new(Duration::minutes(15)).with_duration(Duration::days(1)),
+        );
+        let export = task(
+            exec("backup.exe", ""),
+            TaskTriggersBuilder::new().with_specific_time(once),
+        )
+        .to_powershell("Backup");
+        assert_eq!(
+            lines(&export, "Backup.ps1")[2],
+            "$trigger = New-ScheduledTaskTrigger -Once -At '2021-01-04T09:30:00' -RepetitionInterval (New-TimeSpan -Minutes 15) -RepetitionDuration (New-TimeSpan -Days 1)"
+        );
+    }
+
+    #[test]
+    fn creates_triggers_without_a_cmdlet_from_their_cim_class() {
+        let event = EventTrigger::new("<QueryList><Query Id='0'/></QueryList>".to_string())
+            .with_value_query("Id".to_string(), "Event/System/EventID".to_string())
+            .with_common(|common| common.with_random_delay(Duration::minutes(5)));
+        let export = task(
+            exec("backup.exe", ""),
+            TaskTriggersBuilder::new().with_event(event),
+        )
+        .to_powershell("Backup");
+        let lines = lines(&export, "Backup.ps1");
+        assert_eq!(
+            lines[2..8],
+            [
+                "# New-ScheduledTaskTrigger has no event trigger, so it is created from its CIM class",
+                "$trigger = New-CimInstance -CimClass (Get-CimClass -Namespace Root/Microsoft/Windows/TaskScheduler -ClassName MSFT_TaskEventTrigger) -ClientOnly",
+                "$trigger.Subscription = '<QueryList><Query Id=''0''/></QueryList>'",
+                "$trigger.ValueQueries = @(",
+                "    New-CimInstance -CimClass (Get-CimClass -Namespace Root/Microsoft/Windows/TaskScheduler -ClassName MSFT_TaskNamedValue) -ClientOnly -Property @{ Name = 'Id'; Value = 'Event/System/EventID' }",
+                ")",
+            ]
+        );
+        assert_eq!(
+            export.warnings(),
+            [ExportWarning::new(
+                "EventTrigger",
+                "only time and calendar triggers have a random delay, it is dropped"
+            )]
+        );
+    }
+
+    #[test]
+    fn writes_the_principal_and_settings() {
+        let task = task(exec("backup.exe", ""), weekly())
+            .with_principal(
+                TaskPrincipal::new()
+                    .with_user_id("SYSTEM".to_string())
+                    .with_logon_type(TaskLogon::ServiceAccount)
+                    .with_run_level(RunLevel::HighestAvailable),
+            )
+            .with_settings(
+                Settings::new()
+                    .with_hidden(true)
+                    .with_start_when_available(true)
+                    .with_execution_time_limit(None)
+                    .with_priority(4)
+                    .with_restart_on_failure(RestartOnFailure::new(Duration::minutes(5), 3))
+                    .with_multiple_instances(MultipleInstancesPolicy::StopExisting),
+            );
+        let export = task.to_powershell("Backup");
+        let script = export.file("Backup.ps1").unwrap();
+        assert!(script.contains(
+            "$principal = New-ScheduledTaskPrincipal -UserId 'SYSTEM' -LogonType ServiceAccount -RunLevel Highest\r\n"
+        ));
+        assert!(script.contains(
+            "$settings = New-ScheduledTaskSettingsSet -Hidden -StartWhenAvailable -ExecutionTimeLimit ([TimeSpan]::Zero) -Priority 4 -RestartCount 3 -RestartInterval (New-TimeSpan -Minutes 5)\r\n\
+             # -MultipleInstances has no StopExisting, so it is set by its value\r\n\
+             $settings.CimInstanceProperties.Item('MultipleInstances').Value = 3\r\n"
+        ));
+        assert!(script.contains(
+            "$task = New-ScheduledTask -Action $action -Trigger $trigger -Principal $principal -Settings $settings\r\n"
+        ));
+    }
+
+    #[test]
+    fn asks_for_the_password_of_the_user() {
+        let task = task(exec("backup.exe", ""), weekly()).with_principal(
+            TaskPrincipal::new()
+                .with_user_id("DESKTOP-1\\o'brien".to_string())
+                .with_logon_type(TaskLogon::Password),
+        );
+        let export = task.to_powershell("Backup");
+        let lines = lines(&export, "Backup.ps1");
+        assert_eq!(
+            lines[2],
+            "$credential = Get-Credential -UserName 'DESKTOP-1\\o''brien' -Message 'The password of DESKTOP-1\\o''brien to run the task with'"
+        );
+        assert_eq!(
+            lines.last().unwrap(),
+            "Register-ScheduledTask -TaskName 'Backup' -InputObject $task -User $credential.UserName -Password $credential.GetNetworkCredential().Password -Force"
+        );
+    }
+
+    #[test]
+    fn registers_monthly_tasks_from_their_xml() {
+        let monthly =
+            MonthlyTrigger::new(DaysOfMonth::new().with(1), Months::all()).with_start_time(start());
+        let task = task(
+            exec("backup.exe", "--all"),
+            TaskTriggersBuilder::new().with_monthly(monthly),
+        );
+        let export = task.to_powershell("\\Backup\\Monthly");
+        assert_eq!(
+            lines(&export, "Monthly.ps1"),
+            [
+                "$ErrorActionPreference = 'Stop'",
+                "",
+                "# The task is registered from its XML, which has what the cmdlets can't express",
+                "$xml = Get-Content -LiteralPath (Join-Path $PSScriptRoot 'Monthly.xml') -Raw",
+                "",
+                "Register-ScheduledTask -TaskName 'Monthly' -TaskPath '\\Backup\\' -Xml $xml -Force",
+            ]
+        );
+        assert_eq!(export.file("Monthly.xml"), Some(task.to_xml().as_str()));
+        assert_eq!(
+            export.warnings(),
+            [
+                ExportWarning::new(
+                    "MonthlyTrigger",
+                    "New-ScheduledTaskTrigger has no monthly triggers"
+                ),
+                ExportWarning::new("Task", "is registered from Monthly.xml with -Xml"),
+            ]
+        );
+    }
+
+    #[test]
+    fn writes_a_byte_order_mark_when_the_script_isnt_ascii() {
+        let export = task(exec("backup.exe", "--to Ünterordner"), weekly()).to_powershell("Backup");
+        assert!(export.file("Backup.ps1").unwrap().starts_with('\u{feff}'));
+        let export = task(exec("backup.exe", "--all"), weekly()).to_powershell("Backup");
+        assert!(export.file("Backup.ps1").unwrap().starts_with('$'));
+    }
+
+    #[test]
+    fn writes_durations_as_time_spans() {
+        assert_eq!(timespan(Duration::zero()), "([TimeSpan]::Zero)");
+        assert_eq!(
+            timespan(Duration::seconds(90)),
+            "(New-TimeSpan -Minutes 1 -Seconds 30)"
+        );
+        assert_eq!(
+            timespan(Duration::days(1) + Duration::hours(2)),
+            "(New-TimeSpan -Days 1 -Hours 2)"
+        );
+    }
+}
@@ -0,0 +1,310 @@
+use std::{convert::TryFrom, ops::BitOr};
+
+use bindings::Windows::Win32::{
+    Automation::BSTR,
+    TaskScheduler::{IRegisteredTask, IRegisteredTaskCollection, TASK_RUN_FLAGS, TASK_STATE},
+};
+use chrono::{DateTime, Duration, Local, NaiveDate, TimeZone, Utc};
+
+use crate::{
+    running_task::{collect_running_tasks, RunningTask},
+    task_definition::TaskDefinition,
+    task_result::TaskResult,
+    task_state::TaskState,
+    variant::Variant,
+    VARIANT_FALSE, VARIANT_TRUE,
+};
+
+/// A task that has been registered with Task Scheduler.
+/// This is returned when a task is registered or when it is looked up by its path.
+///
+/// The COM library has to stay initialized on the calling thread for as long
+/// as this is used, so keep a [`Com`](crate::Com) alive alongside it.
+///
+/// https://docs.microsoft.com/en-us/windows/win32/api/taskschd/nn-taskschd-iregisteredtask
+pub struct RegisteredTask(pub(crate) IRegisteredTask);
+
+impl RegisteredTask {
+    pub(crate) fn new(registered_task: IRegisteredTask) -> Self {
+        Self(registered_task)
+    }
+
+    /// Gets the name of the registered task.
+    ///
+    /// https://docs.microsoft.com/en-us/windows/win32/api/taskschd/nf-taskschd-iregisteredtask-get_name
+    pub fn name(&self) -> Result<String, windows::Error> {
+        let mut name = BSTR::default();
+        unsafe {
+            self.0.get_Name(&mut name).ok()?;
+        }
+        Ok(name.to_string())
+    }
+
+    /// Gets the path to where the registered task is stored, e.g. `\MyFolder\MyTask`
+    ///
+    /// https://docs.microsoft.com/en-us/windows/win32/api/taskschd/nf-taskschd-iregisteredtask-get_path
+    pub fn path(&self) -> Result<String, windows::Error> {
+        let mut path = BSTR::default();
+        unsafe {
+            self.0.get_Path(&mut path).ok()?;
+        }
+        Ok(path.to_string())
+    }
+
+    /// Gets the operational state of the registered task.
+    ///
+    /// https://docs.microsoft.com/en-us/windows/win32/api/taskschd/nf-taskschd-iregisteredtask-get_state
+    pub fn state(&self) -> Result<TaskState, windows::Error> {
+        let mut state = TASK_STATE::default();
+        unsafe {
+            self.0.get_State(&mut state).ok()?;
+        }
+        Ok(TaskState::from(state.0))
+    }
+
+    /// Gets a value that indicates if the registered task is enabled.
+    ///
+    /// https://docs.microsoft.com/en-us/windows/win32/api/taskschd/nf-taskschd-iregisteredtask-get_enabled
+    pub fn enabled(&self) -> Result<bool, windows::Error> {
+        let mut enabled = VARIANT_FALSE;
+        unsafe {
+            self.0.get_Enabled(&mut enabled).ok()?;
+        }
+        Ok(enabled != VARIANT_FALSE)
+    }
+
+    /// Enables or disables the registered task.
+    ///
+    /// https://docs.microsoft.com/en-us/windows/win32/api/taskschd/nf-taskschd-iregisteredtask-put_enabled
+    pub fn set_enabled(&self, enabled: bool) -> Result<(), windows::Error> {
+        let enabled = if enabled { VARIANT_TRUE } else { VARIANT_FALSE };
+        unsafe { self.0.put_Enabled(enabled).ok() }
+    }
+
+    /// Gets the time the registered task was last run.
+    /// Returns `None` if the task has never run.
+    ///
+    /// https://docs.microsoft.com/en-us/windows/win32/api/taskschd/nf-taskschd-iregisteredtask-get_lastruntime
+    pub fn last_run_time(&self) -> Result<Option<DateTime<Utc>>, windows::Error> {
+        let mut last_run_time = 0.0;
+        unsafe {
+            self.0.get_LastRunTime(&mut last_run_time).ok()?;
+        }
+        Ok(from_ole_date(last_run_time))
+    }
+
+    /// Gets the results that were returned the last time the registered task was run.
+    ///
+    /// https://docs.microsoft.com/en-us/windows/win32/api/taskschd/nf-taskschd-iregisteredtask-get_lasttaskresult
+    pub fn last_task_result(&self) -> Result<TaskResult, windows::Error> {
+        let mut last_task_result = 0;
+        unsafe {
+            self.0.get_LastTaskResult(&mut last_task_result).ok()?;
+        }
+        Ok(TaskResult::from(last_task_result))
+    }
+
+    /// Gets the time when the registered task is next scheduled to run.
+    /// Returns `None` if the task is not scheduled to run again.
+    ///
+    /// https://docs.microsoft.com/en-us/windows/win32/api/taskschd/nf-taskschd-iregisteredtask-get_nextruntime
+    pub fn next_run_time(&self) -> Result<Option<DateTime<Utc>>, windows::Error> {
+        let mut next_run_time = 0.0;
+        unsafe {
+            self.0.get_NextRunTime(&mut next_run_time).ok()?;
+        }
+        Ok(from_ole_date(next_run_time))
+    }
+
+    /// Gets the number of times the registered task has missed a scheduled run.
+    ///
+    /// https://docs.microsoft.com/en-us/windows/win32/api/taskschd/nf-taskschd-iregisteredtask-get_numberofmissedruns
+    pub fn number_of_missed_runs(&self) -> Result<u32, windows::Error> {
+        let mut missed_runs = 0;
+        unsafe {
+            self.0.get_NumberOfMissedRuns(&mut missed_runs).ok()?;
+        }
+        Ok(u32::try_from(missed_runs).unwrap_or(0))
+    }
+
+    /// Gets the definition of the task.
+    ///
+    /// https://docs.microsoft.com/en-us/windows/win32/api/taskschd/nf-taskschd-iregisteredtask-get_definition
+    pub fn definition(&self) -> Result<TaskDefinition, windows::Error> {
+        let mut definition = None;
+        unsafe {
+            let res = self.0.get_Definition(&mut definition).ok();
+            match res {
+                Ok(_) => Ok(TaskDefinition::new(definition.unwrap())),
+                Err(error) => Err(error),
+            }
+        }
+    }
+
+    /// Runs the registered task immediately.
+    ///
+    /// The parameters replace the `$(Arg0)` through `$(Arg32)` variables in the
+    /// task actions, in the order they are given.
+    ///
+    /// https://docs.microsoft.com/en-us/windows/win32/api/taskschd/nf-taskschd-iregisteredtask-run
+    pub fn run(&self, parameters: &[&str]) -> Result<RunningTask, windows::Error> {
+        let parameters = Variant::from_parameters(parameters)?;
+        let mut running_task = None;
+        unsafe {
+            let res = self.0.Run(&parameters.0, &mut running_task).ok();
+            match res {
+                Ok(_) => Ok(RunningTask::new(running_task.unwrap())),
+                Err(error) => Err(error),
+            }
+        }
+    }
+
+    /// Runs the registered task immediately using the specified flags and
+    /// optionally in a specific terminal server session or as a specific user.
+    ///
+    /// If a session id is given then [`RunFlags::USE_SESSION_ID`] is added to the flags.
+    /// The user can be a user name or, with [`RunFlags::USER_SID`], a security identifier.
+    ///
+    /// https://docs.microsoft.com/en-us/windows/win32/api/taskschd/nf-taskschd-iregisteredtask-runex
+    pub fn run_ex(
+        &self,
+        parameters: &[&str],
+        flags: RunFlags,
+        session_id: Option<u32>,
+        user: Option<&str>,
+    ) -> Result<RunningTask, windows::Error> {
+        let parameters = Variant::from_parameters(parameters)?;
+        let flags = match session_id {
+            Some(_) => flags | RunFlags::USE_SESSION_ID,
+            None => flags,
+        };
+        let session_id = session_id.unwrap_or(0) as i32;
+        let user = user.map(BSTR::from).unwrap_or_default();
+        let mut running_task = None;
+        unsafe {
+            let res = self
+                .0
+                .RunEx(&parameters.0, flags.0, session_id, user, &mut running_task)
+                .ok();
+            match res {
+                Ok(_) => Ok(RunningTask::new(running_task.unwrap())),
+                Err(error) => Err(error),
+            }
+        }
+    }
+
+    /// Stops every running instance of the registered task.
+    ///
+    /// https://docs.microsoft.com/en-us/windows/win32/api/taskschd/nf-taskschd-iregisteredtask-stop
+    pub fn stop(&self) -> Result<(), windows::Error> {
+        // flags is reserved and must be 0
+        const FLAGS: i32 = 0;
+        unsafe { self.0.Stop(FLAGS).ok() }
+    }
+
+    /// Gets the instances of the registered task that are currently running.
+    ///
+    /// https://docs.microsoft.com/en-us/windows/win32/api/taskschd/nf-taskschd-iregisteredtask-getinstances
+    pub fn instances(&self) -> Result<Vec<RunningTask>, windows::Error> {
+        // flags is reserved and must be 0
+        const FLAGS: i32 = 0;
+        let mut running_tasks = None;
+        unsafe {
+            self.0.GetInstances(FLAGS, &mut running_tasks).ok()?;
+        }
+        collect_running_tasks(running_tasks.unwrap())
+    }
+
+    /// Gets the XML-formatted registration information for the registered task.
+    ///
+    /// https://docs.microsoft.com/en-us/windows/win32/api/taskschd/nf-taskschd-iregisteredtask-get_xml
+    pub fn xml(&self) -> Result<String, windows::Error> {
+        let mut xml = BSTR::default();
+        unsafe {
+            self.0.get_Xml(&mut xml).ok()?;
+        }
+        Ok(xml.to_string())
+    }
+}
+
+/// Collects every registered task out of a registered task collection
+///
+/// https://docs.microsoft.com/en-us/windows/win32/api/taskschd/nn-taskschd-iregisteredtaskcollection
+pub(crate) fn collect_registered_tasks(
+    collection: IRegisteredTaskCollection,
+) -> Result<Vec<RegisteredTask>, windows::Error> {
+    let mut count = 0;
+    unsafe {
+        collection.get_Count(&mut count).ok()?;
+    }
+    let count = usize::try_from(count).unwrap_or(0);
+
+    let mut registered_tasks = Vec::with_capacity(count);
+    // the collection is indexed starting from 1
+    for index in 1..=count {
+        let mut registered_task = None;
+        unsafe {
+            collection
+                .get_Item(&Variant::from_i32(index as i32).0, &mut registered_task)
+                .ok()?;
+        }
+        registered_tasks.push(RegisteredTask::new(registered_task.unwrap()));
+    }
+    Ok(registered_tasks)
+}
+
+/// Flags that control how a task is run with [`RegisteredTask::run_ex`].
+/// Flags can be combined with `|`.
+///
+/// https://docs.microsoft.com/en-us/windows/win32/api/taskschd/ne-taskschd-task_run_flags
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct RunFlags(i32);
+
+impl RunFlags {
+    /// The task is run with no flags.
+    pub const NO_FLAGS: RunFlags = RunFlags(TASK_RUN_FLAGS::TASK_RUN_NO_FLAGS.0);
+    /// The task is run as the user who is calling the run method.
+    pub const AS_SELF: RunFlags = RunFlags(TASK_RUN_FLAGS::TASK_RUN_AS_SELF.0);
+    /// The task is run regardless of constraints such as "do not run on batteries" or "run only if idle".
+    pub const IGNORE_CONSTRAINTS: RunFlags =
+        RunFlags(TASK_RUN_FLAGS::TASK_RUN_IGNORE_CONSTRAINTS.0);
+    /// The task is run using a terminal server session identifier.
+    pub const USE_SESSION_ID: RunFlags = RunFlags(TASK_RUN_FLAGS::TASK_RUN_USE_SESSION_ID.0);
+    /// The task is run using a security identifier.
+    pub const USER_SID: RunFlags = RunFlags(TASK_RUN_FLAGS::TASK_RUN_USER_SID.0);
+}
+
+impl Default for RunFlags {
+    fn default() -> Self {
+        RunFlags::NO_FLAGS
+    }
+}
+
+impl BitOr for RunFlags {
+    type Output = RunFlags;
+
+    fn bitor(self, rhs: Self) -> Self::Output {
+        RunFlags(self.0 | rhs.0)
+    }
+}
+
+/// Converts an OLE automation date, which Task Scheduler reports in local time,
+/// into a UTC date. A date of zero means that there is no date.
+///
+/// https://docs.microsoft.com/en-us/dotnet/api/system.datetime.tooadate
+fn from_ole_date(date: f64) -> Option<DateTime<Utc>> {
+    if date == 0.0 {
+        return None;
+    }
+    let days = date.trunc();
+    // for dates before the epoch the fraction is still counted forward from midnight
+    let milliseconds = (date.fract().abs() * 86_400_000.0).round();
+    let epoch = NaiveDate::from_ymd_opt(1899, 12, 30)?.and_hms_opt(0, 0, 0)?;
+    let local = epoch
+        .checked_add_signed(Duration::days(days as i64))?
+        .checked_add_signed(Duration::milliseconds(milliseconds as i64))?;
+    Local
+        .from_local_datetime(&local)
+        .earliest()
+        .map(|date| date.with_timezone(&Utc))
+}
@@ -0,0 +1,1018 @@
+use std::convert::TryFrom;
+
+use chrono::{
+    DateTime, Datelike, Duration, Local, NaiveDate, NaiveDateTime, NaiveTime, TimeZone, Timelike,
+    Utc, Weekday,
+};
+
+use crate::{
+    boundary::Boundary,
+    calendar::WEEKDAYS,
+    cron::{progressions, weekday_patterns, DayPattern},
+    error::RRuleError,
+    export::{group_times, seconds_of_day, Times},
+    DaysOfMonth, DaysOfWeek, Months, Repetition, SpecificTimeTrigger, TaskTriggers,
+    TaskTriggersBuilder, WeeksOfMonth, MAX_TRIGGERS,
+};
+
+/// The two letter iCalendar names of the days of the week, starting on Sunday
+const WEEKDAY_CODES: [&str; 7] = ["SU", "MO", "TU", "WE", "TH", "FR", "SA"];
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Frequency {
+    Secondly,
+    Minutely,
+    Hourly,
+    Daily,
+    Weekly,
+    Monthly,
+    Yearly,
+}
+
+/// A date and time of a DTSTART or UNTIL, either in UTC or floating in local time
+#[derive(Clone, Copy, Debug)]
+struct ICalTime {
+    time: NaiveDateTime,
+    utc: bool,
+    /// Whether the value was a DATE without a time, which starts at midnight
+    date: bool,
+}
+
+impl ICalTime {
+    fn to_boundary(self) -> Boundary {
+        if self.utc {
+            Boundary::Utc(Utc.from_utc_datetime(&self.time))
+        } else {
+            Boundary::Local(self.time)
+        }
+    }
+
+    /// The same kind of time on another day and time of day
+    fn at(self, date: NaiveDate, seconds: u32) -> Option<Boundary> {
+        let time = NaiveTime::from_num_seconds_from_midnight_opt(seconds, 0)?;
+        Some(
+            ICalTime {
+                time: date.and_time(time),
+                utc: self.utc,
+                date: false,
+            }
+            .to_boundary(),
+        )
+    }
+}
+
+#[derive(Debug)]
+struct Rule {
+    frequency: Frequency,
+    interval: u32,
+    until: Option<ICalTime>,
+    count: Option<u32>,
+    seconds: Vec<u32>,
+    minutes: Vec<u32>,
+    hours: Vec<u32>,
+    /// The days of the week, with the week of the month if there is one
+    days: Vec<(Option<i32>, Weekday)>,
+    month_days: Vec<i32>,
+    months: Vec<u32>,
+    week_start: Weekday,
+}
+
+impl TaskTriggers {
+    /// Converts an iCalendar recurrence rule into triggers that start the task at the same times.
+    ///
+    /// The text has a `DTSTART` line and an `RRULE` line, like
+    /// `DTSTART:20210104T090000Z` and `RRULE:FREQ=WEEKLY;BYDAY=MO,WE,FR;UNTIL=20211231T000000Z`.
+    /// Times are in UTC or, without the `Z`, local boundaries. `UNTIL` becomes the end boundary
+    /// of the triggers, and `BYHOUR` and `BYMINUTE` become repetitions where they can.
+    /// An `UNTIL` date includes the whole day, a date and time has to be in UTC when
+    /// `DTSTART` is and floating when it is.
+    ///
+    /// `COUNT` is only supported as `COUNT=1`, which is a single run at the start.
+    /// Rules that triggers can't reproduce, like `BYSETPOS`, `BYYEARDAY`, every few months
+    /// or the fifth week of a month, return [`RRuleError::Unsupported`].
+    ///
+    /// https://datatracker.ietf.org/doc/html/rfc5545#section-3.3.10
+    pub fn from_rrule(text: &str) -> Result<TaskTriggers, RRuleError> {
+        let mut start = None;
+        let mut rule = None;
+        for line in text.lines().map(str::trim).filter(|line| !line.is_empty()) {
+            let (name, value) = line.split_once(':').unwrap_or(("RRULE", line));
+            let (name, parameters) = match name.split_once(';') {
+                Some((name, parameters)) => (name, Some(parameters)),
+                None => (name, None),
+            };
+            match name.to_ascii_uppercase().as_str() {
+                "DTSTART" => start = Some(parse_start(parameters, value)?),
+                "RRULE" => rule = Some(parse_rule(value)?),
+                "EXDATE" | "RDATE" | "EXRULE" => {
+                    return Err(RRuleError::Unsupported(format!(
+                        "{}, triggers can't add or leave out single dates",
+                        name
+                    )))
+                }
+                _ => return Err(syntax_error("property", name)),
+            }
+        }
+        let start = start.ok_or(RRuleError::MissingStart)?;
+        let rule = rule.ok_or_else(|| syntax_error("RRULE", ""))?;
+        rule_to_triggers(start, &rule)
+    }
+
+    /// Writes each time based trigger as `DTSTART` and `RRULE` lines, floating in local
+    /// time for local boundaries and in UTC otherwise. When a start with an offset is
+    /// on another day in UTC its days of the week move with it, days of the month
+    /// can't and return [`RRuleError::Unsupported`].
+    ///
+    /// A trigger with a repetition can need more than one rule, since a rule runs at
+    /// every `BYMINUTE` in every `BYHOUR`. Disabled triggers never run and are left out,
+    /// as are ids, execution time limits and random delays, which aren't part of a
+    /// recurrence rule. Triggers that aren't time based return [`RRuleError::Unsupported`].
+    pub fn to_rrules(&self) -> Result<Vec<String>, RRuleError> {
+        let event_based = [
+            ("BootTrigger", self.boot().len()),
+            ("EventTrigger", self.event().len()),
+            ("IdleTrigger", self.idle().len()),
+            ("LogonTrigger", self.logon().len()),
+            ("RegistrationTrigger", self.registration().len()),
+            (
+                "SessionStateChangeTrigger",
+                self.session_state_change().len(),
+            ),
+        ];
+        if let Some((name, _)) = event_based.iter().find(|(_, count)| *count > 0) {
+            return Err(RRuleError::Unsupported(format!(
+                "{} doesn't run at set times",
+                name
+            )));
+        }
+
+        let mut rules = Vec::new();
+        for trigger in self.daily() {
+            if !trigger.common.enabled {
+                continue;
+            }
+            let interval = trigger.interval.unwrap_or(1).max(1);
+            let mut rule = String::from("FREQ=DAILY");
+            if interval > 1 {
+                rule.push_str(&format!(";INTERVAL={}", interval));
+            }
+            let (start, _) = ical_start(trigger.common.start);
+            rules.extend(calendar_rules(
+                &rule,
+                start,
+                trigger.common.end,
+                trigger.repetition.as_ref(),
+                interval == 1,
+            )?);
+        }
+        for trigger in self.weekly() {
+            if !trigger.common.enabled {
+                continue;
+            }
+            let interval = trigger.weeks_interval.unwrap_or(1).max(1);
+            let (start, shift) = ical_start(trigger.common.start);
+            let mut rule = String::from("FREQ=WEEKLY");
+            if interval > 1 {
+                if shift != 0 {
+                    return Err(RRuleError::Unsupported(
+                        "WeeklyTrigger runs every few weeks and starts on another day in UTC than at its offset, which can move runs to another week"
+                            .to_string(),
+                    ));
+                }
+                // Task Scheduler counts weeks from Sunday
+                rule.push_str(&format!(";INTERVAL={};WKST=SU", interval));
+            }
+            rule.push_str(&format!(
+                ";BYDAY={}",
+                weekday_codes(trigger.days_of_week.shifted(shift))
+            ));
+            rules.extend(calendar_rules(
+                &rule,
+                start,
+                trigger.common.end,
+                trigger.repetition.as_ref(),
+                false,
+            )?);
+        }
+        for trigger in self.monthly() {
+            if !trigger.common.enabled {
+                continue;
+            }
+            let mut days: Vec<String> = trigger
+                .days_of_month
+                .iter()
+                .map(|day| day.to_string())
+                .collect();
+            if trigger.run_on_last_day_of_month {
+                days.push("-1".to_string());
+            }
+            let rule = format!(
+                "FREQ=MONTHLY;BYMONTHDAY={}{}",
+                days.join(","),
+                by_month(trigger.months)
+            );
+            let (start, shift) = ical_start(trigger.common.start);
+            same_day("MonthlyTrigger", shift)?;
+            rules.extend(calendar_rules(
+                &rule,
+                start,
+                trigger.common.end,
+                trigger.repetition.as_ref(),
+                false,
+            )?);
+        }
+        for trigger in self.monthly_dow() {
+            if !trigger.common.enabled {
+                continue;
+            }
+            let mut days = Vec::new();
+            for day in trigger.days_of_week.iter() {
+                let code = WEEKDAY_CODES[day.num_days_from_sunday() as usize];
+                if trigger.weeks_of_month.iter().count() == 4 && trigger.run_on_last_week_of_month {
+                    days.push(code.to_string());
+                    continue;
+                }
+                days.extend(
+                    trigger
+                        .weeks_of_month
+                        .iter()
+                        .map(|week| format!("{}{}", week, code)),
+                );
+                if trigger.run_on_last_week_of_month {
+                    days.push(format!("-1{}", code));
+                }
+            }
+            let rule = format!(
+                "FREQ=MONTHLY;BYDAY={}{}",
+                days.join(","),
+                by_month(trigger.months)
+            );
+            let (start, shift) = ical_start(trigger.common.start);
+            same_day("MonthlyDowTrigger", shift)?;
+            rules.extend(calendar_rules(
+                &rule,
+                start,
+                trigger.common.end,
+                trigger.repetition.as_ref(),
+                false,
+            )?);
+        }
+        for trigger in self.specific_times() {
+            if !trigger.common.enabled {
+                continue;
+            }
+            let start = trigger.boundary();
+            let rule = match &trigger.repetition {
+                None => "FREQ=DAILY;COUNT=1".to_string(),
+                Some(repetition) => {
+                    let interval = repetition.interval.num_seconds().max(1);
+                    let mut rule = if interval % 60 == 0 {
+                        format!("FREQ=MINUTELY;INTERVAL={}", interval / 60)
+                    } else {
+                        format!("FREQ=SECONDLY;INTERVAL={}", interval)
+                    };
+                    // the last run is before the end of the duration
+                    let end = repetition.duration.and_then(|duration| {
+                        let time = start.clock_time() + duration - Duration::seconds(1);
+                        start.with_clock_time(time)
+                    });
+                    let until = match (end, trigger.common.end) {
+                        (Some(end), Some(deactivate)) if deactivate.to_utc() < end.to_utc() => {
+                            Some(deactivate)
+                        }
+                        (end, deactivate) => end.or(deactivate),
+                    };
+                    if let Some(until) = until {
+                        rule.push_str(&format!(";UNTIL={}", format_until(start, until)));
+                    }
+                    rule
+                }
+            };
+            rules.push(format!("DTSTART:{}\nRRULE:{}", format_time(start), rule));
+        }
+        Ok(rules)
+    }
+}
+
+fn syntax_error(part: &str, value: &str) -> RRuleError {
+    RRuleError::Syntax {
+        part: part.to_string(),
+        value: value.to_string(),
+    }
+}
+
+fn parse_time(part: &str, value: &str) -> Result<ICalTime, RRuleError> {
+    let (value, utc) = match value.strip_suffix('Z') {
+        Some(value) => (value, true),
+        None => (value, false),
+    };
+    if let Ok(time) = NaiveDateTime::parse_from_str(value, "%Y%m%dT%H%M%S") {
+        return Ok(ICalTime {
+            time,
+            utc,
+            date: false,
+        });
+    }
+    NaiveDate::parse_from_str(value, "%Y%m%d")
+        .map(|date| ICalTime {
+            time: date.and_time(NaiveTime::MIN),
+            utc,
+            date: true,
+        })
+        .map_err(|_| syntax_error(part, value))
+}
+
+fn parse_start(parameters: Option<&str>, value: &str) -> Result<ICalTime, RRuleError> {
+    for parameter in parameters
+        .unwrap_or("")
+        .split(';')
+        .filter(|p| !p.is_empty())
+    {
+        let (name, parameter_value) = parameter.split_once('=').unwrap_or((parameter, ""));
+        match name.to_ascii_uppercase().as_str() {
+            "VALUE" => {}
+            "TZID" => {
+                return Err(RRuleError::Unsupported(format!(
+                    "DTSTART in the {} time zone, only UTC and local times are supported",
+                    parameter_value
+                )))
+            }
+            _ => return Err(syntax_error("DTSTART", parameter)),
+        }
+    }
+    parse_time("DTSTART", value)
+}
+
+/// Parses a list of numbers from `min` to `max`, where 0 is only allowed if `min` is 0
+fn parse_numbers(part: &str, value: &str, min: i32, max: i32) -> Result<Vec<i32>, RRuleError> {
+    value
+        .split(',')
+        .map(|text| match text.trim_start_matches('+').parse::<i32>() {
+            Ok(number) if (min..=max).contains(&number) && (number != 0 || min == 0) => Ok(number),
+            _ => Err(syntax_error(part, text)),
+        })
+        .collect()
+}
+
+fn parse_unsigned(part: &str, value: &str, max: u32) -> Result<Vec<u32>, RRuleError> {
+    let mut numbers: Vec<u32> = parse_numbers(part, value, 0, max as i32)?
+        .into_iter()
+        .map(|number| number as u32)
+        .collect();
+    numbers.sort_unstable();
+    numbers.dedup();
+    Ok(numbers)
+}
+
+fn parse_weekday(part: &str, code: &str) -> Result<Weekday, RRuleError> {
+    WEEKDAY_CODES
+        .iter()
+        .position(|name| name.eq_ignore_ascii_case(code))
+        .map(|index| WEEKDAYS[index])
+        .ok_or_else(|| syntax_error(part, code))
+}
+
+fn parse_rule(value: &str) -> Result<Rule, RRuleError> {
+    let mut frequency = None;
+    let mut rule = Rule {
+        frequency: Frequency::Daily,
+        interval: 1,
+        until: None,
+        count: None,
+        seconds: Vec::new(),
+        minutes: Vec::new(),
+        hours: Vec::new(),
+        days: Vec::new(),
+        month_days: Vec::new(),
+        months: Vec::new(),
+        week_start: Weekday::Mon,
+    };
+    for part in value.split(';').filter(|part| !part.is_empty()) {
+        let (name, value) = part
+            .split_once('=')
+            .ok_or_else(|| syntax_error("RRULE", part))?;
+        let name = name.to_ascii_uppercase();
+        match name.as_str() {
+            "FREQ" => {
+                frequency = Some(match value.to_ascii_uppercase().as_str() {
+                    "SECONDLY" => Frequency::Secondly,
+                    "MINUTELY" => Frequency::Minutely,
+                    "HOURLY" => Frequency::Hourly,
+                    "DAILY" => Frequency::Daily,
+                    "WEEKLY" => Frequency::Weekly,
+                    "MONTHLY" => Frequency::Monthly,
+                    "YEARLY" => Frequency::Yearly,
+                    _ => return Err(syntax_error("FREQ", value)),
+                })
+            }
+            "INTERVAL" => {
+                rule.interval = match value.parse::<u32>() {
+                    Ok(interval) if interval > 0 => interval,
+                    _ => return Err(syntax_error("INTERVAL", value)),
+                }
+            }
+            "UNTIL" => rule.until = Some(parse_time("UNTIL", value)?),
+            "COUNT" => {
+                rule.count = match value.parse::<u32>() {
+                    Ok(count) if count > 0 => Some(count),
+                    _ => return Err(syntax_error("COUNT", value)),
+                }
+            }
+            "BYSECOND" => rule.seconds = parse_unsigned("BYSECOND", value, 60)?,
+            "BYMINUTE" => rule.minutes = parse_unsigned("BYMINUTE", value, 59)?,
+            "BYHOUR" => rule.hours = parse_unsigned("BYHOUR", value, 23)?,
+            "BYMONTH" => {
+                rule.months = parse_unsigned("BYMONTH", value, 12)?;
+                if rule.months.contains(&0) {
+                    return Err(syntax_error("BYMONTH", "0"));
+                }
+            }
+            "BYMONTHDAY" => rule.month_days = parse_numbers("BYMONTHDAY", value, -31, 31)?,
+            "BYDAY" => {
+                for day in value.split(',') {
+                    let split = day.len().saturating_sub(2);
+                    if !day.is_char_boundary(split) {
+                        return Err(syntax_error("BYDAY", day));
+                    }
+                    let (week, code) = day.split_at(split);
+                    let week = match week {
+                        "" => None,
+                        week => match week.trim_start_matches('+').parse::<i32>() {
+                            Ok(week) if week != 0 && (-53..=53).contains(&week) => Some(week),
+                            _ => return Err(syntax_error("BYDAY", day)),
+                        },
+                    };
+                    rule.days.push((week, parse_weekday("BYDAY", code)?));
+                }
+            }
+            "WKST" => rule.week_start = parse_weekday("WKST", value)?,
+            "BYSETPOS" | "BYYEARDAY" | "BYWEEKNO" | "RSCALE" | "SKIP" => {
+                return Err(RRuleError::Unsupported(format!(
+                    "{} has no trigger equivalent",
+                    name
+                )))
+            }
+            _ => return Err(syntax_error("RRULE", part)),
+        }
+    }
+    rule.frequency = frequency.ok_or_else(|| syntax_error("FREQ", ""))?;
+    Ok(rule)
+}
+
+fn rule_to_triggers(start: ICalTime, rule: &Rule) -> Result<TaskTriggers, RRuleError> {
+    let first = start.to_boundary();
+    let until = match rule.until {
+        // a date includes the whole day, on the same clock as the start
+        Some(until) if until.date => Some(ICalTime {
+            time: until.time + Duration::seconds(86_399),
+            utc: start.utc,
+            date: false,
+        }),
+        // a floating start needs a floating UNTIL and a start in UTC one in UTC
+        Some(until) if until.utc != start.utc => {
+            return Err(syntax_error(
+                "UNTIL",
+                if start.utc {
+                    "a floating time with a DTSTART in UTC"
+                } else {
+                    "a time in UTC with a floating DTSTART"
+                },
+            ))
+        }
+        until => until,
+    };
+    let end = until.map(ICalTime::to_boundary);
+
+    match rule.count {
+        Some(1) => {
+            let trigger = SpecificTimeTrigger::new(String::new(), first);
+            return Ok(TaskTriggersBuilder::new()
+                .with_specific_time(trigger)
+                .build());
+        }
+        Some(_) => {
+            return Err(RRuleError::Unsupported(
+                "COUNT above 1, triggers can end at a date but not after some runs".to_string(),
+            ))
+        }
+        None => {}
+    }
+
+    let unit = match rule.frequency {
+        Frequency::Secondly => Some(1),
+        Frequency::Minutely => Some(60),
+        Frequency::Hourly => Some(3_600),
+        _ => None,
+    };
+    if let Some(unit) = unit {
+        let filtered = !(rule.seconds.is_empty()
+            && rule.minutes.is_empty()
+            && rule.hours.is_empty()
+            && rule.days.is_empty()
+            && rule.month_days.is_empty()
+            && rule.months.is_empty());
+        if filtered {
+            return Err(RRuleError::Unsupported(
+                "BY parts with a frequency below a day".to_string(),
+            ));
+        }
+        let interval = i64::from(rule.interval) * unit;
+        if interval < 60 {
+            return Err(RRuleError::Unsupported(
+                "repetitions less than a minute apart".to_string(),
+            ));
+        }
+        let mut trigger = SpecificTimeTrigger::new(String::new(), first)
+            .with_repetition(Repetition::new(Duration::seconds(interval)));
+        if let Some(end) = end {
+            trigger = trigger.deactivate_date(end);
+        }
+        return Ok(TaskTriggersBuilder::new()
+            .with_specific_time(trigger)
+            .build());
+    }
+
+    let patterns = day_patterns(start.time, rule)?;
+
+    let second = match rule.seconds.as_slice() {
+        [] => start.time.second(),
+        [second] if *second < 60 => *second,
+        [_] => return Err(syntax_error("BYSECOND", "60")),
+        _ => {
+            return Err(RRuleError::Unsupported(
+                "more than one BYSECOND, repetitions are at least a minute apart".to_string(),
+            ))
+        }
+    };
+    let hours = if rule.hours.is_empty() {
+        vec![start.time.hour()]
+    } else {
+        rule.hours.clone()
+    };
+    let minutes = if rule.minutes.is_empty() {
+        vec![start.time.minute()]
+    } else {
+        rule.minutes.clone()
+    };
+    let progressions = progressions(&hours, &minutes);
+
+    let needed = progressions.len() * patterns.len();
+    if needed > usize::from(MAX_TRIGGERS) {
+        return Err(RRuleError::TooManyTriggers(needed));
+    }
+    let mut builder = TaskTriggersBuilder::new();
+    for progression in &progressions {
+        let trigger_start = start
+            .at(start.time.date(), progression.start * 60 + second)
+            .ok_or_else(|| syntax_error("DTSTART", &start.time.to_string()))?;
+        for pattern in &patterns {
+            builder = pattern.add_trigger(builder, trigger_start, end, progression.repetition());
+        }
+    }
+    let triggers = builder.build();
+
+    // the triggers start at midnight of the start day, a rule only runs from DTSTART on.
+    // Floating times are compared as if they were in UTC, which keeps them on their clock.
+    let midnight = start
+        .at(start.time.date(), 0)
+        .ok_or_else(|| syntax_error("DTSTART", &start.time.to_string()))?;
+    if !triggers
+        .occurrences_in(
+            midnight.in_zone(&Utc) - Duration::seconds(1),
+            first.in_zone(&Utc) - Duration::seconds(1),
+            &Utc,
+        )
+        .is_empty()
+    {
+        return Err(RRuleError::Unsupported(
+            "a DTSTART after the first run of its day, start the rule at one of its runs"
+                .to_string(),
+        ));
+    }
+    Ok(triggers)
+}
+
+/// The days the rule runs on, with the defaults that come from the start
+fn day_patterns(start: NaiveDateTime, rule: &Rule) -> Result<Vec<DayPattern>, RRuleError> {
+    let mut months: Months = rule.months.iter().copied().collect();
+    if months.is_empty() {
+        months = Months::all();
+    }
+    let mut month_days = rule.month_days.clone();
+    let mut days = rule.days.clone();
+    match rule.frequency {
+        Frequency::Weekly if days.is_empty() => days.push((None, start.weekday())),
+        Frequency::Monthly if days.is_empty() && month_days.is_empty() => {
+            month_days.push(start.day() as i32)
+        }
+        Frequency::Yearly if days.is_empty() && month_days.is_empty() => {
+            month_days.push(start.day() as i32);
+            if rule.months.is_empty() {
+                months = Months::new().with(start.month());
+            }
+        }
+        _ => {}
+    }
+
+    if !days.is_empty() && !month_days.is_empty() {
+        return Err(RRuleError::Unsupported(
+            "BYDAY with BYMONTHDAY, which only runs on days that match both".to_string(),
+        ));
+    }
+    let numbered = days.iter().any(|(week, _)| week.is_some());
+    match rule.frequency {
+        Frequency::Daily | Frequency::Weekly if numbered => {
+            return Err(syntax_error(
+                "BYDAY",
+                "a week number without FREQ=MONTHLY or YEARLY",
+            ))
+        }
+        Frequency::Yearly if numbered && rule.months.is_empty() => {
+            return Err(RRuleError::Unsupported(
+                "a BYDAY week number without BYMONTH counts weeks of the year".to_string(),
+            ))
+        }
+        _ => {}
+    }
+
+    let interval = rule.interval;
+    if interval > 1 {
+        let supported = match rule.frequency {
+            Frequency::Daily => days.is_empty() && month_days.is_empty() && months == Months::all(),
+            Frequency::Weekly => months == Months::all(),
+            _ => false,
+        };
+        if !supported {
+            return Err(RRuleError::Unsupported(format!(
+                "INTERVAL={}, which is only supported for plain FREQ=DAILY and FREQ=WEEKLY without BYMONTH",
+                interval
+            )));
+        }
+        // Sundays are only counted in the same week as with WKST=SU when every run
+        // is on a Sunday, starting from a Sunday
+        let sunday = days.iter().any(|(_, day)| *day == Weekday::Sun);
+        let only_sunday = days.iter().all(|(_, day)| *day == Weekday::Sun);
+        let start_sunday = start.weekday() == Weekday::Sun;
+        if rule.frequency == Frequency::Weekly
+            && rule.week_start != Weekday::Sun
+            && (sunday || start_sunday)
+            && !(only_sunday && start_sunday)
+        {
+            return Err(RRuleError::Unsupported(
+                "INTERVAL with Sundays, triggers count weeks from Sunday so it needs WKST=SU"
+                    .to_string(),
+            ));
+        }
+    }
+    let interval =
+        u16::try_from(interval).map_err(|_| syntax_error("INTERVAL", &interval.to_string()))?;
+
+    if !month_days.is_empty() {
+        let mut days_of_month = DaysOfMonth::new();
+        let mut last = false;
+        for day in month_days {
+            match day {
+                -1 => last = true,
+                1..=31 => days_of_month = days_of_month.with(day as u32),
+                _ => {
+                    return Err(RRuleError::Unsupported(format!(
+                        "BYMONTHDAY={}, triggers only have the last day of the month",
+                        day
+                    )))
+                }
+            }
+        }
+        return Ok(vec![DayPattern::Monthly(days_of_month, last, months)]);
+    }
+
+    if !days.is_empty() {
+        let mut every_week = DaysOfWeek::new();
+        let mut weeks = [WeeksOfMonth::new(); 7];
+        let mut last_week = DaysOfWeek::new();
+        for (week, day) in days {
+            let index = day.num_days_from_sunday() as usize;
+            match week {
+                None => every_week = every_week.with(day),
+                Some(-1) => last_week = last_week.with(day),
+                Some(week @ 1..=4) => weeks[index] = weeks[index].with(week as u32),
+                Some(week) => {
+                    return Err(RRuleError::Unsupported(format!(
+                        "BYDAY={}{}, triggers only have weeks 1 to 4 and the last week",
+                        week, WEEKDAY_CODES[index]
+                    )))
+                }
+            }
+        }
+        let patterns = weekday_patterns(every_week, &weeks, last_week, months)
+            .into_iter()
+            .map(|pattern| match pattern {
+                DayPattern::Weekly(days, _) => DayPattern::Weekly(days, interval),
+                pattern => pattern,
+            })
+            .collect();
+        return Ok(patterns);
+    }
+
+    if months == Months::all() {
+        Ok(vec![DayPattern::Daily(interval)])
+    } else {
+        Ok(vec![DayPattern::Monthly((1..=31).collect(), false, months)])
+    }
+}
+
+/// The start of a calendar trigger as iCalendar writes it, floating or in UTC since
+/// times only have an offset when they name a time zone, with the number of days the
+/// date moves when a start with an offset is written in UTC.
+/// A trigger without a start boundary starts now, in UTC like `next_occurrence` counts it.
+fn ical_start(start: Option<Boundary>) -> (Boundary, i64) {
+    match start {
+        Some(Boundary::Offset(time)) => {
+            let utc = time.with_timezone(&Utc);
+            let shift = (utc.date_naive() - time.date_naive()).num_days();
+            (Boundary::Utc(utc), shift)
+        }
+        Some(start) => (start, 0),
+        None => (Boundary::Utc(Boundary::now().to_utc()), 0),
+    }
+}
+
+/// Days of the month can't move to the day before or after, unlike days of the week
+fn same_day(item: &str, shift: i64) -> Result<(), RRuleError> {
+    if shift == 0 {
+        Ok(())
+    } else {
+        Err(RRuleError::Unsupported(format!(
+            "{} starts on another day in UTC than at its offset, so its days of the month can't be written in UTC",
+            item
+        )))
+    }
+}
+
+/// Formats a boundary as an iCalendar date and time, floating for local boundaries
+fn format_time(time: Boundary) -> String {
+    match time {
+        Boundary::Local(time) => time.format("%Y%m%dT%H%M%S").to_string(),
+        time => time.to_utc().format("%Y%m%dT%H%M%SZ").to_string(),
+    }
+}
+
+/// Formats `UNTIL`, which has to be floating when `DTSTART` is and in UTC otherwise
+fn format_until(start: Boundary, until: Boundary) -> String {
+    if start.is_local() == until.is_local() {
+        format_time(until)
+    } else if start.is_local() {
+        format_time(Boundary::Local(until.local_time()))
+    } else {
+        format_time(Boundary::Utc(until.to_utc()))
+    }
+}
+
+fn weekday_codes(days: DaysOfWeek) -> String {
+    let codes: Vec<&str> = days
+        .iter()
+        .map(|day| WEEKDAY_CODES[day.num_days_from_sunday() as usize])
+        .collect();
+    codes.join(",")
+}
+
+fn by_month(months: Months) -> String {
+    if months == Months::all() {
+        return String::new();
+    }
+    let months: Vec<String> = months.iter().map(|month| month.to_string()).collect();
+    format!(";BYMONTH={}", months.join(","))
+}
+
+fn join(values: &[u32]) -> String {
+    let values: Vec<String> = values.iter().map(|value| value.to_string()).collect();
+    values.join(",")
+}
+
+/// The rules for a calendar trigger, one for each group of times of day it runs at
+fn calendar_rules(
+    rule: &str,
+    start: Boundary,
+    end: Option<Boundary>,
+    repetition: Option<&Repetition>,
+    every_day: bool,
+) -> Result<Vec<String>, RRuleError> {
+    let until = end
+        .map(|end| format!(";UNTIL={}", format_until(start, end)))
+        .unwrap_or_default();
+    if repetition.is_none() {
+        return Ok(vec![format!(
+            "DTSTART:{}\nRRULE:{}{}",
+            format_time(start),
+            rule,
+            until
+        )]);
+    }
+
+    let first = i64::from(start.clock_time().num_seconds_from_midnight());
+    let times = seconds_of_day(first, repetition, every_day).map_err(RRuleError::Unsupported)?;
+    let rules = group_times(&times)
+        .into_iter()
+        .map(
+            |Times {
+                 hours,
+                 minutes,
+                 second,
+             }| {
+                // the start of every rule is one of its own runs
+                let time =
+                    NaiveTime::from_hms_opt(hours[0], minutes[0], second).unwrap_or(NaiveTime::MIN);
+                let start = start
+                    .with_clock_time(start.clock_time().date().and_time(time))
+                    .unwrap_or(start);
+                format!(
+                    "DTSTART:{}\nRRULE:{};BYHOUR={};BYMINUTE={};BYSECOND={}{}",
+                    format_time(start),
+                    rule,
+                    join(&hours),
+                    join(&minutes),
+                    second,
+                    until
+                )
+            },
+        )
+        .collect();
+    Ok(rules)
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::{FixedOffset, TimeZone, Utc, Weekday};
+
+    use crate::{
+        error::RRuleError, DaysOfMonth, DaysOfWeek, MonthlyTrigger, Months, Repetition,
+        TaskTriggers, TaskTriggersBuilder, WeeklyTrigger,
+    };
+
+    fn runs(triggers: &TaskTriggers, from: (i32, u32, u32), until: (i32, u32, u32)) -> usize {
+        triggers
+            .occurrences_in(
+                Utc.with_ymd_and_hms(from.0, from.1, from.2, 0, 0, 0)
+                    .unwrap(),
+                Utc.with_ymd_and_hms(until.0, until.1, until.2, 23, 59, 59)
+                    .unwrap(),
+                &Utc,
+            )
+            .len()
+    }
+
+    #[test]
+    fn round_trips_a_weekly_trigger() {
+        let triggers = TaskTriggersBuilder::new()
+            .with_weekly(
+                WeeklyTrigger::new(
+                    DaysOfWeek::new()
+                        .with(Weekday::Mon)
+                        .with(Weekday::Wed)
+                        .with(Weekday::Fri),
+                )
+                .with_start_time(Utc.with_ymd_and_hms(2021, 1, 4, 9, 0, 0).unwrap())
+                .with_end_time(Utc.with_ymd_and_hms(2021, 12, 31, 0, 0, 0).unwrap()),
+            )
+            .build();
+        let rules = triggers.to_rrules().unwrap();
+        assert_eq!(
+            rules,
+            ["DTSTART:20210104T090000Z\nRRULE:FREQ=WEEKLY;BYDAY=MO,WE,FR;UNTIL=20211231T000000Z"]
+        );
+        assert_eq!(TaskTriggers::from_rrule(&rules[0]).unwrap(), triggers);
+    }
+
+    #[test]
+    fn round_trips_a_repetition() {
+        let triggers = TaskTriggersBuilder::new()
+            .with_weekly(
+                WeeklyTrigger::new(DaysOfWeek::new().with(Weekday::Tue))
+                    .with_start_time(Utc.with_ymd_and_hms(2021, 1, 5, 8, 0, 0).unwrap())
+                    .with_repetition(
+                        Repetition::new(chrono::Duration::hours(1))
+                            .with_duration(chrono::Duration::hours(3)),
+                    ),
+            )
+            .build();
+        let rules = triggers.to_rrules().unwrap();
+        assert_eq!(
+            rules,
+            ["DTSTART:20210105T080000Z\nRRULE:FREQ=WEEKLY;BYDAY=TU;BYHOUR=8,9,10;BYMINUTE=0;BYSECOND=0"]
+        );
+        let read = TaskTriggers::from_rrule(&rules[0]).unwrap();
+        assert_eq!(runs(&read, (2021, 1, 1), (2021, 1, 31)), 12);
+        assert_eq!(
+            read.occurrences_in(
+                Utc.with_ymd_and_hms(2021, 1, 1, 0, 0, 0).unwrap(),
+                Utc.with_ymd_and_hms(2021, 1, 31, 0, 0, 0).unwrap(),
+                &Utc
+            ),
+            triggers.occurrences_in(
+                Utc.with_ymd_and_hms(2021, 1, 1, 0, 0, 0).unwrap(),
+                Utc.with_ymd_and_hms(2021, 1, 31, 0, 0, 0).unwrap(),
+                &Utc
+            )
+        );
+    }
+
+    #[test]
+    fn ends_at_an_until_time() {
+        let triggers = TaskTriggers::from_rrule(
+            "DTSTART:20211201T090000Z\nRRULE:FREQ=DAILY;UNTIL=20211231T000000Z",
+        )
+        .unwrap();
+        assert_eq!(runs(&triggers, (2021, 12, 1), (2022, 1, 31)), 30);
+    }
+
+    #[test]
+    fn includes_the_whole_day_of_an_until_date() {
+        let triggers =
+            TaskTriggers::from_rrule("DTSTART:20211201T090000Z\nRRULE:FREQ=DAILY;UNTIL=20211231")
+                .unwrap();
+        assert_eq!(runs(&triggers, (2021, 12, 1), (2022, 1, 31)), 31);
+    }
+
+    #[test]
+    fn rejects_an_until_on_another_clock_than_the_start() {
+        assert_eq!(
+            TaskTriggers::from_rrule(
+                "DTSTART:20211201T090000\nRRULE:FREQ=DAILY;UNTIL=20211231T000000Z"
+            ),
+            Err(RRuleError::Syntax {
+                part: "UNTIL".to_string(),
+                value: "a time in UTC with a floating DTSTART".to_string(),
+            })
+        );
+    }
+
+    #[test]
+    fn moves_the_days_of_a_start_that_is_on_another_day_in_utc() {
+        // Monday 02:00 in Tokyo is Sunday 17:00 in UTC
+        let start = FixedOffset::east_opt(9 * 3600)
+            .unwrap()
+            .with_ymd_and_hms(2021, 1, 4, 2, 0, 0)
+            .unwrap();
+        let triggers = TaskTriggersBuilder::new()
+            .with_weekly(
+                WeeklyTrigger::new(DaysOfWeek::new().with(Weekday::Mon)).with_start_time(start),
+            )
+            .build();
+        let rules = triggers.to_rrules().unwrap();
+        assert_eq!(
+            rules,
+            ["DTSTART:20210103T170000Z\nRRULE:FREQ=WEEKLY;BYDAY=SU"]
+        );
+        let after = Utc.with_ymd_and_hms(2021, 1, 1, 0, 0, 0).unwrap();
+        assert_eq!(
+            TaskTriggers::from_rrule(&rules[0])
+                .unwrap()
+                .next_occurrence_in(after, &Utc),
+            triggers.next_occurrence_in(after, &Utc)
+        );
+    }
+
+    #[test]
+    fn rejects_days_of_the_month_that_are_on_another_day_in_utc() {
+        let start = FixedOffset::east_opt(9 * 3600)
+            .unwrap()
+            .with_ymd_and_hms(2021, 1, 1, 2, 0, 0)
+            .unwrap();
+        let triggers = TaskTriggersBuilder::new()
+            .with_monthly(
+                MonthlyTrigger::new(DaysOfMonth::new().with(1), Months::all())
+                    .with_start_time(start),
+            )
+            .build();
+        assert!(matches!(
+            triggers.to_rrules(),
+            Err(RRuleError::Unsupported(_))
+        ));
+    }
+
+    #[test]
+    fn rejects_parts_triggers_cant_reproduce() {
+        assert_eq!(
+            TaskTriggers::from_rrule(
+                "DTSTART:20210101T090000Z\nRRULE:FREQ=MONTHLY;BYDAY=MO,TU,WE,TH,FR;BYSETPOS=-1"
+            ),
+            Err(RRuleError::Unsupported(
+                "BYSETPOS has no trigger equivalent".to_string()
+            ))
+        );
+        assert!(matches!(
+            TaskTriggers::from_rrule("DTSTART:20210101T090000Z\nRRULE:FREQ=DAILY;COUNT=5"),
+            Err(RRuleError::Unsupported(_))
+        ));
+        assert_eq!(
+            TaskTriggers::from_rrule("RRULE:FREQ=DAILY"),
+            Err(RRuleError::MissingStart)
+        );
+    }
+
+    #[test]
+    fn reads_a_single_run() {
+        let triggers =
+            TaskTriggers::from_rrule("DTSTART:20210101T090000Z\nRRULE:FREQ=DAILY;COUNT=1").unwrap();
+        assert_eq!(runs(&triggers, (2020, 1, 1), (2022, 1, 1)), 1);
+    }
+}
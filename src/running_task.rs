@@ -0,0 +1,126 @@
+use std::convert::TryFrom;
+
+use bindings::Windows::Win32::{
+    Automation::BSTR,
+    TaskScheduler::{IRunningTask, IRunningTaskCollection, TASK_STATE},
+};
+
+use crate::{task_state::TaskState, variant::Variant};
+
+/// An instance of a registered task that is currently running.
+///
+/// https://docs.microsoft.com/en-us/windows/win32/api/taskschd/nn-taskschd-irunningtask
+pub struct RunningTask(pub(crate) IRunningTask);
+
+impl RunningTask {
+    pub(crate) fn new(running_task: IRunningTask) -> Self {
+        Self(running_task)
+    }
+
+    /// Gets the name of the task.
+    ///
+    /// https://docs.microsoft.com/en-us/windows/win32/api/taskschd/nf-taskschd-irunningtask-get_name
+    pub fn name(&self) -> Result<String, windows::Error> {
+        let mut name = BSTR::default();
+        unsafe {
+            self.0.get_Name(&mut name).ok()?;
+        }
+        Ok(name.to_string())
+    }
+
+    /// Gets the GUID identifier for this instance of the task.
+    ///
+    /// https://docs.microsoft.com/en-us/windows/win32/api/taskschd/nf-taskschd-irunningtask-get_instanceguid
+    pub fn instance_guid(&self) -> Result<String, windows::Error> {
+        let mut instance_guid = BSTR::default();
+        unsafe {
+            self.0.get_InstanceGuid(&mut instance_guid).ok()?;
+        }
+        Ok(instance_guid.to_string())
+    }
+
+    /// Gets the path to where the task is stored.
+    ///
+    /// https://docs.microsoft.com/en-us/windows/win32/api/taskschd/nf-taskschd-irunningtask-get_path
+    pub fn path(&self) -> Result<String, windows::Error> {
+        let mut path = BSTR::default();
+        unsafe {
+            self.0.get_Path(&mut path).ok()?;
+        }
+        Ok(path.to_string())
+    }
+
+    /// Gets an enumeration value that indicates the state of the running task.
+    ///
+    /// https://docs.microsoft.com/en-us/windows/win32/api/taskschd/nf-taskschd-irunningtask-get_state
+    pub fn state(&self) -> Result<TaskState, windows::Error> {
+        let mut state = TASK_STATE::default();
+        unsafe {
+            self.0.get_State(&mut state).ok()?;
+        }
+        Ok(TaskState::from(state.0))
+    }
+
+    /// Gets the name of the current action that the running task is performing.
+    ///
+    /// https://docs.microsoft.com/en-us/windows/win32/api/taskschd/nf-taskschd-irunningtask-get_currentaction
+    pub fn current_action(&self) -> Result<String, windows::Error> {
+        let mut current_action = BSTR::default();
+        unsafe {
+            self.0.get_CurrentAction(&mut current_action).ok()?;
+        }
+        Ok(current_action.to_string())
+    }
+
+    /// Gets the process ID for the engine (process) which is running the task.
+    ///
+    /// https://docs.microsoft.com/en-us/windows/win32/api/taskschd/nf-taskschd-irunningtask-get_enginepid
+    pub fn engine_pid(&self) -> Result<u32, windows::Error> {
+        let mut engine_pid = 0;
+        unsafe {
+            self.0.get_EnginePID(&mut engine_pid).ok()?;
+        }
+        Ok(engine_pid)
+    }
+
+    /// Stops this instance of the task.
+    ///
+    /// https://docs.microsoft.com/en-us/windows/win32/api/taskschd/nf-taskschd-irunningtask-stop
+    pub fn stop(&self) -> Result<(), windows::Error> {
+        unsafe { self.0.Stop().ok() }
+    }
+
+    /// Refreshes all of the local instance variables of the task
+    /// so that the getters return the current values.
+    ///
+    /// https://docs.microsoft.com/en-us/windows/win32/api/taskschd/nf-taskschd-irunningtask-refresh
+    pub fn refresh(&self) -> Result<(), windows::Error> {
+        unsafe { self.0.Refresh().ok() }
+    }
+}
+
+/// Collects every running task out of a running task collection
+///
+/// https://docs.microsoft.com/en-us/windows/win32/api/taskschd/nn-taskschd-irunningtaskcollection
+pub(crate) fn collect_running_tasks(
+    collection: IRunningTaskCollection,
+) -> Result<Vec<RunningTask>, windows::Error> {
+    let mut count = 0;
+    unsafe {
+        collection.get_Count(&mut count).ok()?;
+    }
+    let count = usize::try_from(count).unwrap_or(0);
+
+    let mut running_tasks = Vec::with_capacity(count);
+    // the collection is indexed starting from 1
+    for index in 1..=count {
+        let mut running_task = None;
+        unsafe {
+            collection
+                .get_Item(&Variant::from_i32(index as i32).0, &mut running_task)
+                .ok()?;
+        }
+        running_tasks.push(RunningTask::new(running_task.unwrap()));
+    }
+    Ok(running_tasks)
+}
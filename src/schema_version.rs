@@ -0,0 +1,333 @@
+use std::fmt;
+
+use crate::{
+    error::SchemaError,
+    export::ExportWarning,
+    task::{Settings, Task},
+};
+
+/// A version of the Task Scheduler schema. Each version adds settings that Task Scheduler
+/// on older versions of Windows rejects, so a task is written with the oldest version
+/// that has every setting it uses.
+///
+/// https://docs.microsoft.com/en-us/windows/win32/taskschd/task-scheduler-schema
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum SchemaVersion {
+    /// Windows Vista and Windows Server 2008, `TASK_COMPATIBILITY_V2`
+    V1_2,
+    /// Windows 7 and Windows Server 2008 R2, `TASK_COMPATIBILITY_V2_1`
+    V1_3,
+    /// Windows 8 and Windows Server 2012, `TASK_COMPATIBILITY_V2_2`.
+    /// Windows 10 understands later versions, which add nothing that a task here uses.
+    V1_4,
+}
+
+impl SchemaVersion {
+    /// The newest version that Task Scheduler understands, from the highest version
+    /// it reports as `(major, minor)` like [`TaskService::highest_version`](crate::TaskService)
+    pub fn from_highest_version(major: u32, minor: u32) -> SchemaVersion {
+        match (major, minor) {
+            (1, 3) => SchemaVersion::V1_3,
+            (1, minor) if minor >= 4 => SchemaVersion::V1_4,
+            (major, _) if major > 1 => SchemaVersion::V1_4,
+            _ => SchemaVersion::V1_2,
+        }
+    }
+
+    /// The `version` attribute of the task XML, like `1.2`
+    pub fn as_str(self) -> &'static str {
+        match self {
+            SchemaVersion::V1_2 => "1.2",
+            SchemaVersion::V1_3 => "1.3",
+            SchemaVersion::V1_4 => "1.4",
+        }
+    }
+
+    /// The oldest version of Windows that understands the schema version
+    pub fn windows(self) -> &'static str {
+        match self {
+            SchemaVersion::V1_2 => "Windows Vista",
+            SchemaVersion::V1_3 => "Windows 7",
+            SchemaVersion::V1_4 => "Windows 8",
+        }
+    }
+}
+
+impl Default for SchemaVersion {
+    fn default() -> Self {
+        SchemaVersion::V1_2
+    }
+}
+
+impl fmt::Display for SchemaVersion {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} ({})", self.as_str(), self.windows())
+    }
+}
+
+impl Task {
+    /// The oldest schema version that has every setting the task uses,
+    /// which is the version the XML of the task is written with
+    pub fn schema_version(&self) -> SchemaVersion {
+        self.newer_settings()
+            .into_iter()
+            .map(|(_, version)| version)
+            .max()
+            .unwrap_or_default()
+    }
+
+    /// Fails with every setting that Task Scheduler of the target version doesn't have,
+    /// like [`TaskService::highest_version`](crate::TaskService) of an older computer
+    pub fn check_schema_version(&self, target: SchemaVersion) -> Result<(), SchemaError> {
+        let settings: Vec<(String, SchemaVersion)> = self
+            .newer_settings()
+            .into_iter()
+            .filter(|(_, version)| *version > target)
+            .map(|(setting, version)| (setting.to_string(), version))
+            .collect();
+        if settings.is_empty() {
+            Ok(())
+        } else {
+            Err(SchemaError::TooNew { target, settings })
+        }
+    }
+
+    /// The task without the settings that Task Scheduler of the target version doesn't have,
+    /// with a warning for each setting that is left out
+    pub fn downgrade(&self, target: SchemaVersion) -> (Task, Vec<ExportWarning>) {
+        let mut task = self.clone();
+        let mut warnings = Vec::new();
+        for (setting, version, set) in newer_settings(&mut task.settings).iter_mut() {
+            if **set && *version > target {
+                **set = false;
+                warnings.push(ExportWarning::new(
+                    "Settings",
+                    format!("`{}` needs schema {} and is left out", setting, version),
+                ));
+            }
+        }
+        if !task.principal.required_privileges.is_empty() && SchemaVersion::V1_3 > target {
+            task.principal.required_privileges.clear();
+            warnings.push(ExportWarning::new(
+                "Principal",
+                format!(
+                    "`required_privileges` needs schema {} and is left out",
+                    SchemaVersion::V1_3
+                ),
+            ));
+        }
+        (task, warnings)
+    }
+
+    /// The settings that are set and need a newer version than 1.2, with that version
+    fn newer_settings(&self) -> Vec<(&'static str, SchemaVersion)> {
+        let mut settings: Vec<(&'static str, SchemaVersion)> =
+            newer_settings(&mut self.settings.clone())
+                .iter()
+                .filter(|(_, _, set)| **set)
+                .map(|(setting, version, _)| (*setting, *version))
+                .collect();
+        if !self.principal.required_privileges.is_empty() {
+            settings.push(("required_privileges", SchemaVersion::V1_3));
+        }
+        settings
+    }
+}
+
+/// The settings that need a newer version than 1.2, named like in manifests
+fn newer_settings(settings: &mut Settings) -> [(&'static str, SchemaVersion, &mut bool); 3] {
+    [
+        (
+            "use_unified_scheduling_engine",
+            SchemaVersion::V1_3,
+            &mut settings.use_unified_scheduling_engine,
+        ),
+        (
+            "disallow_start_on_remote_app_session",
+            SchemaVersion::V1_3,
+            &mut settings.disallow_start_on_remote_app_session,
+        ),
+        ("volatile", SchemaVersion::V1_4, &mut settings.volatile),
+    ]
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::PathBuf;
+
+    use super::*;
+    use crate::{
+        task::{Privilege, TaskPrincipal},
+        Action, Actions, BootTrigger, ExecAction, TaskTriggersBuilder,
+    };
+
+    fn task(settings: Settings) -> Task {
+        Task::new(
+            Actions::from_action(Action::Exec(ExecAction::new(PathBuf::from(
+                "C:\\Tools\\backup.exe",
+            )))),
+            TaskTriggersBuilder::new()
+                .with_boot(BootTrigger::new())
+                .build(),
+        )
+        .with_settings(settings)
+    }
+
+    fn privileged(settings: Settings) -> Task {
+        task(settings)
+            .with_principal(TaskPrincipal::new().with_required_privileges(vec![Privilege::Backup]))
+    }
+
+    fn reasons(warnings: &[ExportWarning]) -> Vec<(&str, &str)> {
+        warnings
+            .iter()
+            .map(|warning| (warning.item(), warning.reason()))
+            .collect()
+    }
+
+    #[test]
+    fn reads_the_highest_version() {
+        assert_eq!(
+            SchemaVersion::from_highest_version(1, 2),
+            SchemaVersion::V1_2
+        );
+        assert_eq!(
+            SchemaVersion::from_highest_version(1, 3),
+            SchemaVersion::V1_3
+        );
+        assert_eq!(
+            SchemaVersion::from_highest_version(1, 4),
+            SchemaVersion::V1_4
+        );
+        assert_eq!(
+            SchemaVersion::from_highest_version(1, 6),
+            SchemaVersion::V1_4
+        );
+        assert_eq!(
+            SchemaVersion::from_highest_version(2, 0),
+            SchemaVersion::V1_4
+        );
+        assert_eq!(
+            SchemaVersion::from_highest_version(1, 1),
+            SchemaVersion::V1_2
+        );
+    }
+
+    #[test]
+    fn writes_the_version_with_its_windows() {
+        assert_eq!(SchemaVersion::V1_3.to_string(), "1.3 (Windows 7)");
+        assert_eq!(SchemaVersion::default(), SchemaVersion::V1_2);
+    }
+
+    #[test]
+    fn uses_the_oldest_version_with_every_setting() {
+        assert_eq!(task(Settings::new()).schema_version(), SchemaVersion::V1_2);
+        assert_eq!(
+            task(Settings::new().with_use_unified_scheduling_engine(true)).schema_version(),
+            SchemaVersion::V1_3
+        );
+        assert_eq!(
+            privileged(Settings::new()).schema_version(),
+            SchemaVersion::V1_3
+        );
+        assert_eq!(
+            privileged(Settings::new().with_volatile(true)).schema_version(),
+            SchemaVersion::V1_4
+        );
+    }
+
+    #[test]
+    fn fails_with_every_setting_that_is_too_new() {
+        let task = privileged(
+            Settings::new()
+                .with_disallow_start_on_remote_app_session(true)
+                .with_volatile(true),
+        );
+        assert_eq!(task.check_schema_version(SchemaVersion::V1_4), Ok(()));
+        assert_eq!(
+            task.check_schema_version(SchemaVersion::V1_3),
+            Err(SchemaError::TooNew {
+                target: SchemaVersion::V1_3,
+                settings: vec![("volatile".to_string(), SchemaVersion::V1_4)],
+            })
+        );
+        let error = task.check_schema_version(SchemaVersion::V1_2).unwrap_err();
+        assert_eq!(
+            error,
+            SchemaError::TooNew {
+                target: SchemaVersion::V1_2,
+                settings: vec![
+                    (
+                        "disallow_start_on_remote_app_session".to_string(),
+                        SchemaVersion::V1_3
+                    ),
+                    ("volatile".to_string(), SchemaVersion::V1_4),
+                    ("required_privileges".to_string(), SchemaVersion::V1_3),
+                ],
+            }
+        );
+        assert_eq!(
+            error.to_string(),
+            "The task is too new for schema 1.2 (Windows Vista): \
+             `disallow_start_on_remote_app_session` needs 1.3 (Windows 7), \
+             `volatile` needs 1.4 (Windows 8), \
+             `required_privileges` needs 1.3 (Windows 7)"
+        );
+    }
+
+    #[test]
+    fn leaves_out_the_settings_that_are_too_new() {
+        let task = privileged(
+            Settings::new()
+                .with_use_unified_scheduling_engine(true)
+                .with_volatile(true),
+        );
+
+        let (downgraded, warnings) = task.downgrade(SchemaVersion::V1_3);
+        assert!(downgraded.settings().use_unified_scheduling_engine());
+        assert!(!downgraded.settings().volatile());
+        assert_eq!(
+            downgraded.principal().required_privileges(),
+            [Privilege::Backup]
+        );
+        assert_eq!(
+            reasons(&warnings),
+            [(
+                "Settings",
+                "`volatile` needs schema 1.4 (Windows 8) and is left out"
+            )]
+        );
+        assert_eq!(downgraded.schema_version(), SchemaVersion::V1_3);
+
+        let (downgraded, warnings) = task.downgrade(SchemaVersion::V1_2);
+        assert!(!downgraded.settings().use_unified_scheduling_engine());
+        assert!(!downgraded.settings().volatile());
+        assert!(downgraded.principal().required_privileges().is_empty());
+        assert_eq!(
+            reasons(&warnings),
+            [
+                (
+                    "Settings",
+                    "`use_unified_scheduling_engine` needs schema 1.3 (Windows 7) and is left out"
+                ),
+                (
+                    "Settings",
+                    "`volatile` needs schema 1.4 (Windows 8) and is left out"
+                ),
+                (
+                    "Principal",
+                    "`required_privileges` needs schema 1.3 (Windows 7) and is left out"
+                ),
+            ]
+        );
+        assert_eq!(downgraded.schema_version(), SchemaVersion::V1_2);
+    }
+
+    #[test]
+    fn keeps_a_task_that_fits_the_version() {
+        let task = task(Settings::new().with_volatile(true));
+        let (downgraded, warnings) = task.downgrade(SchemaVersion::V1_4);
+        assert_eq!(downgraded, task);
+        assert!(warnings.is_empty());
+    }
+}
@@ -0,0 +1,1531 @@
+use std::{collections::HashMap, path::PathBuf};
+
+use chrono::{Datelike, Duration, NaiveDate, NaiveDateTime, NaiveTime, Timelike, Weekday};
+use roxmltree::{Document, Node};
+
+use crate::{
+    calendar::{month_name, weekday_name, WEEKDAYS},
+    duration::to_xml_duration,
+    export::{Export, ExportFile, ExportWarning},
+    task::{RunLevel, Settings, Task, TaskLogon, TaskPrincipal, TaskRegistration},
+    task_xml::{escape, is_service_account},
+    Action, Actions, BootTrigger, Boundary, DailyTrigger, DaysOfMonth, DaysOfWeek, EventTrigger,
+    ExecAction, IdleTrigger, LogonTrigger, MonthlyDowTrigger, MonthlyTrigger, Months, Repetition,
+    SchtasksError, SpecificTimeTrigger, TaskTriggers, TaskTriggersBuilder, TriggerCommon,
+    WeeklyTrigger, WeeksOfMonth,
+};
+
+/// The longest command that `/tr` takes
+const MAX_TASK_RUN: usize = 261;
+
+/// The longest `/du`, which is written as `hhhh:mm`
+const MAX_DURATION_MINUTES: i64 = 9999 * 60 + 59;
+
+/// The longest `/delay`, which is written as `mmmm:ss`
+const MAX_DELAY_MINUTES: i64 = 9999;
+
+impl Task {
+    /// Writes the task as a `schtasks /create` command for the task at the path,
+    /// in a `<name>.cmd` file named after the task.
+    ///
+    /// The flags create a single trigger and a single exec action. When the task needs
+    /// more than the flags can express, like several triggers or an event trigger with
+    /// value queries, the task is also written to `<name>.xml` and created with `/xml`,
+    /// and the warnings say why. Otherwise the warnings are the parts of the task that
+    /// have no flag and are dropped, like the description or most settings.
+    ///
+    /// Dates are written as mm/dd/yyyy, schtasks reads them in the date format of
+    /// the computer, which is this one for English (United States).
+    ///
+    /// https://docs.microsoft.com/en-us/windows-server/administration/windows-commands/schtasks-create
+    pub fn to_schtasks(&self, path: &str) -> Export {
+        let name = path
+            .rsplit(|c| c == '\\' || c == '/')
+            .next()
+            .filter(|name| !name.is_empty())
+            .unwrap_or("task");
+        let task_name = quote_argument(path);
+
+        let mut flags = Flags::default();
+        flags.triggers(&self.triggers, &self.settings);
+        flags.principal(&self.principal);
+        flags.actions(&self.actions);
+        flags.registration(&self.registration);
+        flags.settings(&self.settings, !self.triggers.idle().is_empty());
+
+        let mut lines = vec!["@echo off".to_string()];
+        let mut files = Vec::new();
+        let warnings = if flags.fallback.is_empty() {
+            lines.push(batch_line(&format!(
+                "schtasks /create /tn {} {} /f",
+                task_name,
+                flags.flags.join(" ")
+            )));
+            flags.lost
+        } else {
+            let xml_name = format!("{}.xml", name);
+            // %~dp0 is the folder of the script, so the script can be run from anywhere
+            lines.push(format!(
+                "{} /xml \"%~dp0{}\" /f",
+                batch_line(&format!("schtasks /create /tn {}", task_name)),
+                xml_name.replace('%', "%%")
+            ));
+            let mut warnings = flags.fallback;
+            warnings.push(ExportWarning::new(
+                "Task",
+                format!("is created from {} with /xml", xml_name),
+            ));
+            files.push(ExportFile {
+                name: xml_name,
+                contents: self.to_xml(),
+            });
+            warnings
+        };
+        if flags.disable {
+            lines.push(batch_line(&format!(
+                "schtasks /change /tn {} /disable",
+                task_name
+            )));
+        }
+
+        let mut contents = lines.join("\r\n");
+        contents.push_str("\r\n");
+        files.insert(
+            0,
+            ExportFile {
+                name: format!("{}.cmd", name),
+                contents,
+            },
+        );
+        Export { files, warnings }
+    }
+}
+
+/// The flags of a `schtasks /create` command, and what they can't express
+#[derive(Default)]
+struct Flags {
+    flags: Vec<String>,
+    /// Parts of the task that have no flag and are dropped
+    lost: Vec<ExportWarning>,
+    /// Parts of the task that need `/xml` to be created
+    fallback: Vec<ExportWarning>,
+    /// The task is disabled after it is created
+    disable: bool,
+}
+
+impl Flags {
+    fn flag(&mut self, flag: &str, value: &str) {
+        self.flags
+            .push(format!("{} {}", flag, quote_argument(value)));
+    }
+
+    fn switch(&mut self, flag: &str) {
+        self.flags.push(flag.to_string());
+    }
+
+    fn lost(&mut self, item: &str, reason: impl Into<String>) {
+        self.lost.push(ExportWarning::new(item, reason));
+    }
+
+    fn fallback(&mut self, item: &str, reason: impl Into<String>) {
+        self.fallback.push(ExportWarning::new(item, reason));
+    }
+
+    fn triggers(&mut self, triggers: &TaskTriggers, settings: &Settings) {
+        if triggers.len() != 1 {
+            return self.fallback(
+                "Triggers",
+                format!(
+                    "schtasks creates one trigger, the task has {}",
+                    triggers.len()
+                ),
+            );
+        }
+
+        if let Some(trigger) = triggers.daily().first() {
+            self.schedule("DAILY");
+            if let Some(interval) = trigger.interval.filter(|interval| *interval > 1) {
+                self.flag("/mo", &interval.to_string());
+            }
+            self.calendar("DailyTrigger", &trigger.common, true);
+            self.repetition("DailyTrigger", trigger.repetition.as_ref());
+        } else if let Some(trigger) = triggers.weekly().first() {
+            self.schedule("WEEKLY");
+            if let Some(interval) = trigger.weeks_interval.filter(|interval| *interval > 1) {
+                self.flag("/mo", &interval.to_string());
+            }
+            if trigger.days_of_week.is_empty() {
+                self.fallback("WeeklyTrigger", "runs on no days of the week");
+            }
+            self.flag("/d", &weekday_list(trigger.days_of_week));
+            self.calendar("WeeklyTrigger", &trigger.common, true);
+            self.repetition("WeeklyTrigger", trigger.repetition.as_ref());
+        } else if let Some(trigger) = triggers.monthly().first() {
+            self.schedule("MONTHLY");
+            let days: Vec<String> = trigger
+                .days_of_month
+                .iter()
+                .map(|day| day.to_string())
+                .collect();
+            match (days.is_empty(), trigger.run_on_last_day_of_month) {
+                (true, false) => self.fallback("MonthlyTrigger", "runs on no days of the month"),
+                (false, true) => self.fallback(
+                    "MonthlyTrigger",
+                    "runs on days of the month and the last day, which /mo LASTDAY can't combine",
+                ),
+                (true, true) => self.flag("/mo", "LASTDAY"),
+                (false, false) => self.flag("/d", &days.join(",")),
+            }
+            self.months(
+                "MonthlyTrigger",
+                trigger.months,
+                trigger.run_on_last_day_of_month,
+            );
+            self.calendar("MonthlyTrigger", &trigger.common, true);
+            self.repetition("MonthlyTrigger", trigger.repetition.as_ref());
+        } else if let Some(trigger) = triggers.monthly_dow().first() {
+            self.schedule("MONTHLY");
+            let mut weeks: Vec<&str> = trigger
+                .weeks_of_month
+                .iter()
+                .map(|week| ["FIRST", "SECOND", "THIRD", "FOURTH"][week as usize - 1])
+                .collect();
+            if trigger.run_on_last_week_of_month {
+                weeks.push("LAST");
+            }
+            if weeks.len() != 1 || trigger.days_of_week.iter().count() != 1 {
+                self.fallback(
+                    "MonthlyDowTrigger",
+                    "runs on more than one week or day of the week, /mo takes one week and /d one day",
+                );
+            } else {
+                self.flag("/mo", weeks[0]);
+                self.flag("/d", &weekday_list(trigger.days_of_week));
+            }
+            self.months("MonthlyDowTrigger", trigger.months, false);
+            self.calendar("MonthlyDowTrigger", &trigger.common, true);
+            self.repetition("MonthlyDowTrigger", trigger.repetition.as_ref());
+        } else if let Some(trigger) = triggers.specific_times().first() {
+            match trigger.repetition.as_ref() {
+                // repeating indefinitely is what the minute and hourly schedules create
+                Some(repetition) if repetition.duration.is_none() => {
+                    match whole_minutes(repetition.interval) {
+                        Some(minutes) if minutes % 60 == 0 && minutes / 60 <= 23 => {
+                            self.schedule("HOURLY");
+                            self.flag("/mo", &(minutes / 60).to_string());
+                        }
+                        Some(minutes) if (1..=1439).contains(&minutes) => {
+                            self.schedule("MINUTE");
+                            self.flag("/mo", &minutes.to_string());
+                        }
+                        _ => self.fallback(
+                            "TimeTrigger",
+                            format!(
+                                "repeats every {} indefinitely, which /sc MINUTE and HOURLY can't express",
+                                to_xml_duration(&repetition.interval)
+                            ),
+                        ),
+                    }
+                    self.calendar("TimeTrigger", &trigger.common, true);
+                }
+                repetition => {
+                    self.schedule("ONCE");
+                    self.calendar("TimeTrigger", &trigger.common, false);
+                    self.repetition("TimeTrigger", repetition);
+                }
+            }
+        } else if let Some(trigger) = triggers.boot().first() {
+            self.schedule("ONSTART");
+            self.untimed("BootTrigger", &trigger.common);
+            self.delay("BootTrigger", trigger.delay);
+        } else if let Some(trigger) = triggers.logon().first() {
+            self.schedule("ONLOGON");
+            if let Some(user_id) = &trigger.user_id {
+                self.fallback(
+                    "LogonTrigger",
+                    format!(
+                        "runs when {} logs on, /sc ONLOGON runs when any user logs on",
+                        user_id
+                    ),
+                );
+            }
+            self.untimed("LogonTrigger", &trigger.common);
+            self.delay("LogonTrigger", trigger.delay);
+        } else if let Some(trigger) = triggers.idle().first() {
+            self.schedule("ONIDLE");
+            match whole_minutes(settings.idle_conditions.duration) {
+                Some(minutes) if (1..=999).contains(&minutes) => {
+                    self.flag("/i", &minutes.to_string())
+                }
+                _ => self.fallback(
+                    "IdleTrigger",
+                    format!(
+                        "waits for {} of idle time, /i takes 1 to 999 minutes",
+                        to_xml_duration(&settings.idle_conditions.duration)
+                    ),
+                ),
+            }
+            self.untimed("IdleTrigger", &trigger.common);
+            self.no_repetition("IdleTrigger", trigger.repetition.as_ref());
+        } else if let Some(trigger) = triggers.event().first() {
+            self.schedule("ONEVENT");
+            match event_query(&trigger.subscription) {
+                Some((channel, query)) => {
+                    self.flag("/ec", &channel);
+                    self.flag("/mo", &query);
+                }
+                None => self.fallback(
+                    "EventTrigger",
+                    "the subscription isn't a single query, /ec and /mo take one channel and XPath query",
+                ),
+            }
+            if !trigger.value_queries.is_empty() {
+                self.fallback("EventTrigger", "value queries have no flag");
+            }
+            self.untimed("EventTrigger", &trigger.common);
+            self.delay("EventTrigger", trigger.delay);
+            self.no_repetition("EventTrigger", trigger.repetition.as_ref());
+        } else if !triggers.registration().is_empty() {
+            self.fallback("RegistrationTrigger", "has no /sc schedule");
+        } else if !triggers.session_state_change().is_empty() {
+            self.fallback("SessionStateChangeTrigger", "has no /sc schedule");
+        }
+    }
+
+    fn schedule(&mut self, schedule: &str) {
+        self.flag("/sc", schedule);
+    }
+
+    /// The parts of every trigger that have no flag
+    fn common(&mut self, item: &str, common: &TriggerCommon) {
+        if !common.enabled {
+            self.fallback(item, "is disabled, schtasks creates enabled triggers");
+        }
+        if common.id.is_some() {
+            self.lost(item, "the id has no flag and is dropped");
+        }
+        if common.execution_time_limit.is_some() {
+            self.lost(
+                item,
+                "the execution time limit of the trigger has no flag and is dropped",
+            );
+        }
+        if common.random_delay.is_some() {
+            self.lost(item, "the random delay has no flag and is dropped");
+        }
+    }
+
+    /// The start and end of a trigger that runs on a schedule, `/ed` isn't valid
+    /// for triggers that run once
+    fn calendar(&mut self, item: &str, common: &TriggerCommon, has_end: bool) {
+        self.common(item, common);
+        if let Some(start) = common.start {
+            if !start.is_local() {
+                self.lost(
+                    item,
+                    "the start is written as the time on its own clock, schtasks makes it local time, so it no longer synchronizes across time zones",
+                );
+            }
+            let time = start.clock_time();
+            if time.second() != 0 || time.nanosecond() != 0 {
+                self.lost(
+                    item,
+                    "/st takes whole minutes, the seconds of the start are dropped",
+                );
+            }
+            self.flag("/sd", &flag_date(time));
+            self.flag("/st", &time.format("%H:%M").to_string());
+        }
+        if let Some(end) = common.end {
+            if has_end {
+                let time = end.clock_time();
+                if time.time() != last_second() {
+                    self.lost(
+                        item,
+                        "/ed takes the last day the trigger runs, so the end moves to the end of that day",
+                    );
+                }
+                self.flag("/ed", &flag_date(time));
+            } else {
+                self.lost(
+                    item,
+                    "the end boundary has no flag for /sc ONCE and is dropped",
+                );
+            }
+        }
+    }
+
+    /// The start and end of a trigger that runs on an event, which have no flag
+    fn untimed(&mut self, item: &str, common: &TriggerCommon) {
+        self.common(item, common);
+        if common.start.is_some() || common.end.is_some() {
+            self.lost(
+                item,
+                "the start and end boundaries have no flag for this schedule and are dropped",
+            );
+        }
+    }
+
+    fn repetition(&mut self, item: &str, repetition: Option<&Repetition>) {
+        let repetition = match repetition {
+            Some(repetition) => repetition,
+            None => return,
+        };
+        let interval = match whole_minutes(repetition.interval) {
+            Some(minutes) if minutes > 0 => minutes,
+            _ => {
+                return self.fallback(
+                    item,
+                    format!(
+                        "repeats every {}, /ri takes whole minutes",
+                        to_xml_duration(&repetition.interval)
+                    ),
+                )
+            }
+        };
+        let duration = match repetition.duration.map(whole_minutes) {
+            Some(Some(minutes)) if minutes <= MAX_DURATION_MINUTES => minutes,
+            Some(_) => {
+                return self.fallback(
+                    item,
+                    "repeats for longer than /du takes, or not for whole minutes",
+                )
+            }
+            None => {
+                return self.fallback(
+                    item,
+                    "repeats indefinitely, /ri needs a duration for this schedule",
+                )
+            }
+        };
+        self.flag("/ri", &interval.to_string());
+        self.flag("/du", &format!("{:04}:{:02}", duration / 60, duration % 60));
+        if repetition.stop_at_duration_end {
+            self.switch("/k");
+        }
+    }
+
+    /// `/ri` is only valid for time and calendar schedules
+    fn no_repetition(&mut self, item: &str, repetition: Option<&Repetition>) {
+        if repetition.is_some() {
+            self.fallback(item, "repeats, /ri isn't valid for this schedule");
+        }
+    }
+
+    fn delay(&mut self, item: &str, delay: Option<Duration>) {
+        let delay = match delay {
+            Some(delay) => delay,
+            None => return,
+        };
+        let seconds = delay.num_seconds();
+        if seconds / 60 > MAX_DELAY_MINUTES {
+            return self.fallback(
+                item,
+                format!(
+                    "waits {}, which is longer than /delay takes",
+                    to_xml_duration(&delay)
+                ),
+            );
+        }
+        self.flag(
+            "/delay",
+            &format!("{:04}:{:02}", seconds / 60, seconds % 60),
+        );
+    }
+
+    /// `/m` is needed with `/mo LASTDAY`, otherwise it is left out for every month
+    fn months(&mut self, item: &str, months: Months, required: bool) {
+        if months.is_empty() {
+            self.fallback(item, "runs in no months");
+        } else if months == Months::all() {
+            if required {
+                self.flag("/m", "*");
+            }
+        } else {
+            let names: Vec<String> = months
+                .iter()
+                .map(|month| month_name(month)[..3].to_uppercase())
+                .collect();
+            self.flag("/m", &names.join(","));
+        }
+    }
+
+    fn principal(&mut self, principal: &TaskPrincipal) {
+        if let Some(group_id) = &principal.group_id {
+            self.fallback(
+                "Principal",
+                format!("runs for the members of {}, /ru takes a user", group_id),
+            );
+        }
+        if let Some(user_id) = &principal.user_id {
+            self.flag("/ru", user_id);
+        }
+        match principal.logon_type {
+            Some(TaskLogon::S4U) => self.switch("/np"),
+            Some(TaskLogon::InteractiveToken) => self.switch("/it"),
+            _ => {}
+        }
+        if principal.run_level == RunLevel::HighestAvailable {
+            self.flag("/rl", "HIGHEST");
+        }
+    }
+
+    fn actions(&mut self, actions: &Actions) {
+        let mut iter = actions.iter();
+        let action = match (iter.next(), iter.next()) {
+            (Some(Action::Exec(action)), None) => action,
+            (Some(Action::ComHandler(_)), None) => {
+                return self.fallback("ComHandlerAction", "/tr only runs programs")
+            }
+            _ => {
+                return self.fallback(
+                    "Actions",
+                    format!(
+                        "schtasks creates one action, the task has {}",
+                        actions.len()
+                    ),
+                )
+            }
+        };
+        let mut run = quote_argument(&action.path.to_string_lossy());
+        if let Some(arguments) = action
+            .arguments
+            .as_deref()
+            .filter(|arguments| !arguments.is_empty())
+        {
+            run.push(' ');
+            run.push_str(arguments);
+        }
+        if run.chars().count() > MAX_TASK_RUN {
+            return self.fallback(
+                "ExecAction",
+                format!(
+                    "the command is longer than the {} characters /tr takes",
+                    MAX_TASK_RUN
+                ),
+            );
+        }
+        self.flag("/tr", &run);
+        if action.id.is_some() {
+            self.lost("ExecAction", "the id has no flag and is dropped");
+        }
+        if action.working_directory.is_some() {
+            self.lost(
+                "ExecAction",
+                "the working directory has no flag and is dropped",
+            );
+        }
+    }
+
+    fn registration(&mut self, registration: &TaskRegistration) {
+        let fields = [
+            ("author", &registration.author),
+            ("description", &registration.description),
+            ("documentation", &registration.documentation),
+            ("source", &registration.source),
+            ("version", &registration.version),
+        ];
+        for (field, value) in fields.iter() {
+            if value.is_some() {
+                self.lost(
+                    "Registration",
+                    format!("the {} has no flag and is dropped", field),
+                );
+            }
+        }
+    }
+
+    /// The idle duration is written with `/i` when the trigger is an idle trigger
+    fn settings(&mut self, settings: &Settings, idle_trigger: bool) {
+        let default = Settings::default();
+        self.disable = !settings.enabled;
+        match settings.delete_expired_task_after {
+            Some(after) if after.is_zero() => self.switch("/z"),
+            Some(after) => {
+                self.switch("/z");
+                self.lost(
+                    "Settings",
+                    format!(
+                        "/z deletes the task as soon as it expires, the wait of {} is dropped",
+                        to_xml_duration(&after)
+                    ),
+                );
+            }
+            None => {}
+        }
+        let mut idle_conditions = settings.idle_conditions.clone();
+        if idle_trigger {
+            idle_conditions.duration = default.idle_conditions.duration;
+        }
+        let changed = [
+            (
+                "allow_demand_start",
+                settings.allow_demand_start != default.allow_demand_start,
+            ),
+            (
+                "allow_hard_terminate",
+                settings.allow_hard_terminate != default.allow_hard_terminate,
+            ),
+            (
+                "disallow_start_if_on_batteries",
+                settings.disallow_start_if_on_batteries != default.disallow_start_if_on_batteries,
+            ),
+            (
+                "stop_if_going_on_batteries",
+                settings.stop_if_going_on_batteries != default.stop_if_going_on_batteries,
+            ),
+            ("hidden", settings.hidden != default.hidden),
+            (
+                "start_when_available",
+                settings.start_when_available != default.start_when_available,
+            ),
+            (
+                "run_only_if_idle",
+                settings.run_only_if_idle != default.run_only_if_idle,
+            ),
+            (
+                "run_only_if_network_available",
+                settings.run_only_if_network_available != default.run_only_if_network_available,
+            ),
+            ("wake_to_run", settings.wake_to_run != default.wake_to_run),
+            (
+                "execution_time_limit",
+                settings.execution_time_limit != default.execution_time_limit,
+            ),
+            (
+                "multiple_instances",
+                settings.multiple_instances != default.multiple_instances,
+            ),
+            ("priority", settings.priority != default.priority),
+            (
+                "restart_on_failure",
+                settings.restart_on_failure != default.restart_on_failure,
+            ),
+            (
+                "idle_conditions",
+                idle_conditions != default.idle_conditions,
+            ),
+            (
+                "use_unified_scheduling_engine",
+                settings.use_unified_scheduling_engine,
+            ),
+            (
+                "disallow_start_on_remote_app_session",
+                settings.disallow_start_on_remote_app_session,
+            ),
+            ("volatile", settings.volatile),
+        ];
+        for (field, changed) in changed.iter() {
+            if *changed {
+                self.lost(
+                    "Settings",
+                    format!("`{}` has no flag and is dropped", field),
+                );
+            }
+        }
+    }
+}
+
+/// The duration in minutes, when it is whole minutes
+fn whole_minutes(duration: Duration) -> Option<i64> {
+    if duration < Duration::zero() || duration != Duration::minutes(duration.num_minutes()) {
+        return None;
+    }
+    Some(duration.num_minutes())
+}
+
+/// A date for `/sd` and `/ed`
+fn flag_date(time: NaiveDateTime) -> String {
+    time.format("%m/%d/%Y").to_string()
+}
+
+/// Days of the week for `/d`, like `MON,FRI`
+fn weekday_list(days: DaysOfWeek) -> String {
+    let names: Vec<String> = days
+        .iter()
+        .map(|day| weekday_name(day)[..3].to_uppercase())
+        .collect();
+    names.join(",")
+}
+
+/// The channel and XPath query of a subscription with a single query,
+/// the only kind `/ec` and `/mo` can express
+fn event_query(subscription: &str) -> Option<(String, String)> {
+    let document = Document::parse(subscription).ok()?;
+    let root = document.root_element();
+    if root.tag_name().name() != "QueryList" {
+        return None;
+    }
+    let mut queries = root.children().filter(Node::is_element);
+    let query = queries.next()?;
+    let mut selects = query.children().filter(Node::is_element);
+    let select = selects.next()?;
+    if queries.next().is_some() || selects.next().is_some() || select.tag_name().name() != "Select"
+    {
+        return None;
+    }
+    let channel = select
+        .attribute("Path")
+        .or_else(|| query.attribute("Path"))?;
+    Some((channel.to_string(), select.text()?.trim().to_string()))
+}
+
+/// Quotes an argument the way Windows programs split their command line,
+/// when it has spaces or quotes
+fn quote_argument(argument: &str) -> String {
+    if !argument.is_empty() && !argument.contains(|c: char| c.is_whitespace() || c == '"') {
+        return argument.to_string();
+    }
+    let mut quoted = String::from("\"");
+    let mut backslashes = 0;
+    for c in argument.chars() {
+        if c == '\\' {
+            backslashes += 1;
+            continue;
+        }
+        // backslashes are only special before a quote
+        if c == '"' {
+            quoted.push_str(&"\\".repeat(backslashes * 2 + 1));
+        } else {
+            quoted.push_str(&"\\".repeat(backslashes));
+        }
+        backslashes = 0;
+        quoted.push(c);
+    }
+    quoted.push_str(&"\\".repeat(backslashes * 2));
+    quoted.push('"');
+    quoted
+}
+
+/// Escapes a command for a batch file, `%` is doubled and the characters that
+/// cmd treats specially are escaped with `^` outside of quotes
+fn batch_line(line: &str) -> String {
+    let mut escaped = String::with_capacity(line.len());
+    let mut quoted = false;
+    for c in line.chars() {
+        match c {
+            '"' => quoted = !quoted,
+            '%' => escaped.push('%'),
+            '&' | '|' | '<' | '>' | '^' if !quoted => escaped.push('^'),
+            _ => {}
+        }
+        escaped.push(c);
+    }
+    escaped
+}
+
+/// A `schtasks /create` command line, read into the task it creates
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct SchtasksCommand {
+    path: String,
+    task: Task,
+}
+
+impl SchtasksCommand {
+    /// Reads a `schtasks /create` command line, as schtasks gets it after cmd has
+    /// expanded `%` variables and removed `^` escapes. The command can start with
+    /// `schtasks`, or with `/create`.
+    ///
+    /// Every `/sc` schedule becomes a trigger: `MINUTE` and `HOURLY` are a time trigger
+    /// that repeats indefinitely and `MONTHLY` with `/mo FIRST` to `LAST` is a monthly
+    /// day of the week trigger. Dates are mm/dd/yyyy, or yyyy/mm/dd, and `/ed` is the last
+    /// day the trigger runs. Like schtasks, `/sd` defaults to today and `/st` to the current
+    /// minute, also when both are left out, except for the `ON...` schedules, which only
+    /// have a start when one is given. A weekly schedule runs on Monday and a monthly one
+    /// on the first day of the month.
+    ///
+    /// `/rp` makes the logon type `Password`, the password itself isn't kept.
+    /// `/s`, `/u`, `/p`, `/f`, `/v1` and `/hresult` only change how schtasks runs and are ignored.
+    pub fn parse(command_line: &str) -> Result<SchtasksCommand, SchtasksError> {
+        let switches = Switches::parse(&split_command_line(command_line))?;
+        switches.check()?;
+        let schedule = switches.schedule()?;
+        let path = switches
+            .value("/tn")
+            .ok_or_else(|| SchtasksError::Missing("/tn".to_string()))?
+            .to_string();
+        let run = switches
+            .value("/tr")
+            .ok_or_else(|| SchtasksError::Missing("/tr".to_string()))?;
+
+        let now = Boundary::now().local_time();
+        let start_date = switches.date("/sd")?;
+        let start_time = switches.time("/st")?;
+        if schedule == Schedule::Once && start_time.is_none() {
+            return Err(SchtasksError::Missing("/st for /sc ONCE".to_string()));
+        }
+        let start = match (start_date, start_time) {
+            (None, None) if schedule.is_event() => None,
+            (date, time) => Some(NaiveDateTime::new(
+                date.unwrap_or_else(|| now.date()),
+                time.unwrap_or_else(|| now.time()),
+            )),
+        };
+        let common = TriggerCommon {
+            start: start.map(Boundary::Local),
+            end: switches
+                .date("/ed")?
+                .map(|date| Boundary::Local(date.and_time(last_second()))),
+            ..TriggerCommon::new()
+        };
+
+        let mut settings = Settings::default();
+        if switches.has("/z") {
+            settings.delete_expired_task_after = Some(Duration::zero());
+        }
+        let builder = TaskTriggersBuilder::new();
+        let triggers = match schedule {
+            Schedule::Minute | Schedule::Hourly => {
+                let unit = if schedule == Schedule::Minute {
+                    Duration::minutes
+                } else {
+                    Duration::hours
+                };
+                let limit = if schedule == Schedule::Minute {
+                    1439
+                } else {
+                    23
+                };
+                let interval = switches.number("/mo", 1, limit)?.unwrap_or(1);
+                let start = start.unwrap_or(now);
+                builder.with_specific_time(SpecificTimeTrigger {
+                    common: TriggerCommon {
+                        start: Some(Boundary::Local(start)),
+                        ..common
+                    },
+                    repetition: Some(Repetition {
+                        interval: unit(i64::from(interval)),
+                        duration: switches.repetition_duration(start.time())?,
+                        stop_at_duration_end: switches.has("/k"),
+                    }),
+                })
+            }
+            Schedule::Daily => builder.with_daily(DailyTrigger {
+                interval: switches.number("/mo", 1, 365)?.map(|days| days as u16),
+                repetition: switches.repetition(start)?,
+                common,
+            }),
+            Schedule::Weekly => builder.with_weekly(WeeklyTrigger {
+                days_of_week: match switches.value("/d") {
+                    Some(days) => weekdays(days)?,
+                    None => DaysOfWeek::new().with(Weekday::Mon),
+                },
+                weeks_interval: switches.number("/mo", 1, 52)?.map(|weeks| weeks as u16),
+                repetition: switches.repetition(start)?,
+                common,
+            }),
+            Schedule::Monthly => {
+                let modifier = switches.value("/mo").map(str::to_uppercase);
+                let week = ["FIRST", "SECOND", "THIRD", "FOURTH", "LAST"]
+                    .iter()
+                    .position(|week| Some(*week) == modifier.as_deref());
+                match (modifier.as_deref(), week) {
+                    (_, Some(week)) => {
+                        let day = switches.value("/d").ok_or_else(|| {
+                            SchtasksError::Missing(format!(
+                                "/d for /mo {}",
+                                modifier.as_deref().unwrap_or_default()
+                            ))
+                        })?;
+                        let days_of_week = weekdays(day)?;
+                        let weeks_of_month = if week < 4 {
+                            WeeksOfMonth::new().with(week as u32 + 1)
+                        } else {
+                            WeeksOfMonth::new()
+                        };
+                        builder.with_monthly_dow(MonthlyDowTrigger {
+                            days_of_week,
+                            weeks_of_month,
+                            months: switches.months()?.unwrap_or_else(Months::all),
+                            run_on_last_week_of_month: week == 4,
+                            repetition: switches.repetition(start)?,
+                            common,
+                        })
+                    }
+                    (Some("LASTDAY"), None) => {
+                        if switches.has("/d") {
+                            return Err(SchtasksError::Conflict {
+                                switch: "/d".to_string(),
+                                other: "/mo LASTDAY".to_string(),
+                            });
+                        }
+                        builder.with_monthly(MonthlyTrigger {
+                            days_of_month: DaysOfMonth::new(),
+                            months: switches.months()?.unwrap_or_else(Months::all),
+                            run_on_last_day_of_month: true,
+                            repetition: switches.repetition(start)?,
+                            common,
+                        })
+                    }
+                    // every few months, counting from the month the schedule starts in
+                    (_, None) => {
+                        let every = switches.number("/mo", 1, 12)?;
+                        if every.is_some() && switches.has("/m") {
+                            return Err(SchtasksError::Conflict {
+                                switch: "/m".to_string(),
+                                other: "/mo".to_string(),
+                            });
+                        }
+                        let months = match every {
+                            Some(every) => {
+                                let first = start.unwrap_or(now).month() - 1;
+                                (0..12)
+                                    .step_by(every as usize)
+                                    .map(|offset| (first + offset) % 12 + 1)
+                                    .collect()
+                            }
+                            None => switches.months()?.unwrap_or_else(Months::all),
+                        };
+                        builder.with_monthly(MonthlyTrigger {
+                            days_of_month: match switches.value("/d") {
+                                Some(days) => days_of_month(days)?,
+                                None => DaysOfMonth::new().with(1),
+                            },
+                            months,
+                            run_on_last_day_of_month: false,
+                            repetition: switches.repetition(start)?,
+                            common,
+                        })
+                    }
+                }
+            }
+            Schedule::Once => builder.with_specific_time(SpecificTimeTrigger {
+                repetition: switches.repetition(start)?,
+                common,
+            }),
+            Schedule::OnStart => builder.with_boot(BootTrigger {
+                delay: switches.delay()?,
+                common,
+            }),
+            Schedule::OnLogon => builder.with_logon(LogonTrigger {
+                user_id: None,
+                delay: switches.delay()?,
+                common,
+            }),
+            Schedule::OnIdle => {
+                let minutes = switches
+                    .number("/i", 1, 999)?
+                    .ok_or_else(|| SchtasksError::Missing("/i for /sc ONIDLE".to_string()))?;
+                settings.idle_conditions.duration = Duration::minutes(i64::from(minutes));
+                builder.with_idle(IdleTrigger {
+                    common,
+                    repetition: None,
+                })
+            }
+            Schedule::OnEvent => {
+                let needs =
+                    |switch: &str| SchtasksError::Missing(format!("{} for /sc ONEVENT", switch));
+                let channel = switches.value("/ec").ok_or_else(|| needs("/ec"))?;
+                let query = switches.value("/mo").ok_or_else(|| needs("/mo"))?;
+                builder.with_event(EventTrigger {
+                    subscription: format!(
+                        "<QueryList><Query Id=\"0\" Path=\"{0}\"><Select Path=\"{0}\">{1}</Select></Query></QueryList>",
+                        escape(channel),
+                        escape(query)
+                    ),
+                    delay: switches.delay()?,
+                    value_queries: Vec::new(),
+                    repetition: None,
+                    common,
+                })
+            }
+        };
+
+        let task = Task::new(
+            Actions::from_action(Action::Exec(exec_action(run))),
+            triggers.build(),
+        )
+        .with_principal(switches.principal())
+        .with_settings(settings);
+        Ok(SchtasksCommand { path, task })
+    }
+
+    /// The path of the task from `/tn`
+    pub fn path(&self) -> &str {
+        &self.path
+    }
+
+    pub fn task(&self) -> &Task {
+        &self.task
+    }
+}
+
+/// The `/sc` schedules
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Schedule {
+    Minute,
+    Hourly,
+    Daily,
+    Weekly,
+    Monthly,
+    Once,
+    OnStart,
+    OnLogon,
+    OnIdle,
+    OnEvent,
+}
+
+impl Schedule {
+    const ALL: [Schedule; 10] = [
+        Schedule::Minute,
+        Schedule::Hourly,
+        Schedule::Daily,
+        Schedule::Weekly,
+        Schedule::Monthly,
+        Schedule::Once,
+        Schedule::OnStart,
+        Schedule::OnLogon,
+        Schedule::OnIdle,
+        Schedule::OnEvent,
+    ];
+
+    fn name(self) -> &'static str {
+        match self {
+            Schedule::Minute => "MINUTE",
+            Schedule::Hourly => "HOURLY",
+            Schedule::Daily => "DAILY",
+            Schedule::Weekly => "WEEKLY",
+            Schedule::Monthly => "MONTHLY",
+            Schedule::Once => "ONCE",
+            Schedule::OnStart => "ONSTART",
+            Schedule::OnLogon => "ONLOGON",
+            Schedule::OnIdle => "ONIDLE",
+            Schedule::OnEvent => "ONEVENT",
+        }
+    }
+
+    /// Whether the schedule starts on an event rather than at a time
+    fn is_event(self) -> bool {
+        matches!(
+            self,
+            Schedule::OnStart | Schedule::OnLogon | Schedule::OnIdle | Schedule::OnEvent
+        )
+    }
+
+    /// Whether the switch can be used with the schedule
+    fn allows(self, switch: &str) -> bool {
+        use Schedule::*;
+        match switch {
+            "/mo" => !matches!(self, Once | OnStart | OnLogon | OnIdle),
+            "/d" => matches!(self, Weekly | Monthly),
+            "/m" => self == Monthly,
+            "/i" => self == OnIdle,
+            "/st" | "/sd" | "/et" | "/du" | "/k" => {
+                matches!(self, Minute | Hourly | Daily | Weekly | Monthly | Once)
+            }
+            "/ed" => matches!(self, Minute | Hourly | Daily | Weekly | Monthly),
+            "/ri" => matches!(self, Daily | Weekly | Monthly | Once),
+            "/delay" => matches!(self, OnStart | OnLogon | OnEvent),
+            "/ec" => self == OnEvent,
+            _ => true,
+        }
+    }
+}
+
+/// Switches that take a value
+const VALUE_SWITCHES: [&str; 22] = [
+    "/sc", "/tn", "/tr", "/s", "/u", "/p", "/ru", "/rp", "/mo", "/d", "/m", "/i", "/st", "/ri",
+    "/et", "/du", "/sd", "/ed", "/ec", "/rl", "/delay", "/xml",
+];
+
+/// Switches that are on their own
+const FLAG_SWITCHES: [&str; 7] = ["/k", "/it", "/z", "/f", "/np", "/v1", "/hresult"];
+
+/// The switches of a command, by their lowercase names
+struct Switches {
+    values: HashMap<String, Option<String>>,
+}
+
+impl Switches {
+    fn parse(arguments: &[String]) -> Result<Switches, SchtasksError> {
+        let mut arguments = arguments.iter().peekable();
+        let is_program = |argument: &String| {
+            let name = argument
+                .rsplit(|c| c == '\\' || c == '/')
+                .next()
+                .unwrap_or_default()
+                .to_lowercase();
+            name == "schtasks" || name == "schtasks.exe"
+        };
+        if arguments
+            .peek()
+            .map_or(false, |argument| is_program(argument))
+        {
+            arguments.next();
+        }
+        match arguments.next().map(|argument| switch_name(argument)) {
+            Some(Some(create)) if create == "/create" => {}
+            _ => {
+                return Err(SchtasksError::Syntax(
+                    "the command isn't schtasks /create".to_string(),
+                ))
+            }
+        }
+
+        let mut values = HashMap::new();
+        while let Some(argument) = arguments.next() {
+            let switch = switch_name(argument)
+                .ok_or_else(|| SchtasksError::Syntax(format!("{} isn't a switch", argument)))?;
+            let value = if switch == "/rp" {
+                // without a password schtasks asks for it
+                arguments
+                    .next_if(|value| switch_name(value).is_none())
+                    .cloned()
+            } else if VALUE_SWITCHES.contains(&switch.as_str()) {
+                Some(
+                    arguments
+                        .next()
+                        .ok_or_else(|| SchtasksError::MissingValue(switch.clone()))?
+                        .clone(),
+                )
+            } else if FLAG_SWITCHES.contains(&switch.as_str()) {
+                None
+            } else {
+                return Err(SchtasksError::UnknownSwitch(argument.clone()));
+            };
+            if values.insert(switch.clone(), value).is_some() {
+                return Err(SchtasksError::Duplicate(switch));
+            }
+        }
+        Ok(Switches { values })
+    }
+
+    /// Switches that can't be used together
+    fn check(&self) -> Result<(), SchtasksError> {
+        if self.has("/xml") {
+            return Err(SchtasksError::Unsupported(
+                "the task is created from an XML file, read it with Task::from_xml".to_string(),
+            ));
+        }
+        let conflicts = [("/et", "/du"), ("/np", "/rp"), ("/np", "/it")];
+        for (switch, other) in conflicts.iter() {
+            if self.has(switch) && self.has(other) {
+                return Err(SchtasksError::Conflict {
+                    switch: switch.to_string(),
+                    other: other.to_string(),
+                });
+            }
+        }
+        let requires = [
+            ("/k", "/et", "/du"),
+            ("/rp", "/ru", "/ru"),
+            ("/np", "/ru", "/ru"),
+        ];
+        for (switch, needs, or) in requires.iter() {
+            if self.has(switch) && !self.has(needs) && !self.has(or) {
+                let needs = if needs == or {
+                    needs.to_string()
+                } else {
+                    format!("{} or {}", needs, or)
+                };
+                return Err(SchtasksError::Requires {
+                    switch: switch.to_string(),
+                    needs,
+                });
+            }
+        }
+        Ok(())
+    }
+
+    fn schedule(&self) -> Result<Schedule, SchtasksError> {
+        let name = self
+            .value("/sc")
+            .ok_or_else(|| SchtasksError::Missing("/sc".to_string()))?;
+        let schedule = Schedule::ALL
+            .iter()
+            .copied()
+            .find(|schedule| schedule.name().eq_ignore_ascii_case(name))
+            .ok_or_else(|| self.invalid("/sc", name))?;
+        let mut switches: Vec<&String> = self.values.keys().collect();
+        switches.sort();
+        match switches.into_iter().find(|switch| !schedule.allows(switch)) {
+            Some(switch) => Err(SchtasksError::Conflict {
+                switch: switch.clone(),
+                other: format!("/sc {}", schedule.name()),
+            }),
+            None => Ok(schedule),
+        }
+    }
+
+    fn has(&self, switch: &str) -> bool {
+        self.values.contains_key(switch)
+    }
+
+    fn value(&self, switch: &str) -> Option<&str> {
+        self.values.get(switch).and_then(|value| value.as_deref())
+    }
+
+    fn invalid(&self, switch: &str, value: &str) -> SchtasksError {
+        SchtasksError::Invalid {
+            switch: switch.to_string(),
+            value: value.to_string(),
+        }
+    }
+
+    /// A number from `min` to `max`
+    fn number(&self, switch: &str, min: u32, max: u32) -> Result<Option<u32>, SchtasksError> {
+        self.value(switch)
+            .map(|value| {
+                value
+                    .trim()
+                    .parse()
+                    .ok()
+                    .filter(|number| (min..=max).contains(number))
+                    .ok_or_else(|| self.invalid(switch, value))
+            })
+            .transpose()
+    }
+
+    /// A date as mm/dd/yyyy, or yyyy/mm/dd
+    fn date(&self, switch: &str) -> Result<Option<NaiveDate>, SchtasksError> {
+        self.value(switch)
+            .map(|value| {
+                ["%m/%d/%Y", "%Y/%m/%d", "%Y-%m-%d"]
+                    .iter()
+                    .find_map(|format| NaiveDate::parse_from_str(value.trim(), format).ok())
+                    .ok_or_else(|| self.invalid(switch, value))
+            })
+            .transpose()
+    }
+
+    /// A time of day as HH:mm, or HH:mm:ss
+    fn time(&self, switch: &str) -> Result<Option<NaiveTime>, SchtasksError> {
+        self.value(switch)
+            .map(|value| {
+                ["%H:%M", "%H:%M:%S"]
+                    .iter()
+                    .find_map(|format| NaiveTime::parse_from_str(value.trim(), format).ok())
+                    .ok_or_else(|| self.invalid(switch, value))
+            })
+            .transpose()
+    }
+
+    /// A duration of `large:small`, like `hhhh:mm` for `/du`, where the small part is below 60
+    fn duration(
+        &self,
+        switch: &str,
+        large: fn(i64) -> Duration,
+        small: fn(i64) -> Duration,
+    ) -> Result<Option<Duration>, SchtasksError> {
+        self.value(switch)
+            .map(|value| {
+                let (first, second) = value.trim().split_once(':').unwrap_or(("", ""));
+                match (first.parse::<u16>(), second.parse::<u8>()) {
+                    (Ok(first), Ok(second)) if first <= 9999 && second < 60 => {
+                        Ok(large(i64::from(first)) + small(i64::from(second)))
+                    }
+                    _ => Err(self.invalid(switch, value)),
+                }
+            })
+            .transpose()
+    }
+
+    fn delay(&self) -> Result<Option<Duration>, SchtasksError> {
+        self.duration("/delay", Duration::minutes, Duration::seconds)
+    }
+
+    /// How long a repetition lasts from `/du`, or from the start until `/et`
+    fn repetition_duration(&self, start: NaiveTime) -> Result<Option<Duration>, SchtasksError> {
+        if let Some(end) = self.time("/et")? {
+            let duration = end - start;
+            // an end time before the start is on the next day
+            return Ok(Some(if duration > Duration::zero() {
+                duration
+            } else {
+                duration + Duration::days(1)
+            }));
+        }
+        self.duration("/du", Duration::hours, Duration::minutes)
+    }
+
+    /// The repetition from `/ri`, `/et`, `/du` and `/k`. The interval is 10 minutes
+    /// when there is only an end or duration, and it repeats indefinitely when there
+    /// is only an interval.
+    fn repetition(
+        &self,
+        start: Option<NaiveDateTime>,
+    ) -> Result<Option<Repetition>, SchtasksError> {
+        let interval = self.number("/ri", 1, 599_940)?;
+        if interval.is_none() && !self.has("/et") && !self.has("/du") {
+            return Ok(None);
+        }
+        let start = start.unwrap_or_else(|| Boundary::now().local_time()).time();
+        Ok(Some(Repetition {
+            interval: Duration::minutes(i64::from(interval.unwrap_or(10))),
+            duration: self.repetition_duration(start)?,
+            stop_at_duration_end: self.has("/k"),
+        }))
+    }
+
+    fn months(&self) -> Result<Option<Months>, SchtasksError> {
+        self.value("/m")
+            .map(|value| {
+                if value.trim() == "*" {
+                    return Ok(Months::all());
+                }
+                value.split(',').try_fold(Months::new(), |months, name| {
+                    (1..=12)
+                        .find(|month| month_name(*month)[..3].eq_ignore_ascii_case(name.trim()))
+                        .map(|month| months.with(month))
+                        .ok_or_else(|| self.invalid("/m", value))
+                })
+            })
+            .transpose()
+    }
+
+    fn principal(&self) -> TaskPrincipal {
+        let mut principal = TaskPrincipal::new();
+        if let Some(user_id) = self.value("/ru") {
+            // an empty user is the same as SYSTEM
+            let user_id = if user_id.is_empty() {
+                "SYSTEM"
+            } else {
+                user_id
+            };
+            principal.user_id = Some(user_id.to_string());
+            principal.logon_type = Some(if is_service_account(user_id) {
+                TaskLogon::ServiceAccount
+            } else if self.has("/np") {
+                TaskLogon::S4U
+            } else if self.has("/it") {
+                TaskLogon::InteractiveToken
+            } else {
+                TaskLogon::Password
+            });
+        } else if self.has("/it") {
+            principal.logon_type = Some(TaskLogon::InteractiveToken);
+        }
+        if let Some(run_level) = self.value("/rl") {
+            if run_level.eq_ignore_ascii_case("HIGHEST") {
+                principal.run_level = RunLevel::HighestAvailable;
+            }
+        }
+        principal
+    }
+}
+
+/// The lowercase name of a switch, which can start with `/` or `-`
+fn switch_name(argument: &str) -> Option<String> {
+    let name = argument
+        .strip_prefix('/')
+        .or_else(|| argument.strip_prefix('-'))?;
+    if name.is_empty() || name.contains(|c: char| !c.is_ascii_alphanumeric()) {
+        return None;
+    }
+    Some(format!("/{}", name.to_lowercase()))
+}
+
+/// Days of the week for `/d`, like `MON,FRI` or `*`
+fn weekdays(value: &str) -> Result<DaysOfWeek, SchtasksError> {
+    if value.trim() == "*" {
+        return Ok(DaysOfWeek::all());
+    }
+    value.split(',').try_fold(DaysOfWeek::new(), |days, name| {
+        WEEKDAYS
+            .iter()
+            .find(|day| weekday_name(**day)[..3].eq_ignore_ascii_case(name.trim()))
+            .map(|day| days.with(*day))
+            .ok_or_else(|| SchtasksError::Invalid {
+                switch: "/d".to_string(),
+                value: value.to_string(),
+            })
+    })
+}
+
+/// Days of the month for `/d`, like `1,15`
+fn days_of_month(value: &str) -> Result<DaysOfMonth, SchtasksError> {
+    value.split(',').try_fold(DaysOfMonth::new(), |days, day| {
+        day.trim()
+            .parse()
+            .ok()
+            .filter(|day| (1..=31).contains(day))
+            .map(|day| days.with(day))
+            .ok_or_else(|| SchtasksError::Invalid {
+                switch: "/d".to_string(),
+                value: value.to_string(),
+            })
+    })
+}
+
+/// The program of `/tr` is quoted or ends at the first space, the rest are its arguments
+fn exec_action(run: &str) -> ExecAction {
+    let run = run.trim();
+    let (path, arguments) = match run.strip_prefix('"') {
+        Some(quoted) => match quoted.split_once('"') {
+            Some((path, arguments)) => (path, arguments),
+            None => (quoted, ""),
+        },
+        None => run.split_once(' ').unwrap_or((run, "")),
+    };
+    let arguments = arguments.trim();
+    ExecAction {
+        id: None,
+        path: PathBuf::from(path),
+        arguments: Some(arguments.to_string()).filter(|arguments| !arguments.is_empty()),
+        working_directory: None,
+    }
+}
+
+/// The end of a day, since `/ed` is the last day a trigger runs
+fn last_second() -> NaiveTime {
+    NaiveTime::from_hms_opt(23, 59, 59).unwrap()
+}
+
+/// Splits a command line the way Windows programs do: spaces separate arguments
+/// outside of quotes, and backslashes only escape quotes
+fn split_command_line(command_line: &str) -> Vec<String> {
+    let mut arguments = Vec::new();
+    let mut current = String::new();
+    let mut started = false;
+    let mut quoted = false;
+    let mut backslashes = 0;
+    let mut chars = command_line.chars().peekable();
+    while let Some(c) = chars.next() {
+        if c == '\\' {
+            backslashes += 1;
+            started = true;
+            continue;
+        }
+        if c == '"' {
+            current.push_str(&"\\".repeat(backslashes / 2));
+            if backslashes % 2 == 1 {
+                current.push('"');
+            } else if quoted && chars.peek() == Some(&'"') {
+                // "" inside quotes is a quote
+                chars.next();
+                current.push('"');
+            } else {
+                quoted = !quoted;
+            }
+            backslashes = 0;
+            started = true;
+            continue;
+        }
+        current.push_str(&"\\".repeat(backslashes));
+        backslashes = 0;
+        if c.is_whitespace() && !quoted {
+            if started {
+                arguments.push(std::mem::take(&mut current));
+                started = false;
+            }
+        } else {
+            current.push(c);
+            started = true;
+        }
+    }
+    current.push_str(&"\\".repeat(backslashes));
+    if started {
+        arguments.push(current);
+    }
+    arguments
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::{Local, NaiveDate, NaiveDateTime, NaiveTime, Timelike};
+
+    use super::SchtasksCommand;
+    use crate::{Boundary, SchtasksError};
+
+    fn parse(command_line: &str) -> SchtasksCommand {
+        SchtasksCommand::parse(&format!(
+            "schtasks /create /tn T /tr a.exe {}",
+            command_line
+        ))
+        .unwrap()
+    }
+
+    /// The local start of the time or daily trigger of the command, with the
+    /// time on the clock before and after it was parsed
+    fn start(command_line: &str) -> (NaiveDateTime, NaiveDateTime, NaiveDateTime) {
+        let before = Local::now().naive_local();
+        let command = parse(command_line);
+        let after = Local::now().naive_local();
+        let triggers = command.task().triggers();
+        let start = triggers
+            .specific_times()
+            .first()
+            .map(|trigger| trigger.common().start())
+            .or_else(|| {
+                triggers
+                    .daily()
+                    .first()
+                    .map(|trigger| trigger.common().start())
+            });
+        match start {
+            Some(Some(Boundary::Local(start))) => (before, start, after),
+            start => panic!("expected a local start, got {:?}", start),
+        }
+    }
+
+    fn date(year: i32, month: u32, day: u32) -> NaiveDate {
+        NaiveDate::from_ymd_opt(year, month, day).unwrap()
+    }
+
+    fn time(hour: u32, minute: u32) -> NaiveTime {
+        NaiveTime::from_hms_opt(hour, minute, 0).unwrap()
+    }
+
+    fn whole_minute(time: NaiveDateTime) -> NaiveDateTime {
+        time.with_second(0).unwrap().with_nanosecond(0).unwrap()
+    }
+
+    #[test]
+    fn once_with_date_and_time() {
+        let (_, start, _) = start("/sc once /sd 01/02/2030 /st 09:30");
+        assert_eq!(start, date(2030, 1, 2).and_time(time(9, 30)));
+    }
+
+    #[test]
+    fn once_without_date_is_today() {
+        let (before, start, after) = start("/sc once /st 09:30");
+        assert_eq!(start.time(), time(9, 30));
+        assert!(before.date() <= start.date() && start.date() <= after.date());
+    }
+
+    #[test]
+    fn once_without_time_is_rejected() {
+        for switches in ["/sc once", "/sc once /sd 01/02/2030"] {
+            assert_eq!(
+                SchtasksCommand::parse(&format!("schtasks /create /tn T /tr a.exe {}", switches)),
+                Err(SchtasksError::Missing("/st for /sc ONCE".to_string()))
+            );
+        }
+    }
+
+    #[test]
+    fn no_date_and_time_is_now_to_the_minute() {
+        let (before, start, after) = start("/sc daily");
+        assert_eq!(start, whole_minute(start));
+        assert!(whole_minute(before) <= start && start <= after);
+    }
+
+    #[test]
+    fn no_time_is_the_current_minute() {
+        let (before, start, after) = start("/sc daily /sd 2030/01/02");
+        assert_eq!(start.date(), date(2030, 1, 2));
+        let now = date(2030, 1, 2).and_time(start.time());
+        assert_eq!(now, whole_minute(now));
+        if before.date() == after.date() {
+            assert!(whole_minute(before).time() <= now.time() && now.time() <= after.time());
+        }
+    }
+
+    #[test]
+    fn start_is_written_as_local_time() {
+        let xml = parse("/sc daily").task().to_xml();
+        let start = xml
+            .split("<StartBoundary>")
+            .nth(1)
+            .and_then(|rest| rest.split("</StartBoundary>").next())
+            .unwrap();
+        assert!(Boundary::parse(start).unwrap().is_local(), "{}", start);
+        assert!(start.ends_with(":00"), "{}", start);
+    }
+
+    #[test]
+    fn event_schedules_only_start_when_given() {
+        let command = parse("/sc onstart");
+        assert_eq!(command.task().triggers().boot()[0].common().start(), None);
+    }
+}
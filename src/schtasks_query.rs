@@ -0,0 +1,555 @@
+use chrono::{NaiveDate, NaiveDateTime};
+
+use crate::{error::SchtasksError, TaskResult, TaskState};
+
+/// The names of the columns in the header of `schtasks /query /fo csv /v`, in English
+/// and German. The header is written in the language of the computer.
+const HEADERS: &[(&str, SchtasksColumn)] = &[
+    ("HostName", SchtasksColumn::HostName),
+    ("TaskName", SchtasksColumn::TaskName),
+    ("Next Run Time", SchtasksColumn::NextRunTime),
+    ("Status", SchtasksColumn::Status),
+    ("Logon Mode", SchtasksColumn::LogonMode),
+    ("Last Run Time", SchtasksColumn::LastRunTime),
+    ("Last Result", SchtasksColumn::LastResult),
+    ("Author", SchtasksColumn::Author),
+    ("Task To Run", SchtasksColumn::TaskToRun),
+    ("Start In", SchtasksColumn::StartIn),
+    ("Comment", SchtasksColumn::Comment),
+    ("Scheduled Task State", SchtasksColumn::ScheduledTaskState),
+    ("Idle Time", SchtasksColumn::IdleTime),
+    ("Power Management", SchtasksColumn::PowerManagement),
+    ("Run As User", SchtasksColumn::RunAsUser),
+    (
+        "Delete Task If Not Rescheduled",
+        SchtasksColumn::DeleteTaskIfNotRescheduled,
+    ),
+    (
+        "Stop Task If Runs X Hours and X Mins",
+        SchtasksColumn::StopTaskAfter,
+    ),
+    ("Schedule", SchtasksColumn::Schedule),
+    ("Schedule Type", SchtasksColumn::ScheduleType),
+    ("Start Time", SchtasksColumn::StartTime),
+    ("Start Date", SchtasksColumn::StartDate),
+    ("End Date", SchtasksColumn::EndDate),
+    ("Days", SchtasksColumn::Days),
+    ("Months", SchtasksColumn::Months),
+    ("Repeat: Every", SchtasksColumn::RepeatEvery),
+    ("Repeat: Until: Time", SchtasksColumn::RepeatUntilTime),
+    (
+        "Repeat: Until: Duration",
+        SchtasksColumn::RepeatUntilDuration,
+    ),
+    (
+        "Repeat: Stop If Still Running",
+        SchtasksColumn::RepeatStopIfStillRunning,
+    ),
+    ("Aufgabenname", SchtasksColumn::TaskName),
+    ("Nächste Laufzeit", SchtasksColumn::NextRunTime),
+    ("Anmeldemodus", SchtasksColumn::LogonMode),
+    ("Letzte Laufzeit", SchtasksColumn::LastRunTime),
+    ("Letztes Ergebnis", SchtasksColumn::LastResult),
+    ("Autor", SchtasksColumn::Author),
+    ("Auszuführende Aufgabe", SchtasksColumn::TaskToRun),
+    ("Starten in", SchtasksColumn::StartIn),
+    ("Kommentar", SchtasksColumn::Comment),
+    ("Als Benutzer ausführen", SchtasksColumn::RunAsUser),
+    ("Zeitplan", SchtasksColumn::Schedule),
+    ("Zeitplantyp", SchtasksColumn::ScheduleType),
+    ("Startzeit", SchtasksColumn::StartTime),
+    ("Startdatum", SchtasksColumn::StartDate),
+    ("Enddatum", SchtasksColumn::EndDate),
+    ("Tage", SchtasksColumn::Days),
+    ("Monate", SchtasksColumn::Months),
+];
+
+/// The values of the status column for each state
+const STATES: &[(&str, TaskState)] = &[
+    ("Ready", TaskState::Ready),
+    ("Running", TaskState::Running),
+    ("Queued", TaskState::Queued),
+    ("Disabled", TaskState::Disabled),
+    ("Bereit", TaskState::Ready),
+    ("Wird ausgeführt", TaskState::Running),
+    ("In Warteschlange", TaskState::Queued),
+    ("Deaktiviert", TaskState::Disabled),
+];
+
+/// The value schtasks writes when a column doesn't apply to the task
+const NOT_AVAILABLE: &str = "N/A";
+
+/// The columns of `schtasks /query /fo csv /v`
+///
+/// https://docs.microsoft.com/en-us/windows-server/administration/windows-commands/schtasks-query
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum SchtasksColumn {
+    HostName,
+    /// The path of the task, like `\Microsoft\Windows\Defrag\ScheduledDefrag`
+    TaskName,
+    NextRunTime,
+    Status,
+    LogonMode,
+    LastRunTime,
+    LastResult,
+    Author,
+    TaskToRun,
+    StartIn,
+    Comment,
+    ScheduledTaskState,
+    IdleTime,
+    PowerManagement,
+    RunAsUser,
+    DeleteTaskIfNotRescheduled,
+    /// The execution time limit, `Stop Task If Runs X Hours and X Mins` in English
+    StopTaskAfter,
+    Schedule,
+    ScheduleType,
+    StartTime,
+    StartDate,
+    EndDate,
+    Days,
+    Months,
+    RepeatEvery,
+    RepeatUntilTime,
+    RepeatUntilDuration,
+    RepeatStopIfStillRunning,
+}
+
+/// The tasks in the output of `schtasks /query /fo csv`, usually with `/v`.
+///
+/// The columns are found by the names in the header, so the output can be verbose or not.
+/// schtasks repeats the header for every folder, those rows are skipped.
+/// Headers in English and German are known, others can be given to
+/// [`parse_with_headers`](SchtasksQuery::parse_with_headers).
+///
+/// https://docs.microsoft.com/en-us/windows-server/administration/windows-commands/schtasks-query
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct SchtasksQuery {
+    records: Vec<SchtasksRecord>,
+}
+
+impl SchtasksQuery {
+    /// Reads the output, which has to start with a header
+    pub fn parse(csv: &str) -> Result<SchtasksQuery, SchtasksError> {
+        SchtasksQuery::parse_with_headers(csv, &[])
+    }
+
+    /// Reads the output with more names for the columns, like the ones of another language.
+    /// These are used before the names that are known.
+    pub fn parse_with_headers(
+        csv: &str,
+        headers: &[(&str, SchtasksColumn)],
+    ) -> Result<SchtasksQuery, SchtasksError> {
+        let mut rows = csv_rows(csv)?.into_iter();
+        let (_, header) = match rows.next() {
+            Some(header) => header,
+            None => return Ok(SchtasksQuery::default()),
+        };
+        let columns: Vec<Option<SchtasksColumn>> = header
+            .iter()
+            .map(|name| column(name.trim(), headers))
+            .collect();
+        if !columns.contains(&Some(SchtasksColumn::TaskName)) {
+            return Err(SchtasksError::MissingColumn("TaskName".to_string()));
+        }
+
+        let mut records = Vec::new();
+        for (line, row) in rows {
+            if row == header {
+                continue;
+            }
+            if row.len() != header.len() {
+                return Err(SchtasksError::Csv {
+                    line,
+                    description: format!(
+                        "the row has {} values and the header {}",
+                        row.len(),
+                        header.len()
+                    ),
+                });
+            }
+            let values = columns
+                .iter()
+                .zip(row)
+                .filter_map(|(column, value)| column.map(|column| (column, value)))
+                .collect();
+            records.push(SchtasksRecord { values });
+        }
+        Ok(SchtasksQuery { records })
+    }
+
+    pub fn records(&self) -> &[SchtasksRecord] {
+        &self.records
+    }
+
+    /// Finds a task by its path. Task paths aren't case sensitive.
+    pub fn record(&self, path: &str) -> Option<&SchtasksRecord> {
+        self.records
+            .iter()
+            .find(|record| record.path().eq_ignore_ascii_case(path))
+    }
+}
+
+/// A task in the output of `schtasks /query`
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct SchtasksRecord {
+    values: Vec<(SchtasksColumn, String)>,
+}
+
+impl SchtasksRecord {
+    /// The value of the column, `None` when the output doesn't have the column
+    /// or the value is `N/A` or empty
+    pub fn get(&self, column: SchtasksColumn) -> Option<&str> {
+        self.values
+            .iter()
+            .find(|(name, _)| *name == column)
+            .map(|(_, value)| value.trim())
+            .filter(|value| !value.is_empty() && *value != NOT_AVAILABLE)
+    }
+
+    /// The path of the task, like `\Microsoft\Windows\Defrag\ScheduledDefrag`
+    pub fn path(&self) -> &str {
+        self.get(SchtasksColumn::TaskName).unwrap_or_default()
+    }
+
+    /// The status as schtasks writes it, like `Ready`
+    pub fn status(&self) -> Option<&str> {
+        self.get(SchtasksColumn::Status)
+    }
+
+    /// The state of the task from its status, `Unknown` for a status that isn't known,
+    /// like `Could not start`
+    pub fn state(&self) -> TaskState {
+        self.status()
+            .and_then(|status| {
+                STATES
+                    .iter()
+                    .find(|(name, _)| name.eq_ignore_ascii_case(status))
+            })
+            .map_or(TaskState::Unknown, |(_, state)| *state)
+    }
+
+    /// The next time the task runs, in the local time of the computer
+    pub fn next_run_time(&self) -> Option<NaiveDateTime> {
+        self.get(SchtasksColumn::NextRunTime).and_then(parse_time)
+    }
+
+    /// The last time the task ran, in the local time of the computer.
+    /// This is `None` when the task has never run, which schtasks writes as 11/30/1999.
+    pub fn last_run_time(&self) -> Option<NaiveDateTime> {
+        self.get(SchtasksColumn::LastRunTime)
+            .and_then(parse_time)
+            .filter(|time| Some(time.date()) != NaiveDate::from_ymd_opt(1999, 11, 30))
+    }
+
+    /// The result of the last run, which schtasks writes as a decimal number
+    pub fn last_result(&self) -> Option<TaskResult> {
+        self.get(SchtasksColumn::LastResult)
+            .and_then(TaskResult::parse)
+    }
+
+    pub fn author(&self) -> Option<&str> {
+        self.get(SchtasksColumn::Author)
+    }
+
+    /// The user the task runs as, like `SYSTEM`
+    pub fn run_as_user(&self) -> Option<&str> {
+        self.get(SchtasksColumn::RunAsUser)
+    }
+
+    /// The command of the first action
+    pub fn task_to_run(&self) -> Option<&str> {
+        self.get(SchtasksColumn::TaskToRun)
+    }
+
+    /// The schedule type with the columns that describe it,
+    /// like `Weekly, 3:00:00 AM, 1/1/2005, WED, Every 1 week(s)`.
+    /// The schedule column itself usually only says the schedule isn't available.
+    pub fn schedule(&self) -> Option<String> {
+        let parts: Vec<&str> = [
+            SchtasksColumn::ScheduleType,
+            SchtasksColumn::StartTime,
+            SchtasksColumn::StartDate,
+            SchtasksColumn::EndDate,
+            SchtasksColumn::Days,
+            SchtasksColumn::Months,
+        ]
+        .iter()
+        .filter_map(|column| self.get(*column))
+        .collect();
+        if parts.is_empty() {
+            return self.get(SchtasksColumn::Schedule).map(str::to_string);
+        }
+        Some(parts.join(", "))
+    }
+}
+
+fn column(name: &str, headers: &[(&str, SchtasksColumn)]) -> Option<SchtasksColumn> {
+    headers
+        .iter()
+        .chain(HEADERS)
+        .find(|(header, _)| header.eq_ignore_ascii_case(name))
+        .map(|(_, column)| *column)
+}
+
+/// A time in the format of the computer. A time with AM or PM is month/day/year
+/// like in the United States, other times with slashes are day/month/year.
+fn parse_time(time: &str) -> Option<NaiveDateTime> {
+    [
+        "%m/%d/%Y %I:%M:%S %p",
+        "%d/%m/%Y %H:%M:%S",
+        "%d.%m.%Y %H:%M:%S",
+        "%Y-%m-%d %H:%M:%S",
+        "%Y/%m/%d %H:%M:%S",
+    ]
+    .iter()
+    .find_map(|format| NaiveDateTime::parse_from_str(time, format).ok())
+}
+
+/// Splits CSV into rows with the line each row starts on, blank lines are skipped.
+/// Values can be quoted, with `""` for a quote, and quoted values can span lines.
+fn csv_rows(csv: &str) -> Result<Vec<(usize, Vec<String>)>, SchtasksError> {
+    let mut rows = Vec::new();
+    let mut row = Vec::new();
+    let mut value = String::new();
+    let mut quoted = false;
+    let mut was_quoted = false;
+    let mut line = 1;
+    let mut row_line = 1;
+    let mut chars = csv.trim_start_matches('\u{feff}').chars().peekable();
+    while let Some(c) = chars.next() {
+        match c {
+            '"' if quoted => {
+                if chars.peek() == Some(&'"') {
+                    chars.next();
+                    value.push('"');
+                } else {
+                    quoted = false;
+                }
+            }
+            '"' if value.trim().is_empty() && !was_quoted => {
+                value.clear();
+                quoted = true;
+                was_quoted = true;
+            }
+            '"' => {
+                return Err(SchtasksError::Csv {
+                    line,
+                    description: "a quote is in the middle of a value".to_string(),
+                })
+            }
+            ',' if !quoted => {
+                row.push(std::mem::take(&mut value));
+                was_quoted = false;
+            }
+            '\r' if !quoted && chars.peek() == Some(&'\n') => {}
+            '\n' if !quoted => {
+                if !row.is_empty() || !value.trim().is_empty() || was_quoted {
+                    row.push(std::mem::take(&mut value));
+                    rows.push((row_line, std::mem::take(&mut row)));
+                }
+                value.clear();
+                was_quoted = false;
+                line += 1;
+                row_line = line;
+            }
+            _ if !quoted && was_quoted => {
+                if !c.is_whitespace() {
+                    return Err(SchtasksError::Csv {
+                        line,
+                        description: "a value continues after its closing quote".to_string(),
+                    });
+                }
+            }
+            _ => {
+                if c == '\n' {
+                    line += 1;
+                }
+                value.push(c);
+            }
+        }
+    }
+    if quoted {
+        return Err(SchtasksError::Csv {
+            line: row_line,
+            description: "a quoted value isn't closed".to_string(),
+        });
+    }
+    if !row.is_empty() || !value.trim().is_empty() || was_quoted {
+        row.push(value);
+        rows.push((row_line, row));
+    }
+    Ok(rows)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const VERBOSE: &str = include_str!("../tests/fixtures/schtasks/verbose.csv");
+    const GERMAN: &str = include_str!("../tests/fixtures/schtasks/german.csv");
+
+    fn time(date: (i32, u32, u32), time: (u32, u32, u32)) -> NaiveDateTime {
+        NaiveDate::from_ymd_opt(date.0, date.1, date.2)
+            .unwrap()
+            .and_hms_opt(time.0, time.1, time.2)
+            .unwrap()
+    }
+
+    #[test]
+    fn skips_the_header_rows_between_folders() {
+        let query = SchtasksQuery::parse(VERBOSE).unwrap();
+        let paths: Vec<&str> = query.records().iter().map(SchtasksRecord::path).collect();
+        assert_eq!(
+            paths,
+            [
+                "\\Backup",
+                "\\Microsoft\\Windows\\Defrag\\ScheduledDefrag",
+                "\\Microsoft\\Windows\\Defrag\\Report"
+            ]
+        );
+    }
+
+    #[test]
+    fn keeps_commas_and_quotes_in_quoted_values() {
+        let query = SchtasksQuery::parse(VERBOSE).unwrap();
+        let backup = query.record("\\backup").unwrap();
+        assert_eq!(
+            backup.task_to_run(),
+            Some("\"C:\\Program Files\\Backup\\backup.exe\" --target D:\\,E:\\")
+        );
+        assert_eq!(
+            backup.get(SchtasksColumn::Comment),
+            Some("Copies C:, D: and E: to the share, \"nightly\"")
+        );
+        assert_eq!(
+            backup.get(SchtasksColumn::PowerManagement),
+            Some("Stop On Battery Mode, No Start On Batteries")
+        );
+    }
+
+    #[test]
+    fn reads_the_values_of_a_record() {
+        let query = SchtasksQuery::parse(VERBOSE).unwrap();
+        let backup = query.record("\\Backup").unwrap();
+        assert_eq!(backup.state(), TaskState::Ready);
+        assert_eq!(backup.next_run_time(), Some(time((2021, 1, 2), (3, 0, 0))));
+        assert_eq!(backup.last_run_time(), Some(time((2021, 1, 1), (3, 0, 5))));
+        assert_eq!(backup.last_result(), Some(TaskResult(0)));
+        assert_eq!(backup.author(), Some("DESKTOP-1\\admin"));
+        assert_eq!(backup.run_as_user(), Some("admin"));
+        assert_eq!(
+            backup.schedule().as_deref(),
+            Some("Daily, 3:00:00 AM, 1/1/2021, Every 1 day(s)")
+        );
+
+        let defrag = query
+            .record("\\Microsoft\\Windows\\Defrag\\ScheduledDefrag")
+            .unwrap();
+        assert_eq!(defrag.state(), TaskState::Disabled);
+        assert_eq!(defrag.last_result(), Some(TaskResult(0x0004_1303)));
+        assert_eq!(
+            defrag.schedule().as_deref(),
+            Some("Weekly, 1:00:00 AM, 1/1/2005, WED, Every 1 week(s)")
+        );
+    }
+
+    #[test]
+    fn treats_not_available_and_empty_values_as_missing() {
+        let query = SchtasksQuery::parse(VERBOSE).unwrap();
+        let defrag = query
+            .record("\\Microsoft\\Windows\\Defrag\\ScheduledDefrag")
+            .unwrap();
+        assert_eq!(defrag.next_run_time(), None);
+        assert_eq!(defrag.get(SchtasksColumn::StartIn), None);
+        // 11/30/1999 is what schtasks writes for a task that has never run
+        assert_eq!(defrag.last_run_time(), None);
+
+        let report = query
+            .record("\\Microsoft\\Windows\\Defrag\\Report")
+            .unwrap();
+        assert_eq!(report.author(), None);
+        assert_eq!(report.get(SchtasksColumn::PowerManagement), None);
+        assert_eq!(report.get(SchtasksColumn::StopTaskAfter), None);
+        assert_eq!(report.schedule().as_deref(), Some("At logon time"));
+        assert_eq!(report.status(), Some("Could not start"));
+        assert_eq!(report.state(), TaskState::Unknown);
+        assert_eq!(report.last_result(), Some(TaskResult(0x8007_10E0)));
+    }
+
+    #[test]
+    fn reads_german_headers_and_states() {
+        let query = SchtasksQuery::parse(GERMAN).unwrap();
+        let backup = query.record("\\Sicherung").unwrap();
+        assert_eq!(backup.state(), TaskState::Ready);
+        assert_eq!(backup.next_run_time(), Some(time((2021, 1, 2), (3, 0, 0))));
+        let report = query.record("\\Bericht").unwrap();
+        assert_eq!(report.state(), TaskState::Running);
+        assert_eq!(report.next_run_time(), None);
+    }
+
+    #[test]
+    fn reads_headers_that_are_given() {
+        let csv = "\"Nom de la tâche\",\"Statut\"\r\n\"\\Sauvegarde\",\"Ready\"\r\n";
+        assert_eq!(
+            SchtasksQuery::parse(csv).unwrap_err(),
+            SchtasksError::MissingColumn("TaskName".to_string())
+        );
+        let query = SchtasksQuery::parse_with_headers(
+            csv,
+            &[
+                ("Nom de la tâche", SchtasksColumn::TaskName),
+                ("Statut", SchtasksColumn::Status),
+            ],
+        )
+        .unwrap();
+        assert_eq!(query.records()[0].path(), "\\Sauvegarde");
+        assert_eq!(query.records()[0].state(), TaskState::Ready);
+    }
+
+    #[test]
+    fn reads_nothing_from_empty_output() {
+        assert_eq!(
+            SchtasksQuery::parse("\r\n").unwrap(),
+            SchtasksQuery::default()
+        );
+    }
+
+    #[test]
+    fn rejects_rows_that_dont_match_the_header() {
+        let csv = "\"TaskName\",\"Status\"\r\n\"\\A\",\"Ready\"\r\n\"\\B\"\r\n";
+        assert_eq!(
+            SchtasksQuery::parse(csv).unwrap_err(),
+            SchtasksError::Csv {
+                line: 3,
+                description: "the row has 1 values and the header 2".to_string()
+            }
+        );
+    }
+
+    #[test]
+    fn rejects_quotes_that_arent_valid() {
+        assert_eq!(
+            SchtasksQuery::parse("\"TaskName\"\r\n\\A\"B\r\n").unwrap_err(),
+            SchtasksError::Csv {
+                line: 2,
+                description: "a quote is in the middle of a value".to_string()
+            }
+        );
+        assert_eq!(
+            SchtasksQuery::parse("\"TaskName\"\r\n\"\\A\"B\r\n").unwrap_err(),
+            SchtasksError::Csv {
+                line: 2,
+                description: "a value continues after its closing quote".to_string()
+            }
+        );
+        assert_eq!(
+            SchtasksQuery::parse("\"TaskName\"\r\n\"\\A\r\n").unwrap_err(),
+            SchtasksError::Csv {
+                line: 2,
+                description: "a quoted value isn't closed".to_string()
+            }
+        );
+    }
+}
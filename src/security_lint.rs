@@ -0,0 +1,500 @@
+use crate::{
+    lint::{Finding, LintRule, Severity},
+    task::{RunLevel, Task},
+    Action, ExecAction,
+};
+
+/// Directories that users can write to, so a program in them can be replaced
+/// by anyone who can log on. Paths are compared in lower case with backslashes.
+const USER_WRITABLE: &[&str] = &[
+    "%temp%",
+    "%tmp%",
+    "%appdata%",
+    "%localappdata%",
+    "%userprofile%",
+    "%public%",
+    "%programdata%",
+    "\\users\\",
+    "\\appdata\\",
+    "\\temp\\",
+    "\\tmp\\",
+    "\\downloads\\",
+    "\\programdata\\",
+    "\\$recycle.bin\\",
+];
+
+/// Programs that ship with Windows and can run code from a script or a url,
+/// which is why they turn up in persistence
+const LOLBINS: &[&str] = &[
+    "mshta",
+    "rundll32",
+    "regsvr32",
+    "certutil",
+    "bitsadmin",
+    "msbuild",
+    "installutil",
+    "cmstp",
+];
+
+const SYSTEM_ACCOUNTS: &[&str] = &["S-1-5-18", "SYSTEM", "NT AUTHORITY\\SYSTEM"];
+
+pub(crate) fn rules() -> Vec<Box<dyn LintRule>> {
+    vec![
+        Box::new(UserWritablePath),
+        Box::new(EncodedPowerShell),
+        Box::new(HiddenSystemTask),
+        Box::new(NoAuthorOrDescription),
+        Box::new(ElevatedLogonForEveryone),
+        Box::new(Lolbin),
+    ]
+}
+
+/// The exec actions of the task, numbered from 1 like in the Task Scheduler UI
+fn exec_actions(task: &Task) -> impl Iterator<Item = (usize, &ExecAction)> {
+    task.actions()
+        .iter()
+        .enumerate()
+        .filter_map(|(index, action)| match action {
+            Action::Exec(action) => Some((index + 1, action)),
+            Action::ComHandler(_) => None,
+        })
+}
+
+/// The path in lower case with backslashes, without quotes
+fn normalize(path: &str) -> String {
+    path.trim_matches('"').to_lowercase().replace('/', "\\")
+}
+
+/// The file name of a program without `.exe`, in lower case
+fn program_name(path: &str) -> String {
+    let path = normalize(path);
+    let name = path.rsplit('\\').next().unwrap_or(&path);
+    name.strip_suffix(".exe").unwrap_or(name).to_string()
+}
+
+/// The program and its arguments split on whitespace
+fn command_words(action: &ExecAction) -> Vec<String> {
+    let mut words = vec![action.path().to_string_lossy().into_owned()];
+    words.extend(
+        action
+            .arguments()
+            .unwrap_or("")
+            .split_whitespace()
+            .map(str::to_string),
+    );
+    words
+}
+
+struct UserWritablePath;
+
+impl LintRule for UserWritablePath {
+    fn id(&self) -> &str {
+        "SEC001"
+    }
+
+    fn check(&self, task: &Task) -> Vec<Finding> {
+        exec_actions(task)
+            .filter(|(_, action)| {
+                let path = normalize(&action.path().to_string_lossy());
+                USER_WRITABLE
+                    .iter()
+                    .any(|directory| path.contains(directory))
+            })
+            .map(|(number, action)| {
+                Finding::new(
+                    self.id(),
+                    Severity::High,
+                    format!(
+                        "Action {} runs {}, which is in a directory users can write to, so anyone who can log on can replace it",
+                        number,
+                        action.path().display()
+                    ),
+                )
+            })
+            .collect()
+    }
+}
+
+struct EncodedPowerShell;
+
+impl EncodedPowerShell {
+    /// Any prefix of `-EncodedCommand` starts PowerShell with a base64 command,
+    /// as does the `-ec` alias
+    fn is_encoded_flag(word: &str) -> bool {
+        let flag = match word.strip_prefix('-').or_else(|| word.strip_prefix('/')) {
+            Some(flag) => flag.to_lowercase(),
+            None => return false,
+        };
+        flag == "ec" || (!flag.is_empty() && "encodedcommand".starts_with(&flag))
+    }
+}
+
+impl LintRule for EncodedPowerShell {
+    fn id(&self) -> &str {
+        "SEC002"
+    }
+
+    fn check(&self, task: &Task) -> Vec<Finding> {
+        let mut findings = Vec::new();
+        for (number, action) in exec_actions(task) {
+            let words = command_words(action);
+            let powershell = words.iter().position(|word| {
+                let name = program_name(word);
+                name == "powershell" || name == "pwsh"
+            });
+            let powershell = match powershell {
+                Some(powershell) => powershell,
+                None => continue,
+            };
+            let arguments = &words[powershell + 1..];
+            if arguments.iter().any(|word| Self::is_encoded_flag(word)) {
+                findings.push(Finding::new(
+                    self.id(),
+                    Severity::High,
+                    format!(
+                        "Action {} runs an encoded PowerShell command, which hides what the task does",
+                        number
+                    ),
+                ));
+            } else if arguments
+                .iter()
+                .any(|word| word.to_lowercase().contains("frombase64string"))
+            {
+                findings.push(Finding::new(
+                    self.id(),
+                    Severity::High,
+                    format!(
+                        "Action {} decodes base64 in PowerShell, which hides what the task does",
+                        number
+                    ),
+                ));
+            }
+        }
+        findings
+    }
+}
+
+struct HiddenSystemTask;
+
+impl LintRule for HiddenSystemTask {
+    fn id(&self) -> &str {
+        "SEC003"
+    }
+
+    fn check(&self, task: &Task) -> Vec<Finding> {
+        let system = task.principal().user_id().map_or(false, |user| {
+            SYSTEM_ACCOUNTS
+                .iter()
+                .any(|account| account.eq_ignore_ascii_case(user))
+        });
+        if !task.settings().hidden() || !system {
+            return Vec::new();
+        }
+        vec![Finding::new(
+            self.id(),
+            Severity::High,
+            "The task is hidden and runs as SYSTEM, so it has full control of the computer without showing in the Task Scheduler UI".to_string(),
+        )]
+    }
+}
+
+struct NoAuthorOrDescription;
+
+impl LintRule for NoAuthorOrDescription {
+    fn id(&self) -> &str {
+        "SEC004"
+    }
+
+    fn check(&self, task: &Task) -> Vec<Finding> {
+        let registration = task.registration();
+        let missing = |value: Option<&str>| value.map_or(true, |value| value.trim().is_empty());
+        if !missing(registration.author()) || !missing(registration.description()) {
+            return Vec::new();
+        }
+        vec![Finding::new(
+            self.id(),
+            Severity::Low,
+            "The task has no author or description, so there's no telling who added it or why"
+                .to_string(),
+        )]
+    }
+}
+
+struct ElevatedLogonForEveryone;
+
+impl LintRule for ElevatedLogonForEveryone {
+    fn id(&self) -> &str {
+        "SEC005"
+    }
+
+    fn check(&self, task: &Task) -> Vec<Finding> {
+        let for_everyone = task
+            .triggers()
+            .logon()
+            .iter()
+            .any(|trigger| trigger.user_id().is_none());
+        if !for_everyone || task.principal().run_level() != RunLevel::HighestAvailable {
+            return Vec::new();
+        }
+        vec![Finding::new(
+            self.id(),
+            Severity::Medium,
+            "The task runs with the highest privileges whenever any user logs on".to_string(),
+        )]
+    }
+}
+
+struct Lolbin;
+
+impl LintRule for Lolbin {
+    fn id(&self) -> &str {
+        "SEC006"
+    }
+
+    fn check(&self, task: &Task) -> Vec<Finding> {
+        let mut findings = Vec::new();
+        for (number, action) in exec_actions(task) {
+            // also through a shell, like cmd /c mshta
+            let lolbin = command_words(action)
+                .iter()
+                .map(|word| program_name(word))
+                .find(|name| LOLBINS.contains(&name.as_str()));
+            if let Some(lolbin) = lolbin {
+                findings.push(Finding::new(
+                    self.id(),
+                    Severity::Medium,
+                    format!(
+                        "Action {} uses {}, a Windows program that is often used to run code while looking legitimate",
+                        number, lolbin
+                    ),
+                ));
+            }
+        }
+        findings
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::PathBuf;
+
+    use super::*;
+    use crate::{
+        task::{Settings, TaskPrincipal, TaskRegistration},
+        Actions, BootTrigger, ComHandlerAction, LogonTrigger, TaskTriggersBuilder,
+    };
+
+    fn action(path: &str, arguments: &str) -> Action {
+        let action = ExecAction::new(PathBuf::from(path));
+        if arguments.is_empty() {
+            Action::Exec(action)
+        } else {
+            Action::Exec(action.with_arguments(arguments.to_string()))
+        }
+    }
+
+    fn task(path: &str, arguments: &str) -> Task {
+        Task::new(
+            Actions::from_action(action(path, arguments)),
+            TaskTriggersBuilder::new()
+                .with_boot(BootTrigger::new())
+                .build(),
+        )
+    }
+
+    fn explanations(findings: Vec<Finding>) -> Vec<String> {
+        findings
+            .into_iter()
+            .map(|finding| finding.explanation().to_string())
+            .collect()
+    }
+
+    #[test]
+    fn finds_programs_in_directories_users_can_write_to() {
+        let task = Task::new(
+            Actions::from_action(action("C:\\Windows\\System32\\cmd.exe", ""))
+                .with_action(action("%APPDATA%\\Updater\\update.exe", ""))
+                .with_action(action("\"C:/Users/Public/Downloads/run.exe\"", "")),
+            TaskTriggersBuilder::new()
+                .with_boot(BootTrigger::new())
+                .build(),
+        );
+        let findings = UserWritablePath.check(&task);
+        assert!(findings
+            .iter()
+            .all(|finding| finding.id() == "SEC001" && finding.severity() == Severity::High));
+        assert_eq!(
+            explanations(findings),
+            [
+                "Action 2 runs %APPDATA%\\Updater\\update.exe, which is in a directory users can write to, so anyone who can log on can replace it",
+                "Action 3 runs \"C:/Users/Public/Downloads/run.exe\", which is in a directory users can write to, so anyone who can log on can replace it",
+            ]
+        );
+    }
+
+    #[test]
+    fn passes_programs_in_system_directories() {
+        assert!(UserWritablePath
+            .check(&task(
+                "C:\\Program Files\\Backup\\backup.exe",
+                "C:\\Users\\admin"
+            ))
+            .is_empty());
+    }
+
+    #[test]
+    fn recognizes_every_prefix_of_the_encoded_command_flag() {
+        for flag in [
+            "-e",
+            "-ec",
+            "-enc",
+            "-Enc",
+            "-encodedCommand",
+            "/EncodedCommand",
+        ] {
+            assert!(EncodedPowerShell::is_encoded_flag(flag), "{}", flag);
+        }
+        for flag in [
+            "-",
+            "e",
+            "enc",
+            "-encodedcommands",
+            "-ep",
+            "-ExecutionPolicy",
+            "-c",
+        ] {
+            assert!(!EncodedPowerShell::is_encoded_flag(flag), "{}", flag);
+        }
+    }
+
+    #[test]
+    fn finds_encoded_powershell_commands() {
+        let findings = EncodedPowerShell.check(&task(
+            "C:\\Windows\\System32\\WindowsPowerShell\\v1.0\\powershell.exe",
+            "-NoProfile -enc SQBFAFgA",
+        ));
+        assert_eq!(
+            explanations(findings),
+            ["Action 1 runs an encoded PowerShell command, which hides what the task does"]
+        );
+        // through a shell, and decoding base64 in the command itself
+        let findings = EncodedPowerShell.check(&task(
+            "cmd.exe",
+            "/c pwsh -Command [Convert]::FromBase64String('SQBFAFgA')",
+        ));
+        assert_eq!(
+            explanations(findings),
+            ["Action 1 decodes base64 in PowerShell, which hides what the task does"]
+        );
+    }
+
+    #[test]
+    fn passes_plain_powershell_commands() {
+        assert!(EncodedPowerShell
+            .check(&task(
+                "powershell.exe",
+                "-ExecutionPolicy Bypass -File C:\\Scripts\\backup.ps1"
+            ))
+            .is_empty());
+        // the flag only counts when PowerShell runs it
+        assert!(EncodedPowerShell
+            .check(&task("C:\\Tools\\zip.exe", "-e archive.zip"))
+            .is_empty());
+    }
+
+    #[test]
+    fn finds_hidden_tasks_that_run_as_system() {
+        let hidden = |user: &str| {
+            task("C:\\Tools\\backup.exe", "")
+                .with_settings(Settings::new().with_hidden(true))
+                .with_principal(TaskPrincipal::new().with_user_id(user.to_string()))
+        };
+        for user in ["SYSTEM", "nt authority\\system", "S-1-5-18"] {
+            let findings = HiddenSystemTask.check(&hidden(user));
+            assert_eq!(findings.len(), 1, "{}", user);
+            assert_eq!(findings[0].id(), "SEC003");
+        }
+        assert!(HiddenSystemTask
+            .check(&hidden("DESKTOP-1\\admin"))
+            .is_empty());
+        let visible = hidden("SYSTEM").with_settings(Settings::new());
+        assert!(HiddenSystemTask.check(&visible).is_empty());
+    }
+
+    #[test]
+    fn finds_tasks_without_an_author_or_description() {
+        let task = task("C:\\Tools\\backup.exe", "");
+        let findings = NoAuthorOrDescription.check(&task);
+        assert_eq!(findings.len(), 1);
+        assert_eq!(findings[0].severity(), Severity::Low);
+        let blank = task
+            .clone()
+            .with_registration(TaskRegistration::new().with_description(" ".to_string()));
+        assert_eq!(NoAuthorOrDescription.check(&blank).len(), 1);
+
+        let with_author = task
+            .clone()
+            .with_registration(TaskRegistration::new().with_author("IT".to_string()));
+        assert!(NoAuthorOrDescription.check(&with_author).is_empty());
+        let with_description =
+            task.with_registration(TaskRegistration::new().with_description("Backup".to_string()));
+        assert!(NoAuthorOrDescription.check(&with_description).is_empty());
+    }
+
+    #[test]
+    fn finds_elevated_logon_triggers_for_every_user() {
+        let logon = |trigger: LogonTrigger, run_level: RunLevel| {
+            Task::new(
+                Actions::from_action(action("C:\\Tools\\backup.exe", "")),
+                TaskTriggersBuilder::new().with_logon(trigger).build(),
+            )
+            .with_principal(TaskPrincipal::new().with_run_level(run_level))
+        };
+        let findings =
+            ElevatedLogonForEveryone.check(&logon(LogonTrigger::new(), RunLevel::HighestAvailable));
+        assert_eq!(findings.len(), 1);
+        assert_eq!(findings[0].id(), "SEC005");
+
+        assert!(ElevatedLogonForEveryone
+            .check(&logon(LogonTrigger::new(), RunLevel::LeastPrivilege))
+            .is_empty());
+        assert!(ElevatedLogonForEveryone
+            .check(&logon(
+                LogonTrigger::new().with_user_id("DESKTOP-1\\admin".to_string()),
+                RunLevel::HighestAvailable
+            ))
+            .is_empty());
+    }
+
+    #[test]
+    fn finds_programs_that_run_code_while_looking_legitimate() {
+        let findings = Lolbin.check(&task(
+            "C:\\Windows\\System32\\MSHTA.EXE",
+            "http://example.test",
+        ));
+        assert_eq!(
+            explanations(findings),
+            ["Action 1 uses mshta, a Windows program that is often used to run code while looking legitimate"]
+        );
+        let findings = Lolbin.check(&task("cmd.exe", "/c rundll32 payload.dll,Run"));
+        assert_eq!(findings.len(), 1);
+        assert_eq!(findings[0].id(), "SEC006");
+    }
+
+    #[test]
+    fn passes_other_programs_and_com_handlers() {
+        assert!(Lolbin
+            .check(&task("C:\\Tools\\backup.exe", "--certutil-compatible"))
+            .is_empty());
+        let com = Task::new(
+            Actions::from_action(Action::ComHandler(ComHandlerAction::new(
+                "{00000000-0000-0000-0000-000000000000}".to_string(),
+            ))),
+            TaskTriggersBuilder::new()
+                .with_boot(BootTrigger::new())
+                .build(),
+        );
+        assert!(Lolbin.check(&com).is_empty());
+        assert!(UserWritablePath.check(&com).is_empty());
+    }
+}
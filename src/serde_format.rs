@@ -0,0 +1,469 @@
+use std::{convert::TryFrom, fmt};
+
+use serde::{
+    de::{self, MapAccess, Visitor},
+    ser::{SerializeMap, SerializeSeq},
+    Deserialize, Deserializer, Serialize, Serializer,
+};
+
+use crate::{
+    boundary::Boundary,
+    calendar::{month_from_name, month_name, weekday_from_name, weekday_name},
+    Action, Actions, BootTrigger, DailyTrigger, DaysOfMonth, DaysOfWeek, EventTrigger, IdleTrigger,
+    LogonTrigger, MonthlyDowTrigger, MonthlyTrigger, Months, RegistrationTrigger,
+    SessionStateChangeTrigger, SpecificTimeTrigger, TaskTriggers, WeeklyTrigger, WeeksOfMonth,
+    MAX_ACTIONS, MAX_TRIGGERS,
+};
+
+/// Durations as xsd:duration strings like `PT15M`, the same as in task XML
+pub(crate) mod duration {
+    use chrono::Duration;
+    use serde::{de, Deserialize, Deserializer, Serializer};
+
+    use crate::duration::{parse_xml_duration, to_xml_duration};
+
+    pub(crate) fn serialize<S: Serializer>(
+        duration: &Duration,
+        serializer: S,
+    ) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(&to_xml_duration(duration))
+    }
+
+    pub(crate) fn deserialize<'de, D: Deserializer<'de>>(
+        deserializer: D,
+    ) -> Result<Duration, D::Error> {
+        let text = String::deserialize(deserializer)?;
+        parse_xml_duration(&text).ok_or_else(|| {
+            de::Error::invalid_value(de::Unexpected::Str(&text), &"an xsd:duration like PT15M")
+        })
+    }
+}
+
+/// Durations that can be left out, `null` when they aren't set
+pub(crate) mod optional_duration {
+    use chrono::Duration;
+    use serde::{de, Deserialize, Deserializer, Serializer};
+
+    use crate::duration::{parse_xml_duration, to_xml_duration};
+
+    pub(crate) fn serialize<S: Serializer>(
+        duration: &Option<Duration>,
+        serializer: S,
+    ) -> Result<S::Ok, S::Error> {
+        match duration {
+            Some(duration) => serializer.serialize_some(&to_xml_duration(duration)),
+            None => serializer.serialize_none(),
+        }
+    }
+
+    pub(crate) fn deserialize<'de, D: Deserializer<'de>>(
+        deserializer: D,
+    ) -> Result<Option<Duration>, D::Error> {
+        match Option::<String>::deserialize(deserializer)? {
+            Some(text) => parse_xml_duration(&text).map(Some).ok_or_else(|| {
+                de::Error::invalid_value(de::Unexpected::Str(&text), &"an xsd:duration like PT15M")
+            }),
+            None => Ok(None),
+        }
+    }
+}
+
+/// The value queries of an event trigger as an object from names to queries, in order
+pub(crate) mod value_queries {
+    use super::*;
+
+    pub(crate) fn serialize<S: Serializer>(
+        value_queries: &[(String, String)],
+        serializer: S,
+    ) -> Result<S::Ok, S::Error> {
+        let mut map = serializer.serialize_map(Some(value_queries.len()))?;
+        for (name, query) in value_queries {
+            map.serialize_entry(name, query)?;
+        }
+        map.end()
+    }
+
+    pub(crate) fn deserialize<'de, D: Deserializer<'de>>(
+        deserializer: D,
+    ) -> Result<Vec<(String, String)>, D::Error> {
+        deserializer.deserialize_map(ValueQueriesVisitor)
+    }
+
+    struct ValueQueriesVisitor;
+
+    impl<'de> Visitor<'de> for ValueQueriesVisitor {
+        type Value = Vec<(String, String)>;
+
+        fn expecting(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+            write!(f, "an object from value names to XPath queries")
+        }
+
+        fn visit_map<A: MapAccess<'de>>(self, mut map: A) -> Result<Self::Value, A::Error> {
+            let mut value_queries = Vec::new();
+            while let Some(entry) = map.next_entry()? {
+                value_queries.push(entry);
+            }
+            Ok(value_queries)
+        }
+    }
+}
+
+/// Actions are a list, which is read with the same limits as [`Actions::with_action`]
+impl TryFrom<Vec<Action>> for Actions {
+    type Error = String;
+
+    fn try_from(actions: Vec<Action>) -> Result<Self, Self::Error> {
+        if actions.is_empty() || actions.len() > MAX_ACTIONS {
+            return Err(format!(
+                "a task needs between 1 and {} actions, not {}",
+                MAX_ACTIONS,
+                actions.len()
+            ));
+        }
+        Ok(Actions(actions))
+    }
+}
+
+impl From<Actions> for Vec<Action> {
+    fn from(actions: Actions) -> Self {
+        actions.0
+    }
+}
+
+/// The triggers as they are written, which are read into [`TaskTriggers`]
+/// with the same limit as [`TaskTriggersBuilder`](crate::TaskTriggersBuilder)
+#[derive(Deserialize)]
+pub(crate) struct TriggerLists {
+    daily: Option<Vec<DailyTrigger>>,
+    event: Option<Vec<EventTrigger>>,
+    idle: Option<Vec<IdleTrigger>>,
+    registration: Option<Vec<RegistrationTrigger>>,
+    specific_times: Option<Vec<SpecificTimeTrigger>>,
+    logon: Option<Vec<LogonTrigger>>,
+    boot: Option<Vec<BootTrigger>>,
+    monthly: Option<Vec<MonthlyTrigger>>,
+    monthly_dow: Option<Vec<MonthlyDowTrigger>>,
+    weekly: Option<Vec<WeeklyTrigger>>,
+    session_state_change: Option<Vec<SessionStateChangeTrigger>>,
+}
+
+impl TryFrom<TriggerLists> for TaskTriggers {
+    type Error = String;
+
+    fn try_from(lists: TriggerLists) -> Result<Self, Self::Error> {
+        let triggers = TaskTriggers {
+            daily: lists.daily,
+            event: lists.event,
+            idle: lists.idle,
+            registration: lists.registration,
+            time: lists.specific_times,
+            logon: lists.logon,
+            boot: lists.boot,
+            monthly: lists.monthly,
+            monthly_dow: lists.monthly_dow,
+            weekly: lists.weekly,
+            session_state_change: lists.session_state_change,
+        };
+        if triggers.len() > usize::from(MAX_TRIGGERS) {
+            return Err(format!(
+                "a task can only have up to {} triggers, not {}",
+                MAX_TRIGGERS,
+                triggers.len()
+            ));
+        }
+        Ok(triggers)
+    }
+}
+
+/// Boundaries as xsd:dateTime strings, without an offset when they are local
+impl Serialize for Boundary {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(&self.to_xml())
+    }
+}
+
+impl<'de> Deserialize<'de> for Boundary {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let text = String::deserialize(deserializer)?;
+        Boundary::parse(&text).ok_or_else(|| {
+            de::Error::invalid_value(
+                de::Unexpected::Str(&text),
+                &"an xsd:dateTime like 2021-01-01T09:00:00",
+            )
+        })
+    }
+}
+
+/// Writes the items of a set as a list
+fn serialize_list<S, T, I>(items: I, serializer: S) -> Result<S::Ok, S::Error>
+where
+    S: Serializer,
+    T: Serialize,
+    I: Iterator<Item = T>,
+{
+    let items: Vec<T> = items.collect();
+    let mut seq = serializer.serialize_seq(Some(items.len()))?;
+    for item in items {
+        seq.serialize_element(&item)?;
+    }
+    seq.end()
+}
+
+/// Reads a list into a set, the error names what the items should be
+fn deserialize_list<'de, D, T, I>(
+    deserializer: D,
+    empty: T,
+    add: impl Fn(T, &I) -> Option<T>,
+    expected: &'static str,
+) -> Result<T, D::Error>
+where
+    D: Deserializer<'de>,
+    I: Deserialize<'de> + fmt::Display,
+{
+    Vec::<I>::deserialize(deserializer)?
+        .iter()
+        .try_fold(empty, |set, item| {
+            add(set, item)
+                .ok_or_else(|| de::Error::custom(format!("expected {}, found {}", expected, item)))
+        })
+}
+
+/// Days of the week by their names, like `["Monday", "Friday"]`
+impl Serialize for DaysOfWeek {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serialize_list(self.iter().map(weekday_name), serializer)
+    }
+}
+
+impl<'de> Deserialize<'de> for DaysOfWeek {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        deserialize_list(
+            deserializer,
+            DaysOfWeek::new(),
+            |days, name: &String| weekday_from_name(name).map(|day| days.with(day)),
+            "days of the week like Monday",
+        )
+    }
+}
+
+/// Months by their names, like `["January", "July"]`
+impl Serialize for Months {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serialize_list(self.iter().map(month_name), serializer)
+    }
+}
+
+impl<'de> Deserialize<'de> for Months {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        deserialize_list(
+            deserializer,
+            Months::new(),
+            |months, name: &String| month_from_name(name).map(|month| months.with(month)),
+            "months like January",
+        )
+    }
+}
+
+/// Days of the month as numbers from 1 to 31
+impl Serialize for DaysOfMonth {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serialize_list(self.iter(), serializer)
+    }
+}
+
+impl<'de> Deserialize<'de> for DaysOfMonth {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        deserialize_list(
+            deserializer,
+            DaysOfMonth::new(),
+            |days, day: &u32| (1..=31).contains(day).then(|| days.with(*day)),
+            "days of the month from 1 to 31",
+        )
+    }
+}
+
+/// Weeks of the month as numbers from 1 to 4
+impl Serialize for WeeksOfMonth {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serialize_list(self.iter(), serializer)
+    }
+}
+
+impl<'de> Deserialize<'de> for WeeksOfMonth {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        deserialize_list(
+            deserializer,
+            WeeksOfMonth::new(),
+            |weeks, week: &u32| (1..=4).contains(week).then(|| weeks.with(*week)),
+            "weeks of the month from 1 to 4",
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::PathBuf;
+
+    use chrono::{Duration, NaiveDate, Weekday};
+
+    use super::*;
+    use crate::{
+        task::{RunLevel, Settings, Task, TaskLogon, TaskPrincipal, TaskRegistration},
+        ComHandlerAction, ExecAction, Repetition, TaskTriggersBuilder,
+    };
+
+    fn local(year: i32, month: u32, day: u32, hour: u32, minute: u32) -> Boundary {
+        Boundary::Local(
+            NaiveDate::from_ymd_opt(year, month, day)
+                .unwrap()
+                .and_hms_opt(hour, minute, 0)
+                .unwrap(),
+        )
+    }
+
+    /// The task in the version 1 fixture
+    fn task() -> Task {
+        let triggers = TaskTriggersBuilder::new()
+            .with_daily(
+                DailyTrigger::new("nightly".to_string())
+                    .with_start_time(local(2021, 1, 1, 2, 0))
+                    .with_interval(2)
+                    .with_repetition(
+                        Repetition::new(Duration::minutes(15)).with_duration(Duration::hours(1)),
+                    ),
+            )
+            .with_weekly(
+                WeeklyTrigger::new(DaysOfWeek::new().with(Weekday::Mon).with(Weekday::Fri))
+                    .with_common(|common| {
+                        common
+                            .with_start(local(2021, 1, 4, 9, 0))
+                            .with_random_delay(Duration::minutes(5))
+                    }),
+            )
+            .with_monthly_dow(
+                MonthlyDowTrigger::new(
+                    DaysOfWeek::new().with(Weekday::Tue),
+                    WeeksOfMonth::new().with(2),
+                    Months::new().with(1).with(7),
+                )
+                .with_common(|common| common.with_start(local(2021, 1, 1, 12, 0))),
+            )
+            .with_event(
+                EventTrigger::new(
+                    "<QueryList><Query><Select Path=\"System\">*</Select></Query></QueryList>"
+                        .to_string(),
+                )
+                .with_value_query("Id".to_string(), "Event/System/EventID".to_string()),
+            )
+            .with_boot(
+                BootTrigger::new()
+                    .with_common(|common| common.with_enabled(false))
+                    .with_delay(Duration::seconds(30)),
+            )
+            .build();
+        let actions = Actions::from_action(
+            ExecAction::new(PathBuf::from(r"C:\Tools\backup.exe"))
+                .with_arguments("--all".to_string())
+                .into(),
+        )
+        .with_action(
+            ComHandlerAction::new("{0F87369F-A4E5-4CFC-BD3E-73E6154572DD}".to_string())
+                .with_data("nightly".to_string())
+                .into(),
+        );
+        Task::new(actions, triggers)
+            .with_registration(
+                TaskRegistration::new()
+                    .with_author("me".to_string())
+                    .with_description("Backs up the tools".to_string()),
+            )
+            .with_principal(
+                TaskPrincipal::new()
+                    .with_user_id("SYSTEM".to_string())
+                    .with_logon_type(TaskLogon::ServiceAccount)
+                    .with_run_level(RunLevel::HighestAvailable),
+            )
+            .with_settings(
+                Settings::new()
+                    .with_start_when_available(true)
+                    .with_execution_time_limit(Some(Duration::hours(1))),
+            )
+    }
+
+    /// A boot trigger in the JSON format
+    const BOOT: &str = r#"{ "id": null, "enabled": true, "start": null, "end": null,
+        "execution_time_limit": null, "random_delay": null, "delay": null }"#;
+    /// An exec action in the JSON format
+    const EXEC: &str = r#"{ "type": "exec", "id": null, "path": "C:\\Tools\\backup.exe",
+        "arguments": null, "working_directory": null }"#;
+
+    fn json(triggers: usize, actions: usize) -> String {
+        format!(
+            r#"{{ "triggers": {{ "boot": [{}] }}, "actions": [{}] }}"#,
+            vec![BOOT; triggers].join(", "),
+            vec![EXEC; actions].join(", ")
+        )
+    }
+
+    #[test]
+    fn reads_version_1() {
+        let task: Task =
+            serde_json::from_str(include_str!("../tests/fixtures/serde/v1/task.json")).unwrap();
+        assert_eq!(task, self::task());
+    }
+
+    #[test]
+    fn writes_version_1() {
+        let fixture: serde_json::Value =
+            serde_json::from_str(include_str!("../tests/fixtures/serde/v1/task.json")).unwrap();
+        assert_eq!(serde_json::to_value(task()).unwrap(), fixture);
+    }
+
+    #[test]
+    fn reads_version_1_with_the_defaults_left_out() {
+        let task: Task =
+            serde_json::from_str(include_str!("../tests/fixtures/serde/v1/minimal.json")).unwrap();
+        let expected = Task::new(
+            Actions::from_action(ExecAction::new(PathBuf::from(r"C:\Tools\backup.exe")).into()),
+            TaskTriggersBuilder::new()
+                .with_boot(BootTrigger::new())
+                .build(),
+        );
+        assert_eq!(task, expected);
+        assert_eq!(
+            task.settings().execution_time_limit,
+            Some(Duration::hours(72))
+        );
+    }
+
+    #[test]
+    fn reads_up_to_48_triggers_and_32_actions() {
+        let task: Task = serde_json::from_str(&json(48, 32)).unwrap();
+        assert_eq!(task.triggers().len(), 48);
+        assert_eq!(task.actions().len(), 32);
+    }
+
+    #[test]
+    fn rejects_more_than_48_triggers() {
+        let error = serde_json::from_str::<Task>(&json(49, 1)).unwrap_err();
+        assert!(error
+            .to_string()
+            .starts_with("a task can only have up to 48 triggers, not 49"));
+    }
+
+    #[test]
+    fn rejects_more_than_32_actions() {
+        let error = serde_json::from_str::<Task>(&json(1, 33)).unwrap_err();
+        assert!(error
+            .to_string()
+            .starts_with("a task needs between 1 and 32 actions, not 33"));
+    }
+
+    #[test]
+    fn rejects_a_task_without_actions() {
+        let error = serde_json::from_str::<Task>(&json(1, 0)).unwrap_err();
+        assert!(error
+            .to_string()
+            .starts_with("a task needs between 1 and 32 actions, not 0"));
+    }
+}
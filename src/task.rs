@@ -0,0 +1,744 @@
+use chrono::Duration;
+
+use crate::{task_xml::is_service_account, Actions, TaskTriggers};
+
+/// Everything that describes a task: what it runs, when it runs, who it runs as
+/// and how Task Scheduler treats it.
+///
+/// This is plain data, so it can be built, compared and turned into task XML
+/// on any platform, and registered on Windows.
+///
+/// https://docs.microsoft.com/en-us/windows/win32/taskschd/task-scheduler-schema
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Task {
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub(crate) registration: TaskRegistration,
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub(crate) principal: TaskPrincipal,
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub(crate) settings: Settings,
+    pub(crate) triggers: TaskTriggers,
+    pub(crate) actions: Actions,
+}
+
+impl Task {
+    /// Creates a task with the default registration info, principal and settings
+    pub fn new(actions: Actions, triggers: TaskTriggers) -> Self {
+        Self {
+            registration: TaskRegistration::new(),
+            principal: TaskPrincipal::new(),
+            settings: Settings::default(),
+            triggers,
+            actions,
+        }
+    }
+
+    pub fn with_registration(mut self, registration: TaskRegistration) -> Self {
+        self.registration = registration;
+        self
+    }
+
+    pub fn with_principal(mut self, principal: TaskPrincipal) -> Self {
+        self.principal = principal;
+        self
+    }
+
+    pub fn with_settings(mut self, settings: Settings) -> Self {
+        self.settings = settings;
+        self
+    }
+
+    pub fn registration(&self) -> &TaskRegistration {
+        &self.registration
+    }
+
+    pub fn principal(&self) -> &TaskPrincipal {
+        &self.principal
+    }
+
+    pub fn settings(&self) -> &Settings {
+        &self.settings
+    }
+
+    pub fn triggers(&self) -> &TaskTriggers {
+        &self.triggers
+    }
+
+    pub fn actions(&self) -> &Actions {
+        &self.actions
+    }
+}
+
+/// Administrative information that describes a task
+///
+/// https://docs.microsoft.com/en-us/windows/win32/taskschd/taskschedulerschema-registrationinfo-tasktype-element
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(default))]
+pub struct TaskRegistration {
+    pub(crate) author: Option<String>,
+    pub(crate) description: Option<String>,
+    pub(crate) documentation: Option<String>,
+    pub(crate) source: Option<String>,
+    pub(crate) version: Option<String>,
+    /// Filled in by Task Scheduler with the path of the task
+    pub(crate) uri: Option<String>,
+    /// Filled in by Task Scheduler with the date the task was registered
+    pub(crate) date: Option<String>,
+}
+
+impl TaskRegistration {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn with_author(mut self, author: String) -> Self {
+        self.author = Some(author);
+        self
+    }
+
+    pub fn with_description(mut self, description: String) -> Self {
+        self.description = Some(description);
+        self
+    }
+
+    pub fn with_documentation(mut self, documentation: String) -> Self {
+        self.documentation = Some(documentation);
+        self
+    }
+
+    pub fn with_source(mut self, source: String) -> Self {
+        self.source = Some(source);
+        self
+    }
+
+    pub fn with_version(mut self, version: String) -> Self {
+        self.version = Some(version);
+        self
+    }
+
+    pub fn author(&self) -> Option<&str> {
+        self.author.as_deref()
+    }
+
+    pub fn description(&self) -> Option<&str> {
+        self.description.as_deref()
+    }
+
+    pub fn documentation(&self) -> Option<&str> {
+        self.documentation.as_deref()
+    }
+
+    pub fn source(&self) -> Option<&str> {
+        self.source.as_deref()
+    }
+
+    pub fn version(&self) -> Option<&str> {
+        self.version.as_deref()
+    }
+
+    /// The path of the task, only set on tasks read back from Task Scheduler
+    pub fn uri(&self) -> Option<&str> {
+        self.uri.as_deref()
+    }
+
+    /// The date the task was registered, only set on tasks read back from Task Scheduler
+    pub fn date(&self) -> Option<&str> {
+        self.date.as_deref()
+    }
+}
+
+/// The security context that the task runs in
+///
+/// https://docs.microsoft.com/en-us/windows/win32/taskschd/taskschedulerschema-principal-principaltype-element
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(default))]
+pub struct TaskPrincipal {
+    pub(crate) id: String,
+    pub(crate) user_id: Option<String>,
+    pub(crate) group_id: Option<String>,
+    pub(crate) logon_type: Option<TaskLogon>,
+    pub(crate) run_level: RunLevel,
+    #[cfg_attr(feature = "serde", serde(skip_serializing_if = "Vec::is_empty"))]
+    pub(crate) required_privileges: Vec<Privilege>,
+}
+
+impl TaskPrincipal {
+    /// Runs as the user that registers the task with the least privileges
+    pub fn new() -> Self {
+        Self {
+            id: "Author".to_string(),
+            user_id: None,
+            group_id: None,
+            logon_type: None,
+            run_level: RunLevel::LeastPrivilege,
+            required_privileges: Vec::new(),
+        }
+    }
+
+    /// The user can be a user name like `DOMAIN\user`, a well known account
+    /// like `SYSTEM` or a security identifier like `S-1-5-18`
+    pub fn with_user_id(mut self, user_id: String) -> Self {
+        self.user_id = Some(user_id);
+        self
+    }
+
+    /// Runs the task for every member of the group, this is used instead of a user
+    pub fn with_group_id(mut self, group_id: String) -> Self {
+        self.group_id = Some(group_id);
+        self
+    }
+
+    pub fn with_logon_type(mut self, logon_type: TaskLogon) -> Self {
+        self.logon_type = Some(logon_type);
+        self
+    }
+
+    pub fn with_run_level(mut self, run_level: RunLevel) -> Self {
+        self.run_level = run_level;
+        self
+    }
+
+    /// Runs the task with only these privileges of the user, this needs schema 1.3
+    pub fn with_required_privileges(mut self, required_privileges: Vec<Privilege>) -> Self {
+        self.required_privileges = required_privileges;
+        self
+    }
+
+    pub fn user_id(&self) -> Option<&str> {
+        self.user_id.as_deref()
+    }
+
+    pub fn group_id(&self) -> Option<&str> {
+        self.group_id.as_deref()
+    }
+
+    pub fn logon_type(&self) -> Option<TaskLogon> {
+        self.logon_type
+    }
+
+    pub fn run_level(&self) -> RunLevel {
+        self.run_level
+    }
+
+    pub fn required_privileges(&self) -> &[Privilege] {
+        &self.required_privileges
+    }
+
+    /// The logon type, or the one Task Scheduler uses when there is none:
+    /// a group for a group id and a service account for a well known service account
+    pub(crate) fn implied_logon_type(&self) -> Option<TaskLogon> {
+        match self.logon_type {
+            Some(logon_type) => Some(logon_type),
+            None if self.group_id.is_some() => Some(TaskLogon::Group),
+            None if self.user_id.as_deref().map_or(false, is_service_account) => {
+                Some(TaskLogon::ServiceAccount)
+            }
+            None => None,
+        }
+    }
+}
+
+impl Default for TaskPrincipal {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Defines what logon technique is required to run a task.
+///
+/// https://docs.microsoft.com/en-us/windows/win32/api/taskschd/ne-taskschd-task_logon_type
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "snake_case"))]
+#[repr(i32)]
+pub enum TaskLogon {
+    /// The logon method is not specified. Used for non-NT credentials.
+    None = 0,
+    /// Use a password for logging on the user. The password must be supplied at registration time.
+    Password = 1,
+    /// The service will log the user on using Service For User (S4U), and the task will run in a non-interactive desktop. When an S4U logon is used, no password is stored by the system and there is no access to either the network or to encrypted files.
+    #[cfg_attr(feature = "serde", serde(rename = "s4u"))]
+    S4U = 2,
+    /// User must already be logged on. The task will be run only in an existing interactive session.
+    InteractiveToken = 3,
+    /// Group activation. The groupId field specifies the group.
+    Group = 4,
+    /// Indicates that a Local System, Local Service, or Network Service account is being used as a security context to run the task.
+    ServiceAccount = 5,
+    /// Not in use; currently identical to Password
+    InteractiveTokenOrPassword = 6,
+}
+
+/// The privilege level that is required to run the task
+///
+/// https://docs.microsoft.com/en-us/windows/win32/api/taskschd/ne-taskschd-task_runlevel_type
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "snake_case"))]
+pub enum RunLevel {
+    /// Tasks run with the least privileges.
+    LeastPrivilege,
+    /// Tasks run with the highest privileges.
+    HighestAvailable,
+}
+
+impl Default for RunLevel {
+    fn default() -> Self {
+        RunLevel::LeastPrivilege
+    }
+}
+
+/// Generates the privileges with the names the task scheduler schema uses
+macro_rules! privileges {
+    ($($privilege:ident => $name:literal,)*) => {
+        /// A privilege that a task can require, the task runs with only the privileges it requires
+        ///
+        /// https://docs.microsoft.com/en-us/windows/win32/taskschd/taskschedulerschema-requiredprivileges-principaltype-element
+        #[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+        #[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+        pub enum Privilege {
+            $(
+                #[cfg_attr(feature = "serde", serde(rename = $name))]
+                $privilege,
+            )*
+        }
+
+        impl Privilege {
+            pub(crate) const ALL: &'static [Privilege] = &[$(Privilege::$privilege,)*];
+
+            /// The name of the privilege, e.g. `SeBackupPrivilege`
+            pub fn name(self) -> &'static str {
+                match self {
+                    $(Privilege::$privilege => $name,)*
+                }
+            }
+        }
+    };
+}
+
+privileges! {
+    CreateToken => "SeCreateTokenPrivilege",
+    AssignPrimaryToken => "SeAssignPrimaryTokenPrivilege",
+    LockMemory => "SeLockMemoryPrivilege",
+    IncreaseQuota => "SeIncreaseQuotaPrivilege",
+    UnsolicitedInput => "SeUnsolicitedInputPrivilege",
+    MachineAccount => "SeMachineAccountPrivilege",
+    Tcb => "SeTcbPrivilege",
+    Security => "SeSecurityPrivilege",
+    TakeOwnership => "SeTakeOwnershipPrivilege",
+    LoadDriver => "SeLoadDriverPrivilege",
+    SystemProfile => "SeSystemProfilePrivilege",
+    Systemtime => "SeSystemtimePrivilege",
+    ProfileSingleProcess => "SeProfileSingleProcessPrivilege",
+    IncreaseBasePriority => "SeIncreaseBasePriorityPrivilege",
+    CreatePagefile => "SeCreatePagefilePrivilege",
+    CreatePermanent => "SeCreatePermanentPrivilege",
+    Backup => "SeBackupPrivilege",
+    Restore => "SeRestorePrivilege",
+    Shutdown => "SeShutdownPrivilege",
+    Debug => "SeDebugPrivilege",
+    Audit => "SeAuditPrivilege",
+    SystemEnvironment => "SeSystemEnvironmentPrivilege",
+    ChangeNotify => "SeChangeNotifyPrivilege",
+    RemoteShutdown => "SeRemoteShutdownPrivilege",
+    Undock => "SeUndockPrivilege",
+    SyncAgent => "SeSyncAgentPrivilege",
+    EnableDelegation => "SeEnableDelegationPrivilege",
+    ManageVolume => "SeManageVolumePrivilege",
+    Impersonate => "SeImpersonatePrivilege",
+    CreateGlobal => "SeCreateGlobalPrivilege",
+    TrustedCredManAccess => "SeTrustedCredManAccessPrivilege",
+    Relabel => "SeRelabelPrivilege",
+    IncreaseWorkingSet => "SeIncreaseWorkingSetPrivilege",
+    TimeZone => "SeTimeZonePrivilege",
+    CreateSymbolicLink => "SeCreateSymbolicLinkPrivilege",
+}
+
+impl Privilege {
+    /// Parses the name of a privilege, e.g. `SeBackupPrivilege`
+    pub fn from_name(name: &str) -> Option<Privilege> {
+        Privilege::ALL
+            .iter()
+            .copied()
+            .find(|privilege| privilege.name() == name)
+    }
+}
+
+/// How Task Scheduler treats a new instance of the task while another one is still running
+///
+/// https://docs.microsoft.com/en-us/windows/win32/api/taskschd/ne-taskschd-task_instances_policy
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "snake_case"))]
+pub enum MultipleInstancesPolicy {
+    /// Starts a new instance while an existing instance of the task is running.
+    Parallel,
+    /// Starts a new instance of the task after all other instances of the task are complete.
+    Queue,
+    /// Does not start a new instance if an existing instance of the task is running.
+    IgnoreNew,
+    /// Stops an existing instance of the task before it starts a new instance.
+    StopExisting,
+}
+
+impl Default for MultipleInstancesPolicy {
+    fn default() -> Self {
+        MultipleInstancesPolicy::IgnoreNew
+    }
+}
+
+/// Restarts the task when it fails
+///
+/// https://docs.microsoft.com/en-us/windows/win32/taskschd/taskschedulerschema-restartonfailure-settingstype-element
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct RestartOnFailure {
+    #[cfg_attr(feature = "serde", serde(with = "crate::serde_format::duration"))]
+    pub(crate) interval: Duration,
+    pub(crate) count: u32,
+}
+
+impl RestartOnFailure {
+    /// The interval has to be between 1 minute and 31 days
+    pub fn new(interval: Duration, count: u32) -> Self {
+        Self { interval, count }
+    }
+
+    pub fn interval(&self) -> Duration {
+        self.interval
+    }
+
+    pub fn count(&self) -> u32 {
+        self.count
+    }
+}
+
+/// The conditions for an idle computer that the task waits for when it
+/// should only run while idle or is triggered by an [`IdleTrigger`](crate::IdleTrigger)
+///
+/// https://docs.microsoft.com/en-us/windows/win32/taskschd/taskschedulerschema-idlesettings-settingstype-element
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(default))]
+pub struct IdleConditions {
+    #[cfg_attr(feature = "serde", serde(with = "crate::serde_format::duration"))]
+    pub(crate) duration: Duration,
+    #[cfg_attr(feature = "serde", serde(with = "crate::serde_format::duration"))]
+    pub(crate) wait_timeout: Duration,
+    pub(crate) stop_on_idle_end: bool,
+    pub(crate) restart_on_idle: bool,
+}
+
+impl IdleConditions {
+    /// The computer has to be idle for 10 minutes, which is waited on for up to an hour
+    pub fn new() -> Self {
+        Self {
+            duration: Duration::minutes(10),
+            wait_timeout: Duration::hours(1),
+            stop_on_idle_end: true,
+            restart_on_idle: false,
+        }
+    }
+
+    /// How long the computer has to be idle before the task starts
+    pub fn with_duration(mut self, duration: Duration) -> Self {
+        self.duration = duration;
+        self
+    }
+
+    /// How long Task Scheduler waits for the computer to become idle
+    pub fn with_wait_timeout(mut self, wait_timeout: Duration) -> Self {
+        self.wait_timeout = wait_timeout;
+        self
+    }
+
+    pub fn with_stop_on_idle_end(mut self, stop_on_idle_end: bool) -> Self {
+        self.stop_on_idle_end = stop_on_idle_end;
+        self
+    }
+
+    pub fn with_restart_on_idle(mut self, restart_on_idle: bool) -> Self {
+        self.restart_on_idle = restart_on_idle;
+        self
+    }
+
+    pub fn duration(&self) -> Duration {
+        self.duration
+    }
+
+    pub fn wait_timeout(&self) -> Duration {
+        self.wait_timeout
+    }
+
+    pub fn stop_on_idle_end(&self) -> bool {
+        self.stop_on_idle_end
+    }
+
+    pub fn restart_on_idle(&self) -> bool {
+        self.restart_on_idle
+    }
+}
+
+impl Default for IdleConditions {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// The settings that Task Scheduler uses to perform the task.
+/// The defaults are the same defaults Task Scheduler uses.
+///
+/// https://docs.microsoft.com/en-us/windows/win32/taskschd/taskschedulerschema-settings-tasktype-element
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(default))]
+pub struct Settings {
+    pub(crate) allow_demand_start: bool,
+    pub(crate) allow_hard_terminate: bool,
+    pub(crate) disallow_start_if_on_batteries: bool,
+    pub(crate) stop_if_going_on_batteries: bool,
+    pub(crate) enabled: bool,
+    pub(crate) hidden: bool,
+    pub(crate) start_when_available: bool,
+    pub(crate) run_only_if_idle: bool,
+    pub(crate) run_only_if_network_available: bool,
+    pub(crate) wake_to_run: bool,
+    /// `None` means the task can run indefinitely
+    #[cfg_attr(
+        feature = "serde",
+        serde(with = "crate::serde_format::optional_duration")
+    )]
+    pub(crate) execution_time_limit: Option<Duration>,
+    #[cfg_attr(
+        feature = "serde",
+        serde(with = "crate::serde_format::optional_duration")
+    )]
+    pub(crate) delete_expired_task_after: Option<Duration>,
+    pub(crate) multiple_instances: MultipleInstancesPolicy,
+    pub(crate) priority: u8,
+    pub(crate) restart_on_failure: Option<RestartOnFailure>,
+    pub(crate) idle_conditions: IdleConditions,
+    pub(crate) use_unified_scheduling_engine: bool,
+    pub(crate) disallow_start_on_remote_app_session: bool,
+    pub(crate) volatile: bool,
+}
+
+impl Default for Settings {
+    fn default() -> Self {
+        Self {
+            allow_demand_start: true,
+            allow_hard_terminate: true,
+            disallow_start_if_on_batteries: true,
+            stop_if_going_on_batteries: true,
+            enabled: true,
+            hidden: false,
+            start_when_available: false,
+            run_only_if_idle: false,
+            run_only_if_network_available: false,
+            wake_to_run: false,
+            execution_time_limit: Some(Duration::hours(72)),
+            delete_expired_task_after: None,
+            multiple_instances: MultipleInstancesPolicy::IgnoreNew,
+            priority: 7,
+            restart_on_failure: None,
+            idle_conditions: IdleConditions::new(),
+            use_unified_scheduling_engine: false,
+            disallow_start_on_remote_app_session: false,
+            volatile: false,
+        }
+    }
+}
+
+impl Settings {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// The task can be started with [`RegisteredTask::run`](crate::RegisteredTask) or from the UI
+    pub fn with_allow_demand_start(mut self, allow_demand_start: bool) -> Self {
+        self.allow_demand_start = allow_demand_start;
+        self
+    }
+
+    /// The task can be terminated with TerminateProcess when it doesn't stop on request
+    pub fn with_allow_hard_terminate(mut self, allow_hard_terminate: bool) -> Self {
+        self.allow_hard_terminate = allow_hard_terminate;
+        self
+    }
+
+    pub fn with_disallow_start_if_on_batteries(mut self, disallow: bool) -> Self {
+        self.disallow_start_if_on_batteries = disallow;
+        self
+    }
+
+    pub fn with_stop_if_going_on_batteries(mut self, stop: bool) -> Self {
+        self.stop_if_going_on_batteries = stop;
+        self
+    }
+
+    pub fn with_enabled(mut self, enabled: bool) -> Self {
+        self.enabled = enabled;
+        self
+    }
+
+    /// Hides the task from the Task Scheduler UI
+    pub fn with_hidden(mut self, hidden: bool) -> Self {
+        self.hidden = hidden;
+        self
+    }
+
+    /// Starts the task as soon as possible after a scheduled start was missed
+    pub fn with_start_when_available(mut self, start_when_available: bool) -> Self {
+        self.start_when_available = start_when_available;
+        self
+    }
+
+    pub fn with_run_only_if_idle(mut self, run_only_if_idle: bool) -> Self {
+        self.run_only_if_idle = run_only_if_idle;
+        self
+    }
+
+    pub fn with_run_only_if_network_available(
+        mut self,
+        run_only_if_network_available: bool,
+    ) -> Self {
+        self.run_only_if_network_available = run_only_if_network_available;
+        self
+    }
+
+    /// Wakes the computer up to run the task
+    pub fn with_wake_to_run(mut self, wake_to_run: bool) -> Self {
+        self.wake_to_run = wake_to_run;
+        self
+    }
+
+    /// How long the task is allowed to run, `None` lets it run indefinitely
+    pub fn with_execution_time_limit(mut self, execution_time_limit: Option<Duration>) -> Self {
+        self.execution_time_limit = execution_time_limit;
+        self
+    }
+
+    /// Deletes the task this long after it expires, the triggers need an end boundary for this
+    pub fn with_delete_expired_task_after(mut self, delete_after: Duration) -> Self {
+        self.delete_expired_task_after = Some(delete_after);
+        self
+    }
+
+    pub fn with_multiple_instances(mut self, multiple_instances: MultipleInstancesPolicy) -> Self {
+        self.multiple_instances = multiple_instances;
+        self
+    }
+
+    /// The priority is between 0, which is the highest, and 10, which is the lowest
+    pub fn with_priority(mut self, priority: u8) -> Self {
+        self.priority = priority;
+        self
+    }
+
+    pub fn with_restart_on_failure(mut self, restart_on_failure: RestartOnFailure) -> Self {
+        self.restart_on_failure = Some(restart_on_failure);
+        self
+    }
+
+    pub fn with_idle_conditions(mut self, idle_conditions: IdleConditions) -> Self {
+        self.idle_conditions = idle_conditions;
+        self
+    }
+
+    /// Runs the task with the Unified Scheduling Engine, this needs schema 1.3
+    pub fn with_use_unified_scheduling_engine(mut self, use_engine: bool) -> Self {
+        self.use_unified_scheduling_engine = use_engine;
+        self
+    }
+
+    /// Doesn't start the task in a RemoteApp session, this needs schema 1.3
+    pub fn with_disallow_start_on_remote_app_session(mut self, disallow: bool) -> Self {
+        self.disallow_start_on_remote_app_session = disallow;
+        self
+    }
+
+    /// Disables the task every time Windows starts, this needs schema 1.4
+    pub fn with_volatile(mut self, volatile: bool) -> Self {
+        self.volatile = volatile;
+        self
+    }
+
+    pub fn allow_demand_start(&self) -> bool {
+        self.allow_demand_start
+    }
+
+    pub fn allow_hard_terminate(&self) -> bool {
+        self.allow_hard_terminate
+    }
+
+    pub fn disallow_start_if_on_batteries(&self) -> bool {
+        self.disallow_start_if_on_batteries
+    }
+
+    pub fn stop_if_going_on_batteries(&self) -> bool {
+        self.stop_if_going_on_batteries
+    }
+
+    pub fn enabled(&self) -> bool {
+        self.enabled
+    }
+
+    pub fn hidden(&self) -> bool {
+        self.hidden
+    }
+
+    pub fn start_when_available(&self) -> bool {
+        self.start_when_available
+    }
+
+    pub fn run_only_if_idle(&self) -> bool {
+        self.run_only_if_idle
+    }
+
+    pub fn run_only_if_network_available(&self) -> bool {
+        self.run_only_if_network_available
+    }
+
+    pub fn wake_to_run(&self) -> bool {
+        self.wake_to_run
+    }
+
+    pub fn execution_time_limit(&self) -> Option<Duration> {
+        self.execution_time_limit
+    }
+
+    pub fn delete_expired_task_after(&self) -> Option<Duration> {
+        self.delete_expired_task_after
+    }
+
+    pub fn multiple_instances(&self) -> MultipleInstancesPolicy {
+        self.multiple_instances
+    }
+
+    pub fn priority(&self) -> u8 {
+        self.priority
+    }
+
+    pub fn restart_on_failure(&self) -> Option<&RestartOnFailure> {
+        self.restart_on_failure.as_ref()
+    }
+
+    pub fn idle_conditions(&self) -> &IdleConditions {
+        &self.idle_conditions
+    }
+
+    pub fn use_unified_scheduling_engine(&self) -> bool {
+        self.use_unified_scheduling_engine
+    }
+
+    pub fn disallow_start_on_remote_app_session(&self) -> bool {
+        self.disallow_start_on_remote_app_session
+    }
+
+    pub fn volatile(&self) -> bool {
+        self.volatile
+    }
+}
@@ -1,11 +1,17 @@
 use std::ptr;
 
-use bindings::Windows::Win32::TaskScheduler::{
-    IPrincipal, IRegistrationInfo, ITaskDefinition, ITaskSettings, ITriggerCollection,
+use bindings::Windows::Win32::{
+    Automation::BSTR,
+    TaskScheduler::{
+        IPrincipal, IRegistrationInfo, ITaskDefinition, ITaskSettings, ITriggerCollection,
+    },
 };
 use log::error;
 
-pub(crate) struct TaskDefinition(pub(crate) ITaskDefinition);
+/// Defines all the components of a task, such as the task settings, triggers, actions, and registration information.
+///
+/// https://docs.microsoft.com/en-us/windows/win32/api/taskschd/nn-taskschd-itaskdefinition
+pub struct TaskDefinition(pub(crate) ITaskDefinition);
 
 impl TaskDefinition {
     /// Create a new task definition
@@ -71,4 +77,15 @@ impl TaskDefinition {
             }
         }
     }
+
+    /// Gets the XML-formatted definition of the task.
+    ///
+    /// https://docs.microsoft.com/en-us/windows/win32/api/taskschd/nf-taskschd-itaskdefinition-get_xmltext
+    pub fn xml(&self) -> Result<String, windows::Error> {
+        let mut xml = BSTR::default();
+        unsafe {
+            self.0.get_XmlText(&mut xml).ok()?;
+        }
+        Ok(xml.to_string())
+    }
 }
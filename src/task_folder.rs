@@ -3,12 +3,19 @@ use std::convert::TryFrom;
 use bindings::Windows::Win32::{
     Automation::BSTR,
     TaskScheduler::{
-        IRegisteredTask, ITaskDefinition, ITaskFolder, TASK_CREATION, TASK_LOGON_TYPE,
+        IRegisteredTask, ITaskDefinition, ITaskFolder, ITaskFolderCollection, TASK_CREATION,
+        TASK_ENUM_FLAGS, TASK_LOGON_TYPE,
     },
 };
 use log::error;
 
-use crate::{task_service::TaskService, to_win_str};
+use crate::{
+    registered_task::{collect_registered_tasks, RegisteredTask},
+    task::TaskLogon,
+    task_service::TaskService,
+    to_win_str,
+    variant::Variant,
+};
 
 pub(crate) struct TaskFolder(pub(crate) ITaskFolder);
 
@@ -27,20 +34,27 @@ impl TaskFolder {
     ///
     /// https://docs.microsoft.com/en-us/windows/win32/api/taskschd/nf-taskschd-itaskfolder-deletetask
     pub(crate) fn delete_task(&self, task_name: &str) -> Result<(), windows::Error> {
-        const flags: i32 = 0;
+        // flags is reserved and must be 0
+        const FLAGS: i32 = 0;
         unsafe {
             self.0
-                .DeleteTask(BSTR::try_from(task_name).unwrap(), flags)
-                .ok();
+                .DeleteTask(BSTR::try_from(task_name).unwrap(), FLAGS)
+                .ok()
         }
-        Ok(())
     }
 
+    /// Registers (creates) a task in the folder using the task definition
+    ///
+    /// Nothing is registered when the options are [`RegistrationMode::ValidateOnly`],
+    /// so no registered task is returned in that case
+    ///
+    /// https://docs.microsoft.com/en-us/windows/win32/api/taskschd/nf-taskschd-itaskfolder-registertaskdefinition
     pub(crate) fn register_task(
         &self,
         task_name: &str,
         task_definition: ITaskDefinition,
-    ) -> Result<IRegisteredTask, windows::Error> {
+        options: RegistrationOptions,
+    ) -> Result<Option<RegisteredTask>, windows::Error> {
         let mut registered_task = None;
         unsafe {
             let err = self
@@ -48,7 +62,7 @@ impl TaskFolder {
                 .RegisterTaskDefinition(
                     BSTR::from(task_name),
                     task_definition.clone(),
-                    TASK_CREATION::TASK_CREATE_OR_UPDATE.0,
+                    options.flags(),
                     None,
                     None,
                     TASK_LOGON_TYPE::TASK_LOGON_INTERACTIVE_TOKEN,
@@ -57,9 +71,208 @@ impl TaskFolder {
                 )
                 .ok();
             match err {
-                Ok(_) => Ok(registered_task.unwrap()),
+                Ok(_) => Ok(registered_task.map(RegisteredTask::new)),
                 Err(error) => Err(error),
             }
         }
     }
+
+    /// Registers (creates) a task in the folder using task scheduler XML,
+    /// such as the XML written by [`Task::to_xml`](crate::Task::to_xml)
+    ///
+    /// Nothing is registered when the options are [`RegistrationMode::ValidateOnly`],
+    /// so no registered task is returned in that case
+    ///
+    /// https://docs.microsoft.com/en-us/windows/win32/api/taskschd/nf-taskschd-itaskfolder-registertask
+    pub(crate) fn register_task_xml(
+        &self,
+        path: &str,
+        xml: &str,
+        logon_type: TaskLogon,
+        options: RegistrationOptions,
+    ) -> Result<Option<RegisteredTask>, windows::Error> {
+        let user = Variant::empty();
+        let password = Variant::empty();
+        let sddl = Variant::empty();
+        let mut registered_task = None;
+        unsafe {
+            self.0
+                .RegisterTask(
+                    BSTR::from(path),
+                    BSTR::from(xml),
+                    options.flags(),
+                    &user.0,
+                    &password.0,
+                    TASK_LOGON_TYPE(logon_type as i32),
+                    &sddl.0,
+                    &mut registered_task,
+                )
+                .ok()?;
+        }
+        Ok(registered_task.map(RegisteredTask::new))
+    }
+
+    /// Gets the tasks that are registered directly in this folder.
+    /// Hidden tasks are only included if `include_hidden` is true.
+    ///
+    /// https://docs.microsoft.com/en-us/windows/win32/api/taskschd/nf-taskschd-itaskfolder-gettasks
+    pub(crate) fn tasks(
+        &self,
+        include_hidden: bool,
+    ) -> Result<Vec<RegisteredTask>, windows::Error> {
+        let flags = if include_hidden {
+            TASK_ENUM_FLAGS::TASK_ENUM_HIDDEN.0
+        } else {
+            0
+        };
+        let mut registered_tasks = None;
+        unsafe {
+            self.0.GetTasks(flags, &mut registered_tasks).ok()?;
+        }
+        collect_registered_tasks(registered_tasks.unwrap())
+    }
+
+    /// Gets the path of the folder, e.g. `\MyFolder`
+    ///
+    /// https://docs.microsoft.com/en-us/windows/win32/api/taskschd/nf-taskschd-itaskfolder-get_path
+    pub(crate) fn path(&self) -> Result<String, windows::Error> {
+        let mut path = BSTR::default();
+        unsafe {
+            self.0.get_Path(&mut path).ok()?;
+        }
+        Ok(path.to_string())
+    }
+
+    /// Gets the folders directly inside this folder
+    ///
+    /// https://docs.microsoft.com/en-us/windows/win32/api/taskschd/nf-taskschd-itaskfolder-getfolders
+    pub(crate) fn folders(&self) -> Result<Vec<TaskFolder>, windows::Error> {
+        let mut folders = None;
+        unsafe {
+            // the flags are reserved and must be 0
+            self.0.GetFolders(0, &mut folders).ok()?;
+        }
+        collect_folders(folders.unwrap())
+    }
+
+    /// Gets a task at the specified location in the folder
+    ///
+    /// The path can be relative to this folder or an absolute path
+    /// such as `\MyFolder\MyTask`
+    ///
+    /// https://docs.microsoft.com/en-us/windows/win32/api/taskschd/nf-taskschd-itaskfolder-gettask
+    pub(crate) fn get_task(&self, path: &str) -> Result<RegisteredTask, windows::Error> {
+        let mut registered_task = None;
+        unsafe {
+            let err = self.0.GetTask(BSTR::from(path), &mut registered_task).ok();
+            match err {
+                Ok(_) => Ok(RegisteredTask::new(registered_task.unwrap())),
+                Err(error) => Err(error),
+            }
+        }
+    }
+}
+
+fn collect_folders(collection: ITaskFolderCollection) -> Result<Vec<TaskFolder>, windows::Error> {
+    let mut count = 0;
+    unsafe {
+        collection.get_Count(&mut count).ok()?;
+    }
+    let count = usize::try_from(count).unwrap_or(0);
+
+    let mut folders = Vec::with_capacity(count);
+    // the collection is indexed starting from 1
+    for index in 1..=count {
+        let mut folder = None;
+        unsafe {
+            collection
+                .get_Item(&Variant::from_i32(index as i32).0, &mut folder)
+                .ok()?;
+        }
+        folders.push(TaskFolder::new(folder.unwrap()));
+    }
+    Ok(folders)
+}
+
+/// How a task definition is registered when a task with the same name may already exist
+///
+/// https://docs.microsoft.com/en-us/windows/win32/api/taskschd/ne-taskschd-task_creation
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum RegistrationMode {
+    /// Register the task as a new task. Fails if the task already exists.
+    Create,
+    /// Update an existing task. Fails if the task doesn't exist yet.
+    Update,
+    /// Register the task as a new task or update the existing task,
+    /// keeping its run history.
+    CreateOrUpdate,
+    /// Only check the syntax of the task definition, nothing is registered.
+    ValidateOnly,
+}
+
+impl RegistrationMode {
+    fn flags(&self) -> i32 {
+        match self {
+            RegistrationMode::Create => TASK_CREATION::TASK_CREATE.0,
+            RegistrationMode::Update => TASK_CREATION::TASK_UPDATE.0,
+            RegistrationMode::CreateOrUpdate => TASK_CREATION::TASK_CREATE_OR_UPDATE.0,
+            RegistrationMode::ValidateOnly => TASK_CREATION::TASK_VALIDATE_ONLY.0,
+        }
+    }
+}
+
+/// Options for registering a task definition
+///
+/// By default a task is created or updated in place, enabled, and registration
+/// triggers fire when it is registered.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct RegistrationOptions {
+    mode: RegistrationMode,
+    disable: bool,
+    ignore_registration_triggers: bool,
+}
+
+impl RegistrationOptions {
+    /// Registers the task with the mode, enabled and with registration triggers firing
+    pub fn new(mode: RegistrationMode) -> Self {
+        Self {
+            mode,
+            disable: false,
+            ignore_registration_triggers: false,
+        }
+    }
+
+    /// Registers the task as disabled
+    pub fn disabled(mut self) -> Self {
+        self.disable = true;
+        self
+    }
+
+    /// The registration triggers of the task won't fire when the task is registered
+    pub fn ignore_registration_triggers(mut self) -> Self {
+        self.ignore_registration_triggers = true;
+        self
+    }
+
+    /// The mode the task will be registered with
+    pub fn mode(&self) -> RegistrationMode {
+        self.mode
+    }
+
+    fn flags(&self) -> i32 {
+        let mut flags = self.mode.flags();
+        if self.disable {
+            flags |= TASK_CREATION::TASK_DISABLE.0;
+        }
+        if self.ignore_registration_triggers {
+            flags |= TASK_CREATION::TASK_IGNORE_REGISTRATION_TRIGGERS.0;
+        }
+        flags
+    }
+}
+
+impl Default for RegistrationOptions {
+    fn default() -> Self {
+        Self::new(RegistrationMode::CreateOrUpdate)
+    }
 }
@@ -0,0 +1,136 @@
+use std::fmt;
+
+/// Known result codes that Task Scheduler reports as the last result of a task,
+/// along with their symbolic name and description.
+///
+/// https://docs.microsoft.com/en-us/windows/win32/taskschd/task-scheduler-error-and-success-constants
+const RESULT_CODES: &[(u32, &str, &str)] = &[
+    (0x0000_0000, "S_OK", "The operation completed successfully."),
+    (0x0000_0001, "ERROR_INVALID_FUNCTION", "Incorrect function called or unknown function called."),
+    (0x0000_0002, "ERROR_FILE_NOT_FOUND", "File not found."),
+    (0x0000_000A, "ERROR_BAD_ENVIRONMENT", "The environment is incorrect."),
+    (0x0004_1300, "SCHED_S_TASK_READY", "The task is ready to run at its next scheduled time."),
+    (0x0004_1301, "SCHED_S_TASK_RUNNING", "The task is currently running."),
+    (0x0004_1302, "SCHED_S_TASK_DISABLED", "The task will not run at the scheduled times because it has been disabled."),
+    (0x0004_1303, "SCHED_S_TASK_HAS_NOT_RUN", "The task has not yet run."),
+    (0x0004_1304, "SCHED_S_TASK_NO_MORE_RUNS", "There are no more runs scheduled for this task."),
+    (0x0004_1305, "SCHED_S_TASK_NOT_SCHEDULED", "One or more of the properties that are needed to run this task on a schedule have not been set."),
+    (0x0004_1306, "SCHED_S_TASK_TERMINATED", "The last run of the task was terminated by the user."),
+    (0x0004_1307, "SCHED_S_TASK_NO_VALID_TRIGGERS", "Either the task has no triggers or the existing triggers are disabled or not set."),
+    (0x0004_1308, "SCHED_S_EVENT_TRIGGER", "Event triggers do not have set run times."),
+    (0x0004_131B, "SCHED_S_SOME_TRIGGERS_FAILED", "The task is registered, but not all specified triggers will start the task."),
+    (0x0004_131C, "SCHED_S_BATCH_LOGON_PROBLEM", "The task is registered, but may fail to start. Batch logon privilege needs to be enabled for the task principal."),
+    (0x0004_1325, "SCHED_S_TASK_QUEUED", "The Task Scheduler service has asked the task to run."),
+    (0x8004_1309, "SCHED_E_TRIGGER_NOT_FOUND", "A task's trigger is not found."),
+    (0x8004_130A, "SCHED_E_TASK_NOT_READY", "One or more of the properties required to run this task have not been set."),
+    (0x8004_130B, "SCHED_E_TASK_NOT_RUNNING", "There is no running instance of the task."),
+    (0x8004_130C, "SCHED_E_SERVICE_NOT_INSTALLED", "The Task Scheduler service is not installed on this computer."),
+    (0x8004_130D, "SCHED_E_CANNOT_OPEN_TASK", "The task object could not be opened."),
+    (0x8004_130E, "SCHED_E_INVALID_TASK", "The object is either an invalid task object or is not a task object."),
+    (0x8004_130F, "SCHED_E_ACCOUNT_INFORMATION_NOT_SET", "No account information could be found in the Task Scheduler security database for the task indicated."),
+    (0x8004_1310, "SCHED_E_ACCOUNT_NAME_NOT_FOUND", "Unable to establish existence of the account specified."),
+    (0x8004_1311, "SCHED_E_ACCOUNT_DBASE_CORRUPT", "Corruption was detected in the Task Scheduler security database; the database has been reset."),
+    (0x8004_1312, "SCHED_E_NO_SECURITY_SERVICES", "Task Scheduler security services are available only on Windows NT."),
+    (0x8004_1313, "SCHED_E_UNKNOWN_OBJECT_VERSION", "The task object version is either unsupported or invalid."),
+    (0x8004_1314, "SCHED_E_UNSUPPORTED_ACCOUNT_OPTION", "The task has been configured with an unsupported combination of account settings and run time options."),
+    (0x8004_1315, "SCHED_E_SERVICE_NOT_RUNNING", "The Task Scheduler Service is not running."),
+    (0x8004_1316, "SCHED_E_UNEXPECTEDNODE", "The task XML contains an unexpected node."),
+    (0x8004_1317, "SCHED_E_NAMESPACE", "The task XML contains an element or attribute from an unexpected namespace."),
+    (0x8004_1318, "SCHED_E_INVALIDVALUE", "The task XML contains a value which is incorrectly formatted or out of range."),
+    (0x8004_1319, "SCHED_E_MISSINGNODE", "The task XML is missing a required element or attribute."),
+    (0x8004_131A, "SCHED_E_MALFORMEDXML", "The task XML is malformed."),
+    (0x8004_131D, "SCHED_E_TOO_MANY_NODES", "The task XML contains too many nodes of the same type."),
+    (0x8004_131E, "SCHED_E_PAST_END_BOUNDARY", "The task cannot be started after the trigger end boundary."),
+    (0x8004_131F, "SCHED_E_ALREADY_RUNNING", "An instance of this task is already running."),
+    (0x8004_1320, "SCHED_E_USER_NOT_LOGGED_ON", "The task will not run because the user is not logged on."),
+    (0x8004_1321, "SCHED_E_INVALID_TASK_HASH", "The task image is corrupt or has been tampered with."),
+    (0x8004_1322, "SCHED_E_SERVICE_NOT_AVAILABLE", "The Task Scheduler service is not available."),
+    (0x8004_1323, "SCHED_E_SERVICE_TOO_BUSY", "The Task Scheduler service is too busy to handle your request. Please try again later."),
+    (0x8004_1324, "SCHED_E_TASK_ATTEMPTED", "The Task Scheduler service attempted to run the task, but the task did not run due to one of the constraints in the task definition."),
+    (0x8004_1326, "SCHED_E_TASK_DISABLED", "The task is disabled."),
+    (0x8004_1327, "SCHED_E_TASK_NOT_V1_COMPAT", "The task has properties that are not compatible with earlier versions of Windows."),
+    (0x8004_1328, "SCHED_E_START_ON_DEMAND", "The task settings do not allow the task to start on demand."),
+    (0x8007_0002, "E_FILE_NOT_FOUND", "The system cannot find the file specified."),
+    (0x8007_0005, "E_ACCESSDENIED", "Access is denied."),
+    (0x8007_010B, "E_DIRECTORY", "The directory name is invalid."),
+    (0x8007_04DD, "ERROR_NOT_LOGGED_ON", "The operation being requested was not performed because the user has not logged on to the network."),
+    (0x8007_10E0, "ERROR_REQUEST_REFUSED", "The operator or administrator has refused the request."),
+    (0xC000_013A, "STATUS_CONTROL_C_EXIT", "The application terminated as a result of a CTRL+C."),
+    (0xC06D_007E, "VCPP_EXCEPTION_MODULE_NOT_FOUND", "Unknown software exception."),
+];
+
+/// The result that was returned the last time a registered task ran.
+///
+/// This is either one of the Task Scheduler status codes or the exit code
+/// of the action that was run, which is why the raw value is always kept around.
+///
+/// https://docs.microsoft.com/en-us/windows/win32/api/taskschd/nf-taskschd-iregisteredtask-get_lasttaskresult
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct TaskResult(pub(crate) u32);
+
+impl TaskResult {
+    /// The raw result code as reported by Task Scheduler
+    pub fn code(&self) -> u32 {
+        self.0
+    }
+
+    /// Returns true if the task ran and completed successfully
+    pub fn is_success(&self) -> bool {
+        self.0 == 0
+    }
+
+    /// Returns true if the code has the severity bit set, which means it is an
+    /// error HRESULT rather than a status code or an exit code
+    pub fn is_error(&self) -> bool {
+        self.0 & 0x8000_0000 != 0
+    }
+
+    /// The symbolic name of the result code if it is a known code, e.g. `SCHED_S_TASK_RUNNING`
+    pub fn name(&self) -> Option<&'static str> {
+        self.lookup().map(|(_, name, _)| *name)
+    }
+
+    /// A human readable description of the result code if it is a known code
+    pub fn description(&self) -> Option<&'static str> {
+        self.lookup().map(|(_, _, description)| *description)
+    }
+
+    /// Reads a code written as a decimal number, signed or not, or as hex with `0x`
+    pub(crate) fn parse(code: &str) -> Option<TaskResult> {
+        let code = match code.strip_prefix("0x").or_else(|| code.strip_prefix("0X")) {
+            Some(hex) => u32::from_str_radix(hex, 16).ok()?,
+            None => code
+                .parse::<u32>()
+                .or_else(|_| code.parse::<i32>().map(|code| code as u32))
+                .ok()?,
+        };
+        Some(TaskResult(code))
+    }
+
+    fn lookup(&self) -> Option<&'static (u32, &'static str, &'static str)> {
+        RESULT_CODES.iter().find(|(code, _, _)| *code == self.0)
+    }
+}
+
+impl From<i32> for TaskResult {
+    fn from(code: i32) -> Self {
+        TaskResult(code as u32)
+    }
+}
+
+impl From<u32> for TaskResult {
+    fn from(code: u32) -> Self {
+        TaskResult(code)
+    }
+}
+
+impl fmt::Display for TaskResult {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.lookup() {
+            Some((code, name, description)) => {
+                write!(f, "{} (0x{:X}): {}", name, code, description)
+            }
+            None => write!(f, "0x{:X}", self.0),
+        }
+    }
+}
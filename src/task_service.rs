@@ -1,4 +1,4 @@
-use std::{convert::TryFrom, ptr};
+use std::{convert::TryFrom, fmt, ptr};
 
 use bindings::Windows::Win32::{
     Automation::BSTR,
@@ -13,65 +13,347 @@ use windows::IntoParam;
 use windows::{Guid, Interface};
 
 use crate::{
+    diff::Plan,
     error::{ComError, TaskError, TaskServiceError, WinError},
+    registered_task::RegisteredTask,
+    running_task::{collect_running_tasks, RunningTask},
+    schema_version::SchemaVersion,
+    task::{Task, TaskLogon},
+    task_folder::{RegistrationMode, RegistrationOptions, TaskFolder},
     to_win_str,
+    variant::Variant,
+    VARIANT_FALSE,
 };
 
-pub(crate) struct TaskService(pub(crate) ITaskService);
+/// HRESULT for ERROR_FILE_NOT_FOUND, returned when there is no task at a path
+const TASK_NOT_FOUND: u32 = 0x8007_0002;
+/// HRESULT for ERROR_PATH_NOT_FOUND, returned when a folder in the path doesn't exist
+const FOLDER_NOT_FOUND: u32 = 0x8007_0003;
+/// HRESULT for E_POINTER, used when a call succeeds without giving back its result
+const NO_RESULT: u32 = 0x8000_4003;
+
+fn no_result() -> windows::Error {
+    windows::Error::fast_error(windows::HRESULT(NO_RESULT))
+}
+
+/// Provides access to the Task Scheduler service for managing registered tasks.
+///
+/// The COM library has to be initialized on the calling thread with [`Com`](crate::Com)
+/// before this is created.
+///
+/// https://docs.microsoft.com/en-us/windows/win32/api/taskschd/nn-taskschd-itaskservice
+pub struct TaskService(pub(crate) ITaskService);
 // pub(crate) task_service: &'a mut ITaskService,
 
 impl TaskService {
-    pub(crate) fn new() -> Self {
+    /// Creates the task service, which fails when COM isn't initialized on the thread
+    pub fn new() -> Result<Self, TaskServiceError> {
         unsafe {
             // Create an instance of the task service
             // this isn't properly documented, however these are pointers to GUIDs for these particular
             // classes. winapi has uuidof method to get the guid
             // I figured it out because of this https://docs.microsoft.com/en-us/archive/msdn-magazine/2007/october/windows-with-c-task-scheduler-2-0
-            let mut task_service: ITaskService = CoCreateInstance(
+            let task_service: ITaskService = CoCreateInstance(
                 &TaskScheduler::TaskScheduler as *const Guid,
                 // TaskScheduler::ITaskScheduler,
                 None,
                 Com::CLSCTX::CLSCTX_INPROC_SERVER,
-            )
-            .unwrap();
-            Self(task_service)
+            )?;
+            Ok(Self(task_service))
         }
     }
 
-    pub(crate) fn connect(&self) -> Result<(), windows::Error> {
-        unsafe { self.0.Connect(None, None, None, None).ok() }
+    /// Connects to the Task Scheduler service on the local computer
+    ///
+    /// https://docs.microsoft.com/en-us/windows/win32/api/taskschd/nf-taskschd-itaskservice-connect
+    pub fn connect(&self) -> Result<(), TaskServiceError> {
+        self.connect_to("", None)
+    }
+
+    /// Connects to the Task Scheduler service on a computer by its name or IP address,
+    /// as the current user or with the credentials. An empty server is the local computer.
+    ///
+    /// https://docs.microsoft.com/en-us/windows/win32/api/taskschd/nf-taskschd-itaskservice-connect
+    pub fn connect_to(
+        &self,
+        server: &str,
+        credentials: Option<&Credentials>,
+    ) -> Result<(), TaskServiceError> {
+        let server = if server.is_empty() {
+            Variant::empty()
+        } else {
+            Variant::from_str(server)
+        };
+        let (user, domain, password) = match credentials {
+            Some(credentials) => (
+                Variant::from_str(&credentials.user),
+                credentials
+                    .domain
+                    .as_deref()
+                    .map_or_else(Variant::empty, Variant::from_str),
+                Variant::from_str(&credentials.password),
+            ),
+            None => (Variant::empty(), Variant::empty(), Variant::empty()),
+        };
+        unsafe {
+            self.0
+                .Connect(&server.0, &user.0, &domain.0, &password.0)
+                .ok()?;
+        }
+        Ok(())
+    }
+
+    /// Whether the service is connected to the Task Scheduler service
+    ///
+    /// https://docs.microsoft.com/en-us/windows/win32/api/taskschd/nf-taskschd-itaskservice-get_connected
+    pub fn connected(&self) -> Result<bool, windows::Error> {
+        let mut connected = VARIANT_FALSE;
+        unsafe {
+            self.0.get_Connected(&mut connected).ok()?;
+        }
+        Ok(connected != VARIANT_FALSE)
+    }
+
+    /// The name of the computer that the service is connected to
+    ///
+    /// https://docs.microsoft.com/en-us/windows/win32/api/taskschd/nf-taskschd-itaskservice-get_targetserver
+    pub fn target_server(&self) -> Result<String, windows::Error> {
+        let mut server = BSTR::default();
+        unsafe {
+            self.0.get_TargetServer(&mut server).ok()?;
+        }
+        Ok(server.to_string())
+    }
+
+    /// The name of the user that is connected to the Task Scheduler service
+    ///
+    /// https://docs.microsoft.com/en-us/windows/win32/api/taskschd/nf-taskschd-itaskservice-get_connecteduser
+    pub fn connected_user(&self) -> Result<String, windows::Error> {
+        let mut user = BSTR::default();
+        unsafe {
+            self.0.get_ConnectedUser(&mut user).ok()?;
+        }
+        Ok(user.to_string())
+    }
+
+    /// The domain of the user that is connected to the Task Scheduler service
+    ///
+    /// https://docs.microsoft.com/en-us/windows/win32/api/taskschd/nf-taskschd-itaskservice-get_connecteddomain
+    pub fn connected_domain(&self) -> Result<String, windows::Error> {
+        let mut domain = BSTR::default();
+        unsafe {
+            self.0.get_ConnectedDomain(&mut domain).ok()?;
+        }
+        Ok(domain.to_string())
+    }
+
+    /// The highest version of Task Scheduler that the computer supports as
+    /// `(major, minor)`, like `(1, 6)` on Windows 10
+    ///
+    /// https://docs.microsoft.com/en-us/windows/win32/api/taskschd/nf-taskschd-itaskservice-get_highestversion
+    pub fn highest_version(&self) -> Result<(u32, u32), windows::Error> {
+        let mut version = 0;
+        unsafe {
+            self.0.get_HighestVersion(&mut version).ok()?;
+        }
+        Ok((version >> 16, version & 0xFFFF))
+    }
+
+    /// The newest schema version that the connected Task Scheduler understands
+    pub fn schema_version(&self) -> Result<SchemaVersion, windows::Error> {
+        let (major, minor) = self.highest_version()?;
+        Ok(SchemaVersion::from_highest_version(major, minor))
     }
 
     pub(crate) fn get_folder(&self) -> Result<ITaskFolder, windows::Error> {
+        self.get_folder_at("\\")
+    }
+
+    /// Gets a folder of registered tasks by its path, e.g. `\MyFolder`
+    ///
+    /// https://docs.microsoft.com/en-us/windows/win32/api/taskschd/nf-taskschd-itaskservice-getfolder
+    pub(crate) fn get_folder_at(&self, path: &str) -> Result<ITaskFolder, windows::Error> {
         let mut task_folder = None;
         unsafe {
-            let err = self
-                .0
-                .GetFolder(
-                    BSTR::try_from("\\".to_string()).unwrap(),
-                    // &None as *mut Option<ITaskFolder>,
-                    &mut task_folder,
-                )
-                .ok();
+            let err = self.0.GetFolder(BSTR::from(path), &mut task_folder).ok();
             match err {
-                Ok(_) => Ok(task_folder.unwrap()),
+                Ok(_) => task_folder.ok_or_else(no_result),
                 Err(error) => Err(error),
             }
         }
-        // let task_folder = task_folder.unwrap();
     }
+
+    /// Gets a registered task by its path, e.g. `\MyFolder\MyTask`
+    ///
+    /// https://docs.microsoft.com/en-us/windows/win32/api/taskschd/nf-taskschd-itaskfolder-gettask
+    pub fn get_task(&self, path: &str) -> Result<RegisteredTask, windows::Error> {
+        let task_folder = TaskFolder::new(self.get_folder()?);
+        task_folder.get_task(path)
+    }
+
+    /// Gets the tasks that are registered directly in the folder, e.g. `\MyFolder`.
+    /// Hidden tasks are only included if `include_hidden` is true.
+    ///
+    /// https://docs.microsoft.com/en-us/windows/win32/api/taskschd/nf-taskschd-itaskfolder-gettasks
+    pub fn tasks(
+        &self,
+        folder: &str,
+        include_hidden: bool,
+    ) -> Result<Vec<RegisteredTask>, windows::Error> {
+        TaskFolder::new(self.get_folder_at(folder)?).tasks(include_hidden)
+    }
+
+    /// Gets the paths of the folders directly inside the folder, e.g. `\MyFolder\Nested`
+    /// inside `\MyFolder`.
+    ///
+    /// https://docs.microsoft.com/en-us/windows/win32/api/taskschd/nf-taskschd-itaskfolder-getfolders
+    pub fn folders(&self, folder: &str) -> Result<Vec<String>, windows::Error> {
+        TaskFolder::new(self.get_folder_at(folder)?)
+            .folders()?
+            .iter()
+            .map(TaskFolder::path)
+            .collect()
+    }
+
+    /// Registers the task at the path, e.g. `\MyFolder\MyTask`.
+    /// Folders in the path that don't exist yet are created.
+    ///
+    /// A task with settings that the connected Task Scheduler doesn't understand
+    /// fails with a [`SchemaError`](crate::SchemaError) before anything is sent,
+    /// see [`Task::downgrade`] to leave them out instead.
+    ///
+    /// Nothing is registered when the options are [`RegistrationMode::ValidateOnly`],
+    /// so no registered task is returned in that case
+    ///
+    /// https://docs.microsoft.com/en-us/windows/win32/api/taskschd/nf-taskschd-itaskfolder-registertask
+    pub fn register(
+        &self,
+        path: &str,
+        task: &Task,
+        options: RegistrationOptions,
+    ) -> Result<Option<RegisteredTask>, TaskError> {
+        task.check_schema_version(self.schema_version()?)?;
+        let task_folder = TaskFolder::new(self.get_folder()?);
+        let registered = task_folder.register_task_xml(
+            path,
+            &task.to_xml(),
+            task.principal()
+                .implied_logon_type()
+                .unwrap_or(TaskLogon::InteractiveToken),
+            options,
+        )?;
+        Ok(registered)
+    }
+
+    /// Deletes the task at the path, e.g. `\MyFolder\MyTask`
+    ///
+    /// https://docs.microsoft.com/en-us/windows/win32/api/taskschd/nf-taskschd-itaskfolder-deletetask
+    pub fn delete_task(&self, path: &str) -> Result<(), windows::Error> {
+        let (folder, name) = match path.rfind('\\') {
+            Some(0) | None => ("\\", path.trim_start_matches('\\')),
+            Some(index) => (&path[..index], &path[index + 1..]),
+        };
+        TaskFolder::new(self.get_folder_at(folder)?).delete_task(name)
+    }
+
+    /// Plans registering the task at the path without changing anything.
+    ///
+    /// Task Scheduler validates the task first, then it is compared against the
+    /// task that is currently registered at the path, if there is one.
+    ///
+    /// https://docs.microsoft.com/en-us/windows/win32/api/taskschd/ne-taskschd-task_creation
+    pub fn plan(&self, path: &str, task: &Task) -> Result<Plan, TaskError> {
+        self.register(
+            path,
+            task,
+            RegistrationOptions::new(RegistrationMode::ValidateOnly),
+        )?;
+
+        let task_folder = TaskFolder::new(self.get_folder()?);
+        let registered = match task_folder.get_task(path) {
+            Ok(registered) => registered,
+            Err(error) if [TASK_NOT_FOUND, FOLDER_NOT_FOUND].contains(&error.code().0) => {
+                return Ok(Plan::Create)
+            }
+            Err(error) => return Err(TaskError::from(error)),
+        };
+        let current = Task::from_xml(&registered.xml()?)?;
+        Ok(Plan::new(task, Some(&current)))
+    }
+
+    /// Gets every running instance of every registered task.
+    /// Hidden tasks are only included if `include_hidden` is true.
+    ///
+    /// https://docs.microsoft.com/en-us/windows/win32/api/taskschd/nf-taskschd-itaskservice-getrunningtasks
+    pub fn running_tasks(&self, include_hidden: bool) -> Result<Vec<RunningTask>, windows::Error> {
+        let flags = if include_hidden {
+            TaskScheduler::TASK_ENUM_FLAGS::TASK_ENUM_HIDDEN.0
+        } else {
+            0
+        };
+        let mut running_tasks = None;
+        unsafe {
+            self.0.GetRunningTasks(flags, &mut running_tasks).ok()?;
+        }
+        collect_running_tasks(running_tasks.ok_or_else(no_result)?)
+    }
+
     pub(crate) fn new_task(&self) -> Result<ITaskDefinition, windows::Error> {
         let mut task_definition = None;
         unsafe {
             let res = self.0.NewTask(0, &mut task_definition).ok();
             match res {
-                Ok(_) => Ok(task_definition.unwrap()),
+                Ok(_) => task_definition.ok_or_else(no_result),
                 Err(error) => Err(error),
             }
         }
     }
 }
 
+/// The user that connects to the Task Scheduler service on another computer.
+/// The password isn't shown when this is debug printed.
+///
+/// https://docs.microsoft.com/en-us/windows/win32/api/taskschd/nf-taskschd-itaskservice-connect
+#[derive(Clone)]
+pub struct Credentials {
+    user: String,
+    domain: Option<String>,
+    password: String,
+}
+
+impl Credentials {
+    pub fn new(user: String, password: String) -> Self {
+        Self {
+            user,
+            domain: None,
+            password,
+        }
+    }
+
+    /// The domain of the user, otherwise it is the domain of the computer
+    pub fn with_domain(mut self, domain: String) -> Self {
+        self.domain = Some(domain);
+        self
+    }
+
+    pub fn user(&self) -> &str {
+        &self.user
+    }
+
+    pub fn domain(&self) -> Option<&str> {
+        self.domain.as_deref()
+    }
+}
+
+impl fmt::Debug for Credentials {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Credentials")
+            .field("user", &self.user)
+            .field("domain", &self.domain)
+            .finish_non_exhaustive()
+    }
+}
+
 // impl Drop for TaskService {
 //     fn drop(&mut self) {
 //         unsafe {
@@ -0,0 +1,42 @@
+use std::fmt;
+
+/// The operational state of a registered task or of one of its running instances.
+///
+/// https://docs.microsoft.com/en-us/windows/win32/api/taskschd/ne-taskschd-task_state
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum TaskState {
+    /// The state of the task is unknown.
+    Unknown,
+    /// The task is registered but is disabled and no instances of the task are queued or running. The task cannot be run until it is enabled.
+    Disabled,
+    /// Instances of the task are queued.
+    Queued,
+    /// The task is ready to be executed, but no instances are queued or running.
+    Ready,
+    /// One or more instances of the task is running.
+    Running,
+}
+
+impl From<i32> for TaskState {
+    fn from(state: i32) -> Self {
+        match state {
+            1 => TaskState::Disabled,
+            2 => TaskState::Queued,
+            3 => TaskState::Ready,
+            4 => TaskState::Running,
+            _ => TaskState::Unknown,
+        }
+    }
+}
+
+impl fmt::Display for TaskState {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            TaskState::Unknown => write!(f, "Unknown"),
+            TaskState::Disabled => write!(f, "Disabled"),
+            TaskState::Queued => write!(f, "Queued"),
+            TaskState::Ready => write!(f, "Ready"),
+            TaskState::Running => write!(f, "Running"),
+        }
+    }
+}
@@ -0,0 +1,1001 @@
+use std::{path::PathBuf, str::FromStr};
+
+use chrono::Duration;
+use log::warn;
+use roxmltree::{Document, Node};
+
+use crate::{
+    boundary::Boundary,
+    calendar::{month_from_name, month_name, weekday_from_name, weekday_name},
+    duration::{parse_xml_duration, to_xml_duration},
+    error::XmlError,
+    task::{
+        IdleConditions, MultipleInstancesPolicy, Privilege, RestartOnFailure, RunLevel, Settings,
+        Task, TaskLogon, TaskPrincipal, TaskRegistration,
+    },
+    Action, Actions, BootTrigger, ComHandlerAction, DailyTrigger, DaysOfMonth, DaysOfWeek,
+    EventTrigger, ExecAction, IdleTrigger, LogonTrigger, MonthlyDowTrigger, MonthlyTrigger, Months,
+    RegistrationTrigger, Repetition, SessionStateChange, SessionStateChangeTrigger,
+    SpecificTimeTrigger, TaskTriggers, TaskTriggersBuilder, TriggerCommon, WeeklyTrigger,
+    WeeksOfMonth,
+};
+
+/// The namespace of the task scheduler schema
+const TASK_NAMESPACE: &str = "http://schemas.microsoft.com/windows/2004/02/mit/task";
+
+impl Task {
+    /// Writes the task as task scheduler XML, which can be registered with
+    /// Task Scheduler or imported with `schtasks /create /xml`
+    ///
+    /// Calendar triggers without a start time start now, since Task Scheduler
+    /// requires a start boundary on them. The version of the XML is the oldest
+    /// [`SchemaVersion`](crate::SchemaVersion) that has every setting of the task.
+    ///
+    /// https://docs.microsoft.com/en-us/windows/win32/taskschd/task-scheduler-schema
+    pub fn to_xml(&self) -> String {
+        let mut xml = XmlWriter::new();
+        xml.open(
+            "Task",
+            &[
+                ("version", self.schema_version().as_str()),
+                ("xmlns", TASK_NAMESPACE),
+            ],
+        );
+        write_registration(&mut xml, &self.registration);
+        write_triggers(&mut xml, &self.triggers);
+        write_principal(&mut xml, &self.principal);
+        write_settings(&mut xml, &self.settings);
+        write_actions(&mut xml, &self.actions, &self.principal.id);
+        xml.close("Task");
+        xml.finish()
+    }
+
+    /// Reads a task from task scheduler XML, such as the XML of a registered task
+    /// or a task exported from the Task Scheduler UI
+    ///
+    /// Settings that aren't in the XML get the same defaults Task Scheduler uses.
+    pub fn from_xml(xml: &str) -> Result<Task, XmlError> {
+        let document =
+            Document::parse(xml).map_err(|error| XmlError::Malformed(error.to_string()))?;
+        let root = document.root_element();
+        if root.tag_name().name() != "Task" {
+            return Err(XmlError::MissingElement("Task".to_string()));
+        }
+
+        let registration = match child(root, "RegistrationInfo") {
+            Some(node) => read_registration(node),
+            None => TaskRegistration::new(),
+        };
+        let triggers = match child(root, "Triggers") {
+            Some(node) => read_triggers(node)?,
+            None => TaskTriggers::from_builder(TaskTriggersBuilder::new()),
+        };
+        let principal = match child(root, "Principals").and_then(|node| child(node, "Principal")) {
+            Some(node) => read_principal(node)?,
+            None => TaskPrincipal::new(),
+        };
+        let settings = match child(root, "Settings") {
+            Some(node) => read_settings(node)?,
+            None => Settings::default(),
+        };
+        let actions = read_actions(
+            child(root, "Actions")
+                .ok_or_else(|| XmlError::MissingElement("Actions".to_string()))?,
+        )?;
+
+        Ok(Task {
+            registration,
+            principal,
+            settings,
+            triggers,
+            actions,
+        })
+    }
+}
+
+/// Builds an indented XML document one element at a time
+struct XmlWriter {
+    xml: String,
+    depth: usize,
+}
+
+impl XmlWriter {
+    fn new() -> Self {
+        Self {
+            xml: String::new(),
+            depth: 0,
+        }
+    }
+
+    fn indent(&mut self) {
+        for _ in 0..self.depth {
+            self.xml.push_str("  ");
+        }
+    }
+
+    fn open(&mut self, name: &str, attributes: &[(&str, &str)]) {
+        self.indent();
+        self.xml.push('<');
+        self.xml.push_str(name);
+        for (attribute, value) in attributes {
+            self.xml
+                .push_str(&format!(" {}=\"{}\"", attribute, escape(value)));
+        }
+        self.xml.push_str(">\n");
+        self.depth += 1;
+    }
+
+    fn close(&mut self, name: &str) {
+        self.depth -= 1;
+        self.indent();
+        self.xml.push_str(&format!("</{}>\n", name));
+    }
+
+    fn empty(&mut self, name: &str) {
+        self.indent();
+        self.xml.push_str(&format!("<{} />\n", name));
+    }
+
+    fn element(&mut self, name: &str, text: &str) {
+        self.indent();
+        self.xml
+            .push_str(&format!("<{}>{}</{}>\n", name, escape(text), name));
+    }
+
+    fn element_with(&mut self, name: &str, attributes: &[(&str, &str)], text: &str) {
+        self.indent();
+        self.xml.push('<');
+        self.xml.push_str(name);
+        for (attribute, value) in attributes {
+            self.xml
+                .push_str(&format!(" {}=\"{}\"", attribute, escape(value)));
+        }
+        self.xml
+            .push_str(&format!(">{}</{}>\n", escape(text), name));
+    }
+
+    fn optional(&mut self, name: &str, text: Option<&str>) {
+        if let Some(text) = text {
+            self.element(name, text);
+        }
+    }
+
+    fn boolean(&mut self, name: &str, value: bool) {
+        self.element(name, if value { "true" } else { "false" });
+    }
+
+    fn duration(&mut self, name: &str, duration: Option<Duration>) {
+        if let Some(duration) = duration {
+            self.element(name, &to_xml_duration(&duration));
+        }
+    }
+
+    fn boundary(&mut self, name: &str, boundary: Option<Boundary>) {
+        if let Some(boundary) = boundary {
+            self.element(name, &boundary.to_xml());
+        }
+    }
+
+    fn finish(self) -> String {
+        self.xml
+    }
+}
+
+/// Escapes the characters that can't appear as is in XML text or attributes
+pub(crate) fn escape(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for character in text.chars() {
+        match character {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            '\'' => escaped.push_str("&apos;"),
+            _ => escaped.push(character),
+        }
+    }
+    escaped
+}
+
+fn write_registration(xml: &mut XmlWriter, registration: &TaskRegistration) {
+    xml.open("RegistrationInfo", &[]);
+    xml.optional("URI", registration.uri.as_deref());
+    xml.optional("Source", registration.source.as_deref());
+    xml.optional("Date", registration.date.as_deref());
+    xml.optional("Author", registration.author.as_deref());
+    xml.optional("Version", registration.version.as_deref());
+    xml.optional("Description", registration.description.as_deref());
+    xml.optional("Documentation", registration.documentation.as_deref());
+    xml.close("RegistrationInfo");
+}
+
+fn write_triggers(xml: &mut XmlWriter, triggers: &TaskTriggers) {
+    if triggers.is_empty() {
+        xml.empty("Triggers");
+        return;
+    }
+
+    xml.open("Triggers", &[]);
+    for trigger in triggers.boot() {
+        open_trigger(xml, "BootTrigger", &trigger.common, None, false);
+        xml.duration("Delay", trigger.delay);
+        xml.close("BootTrigger");
+    }
+    for trigger in triggers.registration() {
+        open_trigger(xml, "RegistrationTrigger", &trigger.common, None, false);
+        xml.duration("Delay", trigger.delay);
+        xml.close("RegistrationTrigger");
+    }
+    for trigger in triggers.idle() {
+        open_trigger(
+            xml,
+            "IdleTrigger",
+            &trigger.common,
+            trigger.repetition.as_ref(),
+            false,
+        );
+        xml.close("IdleTrigger");
+    }
+    for trigger in triggers.logon() {
+        open_trigger(xml, "LogonTrigger", &trigger.common, None, false);
+        xml.optional("UserId", trigger.user_id.as_deref());
+        xml.duration("Delay", trigger.delay);
+        xml.close("LogonTrigger");
+    }
+    for trigger in triggers.session_state_change() {
+        open_trigger(
+            xml,
+            "SessionStateChangeTrigger",
+            &trigger.common,
+            None,
+            false,
+        );
+        xml.element(
+            "StateChange",
+            session_state_change_name(trigger.state_change),
+        );
+        xml.optional("UserId", trigger.user_id.as_deref());
+        xml.duration("Delay", trigger.delay);
+        xml.close("SessionStateChangeTrigger");
+    }
+    for trigger in triggers.event() {
+        open_trigger(
+            xml,
+            "EventTrigger",
+            &trigger.common,
+            trigger.repetition.as_ref(),
+            false,
+        );
+        xml.element("Subscription", &trigger.subscription);
+        xml.duration("Delay", trigger.delay);
+        if !trigger.value_queries.is_empty() {
+            xml.open("ValueQueries", &[]);
+            for (name, query) in &trigger.value_queries {
+                xml.element_with("Value", &[("name", name)], query);
+            }
+            xml.close("ValueQueries");
+        }
+        xml.close("EventTrigger");
+    }
+    for trigger in triggers.specific_times() {
+        open_trigger(
+            xml,
+            "TimeTrigger",
+            &trigger.common,
+            trigger.repetition.as_ref(),
+            true,
+        );
+        xml.duration("RandomDelay", trigger.common.random_delay);
+        xml.close("TimeTrigger");
+    }
+    for trigger in triggers.daily() {
+        open_calendar_trigger(xml, &trigger.common, trigger.repetition.as_ref());
+        xml.open("ScheduleByDay", &[]);
+        xml.element("DaysInterval", &trigger.interval.unwrap_or(1).to_string());
+        xml.close("ScheduleByDay");
+        xml.close("CalendarTrigger");
+    }
+    for trigger in triggers.weekly() {
+        open_calendar_trigger(xml, &trigger.common, trigger.repetition.as_ref());
+        xml.open("ScheduleByWeek", &[]);
+        xml.element(
+            "WeeksInterval",
+            &trigger.weeks_interval.unwrap_or(1).to_string(),
+        );
+        write_days_of_week(xml, trigger.days_of_week);
+        xml.close("ScheduleByWeek");
+        xml.close("CalendarTrigger");
+    }
+    for trigger in triggers.monthly() {
+        open_calendar_trigger(xml, &trigger.common, trigger.repetition.as_ref());
+        xml.open("ScheduleByMonth", &[]);
+        xml.open("DaysOfMonth", &[]);
+        for day in trigger.days_of_month.iter() {
+            xml.element("Day", &day.to_string());
+        }
+        if trigger.run_on_last_day_of_month {
+            xml.element("Day", "Last");
+        }
+        xml.close("DaysOfMonth");
+        write_months(xml, trigger.months);
+        xml.close("ScheduleByMonth");
+        xml.close("CalendarTrigger");
+    }
+    for trigger in triggers.monthly_dow() {
+        open_calendar_trigger(xml, &trigger.common, trigger.repetition.as_ref());
+        xml.open("ScheduleByMonthDayOfWeek", &[]);
+        xml.open("Weeks", &[]);
+        for week in trigger.weeks_of_month.iter() {
+            xml.element("Week", &week.to_string());
+        }
+        if trigger.run_on_last_week_of_month {
+            xml.element("Week", "Last");
+        }
+        xml.close("Weeks");
+        write_days_of_week(xml, trigger.days_of_week);
+        write_months(xml, trigger.months);
+        xml.close("ScheduleByMonthDayOfWeek");
+        xml.close("CalendarTrigger");
+    }
+    xml.close("Triggers");
+}
+
+/// Opens a trigger element and writes the elements every trigger has, in schema order.
+/// The id attribute is left out when the trigger has no id, and a trigger that needs
+/// a start boundary starts now when it doesn't have one.
+fn open_trigger(
+    xml: &mut XmlWriter,
+    name: &str,
+    common: &TriggerCommon,
+    repetition: Option<&Repetition>,
+    needs_start: bool,
+) {
+    match common.id.as_deref().filter(|id| !id.is_empty()) {
+        Some(id) => xml.open(name, &[("id", id)]),
+        None => xml.open(name, &[]),
+    }
+    write_repetition(xml, repetition);
+    let start = match common.start {
+        None if needs_start => Some(Boundary::now()),
+        start => start,
+    };
+    xml.boundary("StartBoundary", start);
+    xml.boundary("EndBoundary", common.end);
+    xml.duration("ExecutionTimeLimit", common.execution_time_limit);
+    if !common.enabled {
+        xml.boolean("Enabled", false);
+    }
+    // only the triggers that need a start have a random delay
+    if !needs_start && common.random_delay.is_some() {
+        warn!("A {} can't have a random delay, it is left out", name);
+    }
+}
+
+fn open_calendar_trigger(
+    xml: &mut XmlWriter,
+    common: &TriggerCommon,
+    repetition: Option<&Repetition>,
+) {
+    open_trigger(xml, "CalendarTrigger", common, repetition, true);
+    xml.duration("RandomDelay", common.random_delay);
+}
+
+fn write_repetition(xml: &mut XmlWriter, repetition: Option<&Repetition>) {
+    if let Some(repetition) = repetition {
+        xml.open("Repetition", &[]);
+        xml.duration("Interval", Some(repetition.interval));
+        xml.duration("Duration", repetition.duration);
+        xml.boolean("StopAtDurationEnd", repetition.stop_at_duration_end);
+        xml.close("Repetition");
+    }
+}
+
+fn write_days_of_week(xml: &mut XmlWriter, days_of_week: DaysOfWeek) {
+    xml.open("DaysOfWeek", &[]);
+    for day in days_of_week.iter() {
+        xml.empty(weekday_name(day));
+    }
+    xml.close("DaysOfWeek");
+}
+
+fn write_months(xml: &mut XmlWriter, months: Months) {
+    xml.open("Months", &[]);
+    for month in months.iter() {
+        xml.empty(month_name(month));
+    }
+    xml.close("Months");
+}
+
+fn write_principal(xml: &mut XmlWriter, principal: &TaskPrincipal) {
+    xml.open("Principals", &[]);
+    xml.open("Principal", &[("id", &principal.id)]);
+    xml.optional("UserId", principal.user_id.as_deref());
+    // service accounts and groups are recognized by their user or group id
+    match principal.logon_type {
+        Some(TaskLogon::Password) => xml.element("LogonType", "Password"),
+        Some(TaskLogon::S4U) => xml.element("LogonType", "S4U"),
+        Some(TaskLogon::InteractiveToken) => xml.element("LogonType", "InteractiveToken"),
+        Some(TaskLogon::InteractiveTokenOrPassword) => {
+            xml.element("LogonType", "InteractiveTokenOrPassword")
+        }
+        _ => {}
+    }
+    xml.optional("GroupId", principal.group_id.as_deref());
+    xml.element(
+        "RunLevel",
+        match principal.run_level {
+            RunLevel::LeastPrivilege => "LeastPrivilege",
+            RunLevel::HighestAvailable => "HighestAvailable",
+        },
+    );
+    if !principal.required_privileges.is_empty() {
+        xml.open("RequiredPrivileges", &[]);
+        for privilege in &principal.required_privileges {
+            xml.element("Privilege", privilege.name());
+        }
+        xml.close("RequiredPrivileges");
+    }
+    xml.close("Principal");
+    xml.close("Principals");
+}
+
+fn write_settings(xml: &mut XmlWriter, settings: &Settings) {
+    xml.open("Settings", &[]);
+    xml.boolean("AllowStartOnDemand", settings.allow_demand_start);
+    if let Some(restart) = &settings.restart_on_failure {
+        xml.open("RestartOnFailure", &[]);
+        xml.duration("Interval", Some(restart.interval));
+        xml.element("Count", &restart.count.to_string());
+        xml.close("RestartOnFailure");
+    }
+    xml.element(
+        "MultipleInstancesPolicy",
+        match settings.multiple_instances {
+            MultipleInstancesPolicy::Parallel => "Parallel",
+            MultipleInstancesPolicy::Queue => "Queue",
+            MultipleInstancesPolicy::IgnoreNew => "IgnoreNew",
+            MultipleInstancesPolicy::StopExisting => "StopExisting",
+        },
+    );
+    xml.boolean(
+        "DisallowStartIfOnBatteries",
+        settings.disallow_start_if_on_batteries,
+    );
+    xml.boolean(
+        "StopIfGoingOnBatteries",
+        settings.stop_if_going_on_batteries,
+    );
+    xml.boolean("AllowHardTerminate", settings.allow_hard_terminate);
+    xml.boolean("StartWhenAvailable", settings.start_when_available);
+    xml.boolean(
+        "RunOnlyIfNetworkAvailable",
+        settings.run_only_if_network_available,
+    );
+    xml.boolean("WakeToRun", settings.wake_to_run);
+    xml.boolean("Enabled", settings.enabled);
+    xml.boolean("Hidden", settings.hidden);
+    xml.duration("DeleteExpiredTaskAfter", settings.delete_expired_task_after);
+
+    let idle = &settings.idle_conditions;
+    xml.open("IdleSettings", &[]);
+    xml.duration("Duration", Some(idle.duration));
+    xml.duration("WaitTimeout", Some(idle.wait_timeout));
+    xml.boolean("StopOnIdleEnd", idle.stop_on_idle_end);
+    xml.boolean("RestartOnIdle", idle.restart_on_idle);
+    xml.close("IdleSettings");
+
+    // a limit of zero lets the task run indefinitely
+    xml.duration(
+        "ExecutionTimeLimit",
+        Some(settings.execution_time_limit.unwrap_or_else(Duration::zero)),
+    );
+    xml.element("Priority", &settings.priority.to_string());
+    xml.boolean("RunOnlyIfIdle", settings.run_only_if_idle);
+    // only written when set, since older schema versions don't have them
+    if settings.use_unified_scheduling_engine {
+        xml.boolean("UseUnifiedSchedulingEngine", true);
+    }
+    if settings.disallow_start_on_remote_app_session {
+        xml.boolean("DisallowStartOnRemoteAppSession", true);
+    }
+    if settings.volatile {
+        xml.boolean("Volatile", true);
+    }
+    xml.close("Settings");
+}
+
+fn write_actions(xml: &mut XmlWriter, actions: &Actions, context: &str) {
+    xml.open("Actions", &[("Context", context)]);
+    for action in actions.iter() {
+        match action {
+            Action::Exec(exec) => {
+                match &exec.id {
+                    Some(id) => xml.open("Exec", &[("id", id)]),
+                    None => xml.open("Exec", &[]),
+                }
+                xml.element("Command", &exec.path.to_string_lossy());
+                xml.optional("Arguments", exec.arguments.as_deref());
+                if let Some(working_directory) = &exec.working_directory {
+                    xml.element("WorkingDirectory", &working_directory.to_string_lossy());
+                }
+                xml.close("Exec");
+            }
+            Action::ComHandler(handler) => {
+                match &handler.id {
+                    Some(id) => xml.open("ComHandler", &[("id", id)]),
+                    None => xml.open("ComHandler", &[]),
+                }
+                xml.element("ClassId", &handler.class_id);
+                xml.optional("Data", handler.data.as_deref());
+                xml.close("ComHandler");
+            }
+        }
+    }
+    xml.close("Actions");
+}
+
+fn session_state_change_name(state_change: SessionStateChange) -> &'static str {
+    match state_change {
+        SessionStateChange::ConsoleConnect => "ConsoleConnect",
+        SessionStateChange::ConsoleDisconnect => "ConsoleDisconnect",
+        SessionStateChange::RemoteConnect => "RemoteConnect",
+        SessionStateChange::RemoteDisconnect => "RemoteDisconnect",
+        SessionStateChange::SessionLock => "SessionLock",
+        SessionStateChange::SessionUnlock => "SessionUnlock",
+    }
+}
+
+/// The first child element with the name, ignoring the namespace
+fn child<'a, 'input>(node: Node<'a, 'input>, name: &str) -> Option<Node<'a, 'input>> {
+    node.children()
+        .find(|child| child.is_element() && child.tag_name().name() == name)
+}
+
+/// Every child element, ignoring text and comments
+fn elements<'a, 'input>(node: Node<'a, 'input>) -> impl Iterator<Item = Node<'a, 'input>> {
+    node.children().filter(|child| child.is_element())
+}
+
+/// The trimmed text of the first child element with the name
+fn text(node: Node, name: &str) -> Option<String> {
+    child(node, name).map(|child| child.text().unwrap_or("").trim().to_string())
+}
+
+fn invalid(element: &str, value: &str) -> XmlError {
+    XmlError::InvalidValue {
+        element: element.to_string(),
+        value: value.to_string(),
+    }
+}
+
+fn parse_bool(node: Node, name: &str) -> Result<Option<bool>, XmlError> {
+    match text(node, name) {
+        Some(value) => match value.as_str() {
+            "true" | "1" => Ok(Some(true)),
+            "false" | "0" => Ok(Some(false)),
+            _ => Err(invalid(name, &value)),
+        },
+        None => Ok(None),
+    }
+}
+
+fn parse_number<T: FromStr>(node: Node, name: &str) -> Result<Option<T>, XmlError> {
+    match text(node, name) {
+        Some(value) => value.parse().map(Some).map_err(|_| invalid(name, &value)),
+        None => Ok(None),
+    }
+}
+
+fn parse_duration(node: Node, name: &str) -> Result<Option<Duration>, XmlError> {
+    match text(node, name) {
+        Some(value) => parse_xml_duration(&value)
+            .map(Some)
+            .ok_or_else(|| invalid(name, &value)),
+        None => Ok(None),
+    }
+}
+
+fn parse_boundary(node: Node, name: &str) -> Result<Option<Boundary>, XmlError> {
+    match text(node, name) {
+        Some(value) => Boundary::parse(&value)
+            .map(Some)
+            .ok_or_else(|| invalid(name, &value)),
+        None => Ok(None),
+    }
+}
+
+fn read_registration(node: Node) -> TaskRegistration {
+    TaskRegistration {
+        author: text(node, "Author"),
+        description: text(node, "Description"),
+        documentation: text(node, "Documentation"),
+        source: text(node, "Source"),
+        version: text(node, "Version"),
+        uri: text(node, "URI"),
+        date: text(node, "Date"),
+    }
+}
+
+fn read_repetition(node: Node) -> Result<Option<Repetition>, XmlError> {
+    let repetition = match child(node, "Repetition") {
+        Some(repetition) => repetition,
+        None => return Ok(None),
+    };
+    let interval = parse_duration(repetition, "Interval")?
+        .ok_or_else(|| XmlError::MissingElement("Interval".to_string()))?;
+    Ok(Some(Repetition {
+        interval,
+        duration: parse_duration(repetition, "Duration")?,
+        stop_at_duration_end: parse_bool(repetition, "StopAtDurationEnd")?.unwrap_or(false),
+    }))
+}
+
+fn read_triggers(node: Node) -> Result<TaskTriggers, XmlError> {
+    let mut builder = TaskTriggersBuilder::new();
+    for trigger in elements(node) {
+        let common = read_trigger_common(trigger)?;
+        let name = trigger.tag_name().name();
+        builder = match name {
+            "BootTrigger" => builder.with_boot(BootTrigger {
+                common,
+                delay: parse_duration(trigger, "Delay")?,
+            }),
+            "RegistrationTrigger" => builder.with_registration(RegistrationTrigger {
+                common,
+                delay: parse_duration(trigger, "Delay")?,
+            }),
+            "IdleTrigger" => builder.with_idle(IdleTrigger {
+                common,
+                repetition: read_repetition(trigger)?,
+            }),
+            "LogonTrigger" => builder.with_logon(LogonTrigger {
+                common,
+                user_id: text(trigger, "UserId"),
+                delay: parse_duration(trigger, "Delay")?,
+            }),
+            "SessionStateChangeTrigger" => {
+                let state_change = text(trigger, "StateChange")
+                    .ok_or_else(|| XmlError::MissingElement("StateChange".to_string()))?;
+                builder.with_session_state_change(SessionStateChangeTrigger {
+                    common,
+                    state_change: parse_session_state_change(&state_change)?,
+                    user_id: text(trigger, "UserId"),
+                    delay: parse_duration(trigger, "Delay")?,
+                })
+            }
+            "EventTrigger" => {
+                let subscription = text(trigger, "Subscription")
+                    .ok_or_else(|| XmlError::MissingElement("Subscription".to_string()))?;
+                let value_queries = match child(trigger, "ValueQueries") {
+                    Some(queries) => elements(queries)
+                        .map(|value| {
+                            (
+                                value.attribute("name").unwrap_or("").to_string(),
+                                value.text().unwrap_or("").trim().to_string(),
+                            )
+                        })
+                        .collect(),
+                    None => Vec::new(),
+                };
+                builder.with_event(EventTrigger {
+                    common,
+                    subscription,
+                    delay: parse_duration(trigger, "Delay")?,
+                    value_queries,
+                    repetition: read_repetition(trigger)?,
+                })
+            }
+            "TimeTrigger" => {
+                if common.start.is_none() {
+                    return Err(XmlError::MissingElement("StartBoundary".to_string()));
+                }
+                builder.with_specific_time(SpecificTimeTrigger {
+                    common,
+                    repetition: read_repetition(trigger)?,
+                })
+            }
+            "CalendarTrigger" => read_calendar_trigger(builder, trigger, common)?,
+            _ => return Err(XmlError::Unsupported(name.to_string())),
+        };
+    }
+    Ok(TaskTriggers::from_builder(builder))
+}
+
+/// Reads the elements every trigger has, the random delay is only there for
+/// time and calendar triggers
+fn read_trigger_common(trigger: Node) -> Result<TriggerCommon, XmlError> {
+    Ok(TriggerCommon {
+        id: trigger
+            .attribute("id")
+            .filter(|id| !id.is_empty())
+            .map(str::to_string),
+        enabled: parse_bool(trigger, "Enabled")?.unwrap_or(true),
+        start: parse_boundary(trigger, "StartBoundary")?,
+        end: parse_boundary(trigger, "EndBoundary")?,
+        execution_time_limit: parse_duration(trigger, "ExecutionTimeLimit")?,
+        random_delay: parse_duration(trigger, "RandomDelay")?,
+    })
+}
+
+fn read_calendar_trigger(
+    builder: TaskTriggersBuilder,
+    trigger: Node,
+    common: TriggerCommon,
+) -> Result<TaskTriggersBuilder, XmlError> {
+    let repetition = read_repetition(trigger)?;
+
+    if let Some(schedule) = child(trigger, "ScheduleByDay") {
+        return Ok(builder.with_daily(DailyTrigger {
+            common,
+            interval: parse_number(schedule, "DaysInterval")?,
+            repetition,
+        }));
+    }
+    if let Some(schedule) = child(trigger, "ScheduleByWeek") {
+        return Ok(builder.with_weekly(WeeklyTrigger {
+            common,
+            days_of_week: read_days_of_week(schedule)?,
+            weeks_interval: parse_number(schedule, "WeeksInterval")?,
+            repetition,
+        }));
+    }
+    if let Some(schedule) = child(trigger, "ScheduleByMonth") {
+        let mut days_of_month = DaysOfMonth::new();
+        let mut run_on_last_day_of_month = false;
+        if let Some(days) = child(schedule, "DaysOfMonth") {
+            for day in elements(days) {
+                let value = day.text().unwrap_or("").trim();
+                match value.parse::<u32>() {
+                    Ok(day) if (1..=31).contains(&day) => days_of_month = days_of_month.with(day),
+                    _ if value == "Last" => run_on_last_day_of_month = true,
+                    _ => return Err(invalid("Day", value)),
+                }
+            }
+        }
+        return Ok(builder.with_monthly(MonthlyTrigger {
+            common,
+            days_of_month,
+            months: read_months(schedule)?,
+            run_on_last_day_of_month,
+            repetition,
+        }));
+    }
+    if let Some(schedule) = child(trigger, "ScheduleByMonthDayOfWeek") {
+        let mut weeks_of_month = WeeksOfMonth::new();
+        let mut run_on_last_week_of_month = false;
+        if let Some(weeks) = child(schedule, "Weeks") {
+            for week in elements(weeks) {
+                let value = week.text().unwrap_or("").trim();
+                match value.parse::<u32>() {
+                    Ok(week) if (1..=4).contains(&week) => {
+                        weeks_of_month = weeks_of_month.with(week)
+                    }
+                    _ if value == "Last" => run_on_last_week_of_month = true,
+                    _ => return Err(invalid("Week", value)),
+                }
+            }
+        }
+        return Ok(builder.with_monthly_dow(MonthlyDowTrigger {
+            common,
+            days_of_week: read_days_of_week(schedule)?,
+            weeks_of_month,
+            months: read_months(schedule)?,
+            run_on_last_week_of_month,
+            repetition,
+        }));
+    }
+    Err(XmlError::MissingElement("ScheduleByDay".to_string()))
+}
+
+fn read_days_of_week(schedule: Node) -> Result<DaysOfWeek, XmlError> {
+    let mut days_of_week = DaysOfWeek::new();
+    if let Some(days) = child(schedule, "DaysOfWeek") {
+        for day in elements(days) {
+            let name = day.tag_name().name();
+            let day = weekday_from_name(name).ok_or_else(|| invalid("DaysOfWeek", name))?;
+            days_of_week = days_of_week.with(day);
+        }
+    }
+    Ok(days_of_week)
+}
+
+fn read_months(schedule: Node) -> Result<Months, XmlError> {
+    let mut months = Months::new();
+    match child(schedule, "Months") {
+        Some(node) => {
+            for month in elements(node) {
+                let name = month.tag_name().name();
+                let month = month_from_name(name).ok_or_else(|| invalid("Months", name))?;
+                months = months.with(month);
+            }
+        }
+        // every month when the months are left out
+        None => months = Months::all(),
+    }
+    Ok(months)
+}
+
+fn parse_session_state_change(value: &str) -> Result<SessionStateChange, XmlError> {
+    match value {
+        "ConsoleConnect" => Ok(SessionStateChange::ConsoleConnect),
+        "ConsoleDisconnect" => Ok(SessionStateChange::ConsoleDisconnect),
+        "RemoteConnect" => Ok(SessionStateChange::RemoteConnect),
+        "RemoteDisconnect" => Ok(SessionStateChange::RemoteDisconnect),
+        "SessionLock" => Ok(SessionStateChange::SessionLock),
+        "SessionUnlock" => Ok(SessionStateChange::SessionUnlock),
+        _ => Err(invalid("StateChange", value)),
+    }
+}
+
+/// Well known service accounts, which don't have a logon type in the XML
+const SERVICE_ACCOUNTS: &[&str] = &[
+    "S-1-5-18",
+    "S-1-5-19",
+    "S-1-5-20",
+    "SYSTEM",
+    "LOCAL SERVICE",
+    "NETWORK SERVICE",
+    "NT AUTHORITY\\SYSTEM",
+    "NT AUTHORITY\\LOCAL SERVICE",
+    "NT AUTHORITY\\NETWORK SERVICE",
+];
+
+/// Whether the user is one of the well known service accounts
+pub(crate) fn is_service_account(user_id: &str) -> bool {
+    SERVICE_ACCOUNTS
+        .iter()
+        .any(|account| account.eq_ignore_ascii_case(user_id))
+}
+
+fn read_principal(node: Node) -> Result<TaskPrincipal, XmlError> {
+    let user_id = text(node, "UserId");
+    let group_id = text(node, "GroupId");
+    let logon_type = match text(node, "LogonType").as_deref() {
+        Some("Password") => Some(TaskLogon::Password),
+        Some("S4U") => Some(TaskLogon::S4U),
+        Some("InteractiveToken") => Some(TaskLogon::InteractiveToken),
+        Some("InteractiveTokenOrPassword") => Some(TaskLogon::InteractiveTokenOrPassword),
+        Some(value) => return Err(invalid("LogonType", value)),
+        None => None,
+    };
+    let run_level = match text(node, "RunLevel").as_deref() {
+        Some("HighestAvailable") => RunLevel::HighestAvailable,
+        Some("LeastPrivilege") | None => RunLevel::LeastPrivilege,
+        Some(value) => return Err(invalid("RunLevel", value)),
+    };
+    let mut required_privileges = Vec::new();
+    if let Some(privileges) = child(node, "RequiredPrivileges") {
+        for privilege in elements(privileges) {
+            let name = privilege.text().unwrap_or("").trim();
+            let privilege =
+                Privilege::from_name(name).ok_or_else(|| invalid("RequiredPrivileges", name))?;
+            required_privileges.push(privilege);
+        }
+    }
+    Ok(TaskPrincipal {
+        id: node.attribute("id").unwrap_or("Author").to_string(),
+        user_id,
+        group_id,
+        logon_type,
+        run_level,
+        required_privileges,
+    })
+}
+
+fn read_settings(node: Node) -> Result<Settings, XmlError> {
+    let mut settings = Settings::default();
+    if let Some(value) = parse_bool(node, "AllowStartOnDemand")? {
+        settings.allow_demand_start = value;
+    }
+    if let Some(value) = parse_bool(node, "AllowHardTerminate")? {
+        settings.allow_hard_terminate = value;
+    }
+    if let Some(value) = parse_bool(node, "DisallowStartIfOnBatteries")? {
+        settings.disallow_start_if_on_batteries = value;
+    }
+    if let Some(value) = parse_bool(node, "StopIfGoingOnBatteries")? {
+        settings.stop_if_going_on_batteries = value;
+    }
+    if let Some(value) = parse_bool(node, "Enabled")? {
+        settings.enabled = value;
+    }
+    if let Some(value) = parse_bool(node, "Hidden")? {
+        settings.hidden = value;
+    }
+    if let Some(value) = parse_bool(node, "StartWhenAvailable")? {
+        settings.start_when_available = value;
+    }
+    if let Some(value) = parse_bool(node, "RunOnlyIfIdle")? {
+        settings.run_only_if_idle = value;
+    }
+    if let Some(value) = parse_bool(node, "RunOnlyIfNetworkAvailable")? {
+        settings.run_only_if_network_available = value;
+    }
+    if let Some(value) = parse_bool(node, "WakeToRun")? {
+        settings.wake_to_run = value;
+    }
+    if let Some(value) = parse_bool(node, "UseUnifiedSchedulingEngine")? {
+        settings.use_unified_scheduling_engine = value;
+    }
+    if let Some(value) = parse_bool(node, "DisallowStartOnRemoteAppSession")? {
+        settings.disallow_start_on_remote_app_session = value;
+    }
+    if let Some(value) = parse_bool(node, "Volatile")? {
+        settings.volatile = value;
+    }
+    if let Some(limit) = parse_duration(node, "ExecutionTimeLimit")? {
+        settings.execution_time_limit = if limit.is_zero() { None } else { Some(limit) };
+    }
+    settings.delete_expired_task_after = parse_duration(node, "DeleteExpiredTaskAfter")?;
+    if let Some(value) = text(node, "MultipleInstancesPolicy") {
+        settings.multiple_instances = match value.as_str() {
+            "Parallel" => MultipleInstancesPolicy::Parallel,
+            "Queue" => MultipleInstancesPolicy::Queue,
+            "IgnoreNew" => MultipleInstancesPolicy::IgnoreNew,
+            "StopExisting" => MultipleInstancesPolicy::StopExisting,
+            _ => return Err(invalid("MultipleInstancesPolicy", &value)),
+        };
+    }
+    if let Some(priority) = parse_number(node, "Priority")? {
+        settings.priority = priority;
+    }
+    if let Some(restart) = child(node, "RestartOnFailure") {
+        settings.restart_on_failure = Some(RestartOnFailure {
+            interval: parse_duration(restart, "Interval")?
+                .ok_or_else(|| XmlError::MissingElement("Interval".to_string()))?,
+            count: parse_number(restart, "Count")?
+                .ok_or_else(|| XmlError::MissingElement("Count".to_string()))?,
+        });
+    }
+    if let Some(idle) = child(node, "IdleSettings") {
+        let mut conditions = IdleConditions::new();
+        if let Some(duration) = parse_duration(idle, "Duration")? {
+            conditions.duration = duration;
+        }
+        if let Some(wait_timeout) = parse_duration(idle, "WaitTimeout")? {
+            conditions.wait_timeout = wait_timeout;
+        }
+        if let Some(value) = parse_bool(idle, "StopOnIdleEnd")? {
+            conditions.stop_on_idle_end = value;
+        }
+        if let Some(value) = parse_bool(idle, "RestartOnIdle")? {
+            conditions.restart_on_idle = value;
+        }
+        settings.idle_conditions = conditions;
+    }
+    Ok(settings)
+}
+
+fn read_actions(node: Node) -> Result<Actions, XmlError> {
+    let mut actions = Vec::new();
+    for action in elements(node) {
+        let id = action.attribute("id").map(str::to_string);
+        let name = action.tag_name().name();
+        match name {
+            "Exec" => {
+                let command = text(action, "Command")
+                    .ok_or_else(|| XmlError::MissingElement("Command".to_string()))?;
+                actions.push(Action::Exec(ExecAction {
+                    id,
+                    path: PathBuf::from(command),
+                    arguments: text(action, "Arguments"),
+                    working_directory: text(action, "WorkingDirectory").map(PathBuf::from),
+                }));
+            }
+            "ComHandler" => {
+                let class_id = text(action, "ClassId")
+                    .ok_or_else(|| XmlError::MissingElement("ClassId".to_string()))?;
+                actions.push(Action::ComHandler(ComHandlerAction {
+                    id,
+                    class_id,
+                    data: text(action, "Data"),
+                }));
+            }
+            _ => return Err(XmlError::Unsupported(name.to_string())),
+        }
+    }
+    if actions.is_empty() {
+        return Err(XmlError::MissingElement("Exec".to_string()));
+    }
+    Ok(Actions(actions))
+}
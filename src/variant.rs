@@ -0,0 +1,77 @@
+use std::{ffi::c_void, mem};
+
+use bindings::Windows::Win32::Automation::{
+    SafeArrayCreateVector, SafeArrayPutElement, VariantClear, BSTR, VARENUM, VARIANT,
+};
+
+/// Owns a VARIANT that is passed into the Task Scheduler apis and clears it when dropped
+///
+/// https://docs.microsoft.com/en-us/windows/win32/api/oaidl/ns-oaidl-variant
+pub(crate) struct Variant(pub(crate) VARIANT);
+
+impl Variant {
+    /// A VARIANT with the type VT_EMPTY
+    pub(crate) fn empty() -> Self {
+        // a zeroed VARIANT is VT_EMPTY
+        unsafe { Self(mem::zeroed()) }
+    }
+
+    /// A VARIANT with the type VT_I4
+    pub(crate) fn from_i32(value: i32) -> Self {
+        let mut variant = Self::empty();
+        unsafe {
+            variant.0.Anonymous.Anonymous.vt = VARENUM::VT_I4.0 as u16;
+            variant.0.Anonymous.Anonymous.Anonymous.lVal = value;
+        }
+        variant
+    }
+
+    /// A VARIANT with the type VT_BSTR, the VARIANT takes ownership of the BSTR
+    pub(crate) fn from_str(value: &str) -> Self {
+        let mut variant = Self::empty();
+        unsafe {
+            variant.0.Anonymous.Anonymous.vt = VARENUM::VT_BSTR.0 as u16;
+            variant.0.Anonymous.Anonymous.Anonymous.bstrVal = mem::transmute(BSTR::from(value));
+        }
+        variant
+    }
+
+    /// A VARIANT with the type VT_ARRAY | VT_BSTR holding a SAFEARRAY of the strings
+    pub(crate) fn from_strs(values: &[&str]) -> Result<Self, windows::Error> {
+        let mut variant = Self::empty();
+        unsafe {
+            let array = SafeArrayCreateVector(VARENUM::VT_BSTR.0 as u16, 0, values.len() as u32);
+            if array.is_null() {
+                return Err(windows::Error::fast_error(windows::HRESULT(0x8007_000E)));
+            }
+            variant.0.Anonymous.Anonymous.vt = (VARENUM::VT_ARRAY.0 | VARENUM::VT_BSTR.0) as u16;
+            variant.0.Anonymous.Anonymous.Anonymous.parray = array;
+            for (index, value) in values.iter().enumerate() {
+                let mut index = index as i32;
+                let string = BSTR::from(*value);
+                // SafeArrayPutElement copies the string so the BSTR is freed as usual
+                let pointer: *mut u16 = mem::transmute_copy(&string);
+                SafeArrayPutElement(array, &mut index, pointer as *mut c_void).ok()?;
+            }
+        }
+        Ok(variant)
+    }
+
+    /// Parameters for running a task, where a single parameter is passed as
+    /// a string and multiple parameters are passed as an array of strings
+    pub(crate) fn from_parameters(parameters: &[&str]) -> Result<Self, windows::Error> {
+        match parameters {
+            [] => Ok(Self::empty()),
+            [parameter] => Ok(Self::from_str(parameter)),
+            parameters => Self::from_strs(parameters),
+        }
+    }
+}
+
+impl Drop for Variant {
+    fn drop(&mut self) {
+        unsafe {
+            VariantClear(&mut self.0);
+        }
+    }
+}